@@ -1,13 +1,24 @@
+use chrono::FixedOffset;
 use config::{Config, ConfigError, File};
 use serde::Deserialize;
 use std::env;
 
+use crate::utils::client_ip::is_valid_ip_entry;
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct Settings {
     pub server: ServerSettings,
     pub redis: RedisSettings,
     pub security: SecuritySettings,
     pub logging: LoggingSettings,
+    #[serde(default)]
+    pub ldap: LdapSettings,
+    #[serde(default)]
+    pub billing: BillingSettings,
+    #[serde(default)]
+    pub usage_retention: UsageRetentionSettings,
+    #[serde(default)]
+    pub metrics: MetricsSettings,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -15,6 +26,36 @@ pub struct ServerSettings {
     pub host: String,
     pub port: u16,
     pub request_timeout: u64, // milliseconds
+    /// Hours from UTC (e.g. 8, 5.5, 5.75), used for "today" in dashboards and statistics
+    pub timezone_offset: f64,
+    /// Reverse proxies (IP or CIDR) whose X-Forwarded-For / X-Real-IP headers are trusted
+    #[serde(default)]
+    pub trusted_proxies: Vec<String>,
+}
+
+impl ServerSettings {
+    /// System timezone used to decide day boundaries
+    pub fn timezone(&self) -> FixedOffset {
+        timezone_from_hours(self.timezone_offset)
+            .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap())
+    }
+}
+
+/// Converts an offset in hours from UTC to a timezone
+///
+/// The offset must be between -12 and 14 hours and a whole number of 15 minutes,
+/// so half-hour and 45-minute zones such as 5.5 and 5.75 are accepted
+pub fn timezone_from_hours(hours: f64) -> Option<FixedOffset> {
+    let minutes = hours * 60.0;
+    if !(-12.0..=14.0).contains(&hours) || minutes % 15.0 != 0.0 {
+        return None;
+    }
+    FixedOffset::east_opt(minutes as i32 * 60)
+}
+
+/// Offset of a timezone in hours from UTC (fractional for non-whole-hour zones)
+pub fn timezone_hours(timezone: FixedOffset) -> f64 {
+    timezone.local_minus_utc() as f64 / 3600.0
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -39,6 +80,152 @@ pub struct LoggingSettings {
     pub format: String, // "json" or "pretty"
 }
 
+/// LDAP 认证设置
+///
+/// 启用后管理员登录和用户登录在本地凭据校验失败时回退到企业目录
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct LdapSettings {
+    pub enabled: bool,
+    pub url: String, // ldap://host:389 or ldaps://host:636
+    pub start_tls: bool,
+    pub tls_skip_verify: bool,
+    pub bind_dn: String,
+    pub bind_password: String,
+    pub search_base: String,
+    pub user_filter: String, // "{username}" is replaced with the escaped login name
+    pub username_attribute: String,
+    pub email_attribute: String,
+    pub display_name_attribute: String,
+    pub group_attribute: String,
+    pub admin_groups: Vec<String>, // group DN or CN, members get the admin role
+    pub user_groups: Vec<String>,  // empty means every directory user may sign in
+    pub timeout: u64,              // seconds
+}
+
+impl Default for LdapSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: "ldap://localhost:389".to_string(),
+            start_tls: false,
+            tls_skip_verify: false,
+            bind_dn: String::new(),
+            bind_password: String::new(),
+            search_base: String::new(),
+            user_filter: "(uid={username})".to_string(),
+            username_attribute: "uid".to_string(),
+            email_attribute: "mail".to_string(),
+            display_name_attribute: "displayName".to_string(),
+            group_attribute: "memberOf".to_string(),
+            admin_groups: Vec::new(),
+            user_groups: Vec::new(),
+            timeout: 10,
+        }
+    }
+}
+
+/// 计费事件设置
+///
+/// 每次记录使用量时向 Redis Stream `billing:events` 发布一条计费事件，供外部账务系统消费
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct BillingSettings {
+    pub events_enabled: bool,
+    pub stream_max_len: usize, // approximate MAXLEN, older events are trimmed
+}
+
+impl Default for BillingSettings {
+    fn default() -> Self {
+        Self {
+            events_enabled: true,
+            stream_max_len: 100_000,
+        }
+    }
+}
+
+/// 使用量统计保留策略
+///
+/// 超过 `hourly_days` 的小时桶汇总进日桶后删除，超过 `daily_days` 的日桶删除
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct UsageRetentionSettings {
+    pub hourly_days: i64,
+    pub daily_days: i64,
+    pub cleanup_interval: u64, // seconds, 0 disables the background cleanup
+}
+
+impl Default for UsageRetentionSettings {
+    fn default() -> Self {
+        Self {
+            hourly_days: 8,
+            daily_days: 90,
+            cleanup_interval: 3600,
+        }
+    }
+}
+
+impl UsageRetentionSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.hourly_days < 1 || self.daily_days < 1 {
+            return Err("Usage retention days must be at least 1".to_string());
+        }
+        if self.daily_days < self.hourly_days {
+            return Err("Usage retention daily_days must not be less than hourly_days".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Prometheus 指标设置
+///
+/// 默认不开启；`token` 为空时 `/metrics` 无需认证，只应在内网中这样开启。
+/// 每个指标的标签组合超过 `max_series` 后，新组合合并到标签值为 `other` 的序列
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct MetricsSettings {
+    pub enabled: bool,
+    pub token: Option<String>, // Bearer token required to scrape /metrics
+    pub max_series: usize,     // label combinations per metric
+    pub per_key_labels: bool,  // label token, cost and concurrency metrics with API key IDs
+}
+
+impl Default for MetricsSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            token: None,
+            max_series: 1000,
+            per_key_labels: false,
+        }
+    }
+}
+
+impl LdapSettings {
+    /// Validate LDAP configuration (only when enabled)
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        let is_ldaps = self.url.starts_with("ldaps://");
+        if !is_ldaps && !self.url.starts_with("ldap://") {
+            return Err("LDAP url must start with ldap:// or ldaps://".to_string());
+        }
+        if is_ldaps && self.start_tls {
+            return Err("LDAP StartTLS cannot be combined with an ldaps:// url".to_string());
+        }
+        if self.search_base.is_empty() {
+            return Err("LDAP search_base is required".to_string());
+        }
+        if !self.user_filter.contains("{username}") {
+            return Err("LDAP user_filter must contain the {username} placeholder".to_string());
+        }
+
+        Ok(())
+    }
+}
+
 impl Settings {
     pub fn new() -> Result<Self, ConfigError> {
         let run_mode = env::var("RUN_MODE").unwrap_or_else(|_| "development".into());
@@ -48,6 +235,7 @@ impl Settings {
             .set_default("server.host", "0.0.0.0")?
             .set_default("server.port", 8080)?
             .set_default("server.request_timeout", 600000)? // 10 minutes
+            .set_default("server.timezone_offset", 8)?
             .set_default("redis.host", "localhost")?
             .set_default("redis.port", 6379)?
             .set_default("redis.db", 0)?
@@ -83,6 +271,18 @@ impl Settings {
         if let Ok(val) = env::var("CRS_SERVER__REQUEST_TIMEOUT") {
             builder = builder.set_override("server.request_timeout", val)?;
         }
+        if let Ok(val) = env::var("CRS_SERVER__TIMEZONE_OFFSET") {
+            builder = builder.set_override("server.timezone_offset", val)?;
+        }
+        if let Ok(val) = env::var("CRS_SERVER__TRUSTED_PROXIES") {
+            let proxies: Vec<String> = val
+                .split(',')
+                .map(str::trim)
+                .filter(|proxy| !proxy.is_empty())
+                .map(str::to_string)
+                .collect();
+            builder = builder.set_override("server.trusted_proxies", proxies)?;
+        }
 
         // Redis settings
         if let Ok(val) = env::var("CRS_REDIS__HOST") {
@@ -109,6 +309,85 @@ impl Settings {
             builder = builder.set_override("logging.format", val)?;
         }
 
+        // LDAP settings
+        for (var, key) in [
+            ("CRS_LDAP__ENABLED", "ldap.enabled"),
+            ("CRS_LDAP__URL", "ldap.url"),
+            ("CRS_LDAP__START_TLS", "ldap.start_tls"),
+            ("CRS_LDAP__TLS_SKIP_VERIFY", "ldap.tls_skip_verify"),
+            ("CRS_LDAP__BIND_DN", "ldap.bind_dn"),
+            ("CRS_LDAP__BIND_PASSWORD", "ldap.bind_password"),
+            ("CRS_LDAP__SEARCH_BASE", "ldap.search_base"),
+            ("CRS_LDAP__USER_FILTER", "ldap.user_filter"),
+            ("CRS_LDAP__USERNAME_ATTRIBUTE", "ldap.username_attribute"),
+            ("CRS_LDAP__EMAIL_ATTRIBUTE", "ldap.email_attribute"),
+            (
+                "CRS_LDAP__DISPLAY_NAME_ATTRIBUTE",
+                "ldap.display_name_attribute",
+            ),
+            ("CRS_LDAP__GROUP_ATTRIBUTE", "ldap.group_attribute"),
+            ("CRS_LDAP__TIMEOUT", "ldap.timeout"),
+        ] {
+            if let Ok(val) = env::var(var) {
+                builder = builder.set_override(key, val)?;
+            }
+        }
+        // Group DNs contain commas, so group lists are separated by semicolons
+        for (var, key) in [
+            ("CRS_LDAP__ADMIN_GROUPS", "ldap.admin_groups"),
+            ("CRS_LDAP__USER_GROUPS", "ldap.user_groups"),
+        ] {
+            if let Ok(val) = env::var(var) {
+                let groups: Vec<String> = val
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|group| !group.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                builder = builder.set_override(key, groups)?;
+            }
+        }
+
+        // Billing event settings
+        if let Ok(val) = env::var("CRS_BILLING__EVENTS_ENABLED") {
+            builder = builder.set_override("billing.events_enabled", val)?;
+        }
+        if let Ok(val) = env::var("CRS_BILLING__STREAM_MAX_LEN") {
+            builder = builder.set_override("billing.stream_max_len", val)?;
+        }
+
+        // Usage retention settings
+        for (var, key) in [
+            (
+                "CRS_USAGE_RETENTION__HOURLY_DAYS",
+                "usage_retention.hourly_days",
+            ),
+            (
+                "CRS_USAGE_RETENTION__DAILY_DAYS",
+                "usage_retention.daily_days",
+            ),
+            (
+                "CRS_USAGE_RETENTION__CLEANUP_INTERVAL",
+                "usage_retention.cleanup_interval",
+            ),
+        ] {
+            if let Ok(val) = env::var(var) {
+                builder = builder.set_override(key, val)?;
+            }
+        }
+
+        // Prometheus metrics settings
+        for (var, key) in [
+            ("CRS_METRICS__ENABLED", "metrics.enabled"),
+            ("CRS_METRICS__TOKEN", "metrics.token"),
+            ("CRS_METRICS__MAX_SERIES", "metrics.max_series"),
+            ("CRS_METRICS__PER_KEY_LABELS", "metrics.per_key_labels"),
+        ] {
+            if let Ok(val) = env::var(var) {
+                builder = builder.set_override(key, val)?;
+            }
+        }
+
         let config = builder.build()?;
         config.try_deserialize()
     }
@@ -125,6 +404,24 @@ impl Settings {
             return Err("ENCRYPTION_KEY must be exactly 32 characters".to_string());
         }
 
+        // Validate system timezone
+        if timezone_from_hours(self.server.timezone_offset).is_none() {
+            return Err(
+                "Server timezone_offset must be between -12 and 14 hours in steps of 15 minutes"
+                    .to_string(),
+            );
+        }
+
+        // Validate trusted proxies
+        if let Some(entry) = self
+            .server
+            .trusted_proxies
+            .iter()
+            .find(|entry| !is_valid_ip_entry(entry))
+        {
+            return Err(format!("Invalid trusted proxy '{}'", entry));
+        }
+
         // Validate Redis pool size
         if self.redis.pool_size == 0 {
             return Err("Redis pool size must be greater than 0".to_string());
@@ -140,6 +437,18 @@ impl Settings {
             ));
         }
 
+        self.ldap.validate()?;
+
+        if self.billing.events_enabled && self.billing.stream_max_len == 0 {
+            return Err("Billing stream_max_len must be greater than 0".to_string());
+        }
+
+        self.usage_retention.validate()?;
+
+        if self.metrics.max_series == 0 {
+            return Err("Metrics max_series must be greater than 0".to_string());
+        }
+
         Ok(())
     }
 
@@ -184,14 +493,31 @@ mod tests {
         let settings = Settings::new().expect("Failed to load settings");
 
         assert_eq!(settings.server.port, 8080);
+        assert_eq!(settings.server.timezone_offset, 8.0);
         assert_eq!(settings.redis.host, "localhost");
         assert_eq!(settings.redis.port, 6379);
+        assert_eq!(settings.usage_retention.hourly_days, 8);
+        assert_eq!(settings.usage_retention.daily_days, 90);
 
         // Clean up env vars
         env::remove_var("CRS_SECURITY__JWT_SECRET");
         env::remove_var("CRS_SECURITY__ENCRYPTION_KEY");
     }
 
+    #[test]
+    fn test_timezone_from_hours() {
+        assert_eq!(
+            timezone_from_hours(8.0).unwrap().local_minus_utc(),
+            8 * 3600
+        );
+        assert_eq!(timezone_from_hours(5.5).unwrap().local_minus_utc(), 19800);
+        assert_eq!(timezone_from_hours(5.75).unwrap().local_minus_utc(), 20700);
+        assert_eq!(timezone_from_hours(-3.5).unwrap().local_minus_utc(), -12600);
+        assert!(timezone_from_hours(5.6).is_none());
+        assert!(timezone_from_hours(15.0).is_none());
+        assert_eq!(timezone_hours(timezone_from_hours(9.5).unwrap()), 9.5);
+    }
+
     #[test]
     #[serial]
     fn test_redis_url_without_password() {
@@ -221,6 +547,8 @@ mod tests {
                 host: "0.0.0.0".to_string(),
                 port: 8080,
                 request_timeout: 600000,
+                timezone_offset: 8.0,
+                trusted_proxies: Vec::new(),
             },
             redis: RedisSettings {
                 host: "localhost".to_string(),
@@ -238,8 +566,117 @@ mod tests {
                 level: "info".to_string(),
                 format: "pretty".to_string(),
             },
+            ldap: LdapSettings::default(),
+            billing: BillingSettings::default(),
+            usage_retention: UsageRetentionSettings::default(),
+            metrics: MetricsSettings::default(),
         };
 
         assert!(settings.validate().is_err());
     }
+
+    #[test]
+    #[serial]
+    fn test_ldap_settings_from_env() {
+        env::set_var(
+            "CRS_SECURITY__JWT_SECRET",
+            "test_secret_key_minimum_32_chars_long",
+        );
+        env::set_var(
+            "CRS_SECURITY__ENCRYPTION_KEY",
+            "12345678901234567890123456789012",
+        );
+        env::set_var("CRS_LDAP__ENABLED", "true");
+        env::set_var("CRS_LDAP__SEARCH_BASE", "ou=people,dc=example,dc=com");
+        env::set_var(
+            "CRS_LDAP__ADMIN_GROUPS",
+            "cn=relay-admins,ou=groups,dc=example,dc=com; ops",
+        );
+
+        let settings = Settings::new().expect("Failed to load settings");
+
+        assert!(settings.ldap.enabled);
+        assert_eq!(settings.ldap.user_filter, "(uid={username})");
+        assert_eq!(
+            settings.ldap.admin_groups,
+            vec!["cn=relay-admins,ou=groups,dc=example,dc=com", "ops"]
+        );
+        assert!(settings.ldap.user_groups.is_empty());
+        assert!(settings.ldap.validate().is_ok());
+
+        env::remove_var("CRS_SECURITY__JWT_SECRET");
+        env::remove_var("CRS_SECURITY__ENCRYPTION_KEY");
+        env::remove_var("CRS_LDAP__ENABLED");
+        env::remove_var("CRS_LDAP__SEARCH_BASE");
+        env::remove_var("CRS_LDAP__ADMIN_GROUPS");
+    }
+
+    #[test]
+    #[serial]
+    fn test_trusted_proxies_from_env() {
+        env::set_var(
+            "CRS_SECURITY__JWT_SECRET",
+            "test_secret_key_minimum_32_chars_long",
+        );
+        env::set_var(
+            "CRS_SECURITY__ENCRYPTION_KEY",
+            "12345678901234567890123456789012",
+        );
+        env::set_var("CRS_SERVER__TRUSTED_PROXIES", "10.0.0.0/8, 127.0.0.1");
+
+        let mut settings = Settings::new().expect("Failed to load settings");
+        assert_eq!(
+            settings.server.trusted_proxies,
+            vec!["10.0.0.0/8", "127.0.0.1"]
+        );
+        assert!(settings.validate().is_ok());
+
+        settings
+            .server
+            .trusted_proxies
+            .push("proxy.local".to_string());
+        assert!(settings.validate().is_err());
+
+        env::remove_var("CRS_SECURITY__JWT_SECRET");
+        env::remove_var("CRS_SECURITY__ENCRYPTION_KEY");
+        env::remove_var("CRS_SERVER__TRUSTED_PROXIES");
+    }
+
+    #[test]
+    fn test_ldap_settings_validation() {
+        let mut ldap = LdapSettings::default();
+        assert!(ldap.validate().is_ok()); // disabled
+
+        ldap.enabled = true;
+        assert!(ldap.validate().is_err()); // missing search base
+
+        ldap.search_base = "dc=example,dc=com".to_string();
+        assert!(ldap.validate().is_ok());
+
+        ldap.url = "ldaps://ldap.example.com".to_string();
+        ldap.start_tls = true;
+        assert!(ldap.validate().is_err());
+
+        ldap.start_tls = false;
+        ldap.user_filter = "(uid=admin)".to_string();
+        assert!(ldap.validate().is_err());
+    }
+
+    #[test]
+    fn test_usage_retention_validation() {
+        assert!(UsageRetentionSettings::default().validate().is_ok());
+
+        let shorter_daily = UsageRetentionSettings {
+            hourly_days: 8,
+            daily_days: 7,
+            cleanup_interval: 3600,
+        };
+        assert!(shorter_daily.validate().is_err());
+
+        let zero = UsageRetentionSettings {
+            hourly_days: 0,
+            ..Default::default()
+        };
+        assert!(zero.validate().is_err());
+    }
 }
@@ -4,25 +4,64 @@ use axum::{
     routing::{get, get_service},
     Router,
 };
-use std::{path::PathBuf, sync::Arc};
+use clap::{Parser, Subcommand};
+use std::{net::SocketAddr, path::PathBuf, sync::Arc};
 use tower_http::services::{ServeDir, ServeFile};
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
+use claude_relay::models::CostRecalculationOptions;
 use claude_relay::routes::{
-    create_admin_routes, create_api_router, create_gemini_router, create_openai_router,
-    health_check, ping, ApiState, AppState, GeminiState, OpenAIState,
+    create_admin_routes, create_api_router, create_api_stats_router, create_gemini_router,
+    create_metrics_router, create_openai_router, create_user_routes, health_check, ping,
+    ApiState, ApiStatsState, AppState, GeminiState, MetricsState, OpenAIState,
 };
 use claude_relay::services::{
     bedrock_relay::BedrockRelayService, claude_relay::ClaudeRelayConfig,
     gemini_relay::GeminiRelayService, pricing_service::PricingService, AccountScheduler,
-    AdminService, ApiKeyService, ClaudeAccountService, ClaudeRelayService, UnifiedClaudeScheduler,
-    UnifiedGeminiScheduler, UnifiedOpenAIScheduler,
+    AdminService, ApiKeyService, ClaudeAccountService, ClaudeRelayService,
+    CostRecalculationService, LoginGuardService, TwoFactorService, UnifiedClaudeScheduler,
+    UnifiedGeminiScheduler, UnifiedOpenAIScheduler, UsageRetentionService, UserService,
+    WebhookService,
 };
+#[cfg(feature = "ldap")]
+use claude_relay::services::LdapAuthProvider;
 use claude_relay::utils::{init_logger, HttpClient};
 use claude_relay::{RedisPool, Settings};
 
+/// Claude Relay Service
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// 运维命令 (执行后退出，不启动服务)
+#[derive(Subcommand)]
+enum Command {
+    /// 重置管理员的双因素认证 (丢失验证器且没有恢复码时使用)
+    ResetTwoFactor {
+        /// 管理员用户名
+        username: String,
+    },
+    /// 按当前定价重算历史成本，输出每个 Key 的差异
+    RecalculateCosts {
+        /// 只计算差异，不写回
+        #[arg(long)]
+        dry_run: bool,
+        /// 只重算指定的 API Key ID (可重复)，默认全部
+        #[arg(long = "key")]
+        key_ids: Vec<String>,
+        /// 使用指定的定价快照文件，默认使用 data 目录中的定价数据
+        #[arg(long)]
+        pricing_file: Option<PathBuf>,
+    },
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
+    let cli = Cli::parse();
+
     // Load .env file from project root (../.env) or current directory (.env)
     dotenvy::from_path("../.env")
         .or_else(|_| dotenvy::from_path(".env"))
@@ -43,6 +82,8 @@ async fn main() -> Result<()> {
         return Err(anyhow::anyhow!("Invalid configuration: {}", e));
     }
     info!("✅ Configuration validated");
+    claude_relay::utils::metrics::configure(&settings.metrics);
+    claude_relay::utils::client_ip::configure_trusted_proxies(&settings.server.trusted_proxies);
 
     // Initialize Redis connection pool
     let redis = RedisPool::new(&settings)?;
@@ -57,6 +98,10 @@ async fn main() -> Result<()> {
         }
     }
 
+    if let Some(command) = cli.command {
+        return run_command(command, &settings, redis).await;
+    }
+
     // Initialize HTTP client
     let http_client = HttpClient::new(&settings)?;
     info!("🌐 HTTP client initialized");
@@ -71,12 +116,15 @@ async fn main() -> Result<()> {
     );
     info!("👤 Account service initialized");
 
-    let api_key_service = Arc::new(ApiKeyService::new(
-        (*redis_arc).clone(),
-        (*settings_arc).clone(),
-    ));
+    let webhook_service = Arc::new(WebhookService::new(redis_arc.clone()));
+    let api_key_service = Arc::new(
+        ApiKeyService::new((*redis_arc).clone(), (*settings_arc).clone())
+            .with_webhook_service(webhook_service.clone()),
+    );
     info!("🔑 API Key service initialized");
 
+    Arc::new(UsageRetentionService::new(redis_arc.clone(), &settings)).start();
+
     let scheduler = Arc::new(AccountScheduler::new(
         redis_arc.clone(),
         account_service.clone(),
@@ -145,7 +193,12 @@ async fn main() -> Result<()> {
     info!("🔄 Bedrock relay service initialized");
 
     // Create pricing service
-    let pricing_service = Arc::new(PricingService::new(reqwest_client.clone()));
+    let pricing_service = Arc::new(
+        PricingService::new(reqwest_client.clone()).with_overrides(redis_arc.clone()),
+    );
+    if let Err(e) = pricing_service.initialize().await {
+        error!("⚠️  Failed to load pricing data: {}", e);
+    }
     info!("💰 Pricing service initialized");
 
     // Create admin service
@@ -156,10 +209,28 @@ async fn main() -> Result<()> {
             "JWT_SECRET must be set (CRS_SECURITY__JWT_SECRET environment variable)"
         ));
     }
-    let admin_service = Arc::new(AdminService::new(
+    let admin_service = AdminService::new(
         Arc::new(redis.clone()),
         jwt_secret.clone(),
-    ));
+    )
+    .with_two_factor_service(Arc::new(TwoFactorService::new(
+        redis_arc.clone(),
+        settings.security.encryption_key.clone(),
+    )));
+    #[cfg(feature = "ldap")]
+    let admin_service = if settings.ldap.enabled {
+        info!("📇 LDAP authentication enabled: {}", settings.ldap.url);
+        admin_service.with_ldap_provider(Arc::new(LdapAuthProvider::from_settings(
+            settings.ldap.clone(),
+        )))
+    } else {
+        admin_service
+    };
+    #[cfg(not(feature = "ldap"))]
+    if settings.ldap.enabled {
+        tracing::warn!("⚠️  LDAP is enabled in config but the ldap feature is not compiled in");
+    }
+    let admin_service = Arc::new(admin_service);
     info!("👮 Admin service initialized");
 
     // Initialize admin from data/init.json (if exists)
@@ -168,6 +239,16 @@ async fn main() -> Result<()> {
         info!("💡 Run setup to create admin credentials");
     }
 
+    let user_service = Arc::new(UserService::new(
+        redis_arc.clone(),
+        admin_service.clone(),
+        api_key_service.clone(),
+    ));
+    let user_login_guard = Arc::new(
+        LoginGuardService::new(redis_arc.clone()).with_webhook_service(webhook_service.clone()),
+    );
+    info!("👥 User service initialized");
+
     // Create shared application states
     let health_state = Arc::new(AppState {
         redis: redis.clone(),
@@ -196,6 +277,11 @@ async fn main() -> Result<()> {
         pricing_service: pricing_service.clone(),
     };
 
+    let api_stats_state = ApiStatsState {
+        redis: redis_arc.clone(),
+        api_key_service: api_key_service.clone(),
+    };
+
     let openai_state = OpenAIState {
         redis: redis_arc,
         settings: settings_arc,
@@ -203,6 +289,7 @@ async fn main() -> Result<()> {
         api_key_service: api_key_service.clone(),
         scheduler,
         unified_openai_scheduler,
+        pricing_service: pricing_service.clone(),
     };
 
     // Setup static file serving for Vue SPA
@@ -236,7 +323,7 @@ async fn main() -> Result<()> {
     let serve_favicon = ServeFile::new(&favicon_path);
 
     // Build router
-    let app = Router::new()
+    let mut app = Router::new()
         .route("/", get(|| async { Redirect::permanent("/admin-next") })) // Redirect root to admin
         .route("/favicon.ico", get_service(serve_favicon))
         .route("/health", get(health_check))
@@ -248,14 +335,35 @@ async fn main() -> Result<()> {
         )
         .nest(
             "/web",
-            create_admin_routes(admin_service, api_key_service, redis.clone()),
+            create_admin_routes(admin_service.clone(), api_key_service, redis.clone()),
         ) // For frontend compatibility
+        .nest(
+            "/users",
+            create_user_routes(user_service, admin_service, user_login_guard),
+        )
         .nest("/api", create_api_router(api_state.clone()))
         .nest("/claude", create_api_router(api_state))
         .nest("/gemini", create_gemini_router(gemini_state))
         .nest("/openai", create_openai_router(openai_state))
+        .nest("/apiStats", create_api_stats_router(api_stats_state))
         .nest_service("/admin-next", serve_dir); // Serve Vue SPA
 
+    if settings.metrics.enabled {
+        let token = settings.metrics.token.clone().filter(|token| !token.trim().is_empty());
+        if token.is_some() {
+            info!("📈 Prometheus metrics enabled at /metrics (token required)");
+        } else {
+            warn!(
+                "⚠️ Prometheus metrics enabled at /metrics without a token, \
+                 set CRS_METRICS__TOKEN unless the endpoint is only reachable internally"
+            );
+        }
+        app = app.merge(create_metrics_router(MetricsState {
+            redis: redis.clone(),
+            token,
+        }));
+    }
+
     // Get bind address
     let bind_addr = settings.bind_address();
     info!("✅ Server starting on http://{}", bind_addr);
@@ -267,16 +375,80 @@ async fn main() -> Result<()> {
 
     info!("🚀 Server ready on http://{}", bind_addr);
 
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
-        .await
-        .map_err(|e| anyhow::anyhow!("Server error: {}", e))?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal())
+    .await
+    .map_err(|e| anyhow::anyhow!("Server error: {}", e))?;
 
     info!("👋 Shutting down...");
 
     Ok(())
 }
 
+/// 执行运维命令
+async fn run_command(command: Command, settings: &Settings, redis: RedisPool) -> Result<()> {
+    match command {
+        Command::ResetTwoFactor { username } => {
+            let two_factor = TwoFactorService::new(
+                Arc::new(redis),
+                settings.security.encryption_key.clone(),
+            );
+            if two_factor.reset(&username, "cli").await? {
+                info!("✅ Two-factor authentication reset for: {}", username);
+            } else {
+                info!("ℹ️  Two-factor authentication is not configured for: {}", username);
+            }
+        }
+        Command::RecalculateCosts {
+            dry_run,
+            key_ids,
+            pricing_file,
+        } => {
+            let pricing = PricingService::new(Arc::new(reqwest::Client::new()))
+                .with_overrides(Arc::new(redis.clone()));
+            match &pricing_file {
+                Some(path) => pricing.load_snapshot(path).await?,
+                None => pricing.load_local().await?,
+            }
+
+            let api_key_service = Arc::new(ApiKeyService::new(redis.clone(), settings.clone()));
+            let service = CostRecalculationService::new(Arc::new(redis), api_key_service);
+            let options = CostRecalculationOptions { dry_run, key_ids };
+            let job = service.start_job(&pricing, &options, "cli").await?;
+            let job = service.run_job(&pricing, job, &options).await;
+
+            for key in &job.keys {
+                info!(
+                    "🔑 {} ({}): ${:.6} -> ${:.6} ({:+.6})",
+                    key.key_name, key.key_id, key.old_cost, key.new_cost, key.diff
+                );
+            }
+            if !job.unpriced_models.is_empty() {
+                info!(
+                    "ℹ️  Models without pricing (cost kept): {}",
+                    job.unpriced_models.iter().cloned().collect::<Vec<_>>().join(", ")
+                );
+            }
+            if let Some(e) = job.error {
+                return Err(anyhow::anyhow!("Cost recalculation failed: {}", e));
+            }
+            info!(
+                "✅ {} keys processed, {} changed: ${:.6} -> ${:.6}{}",
+                job.processed_keys,
+                job.keys.len(),
+                job.old_total_cost,
+                job.new_total_cost,
+                if job.dry_run { " (dry run, nothing written)" } else { "" }
+            );
+        }
+    }
+
+    Ok(())
+}
+
 async fn shutdown_signal() {
     let ctrl_c = async {
         tokio::signal::ctrl_c()
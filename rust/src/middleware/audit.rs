@@ -0,0 +1,154 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{ConnectInfo, MatchedPath, Request, State},
+    http::{header, Method},
+    middleware::Next,
+    response::Response,
+};
+use chrono::Utc;
+use serde_json::{json, Value as JsonValue};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::middleware::auth::JwtAuthState;
+use crate::models::audit::{AuditEvent, AuditLogEntry};
+use crate::services::audit::{diff_changes, redact};
+use crate::services::AuditService;
+use crate::utils::client_ip::extract_client_ip;
+use crate::utils::error::AppError;
+
+/// 审计时缓存的请求体上限
+const MAX_AUDIT_BODY_BYTES: usize = 64 * 1024;
+
+/// 管理接口审计中间件
+///
+/// 必须位于 JWT 认证之后，记录所有写操作 (非 GET/HEAD/OPTIONS)，包括被拒绝的请求。
+///
+/// 处理器可以在响应扩展中附加 [`AuditEvent`] 提供操作名和变更前后的状态；
+/// 否则以 `METHOD 路由模板` 作为操作名，并记录脱敏后的请求体。
+/// 写入失败只记录警告，不影响请求本身。
+pub async fn audit_admin_request(
+    State(audit_service): State<Arc<AuditService>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    if matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    ) {
+        return Ok(next.run(request).await);
+    }
+
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+    let matched_path = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string());
+    let (actor, actor_role) = request
+        .extensions()
+        .get::<JwtAuthState>()
+        .map(|state| (state.claims.sub.clone(), state.claims.role.clone()))
+        .unwrap_or_else(|| ("anonymous".to_string(), String::new()));
+    let ip = extract_client_ip(
+        request.headers(),
+        request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| *addr),
+    );
+
+    // 缓存请求体，用于没有显式审计事件的请求；
+    // 超出上限或长度未知的请求体 (如图标上传) 不缓存，直接交给处理器
+    let body_len = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok());
+    let (request, request_body) = match body_len {
+        Some(len) if len <= MAX_AUDIT_BODY_BYTES => {
+            let (parts, body) = request.into_parts();
+            let bytes = to_bytes(body, MAX_AUDIT_BODY_BYTES)
+                .await
+                .map_err(|_| AppError::BadRequest("Request body too large".to_string()))?;
+            let request_body = serde_json::from_slice::<JsonValue>(&bytes).ok();
+            (Request::from_parts(parts, Body::from(bytes)), request_body)
+        }
+        _ => (request, None),
+    };
+
+    let mut response = next.run(request).await;
+
+    let (action, target_type, target_id, changes) =
+        match response.extensions_mut().remove::<AuditEvent>() {
+            Some(event) => (
+                event.action,
+                Some(event.target_type),
+                event.target_id,
+                diff_changes(event.before.as_ref(), event.after.as_ref()),
+            ),
+            None => {
+                let template = matched_path.as_deref().unwrap_or(&path);
+                (
+                    format!("{} {}", method, template),
+                    None,
+                    path_param(template, &path),
+                    request_body.map(|body| json!({ "request": redact(&body) })),
+                )
+            }
+        };
+
+    let entry = AuditLogEntry {
+        id: Uuid::new_v4().to_string(),
+        timestamp: Utc::now(),
+        actor,
+        actor_role,
+        action,
+        target_type,
+        target_id,
+        method,
+        path,
+        status: response.status().as_u16(),
+        ip,
+        changes,
+    };
+    if let Err(e) = audit_service.record(&entry).await {
+        warn!("⚠️  Failed to record audit log for {}: {}", entry.action, e);
+    }
+
+    Ok(response)
+}
+
+/// 取路由模板中第一个 `:param` 对应的实际路径段
+fn path_param(template: &str, path: &str) -> Option<String> {
+    let template_segments: Vec<&str> = template.split('/').collect();
+    let path_segments: Vec<&str> = path.split('/').collect();
+    let offset = path_segments.len().checked_sub(template_segments.len())?;
+
+    template_segments
+        .iter()
+        .position(|segment| segment.starts_with(':'))
+        .and_then(|index| path_segments.get(index + offset))
+        .map(|segment| segment.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_param() {
+        assert_eq!(
+            path_param("/admin/api-keys/:keyId", "/admin/api-keys/abc"),
+            Some("abc".to_string())
+        );
+        // 嵌套路由时模板可能不含前缀
+        assert_eq!(
+            path_param("/users/:id/role", "/admin/users/u1/role"),
+            Some("u1".to_string())
+        );
+        assert_eq!(path_param("/api-keys/batch", "/admin/api-keys/batch"), None);
+    }
+}
@@ -1,14 +1,22 @@
 use axum::{
-    extract::{Request, State},
+    body::Body,
+    extract::{ConnectInfo, Request, State},
     http::header,
     middleware::Next,
     response::Response,
 };
+use futures::StreamExt;
+use std::net::SocketAddr;
 use std::sync::Arc;
 
+use crate::middleware::metrics::AuthenticatedRequest;
+use crate::models::admin_token::ADMIN_API_TOKEN_PREFIX;
 use crate::models::api_key::ApiKey;
-use crate::services::{AdminService, ApiKeyService, Claims};
+use crate::models::role::Permission;
+use crate::services::{AdminService, ApiKeyService, Claims, RoleService};
+use crate::utils::client_ip::extract_client_ip;
 use crate::utils::error::AppError;
+use crate::utils::metrics::{self, InflightGuard};
 
 /// API Key 认证状态
 ///
@@ -60,14 +68,69 @@ pub async fn authenticate_api_key(
     // 4. 检查速率限制
     service.check_rate_limit(&validated_key).await?;
 
-    // 5. 存储认证状态到请求扩展
+    // 5. 获取并发槽位 (启用排队时可能在此等待)
+    let concurrency_guard = if validated_key.concurrency_limit > 0 {
+        let request_id = uuid::Uuid::new_v4().to_string();
+        service
+            .increment_concurrency(&validated_key, &request_id)
+            .await?;
+        Some(ConcurrencyGuard {
+            service: service.clone(),
+            api_key: validated_key.clone(),
+            request_id,
+        })
+    } else {
+        None
+    };
+
+    let inflight_guard = InflightGuard::new(
+        &metrics::API_KEY_CONCURRENCY,
+        &[metrics::key_label(&validated_key.id)],
+    );
+
+    // 6. 存储认证状态到请求扩展
     let auth_state = AuthState {
         api_key: validated_key,
     };
     request.extensions_mut().insert(auth_state);
 
-    // 6. 继续处理请求
-    Ok(next.run(request).await)
+    // 7. 继续处理请求
+    let response = next.run(request).await;
+
+    // 8. 并发槽位和并发指标在响应体 (包括流式响应) 发送完毕或被丢弃时释放
+    let (mut parts, body) = response.into_parts();
+    parts.extensions.insert(AuthenticatedRequest);
+    let stream = body.into_data_stream().map(move |chunk| {
+        let _ = (&concurrency_guard, &inflight_guard);
+        chunk
+    });
+    Ok(Response::from_parts(parts, Body::from_stream(stream)))
+}
+
+/// API Key 并发槽位守卫
+///
+/// Drop 时异步释放并发计数
+struct ConcurrencyGuard {
+    service: Arc<ApiKeyService>,
+    api_key: ApiKey,
+    request_id: String,
+}
+
+impl Drop for ConcurrencyGuard {
+    fn drop(&mut self) {
+        let service = self.service.clone();
+        let api_key = self.api_key.clone();
+        let request_id = std::mem::take(&mut self.request_id);
+        tokio::spawn(async move {
+            if let Err(e) = service.decrement_concurrency(&api_key, &request_id).await {
+                tracing::warn!(
+                    "⚠️ Failed to release concurrency slot for key {}: {}",
+                    api_key.id,
+                    e
+                );
+            }
+        });
+    }
 }
 
 /// 解析 Bearer token
@@ -199,6 +262,8 @@ mod tests {
 pub struct JwtAuthState {
     /// 已验证的 JWT Claims
     pub claims: Claims,
+    /// 管理 API 令牌的权限范围 (JWT 登录时为 None，按角色取权限)
+    pub scopes: Option<Vec<Permission>>,
 }
 
 /// JWT 认证中间件
@@ -210,7 +275,7 @@ pub struct JwtAuthState {
 ///
 /// 1. 提取 Authorization header
 /// 2. 解析 Bearer token
-/// 3. 验证 JWT token
+/// 3. 验证 JWT token 及其会话
 /// 4. 将 Claims 存储到请求扩展
 ///
 /// # 错误处理
@@ -219,6 +284,7 @@ pub struct JwtAuthState {
 /// - 格式错误: 401 Unauthorized
 /// - Token 无效: 401 Unauthorized
 /// - Token 已过期: 401 Unauthorized
+/// - 会话已登出或令牌版本已变化: 401 Unauthorized
 pub async fn authenticate_jwt(
     State(service): State<Arc<AdminService>>,
     mut request: Request,
@@ -234,17 +300,77 @@ pub async fn authenticate_jwt(
     // 2. 解析 Bearer token
     let token = parse_bearer_token(auth_header)?;
 
-    // 3. 验证 JWT token
+    // 3. 验证 JWT token，并确认会话未被撤销
     let claims = service.verify_token(&token)?;
+    service.validate_session(&claims).await?;
 
     // 4. 存储认证状态到请求扩展
-    let jwt_state = JwtAuthState { claims };
+    let jwt_state = JwtAuthState {
+        claims,
+        scopes: None,
+    };
     request.extensions_mut().insert(jwt_state);
 
     // 5. 继续处理请求
     Ok(next.run(request).await)
 }
 
+/// 使用管理 API 令牌时 Claims 中的角色名 (不对应任何角色)
+pub const API_TOKEN_ROLE: &str = "api_token";
+
+/// 管理接口认证中间件
+///
+/// 除 JWT 外还接受 `cra_` 开头的长期管理 API 令牌。
+/// 令牌校验过期时间和 IP 白名单，通过后以 `api-token:{名称}` 作为操作者，
+/// 权限只取令牌声明的 scopes
+pub async fn authenticate_admin(
+    State(service): State<Arc<AdminService>>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let auth_header = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .ok_or_else(|| AppError::Unauthorized("Missing Authorization header".to_string()))?;
+    let token = parse_bearer_token(auth_header)?;
+
+    if !token.starts_with(ADMIN_API_TOKEN_PREFIX) {
+        return authenticate_jwt(State(service), request, next).await;
+    }
+
+    let client_ip = extract_client_ip(
+        request.headers(),
+        request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| *addr),
+    );
+    let api_token = service
+        .api_token_service()
+        .authenticate(&token, &client_ip)
+        .await?;
+
+    let now = chrono::Utc::now().timestamp() as usize;
+    let jwt_state = JwtAuthState {
+        claims: Claims {
+            sub: format!("api-token:{}", api_token.name),
+            role: API_TOKEN_ROLE.to_string(),
+            exp: api_token
+                .expires_at
+                .map(|at| at.timestamp() as usize)
+                .unwrap_or(usize::MAX),
+            iat: now,
+            sid: String::new(),
+            ver: 0,
+        },
+        scopes: Some(api_token.scopes),
+    };
+    request.extensions_mut().insert(jwt_state);
+
+    Ok(next.run(request).await)
+}
+
 /// 从请求扩展中提取 JWT 认证状态
 ///
 /// # 参数
@@ -279,3 +405,53 @@ pub fn require_admin_role(request: &Request) -> Result<(), AppError> {
 
     Ok(())
 }
+
+/// 管理员角色中间件
+///
+/// 需放在 `authenticate_jwt` 之后，拒绝普通用户的 JWT 访问管理接口
+pub async fn require_admin(request: Request, next: Next) -> Result<Response, AppError> {
+    require_admin_role(&request)?;
+    Ok(next.run(request).await)
+}
+
+/// 权限检查配置
+///
+/// `permission` 为 None 时只要求角色拥有任意管理权限
+#[derive(Clone)]
+pub struct PermissionGuard {
+    pub role_service: Arc<RoleService>,
+    pub permission: Option<Permission>,
+}
+
+/// 权限中间件
+///
+/// 需放在 `authenticate_jwt` 之后，按 JWT 中的角色查找权限 (管理 API 令牌使用其 scopes)，
+/// 普通用户角色没有任何管理权限
+pub async fn require_permission(
+    State(guard): State<PermissionGuard>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let jwt_state = extract_jwt_state(&request)
+        .ok_or_else(|| AppError::Unauthorized("Missing JWT authentication".to_string()))?;
+
+    let permissions = match jwt_state.scopes {
+        Some(scopes) => scopes,
+        None => {
+            guard
+                .role_service
+                .permissions_for(&jwt_state.claims.role)
+                .await?
+        }
+    };
+
+    match guard.permission {
+        Some(permission) if !permissions.contains(&permission) => Err(AppError::Forbidden(
+            format!("Permission {} required", permission.as_str()),
+        )),
+        None if permissions.is_empty() => {
+            Err(AppError::Forbidden("Admin role required".to_string()))
+        }
+        _ => Ok(next.run(request).await),
+    }
+}
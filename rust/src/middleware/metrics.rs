@@ -0,0 +1,116 @@
+use axum::{
+    body::Body,
+    extract::{MatchedPath, Request, State},
+    http::{header, Method},
+    middleware::Next,
+    response::Response,
+};
+use futures::StreamExt;
+use serde::Deserialize;
+use std::time::Instant;
+
+use crate::utils::metrics::{self, InflightGuard};
+
+/// 读取请求体解析模型的最大长度 (与 axum 默认请求体上限一致)
+const MAX_MODEL_PEEK_BYTES: usize = 2 * 1024 * 1024;
+
+#[derive(Deserialize)]
+struct ModelField {
+    model: Option<String>,
+}
+
+/// 响应扩展中的认证标记，由 API Key 认证中间件在认证通过后写入
+#[derive(Clone, Copy, Debug)]
+pub struct AuthenticatedRequest;
+
+/// 转发接口指标中间件
+///
+/// 按平台、路由、模型和状态码记录请求数和延迟 (流式响应计到响应头发出为止)，
+/// 并统计进行中的流式响应。中间件状态为平台名，如 `claude`
+///
+/// 认证失败的请求也会记录，但模型标签固定为 `unknown`，未认证的调用方不能制造新的序列
+pub async fn track_relay_metrics(
+    State(platform): State<&'static str>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let started = Instant::now();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let (request, model) = peek_model(request).await;
+
+    let response = next.run(request).await;
+
+    let authenticated = response
+        .extensions()
+        .get::<AuthenticatedRequest>()
+        .is_some();
+    let model = if authenticated {
+        model.unwrap_or_default()
+    } else {
+        metrics::UNKNOWN_MODEL_LABEL.to_string()
+    };
+
+    let status = response.status().as_u16().to_string();
+    metrics::REQUESTS.inc(&[platform, &route, &model, &status]);
+    metrics::REQUEST_DURATION.observe(&[platform, &route, &model], started.elapsed().as_secs_f64());
+
+    let is_stream = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/event-stream"));
+    if !is_stream {
+        return response;
+    }
+
+    // 流式响应在响应体发送完毕或被丢弃时结束
+    let guard = InflightGuard::new(&metrics::ACTIVE_STREAMS, &[platform]);
+    let (parts, body) = response.into_parts();
+    let stream = body.into_data_stream().map(move |chunk| {
+        let _ = &guard;
+        chunk
+    });
+    Response::from_parts(parts, Body::from_stream(stream))
+}
+
+/// 从请求中取出模型名：Gemini 路径 `models/{model}:operation` 或 JSON 请求体的 `model` 字段
+///
+/// 只读取声明了 Content-Length 且不超过上限的请求体，读取后原样放回
+async fn peek_model(request: Request) -> (Request, Option<String>) {
+    if let Some(model) = request
+        .uri()
+        .path()
+        .split("/models/")
+        .nth(1)
+        .and_then(|rest| rest.split(':').next())
+    {
+        let model = model.to_string();
+        return (request, Some(model));
+    }
+
+    let content_length = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    if request.method() != Method::POST
+        || content_length.is_none_or(|len| len > MAX_MODEL_PEEK_BYTES)
+    {
+        return (request, None);
+    }
+
+    let (parts, body) = request.into_parts();
+    match axum::body::to_bytes(body, MAX_MODEL_PEEK_BYTES).await {
+        Ok(bytes) => {
+            let model = serde_json::from_slice::<ModelField>(&bytes)
+                .ok()
+                .and_then(|field| field.model);
+            (Request::from_parts(parts, Body::from(bytes)), model)
+        }
+        Err(_) => (Request::from_parts(parts, Body::empty()), None),
+    }
+}
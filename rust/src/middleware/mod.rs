@@ -1,6 +1,11 @@
+pub mod audit;
 pub mod auth;
+pub mod metrics;
 
+pub use audit::audit_admin_request;
 pub use auth::{
-    authenticate_api_key, authenticate_jwt, extract_auth_state, extract_jwt_state,
-    optional_authenticate_api_key, require_admin_role, AuthState, JwtAuthState,
+    authenticate_admin, authenticate_api_key, authenticate_jwt, extract_auth_state,
+    extract_jwt_state, optional_authenticate_api_key, require_admin, require_admin_role,
+    require_permission, AuthState, JwtAuthState, PermissionGuard, API_TOKEN_ROLE,
 };
+pub use metrics::track_relay_metrics;
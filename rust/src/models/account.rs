@@ -332,6 +332,8 @@ mod tests {
             claude_ai_oauth: None,
             access_token: None,
             refresh_token: None,
+            session_token: None,
+            custom_api_endpoint: None,
             expires_at: Some((now + 5000).to_string()), // 5 秒后过期
             scopes: None,
             proxy: None,
@@ -378,6 +380,8 @@ mod tests {
             claude_ai_oauth: None,
             access_token: None,
             refresh_token: None,
+            session_token: None,
+            custom_api_endpoint: None,
             expires_at: None,
             scopes: None,
             proxy: None,
@@ -427,6 +431,8 @@ mod tests {
             claude_ai_oauth: None,
             access_token: None,
             refresh_token: None,
+            session_token: None,
+            custom_api_endpoint: None,
             expires_at: None,
             scopes: Some("user:profile claude:conversations".to_string()),
             proxy: None,
@@ -0,0 +1,203 @@
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::models::api_key::{ModelUsage, UsagePeriod};
+
+/// 会话窗口长度 (小时)，与 Claude 官方 5 小时用量窗口一致
+pub const SESSION_WINDOW_HOURS: i64 = 5;
+
+/// 账户累计使用量 `account_usage:{account_id}`
+pub fn account_usage_key(account_id: &str) -> String {
+    format!("account_usage:{}", account_id)
+}
+
+/// 账户在某个周期内按模型的使用量
+/// `account_usage:model:{daily|monthly}:{account_id}:{bucket}:{model}`
+pub fn account_model_usage_key(
+    account_id: &str,
+    period: UsagePeriod,
+    bucket: &str,
+    model: &str,
+) -> String {
+    format!(
+        "account_usage:model:{}:{}:{}:{}",
+        period.as_str(),
+        account_id,
+        bucket,
+        model
+    )
+}
+
+/// 账户在某个周期内使用过的模型集合
+pub fn account_models_key(account_id: &str, period: UsagePeriod, bucket: &str) -> String {
+    format!(
+        "account_usage:models:{}:{}:{}",
+        period.as_str(),
+        account_id,
+        bucket
+    )
+}
+
+/// 账户当前会话窗口的起始时间戳 `session_window:{account_id}`，窗口结束时过期
+pub fn session_window_key(account_id: &str) -> String {
+    format!("session_window:{}", account_id)
+}
+
+/// 新会话窗口的起止时间：从请求所在整点开始，持续 5 小时
+pub fn session_window_for(at: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+    let start = at.duration_trunc(Duration::hours(1)).unwrap_or(at);
+    (start, start + Duration::hours(SESSION_WINDOW_HOURS))
+}
+
+/// 前端展示用的使用量合计
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountUsageTotals {
+    pub requests: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_create_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub all_tokens: i64,
+    pub cost: f64,
+}
+
+impl From<&ModelUsage> for AccountUsageTotals {
+    fn from(usage: &ModelUsage) -> Self {
+        Self {
+            requests: usage.requests,
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+            cache_create_tokens: usage.cache_creation_tokens,
+            cache_read_tokens: usage.cache_read_tokens,
+            all_tokens: usage.total_tokens(),
+            cost: usage.cost,
+        }
+    }
+}
+
+/// 自首次使用以来的平均速率
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UsageAverages {
+    pub rpm: f64,
+    pub tpm: f64,
+}
+
+impl UsageAverages {
+    pub fn since(
+        total: &ModelUsage,
+        first_used_at: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let Some(first_used_at) = first_used_at else {
+            return Self::default();
+        };
+        let minutes = (now - first_used_at).num_minutes().max(1) as f64;
+        Self {
+            rpm: round2(total.requests as f64 / minutes),
+            tpm: round2(total.total_tokens() as f64 / minutes),
+        }
+    }
+}
+
+/// 当前 5 小时会话窗口内的使用量
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionWindowUsage {
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    pub remaining_seconds: i64,
+    pub total_requests: i64,
+    pub total_tokens: i64,
+    pub total_cost: f64,
+}
+
+impl SessionWindowUsage {
+    pub fn new(window_start: DateTime<Utc>, usage: &ModelUsage, now: DateTime<Utc>) -> Self {
+        let window_end = window_start + Duration::hours(SESSION_WINDOW_HOURS);
+        Self {
+            window_start,
+            window_end,
+            remaining_seconds: (window_end - now).num_seconds().max(0),
+            total_requests: usage.requests,
+            total_tokens: usage.total_tokens(),
+            total_cost: usage.cost,
+        }
+    }
+}
+
+/// 单个上游账户的使用量和成本汇总
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountUsageSummary {
+    pub account_id: String,
+    pub daily: AccountUsageTotals,
+    pub monthly: AccountUsageTotals,
+    pub total: AccountUsageTotals,
+    pub averages: UsageAverages,
+    /// 没有活跃窗口时为 None
+    pub session_window: Option<SessionWindowUsage>,
+    pub daily_models: BTreeMap<String, AccountUsageTotals>,
+    pub monthly_models: BTreeMap<String, AccountUsageTotals>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_account_usage_keys() {
+        assert_eq!(account_usage_key("a1"), "account_usage:a1");
+        assert_eq!(
+            account_model_usage_key("a1", UsagePeriod::Daily, "2025-01-31", "claude-sonnet-4"),
+            "account_usage:model:daily:a1:2025-01-31:claude-sonnet-4"
+        );
+        assert_eq!(
+            account_models_key("a1", UsagePeriod::Monthly, "2025-01"),
+            "account_usage:models:monthly:a1:2025-01"
+        );
+    }
+
+    #[test]
+    fn test_session_window_starts_on_the_hour() {
+        let at: DateTime<Utc> = "2025-01-31T16:42:10Z".parse().unwrap();
+        let (start, end) = session_window_for(at);
+        assert_eq!(start.to_rfc3339(), "2025-01-31T16:00:00+00:00");
+        assert_eq!(end.to_rfc3339(), "2025-01-31T21:00:00+00:00");
+
+        let usage = ModelUsage {
+            requests: 2,
+            input_tokens: 100,
+            output_tokens: 50,
+            cost: 0.5,
+            ..Default::default()
+        };
+        let window = SessionWindowUsage::new(start, &usage, at);
+        assert_eq!(window.total_tokens, 150);
+        assert_eq!(window.remaining_seconds, 4 * 3600 + 17 * 60 + 50);
+    }
+
+    #[test]
+    fn test_usage_averages() {
+        let now: DateTime<Utc> = "2025-01-31T12:00:00Z".parse().unwrap();
+        let total = ModelUsage {
+            requests: 120,
+            input_tokens: 6000,
+            ..Default::default()
+        };
+        assert_eq!(
+            UsageAverages::since(&total, None, now),
+            UsageAverages::default()
+        );
+
+        let averages = UsageAverages::since(&total, Some(now - Duration::hours(1)), now);
+        assert_eq!(averages.rpm, 2.0);
+        assert_eq!(averages.tpm, 100.0);
+    }
+}
@@ -0,0 +1,155 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+
+use crate::models::role::Permission;
+use crate::utils::client_ip::ip_matches;
+pub use crate::utils::client_ip::is_valid_ip_entry;
+
+/// 管理 API 令牌前缀 (用于在认证中间件中与 JWT 区分)
+pub const ADMIN_API_TOKEN_PREFIX: &str = "cra_";
+
+/// 长期有效的管理 API 令牌
+///
+/// 供 CI 和自动化脚本调用管理接口，权限由 `scopes` 显式声明。
+/// 创建者被禁用、角色变化或重置密码 (令牌版本递增) 后令牌随之失效。
+/// 存储在 `admin_api_token:{id}`，明文只在创建时返回一次
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminApiToken {
+    pub id: String,
+
+    pub name: String,
+
+    #[serde(default)]
+    pub description: String,
+
+    pub scopes: Vec<Permission>,
+
+    /// 令牌明文的 SHA-256 哈希 (对外返回时清空)
+    #[serde(
+        rename = "tokenHash",
+        default,
+        skip_serializing_if = "String::is_empty"
+    )]
+    pub token_hash: String,
+
+    /// 明文前几位，便于在列表中辨认
+    #[serde(rename = "tokenPrefix")]
+    pub token_prefix: String,
+
+    /// 允许使用的客户端 IP 或 CIDR，为空表示不限制
+    #[serde(rename = "ipAllowlist", default)]
+    pub ip_allowlist: Vec<String>,
+
+    #[serde(rename = "createdBy")]
+    pub created_by: String,
+
+    /// 创建时创建者的令牌版本，与当前版本不一致时令牌失效
+    #[serde(rename = "creatorTokenVersion", default)]
+    pub creator_token_version: u64,
+
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+
+    #[serde(rename = "expiresAt", skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+
+    #[serde(rename = "lastUsedAt", skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<DateTime<Utc>>,
+
+    #[serde(rename = "lastUsedIp", skip_serializing_if = "Option::is_none")]
+    pub last_used_ip: Option<String>,
+}
+
+impl AdminApiToken {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|at| at <= Utc::now())
+    }
+
+    /// 客户端 IP 是否在白名单内 (支持单个 IP 和 CIDR)
+    pub fn allows_ip(&self, ip: &str) -> bool {
+        if self.ip_allowlist.is_empty() {
+            return true;
+        }
+        let Ok(ip) = ip.parse::<IpAddr>() else {
+            return false;
+        };
+
+        self.ip_allowlist.iter().any(|entry| ip_matches(entry, ip))
+    }
+
+    /// 去掉哈希后的副本，用于接口返回
+    pub fn public_view(&self) -> Self {
+        Self {
+            token_hash: String::new(),
+            ..self.clone()
+        }
+    }
+}
+
+/// 创建管理 API 令牌请求
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateAdminApiTokenRequest {
+    pub name: String,
+
+    #[serde(default)]
+    pub description: Option<String>,
+
+    pub scopes: Vec<Permission>,
+
+    #[serde(rename = "expiresAt", default)]
+    pub expires_at: Option<DateTime<Utc>>,
+
+    #[serde(rename = "ipAllowlist", default)]
+    pub ip_allowlist: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token_with_allowlist(allowlist: &[&str]) -> AdminApiToken {
+        AdminApiToken {
+            id: "t1".to_string(),
+            name: "ci".to_string(),
+            description: String::new(),
+            scopes: vec![Permission::KeysWrite],
+            token_hash: "hash".to_string(),
+            token_prefix: "cra_abcd".to_string(),
+            ip_allowlist: allowlist.iter().map(|s| s.to_string()).collect(),
+            created_by: "admin".to_string(),
+            creator_token_version: 0,
+            created_at: Utc::now(),
+            expires_at: None,
+            last_used_at: None,
+            last_used_ip: None,
+        }
+    }
+
+    #[test]
+    fn test_ip_allowlist() {
+        assert!(token_with_allowlist(&[]).allows_ip("203.0.113.9"));
+
+        let token = token_with_allowlist(&["10.0.0.0/8", "203.0.113.9", "2001:db8::/32"]);
+        assert!(token.allows_ip("10.1.2.3"));
+        assert!(token.allows_ip("203.0.113.9"));
+        assert!(token.allows_ip("2001:db8::1"));
+        assert!(!token.allows_ip("11.0.0.1"));
+        assert!(!token.allows_ip("203.0.113.10"));
+        assert!(!token.allows_ip("unknown"));
+
+        assert!(token_with_allowlist(&["0.0.0.0/0"]).allows_ip("8.8.8.8"));
+    }
+
+    #[test]
+    fn test_ip_entry_validation_and_public_view() {
+        assert!(is_valid_ip_entry("192.168.0.0/16"));
+        assert!(is_valid_ip_entry("::1"));
+        assert!(!is_valid_ip_entry("192.168.0.0/33"));
+        assert!(!is_valid_ip_entry("example.com"));
+
+        let json = serde_json::to_value(token_with_allowlist(&[]).public_view()).unwrap();
+        assert!(json.get("tokenHash").is_none());
+        assert_eq!(json["scopes"][0], "keys:write");
+    }
+}
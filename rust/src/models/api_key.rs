@@ -1,11 +1,12 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, FixedOffset, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// API Key 权限类型
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum ApiKeyPermissions {
+    #[default]
     All,
     Claude,
     Gemini,
@@ -31,42 +32,26 @@ impl ApiKeyPermissions {
     }
 }
 
-impl Default for ApiKeyPermissions {
-    fn default() -> Self {
-        Self::All
-    }
-}
-
 /// 过期模式
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum ExpirationMode {
     /// 固定时间过期
+    #[default]
     Fixed,
     /// 首次使用后激活计时
     Activation,
 }
 
-impl Default for ExpirationMode {
-    fn default() -> Self {
-        Self::Fixed
-    }
-}
-
 /// 激活时间单位
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum ActivationUnit {
     Hours,
+    #[default]
     Days,
 }
 
-impl Default for ActivationUnit {
-    fn default() -> Self {
-        Self::Days
-    }
-}
-
 /// API Key 完整数据结构
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiKey {
@@ -140,6 +125,18 @@ pub struct ApiKey {
     /// 并发限制
     pub concurrency_limit: i64,
 
+    /// 启用并发排队 (超过并发限制时排队等待而非直接拒绝)
+    #[serde(default)]
+    pub concurrency_queue_enabled: bool,
+
+    /// 并发排队最大长度
+    #[serde(default = "default_concurrency_queue_max_size")]
+    pub concurrency_queue_max_size: i64,
+
+    /// 并发排队最长等待时间 (毫秒)
+    #[serde(default = "default_concurrency_queue_timeout_ms")]
+    pub concurrency_queue_timeout_ms: i64,
+
     /// 速率限制窗口 (秒)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub rate_limit_window: Option<i64>,
@@ -237,6 +234,15 @@ pub struct ApiKeyCreateOptions {
     #[serde(default)]
     pub concurrency_limit: i64,
 
+    #[serde(default)]
+    pub concurrency_queue_enabled: bool,
+
+    #[serde(default = "default_concurrency_queue_max_size")]
+    pub concurrency_queue_max_size: i64,
+
+    #[serde(default = "default_concurrency_queue_timeout_ms")]
+    pub concurrency_queue_timeout_ms: i64,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub rate_limit_window: Option<i64>,
 
@@ -325,6 +331,9 @@ impl Default for ApiKeyCreateOptions {
             is_active: true,
             token_limit: 0,
             concurrency_limit: 0,
+            concurrency_queue_enabled: false,
+            concurrency_queue_max_size: DEFAULT_CONCURRENCY_QUEUE_MAX_SIZE,
+            concurrency_queue_timeout_ms: DEFAULT_CONCURRENCY_QUEUE_TIMEOUT_MS,
             rate_limit_window: None,
             rate_limit_requests: None,
             rate_limit_cost: None,
@@ -354,6 +363,35 @@ impl Default for ApiKeyCreateOptions {
     }
 }
 
+/// API Key 历史记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyHistoryEntry {
+    /// 操作类型 (如 "rotated")
+    pub action: String,
+    /// 操作时间
+    pub timestamp: DateTime<Utc>,
+    /// 操作者
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actor: Option<String>,
+    /// 操作详情
+    #[serde(default)]
+    pub details: serde_json::Value,
+}
+
+/// 并发排队默认最大长度
+pub const DEFAULT_CONCURRENCY_QUEUE_MAX_SIZE: i64 = 10;
+
+/// 并发排队默认最长等待时间 (毫秒)
+pub const DEFAULT_CONCURRENCY_QUEUE_TIMEOUT_MS: i64 = 30_000;
+
+fn default_concurrency_queue_max_size() -> i64 {
+    DEFAULT_CONCURRENCY_QUEUE_MAX_SIZE
+}
+
+fn default_concurrency_queue_timeout_ms() -> i64 {
+    DEFAULT_CONCURRENCY_QUEUE_TIMEOUT_MS
+}
+
 /// API Key 使用统计
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ApiKeyUsageStats {
@@ -362,9 +400,17 @@ pub struct ApiKeyUsageStats {
     pub total_output_tokens: i64,
     pub total_cache_creation_tokens: i64,
     pub total_cache_read_tokens: i64,
+    /// 上游成本
     pub total_cost: f64,
     pub daily_cost: f64,
     pub weekly_opus_cost: f64,
+    /// 对外计费成本，成本限额按计费成本检查
+    #[serde(default)]
+    pub total_billed_cost: f64,
+    #[serde(default)]
+    pub daily_billed_cost: f64,
+    #[serde(default)]
+    pub weekly_opus_billed_cost: f64,
     pub last_used_at: Option<DateTime<Utc>>,
     pub usage_by_model: HashMap<String, ModelUsage>,
 }
@@ -377,7 +423,195 @@ pub struct ModelUsage {
     pub output_tokens: i64,
     pub cache_creation_tokens: i64,
     pub cache_read_tokens: i64,
+    /// 上游成本
     pub cost: f64,
+    /// 对外计费成本 (上游成本 × 计费倍率)
+    #[serde(default)]
+    pub billed_cost: f64,
+}
+
+impl ModelUsage {
+    /// 所有类型 Token 之和
+    pub fn total_tokens(&self) -> i64 {
+        self.input_tokens + self.output_tokens + self.cache_creation_tokens + self.cache_read_tokens
+    }
+
+    /// 累加另一份使用量
+    pub fn add(&mut self, other: &ModelUsage) {
+        self.requests += other.requests;
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_tokens += other.cache_creation_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+        self.cost += other.cost;
+        self.billed_cost += other.billed_cost;
+    }
+}
+
+/// API Key 并发排队统计
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ConcurrencyQueueStats {
+    /// 当前并发请求数
+    pub active_requests: i64,
+    /// 当前排队长度
+    pub queue_depth: i64,
+    /// 累计排队请求数
+    pub total_queued: i64,
+    /// 排队后成功获取并发槽位的请求数
+    pub total_acquired: i64,
+    /// 因队列已满被拒绝的请求数
+    pub total_rejected: i64,
+    /// 等待超时的请求数
+    pub total_timeouts: i64,
+    /// 平均等待时间 (毫秒)
+    pub avg_wait_ms: f64,
+    /// 最长等待时间 (毫秒)
+    pub max_wait_ms: i64,
+}
+
+/// 按时间段统计的周期
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum UsagePeriod {
+    #[default]
+    Daily,
+    Monthly,
+}
+
+impl UsagePeriod {
+    /// Redis 键中使用的周期名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UsagePeriod::Daily => "daily",
+            UsagePeriod::Monthly => "monthly",
+        }
+    }
+
+    /// 指定时间在系统时区下所在的统计桶，如 `2025-01-31` 或 `2025-01`
+    pub fn bucket(&self, at: DateTime<Utc>, timezone: FixedOffset) -> String {
+        let at = at.with_timezone(&timezone);
+        match self {
+            UsagePeriod::Daily => at.format("%Y-%m-%d").to_string(),
+            UsagePeriod::Monthly => at.format("%Y-%m").to_string(),
+        }
+    }
+
+    /// 统计数据保留时间 (秒)
+    pub fn retention_seconds(&self) -> i64 {
+        match self {
+            UsagePeriod::Daily => 32 * 24 * 3600,
+            UsagePeriod::Monthly => 400 * 24 * 3600,
+        }
+    }
+}
+
+/// 速率限制窗口状态
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct RateLimitWindowState {
+    /// 窗口长度 (秒)
+    pub window_seconds: i64,
+    /// 窗口内允许的最大请求数
+    pub max_requests: i64,
+    /// 当前窗口内已用请求数
+    pub current_requests: i64,
+    /// 当前窗口开始时间，窗口未开始时为 None
+    pub window_start: Option<DateTime<Utc>>,
+    /// 当前窗口重置时间
+    pub reset_at: Option<DateTime<Utc>>,
+}
+
+/// API Key 批量更新内容
+///
+/// 所有字段均为可选，未提供的字段保持不变
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ApiKeyBulkUpdate {
+    #[serde(default, rename = "isActive")]
+    pub is_active: Option<bool>,
+    /// 替换全部标签
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+    /// 追加标签
+    #[serde(default, rename = "addTags")]
+    pub add_tags: Vec<String>,
+    /// 移除标签
+    #[serde(default, rename = "removeTags")]
+    pub remove_tags: Vec<String>,
+    #[serde(default, rename = "tokenLimit")]
+    pub token_limit: Option<i64>,
+    #[serde(default, rename = "concurrencyLimit")]
+    pub concurrency_limit: Option<i64>,
+    #[serde(default, rename = "rateLimitWindow")]
+    pub rate_limit_window: Option<i64>,
+    #[serde(default, rename = "rateLimitRequests")]
+    pub rate_limit_requests: Option<i64>,
+    #[serde(default, rename = "rateLimitCost")]
+    pub rate_limit_cost: Option<f64>,
+    #[serde(default, rename = "dailyCostLimit")]
+    pub daily_cost_limit: Option<f64>,
+    #[serde(default, rename = "totalCostLimit")]
+    pub total_cost_limit: Option<f64>,
+    #[serde(default, rename = "weeklyOpusCostLimit")]
+    pub weekly_opus_cost_limit: Option<f64>,
+}
+
+impl ApiKeyBulkUpdate {
+    /// 将更新内容应用到 ApiKey
+    pub fn apply(&self, api_key: &mut ApiKey) {
+        if let Some(is_active) = self.is_active {
+            api_key.is_active = is_active;
+        }
+
+        if let Some(tags) = &self.tags {
+            api_key.tags = tags.clone();
+        }
+        for tag in &self.add_tags {
+            if !api_key.tags.contains(tag) {
+                api_key.tags.push(tag.clone());
+            }
+        }
+        api_key.tags.retain(|tag| !self.remove_tags.contains(tag));
+
+        if let Some(limit) = self.token_limit {
+            api_key.token_limit = limit;
+        }
+        if let Some(limit) = self.concurrency_limit {
+            api_key.concurrency_limit = limit;
+        }
+        if let Some(window) = self.rate_limit_window {
+            api_key.rate_limit_window = Some(window);
+        }
+        if let Some(requests) = self.rate_limit_requests {
+            api_key.rate_limit_requests = Some(requests);
+        }
+        if let Some(cost) = self.rate_limit_cost {
+            api_key.rate_limit_cost = Some(cost);
+        }
+        if let Some(limit) = self.daily_cost_limit {
+            api_key.daily_cost_limit = limit;
+        }
+        if let Some(limit) = self.total_cost_limit {
+            api_key.total_cost_limit = limit;
+        }
+        if let Some(limit) = self.weekly_opus_cost_limit {
+            api_key.weekly_opus_cost_limit = limit;
+        }
+    }
+}
+
+/// 批量操作中单个 Key 的失败信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkOperationFailure {
+    pub id: String,
+    pub error: String,
+}
+
+/// 批量操作结果
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct BulkOperationResult {
+    /// 成功处理的 Key ID
+    pub succeeded: Vec<String>,
+    /// 处理失败的 Key 及原因
+    pub failed: Vec<BulkOperationFailure>,
 }
 
 #[cfg(test)]
@@ -418,4 +652,109 @@ mod tests {
         assert_eq!(options.token_limit, 0);
         assert_eq!(options.concurrency_limit, 0);
     }
+
+    #[test]
+    fn test_bulk_update_apply_tags() {
+        let now = Utc::now();
+        let mut api_key = ApiKey {
+            id: "key-1".to_string(),
+            key: None,
+            key_hash: "hash".to_string(),
+            name: "Class A 01".to_string(),
+            description: None,
+            icon: None,
+            created_at: now,
+            updated_at: now,
+            expires_at: None,
+            activated_at: None,
+            last_used_at: None,
+            is_active: true,
+            is_deleted: false,
+            deleted_at: None,
+            deleted_by: None,
+            deleted_by_type: None,
+            permissions: ApiKeyPermissions::All,
+            token_limit: 0,
+            concurrency_limit: 0,
+            concurrency_queue_enabled: false,
+            concurrency_queue_max_size: DEFAULT_CONCURRENCY_QUEUE_MAX_SIZE,
+            concurrency_queue_timeout_ms: DEFAULT_CONCURRENCY_QUEUE_TIMEOUT_MS,
+            rate_limit_window: None,
+            rate_limit_requests: None,
+            rate_limit_cost: None,
+            daily_cost_limit: 0.0,
+            total_cost_limit: 0.0,
+            weekly_opus_cost_limit: 0.0,
+            expiration_mode: ExpirationMode::Fixed,
+            activation_days: 0,
+            activation_unit: ActivationUnit::Days,
+            enable_model_restriction: false,
+            restricted_models: vec![],
+            enable_client_restriction: false,
+            allowed_clients: vec![],
+            claude_account_id: None,
+            claude_console_account_id: None,
+            gemini_account_id: None,
+            openai_account_id: None,
+            bedrock_account_id: None,
+            azure_openai_account_id: None,
+            droid_account_id: None,
+            tags: vec!["class-a".to_string(), "trial".to_string()],
+            user_id: None,
+            created_by: None,
+            created_by_type: None,
+        };
+
+        let update: ApiKeyBulkUpdate = serde_json::from_str(
+            r#"{"isActive":false,"addTags":["2025"],"removeTags":["trial"],"dailyCostLimit":5.0}"#,
+        )
+        .unwrap();
+        update.apply(&mut api_key);
+
+        assert!(!api_key.is_active);
+        assert_eq!(
+            api_key.tags,
+            vec!["class-a".to_string(), "2025".to_string()]
+        );
+        assert_eq!(api_key.daily_cost_limit, 5.0);
+        assert_eq!(api_key.token_limit, 0);
+    }
+
+    #[test]
+    fn test_usage_period_bucket() {
+        let at = DateTime::parse_from_rfc3339("2025-03-09T23:59:59Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let utc = FixedOffset::east_opt(0).unwrap();
+        assert_eq!(UsagePeriod::Daily.bucket(at, utc), "2025-03-09");
+        assert_eq!(UsagePeriod::Monthly.bucket(at, utc), "2025-03");
+        // 系统时区 UTC+8 下已是下个月的第一天
+        let at = DateTime::parse_from_rfc3339("2025-03-31T16:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let tz = FixedOffset::east_opt(8 * 3600).unwrap();
+        assert_eq!(UsagePeriod::Daily.bucket(at, tz), "2025-04-01");
+        assert_eq!(UsagePeriod::Monthly.bucket(at, tz), "2025-04");
+
+        let parsed: UsagePeriod = serde_json::from_str(r#""monthly""#).unwrap();
+        assert_eq!(parsed, UsagePeriod::Monthly);
+    }
+
+    #[test]
+    fn test_concurrency_queue_defaults() {
+        let options = ApiKeyCreateOptions::default();
+        assert!(!options.concurrency_queue_enabled);
+        assert_eq!(
+            options.concurrency_queue_max_size,
+            DEFAULT_CONCURRENCY_QUEUE_MAX_SIZE
+        );
+
+        // 旧数据缺少排队字段时使用默认值
+        let parsed: ApiKeyCreateOptions = serde_json::from_str(r#"{"name":"legacy"}"#).unwrap();
+        assert!(!parsed.concurrency_queue_enabled);
+        assert_eq!(
+            parsed.concurrency_queue_timeout_ms,
+            DEFAULT_CONCURRENCY_QUEUE_TIMEOUT_MS
+        );
+    }
 }
@@ -0,0 +1,149 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+/// 管理操作审计日志
+///
+/// 只追加，存储在有序集合 `audit_log` 中 (score 为毫秒时间戳)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditLogEntry {
+    pub id: String,
+
+    pub timestamp: DateTime<Utc>,
+
+    /// 操作者 (JWT sub)
+    pub actor: String,
+
+    #[serde(rename = "actorRole")]
+    pub actor_role: String,
+
+    /// 操作，例如 `api_key.update`；未显式记录的请求为 `PUT /claude-accounts/:id`
+    pub action: String,
+
+    #[serde(rename = "targetType", skip_serializing_if = "Option::is_none")]
+    pub target_type: Option<String>,
+
+    #[serde(rename = "targetId", skip_serializing_if = "Option::is_none")]
+    pub target_id: Option<String>,
+
+    pub method: String,
+
+    pub path: String,
+
+    /// 响应状态码 (被拒绝的操作同样记录)
+    pub status: u16,
+
+    pub ip: String,
+
+    /// 变更内容 (敏感字段已脱敏)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changes: Option<JsonValue>,
+}
+
+/// 处理器附加在响应扩展上的审计事件
+///
+/// 审计中间件补充操作者、IP 和状态码后写入日志
+#[derive(Debug, Clone)]
+pub struct AuditEvent {
+    pub action: String,
+    pub target_type: String,
+    pub target_id: Option<String>,
+    pub before: Option<JsonValue>,
+    pub after: Option<JsonValue>,
+}
+
+impl AuditEvent {
+    pub fn new(action: &str, target_type: &str, target_id: Option<&str>) -> Self {
+        Self {
+            action: action.to_string(),
+            target_type: target_type.to_string(),
+            target_id: target_id.map(str::to_string),
+            before: None,
+            after: None,
+        }
+    }
+
+    /// 变更前的状态
+    pub fn before<T: Serialize>(mut self, value: &T) -> Self {
+        self.before = serde_json::to_value(value).ok();
+        self
+    }
+
+    /// 变更后的状态
+    pub fn after<T: Serialize>(mut self, value: &T) -> Self {
+        self.after = serde_json::to_value(value).ok();
+        self
+    }
+}
+
+/// 审计日志查询条件
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AuditLogQuery {
+    pub actor: Option<String>,
+    /// 操作前缀匹配，例如 `api_key` 匹配所有 API Key 操作
+    pub action: Option<String>,
+    #[serde(rename = "targetType")]
+    pub target_type: Option<String>,
+    #[serde(rename = "targetId")]
+    pub target_id: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+impl AuditLogQuery {
+    /// 时间以外的条件是否匹配 (时间范围由有序集合查询处理)
+    pub fn matches(&self, entry: &AuditLogEntry) -> bool {
+        let eq = |filter: &Option<String>, value: Option<&str>| {
+            filter.as_deref().is_none_or(|filter| Some(filter) == value)
+        };
+
+        eq(&self.actor, Some(&entry.actor))
+            && eq(&self.target_type, entry.target_type.as_deref())
+            && eq(&self.target_id, entry.target_id.as_deref())
+            && self
+                .action
+                .as_deref()
+                .is_none_or(|action| entry.action.starts_with(action))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit_log_query_matches() {
+        let entry = AuditLogEntry {
+            id: "1".to_string(),
+            timestamp: Utc::now(),
+            actor: "admin".to_string(),
+            actor_role: "admin".to_string(),
+            action: "api_key.update".to_string(),
+            target_type: Some("api_key".to_string()),
+            target_id: Some("key-1".to_string()),
+            method: "PUT".to_string(),
+            path: "/admin/api-keys/key-1".to_string(),
+            status: 200,
+            ip: "127.0.0.1".to_string(),
+            changes: None,
+        };
+
+        assert!(AuditLogQuery::default().matches(&entry));
+
+        let query = AuditLogQuery {
+            actor: Some("admin".to_string()),
+            action: Some("api_key".to_string()),
+            target_id: Some("key-1".to_string()),
+            ..Default::default()
+        };
+        assert!(query.matches(&entry));
+
+        let query = AuditLogQuery {
+            target_type: Some("account".to_string()),
+            ..Default::default()
+        };
+        assert!(!query.matches(&entry));
+    }
+}
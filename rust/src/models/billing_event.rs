@@ -0,0 +1,159 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 计费事件 Stream 的键
+pub const BILLING_EVENTS_STREAM: &str = "billing:events";
+
+/// 计费事件格式版本
+pub const BILLING_EVENT_VERSION: &str = "1.0";
+
+/// 计费事件类型
+pub const BILLING_EVENT_TYPE: &str = "usage.recorded";
+
+/// 转发路由在计算成本时附带的计费明细
+#[derive(Debug, Clone)]
+pub struct BillingDetails {
+    pub request_id: String,
+    /// 处理请求的账户平台 (claude-official / claude-console / bedrock / ccr / gemini)
+    pub platform: Option<String>,
+    pub ephemeral_5m_tokens: i64,
+    pub ephemeral_1h_tokens: i64,
+    pub cost_breakdown: CostBreakdown,
+    pub pricing_version: Option<String>,
+    pub is_long_context: bool,
+    /// 请求开始时间
+    pub requested_at: DateTime<Utc>,
+}
+
+/// 成本明细 (USD)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CostBreakdown {
+    pub input: f64,
+    pub output: f64,
+    pub cache_create: f64,
+    pub cache_read: f64,
+    pub ephemeral_5m: f64,
+    pub ephemeral_1h: f64,
+}
+
+/// 发布到 `billing:events` 的计费事件
+///
+/// 每条 Stream 记录只有一个 `data` 字段，内容为该结构的 JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BillingEvent {
+    pub event_id: String,
+    pub event_type: String,
+    pub version: String,
+    pub request_id: Option<String>,
+    /// 使用量记录时间
+    pub timestamp: DateTime<Utc>,
+    pub request_timestamp: Option<DateTime<Utc>>,
+    pub api_key: BillingApiKey,
+    pub account: BillingAccount,
+    pub usage: BillingUsage,
+    pub cost: BillingCost,
+    pub pricing_version: Option<String>,
+    pub is_long_context: bool,
+    /// 重放事件对应的原始 Stream ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replay_of: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BillingApiKey {
+    pub id: String,
+    pub name: Option<String>,
+    pub user_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BillingAccount {
+    pub id: Option<String>,
+    pub platform: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BillingUsage {
+    pub model: String,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    /// 缓存写入合计 (= 5 分钟 + 1 小时)
+    pub cache_create_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub ephemeral_5m_tokens: i64,
+    pub ephemeral_1h_tokens: i64,
+    pub total_tokens: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BillingCost {
+    /// 上游成本
+    pub total: f64,
+    pub currency: String,
+    pub breakdown: CostBreakdown,
+    /// 对外计费成本 (= total × multiplier)，引入计费倍率之前的事件没有该字段
+    #[serde(default)]
+    pub billed_total: Option<f64>,
+    #[serde(default)]
+    pub multiplier: Option<f64>,
+}
+
+/// Stream 中的一条计费事件
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BillingEventEntry {
+    /// Stream ID (`{毫秒时间戳}-{序号}`)
+    pub stream_id: String,
+    pub event: BillingEvent,
+}
+
+/// 时间范围对应的 Stream ID 区间 (XRANGE 两端均包含)
+pub fn stream_id_range(start: DateTime<Utc>, end: DateTime<Utc>) -> (String, String) {
+    (
+        format!("{}-0", start.timestamp_millis()),
+        format!("{}-{}", end.timestamp_millis(), u64::MAX),
+    )
+}
+
+/// 分页时下一页的起始 ID (排除已返回的最后一条)
+pub fn next_stream_id(id: &str) -> Option<String> {
+    let (ms, seq) = id.split_once('-')?;
+    let ms: u64 = ms.parse().ok()?;
+    let seq: u64 = seq.parse().ok()?;
+    Some(match seq.checked_add(1) {
+        Some(seq) => format!("{}-{}", ms, seq),
+        None => format!("{}-0", ms.checked_add(1)?),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_id_range() {
+        let start: DateTime<Utc> = "2025-01-31T00:00:00Z".parse().unwrap();
+        let end: DateTime<Utc> = "2025-01-31T01:00:00Z".parse().unwrap();
+        let (from, to) = stream_id_range(start, end);
+        assert_eq!(from, "1738281600000-0");
+        assert_eq!(to, format!("1738285200000-{}", u64::MAX));
+    }
+
+    #[test]
+    fn test_next_stream_id() {
+        assert_eq!(
+            next_stream_id("1738281600000-5").unwrap(),
+            "1738281600000-6"
+        );
+        assert_eq!(
+            next_stream_id(&format!("1738281600000-{}", u64::MAX)).unwrap(),
+            "1738281600001-0"
+        );
+        assert!(next_stream_id("not-an-id").is_none());
+    }
+}
@@ -0,0 +1,178 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// 计费倍率设置的键 (JSON)
+pub const BILLING_MULTIPLIERS_KEY: &str = "billing_multipliers";
+
+fn default_multiplier() -> f64 {
+    1.0
+}
+
+/// 计费倍率 (对外收费 = 上游成本 × 倍率)
+///
+/// 基础倍率按 Key > 标签 > 全局默认的优先级取值，再乘以模型系列倍率。
+/// Key 有多个标签配置了倍率时取 Key 标签列表中靠前的一个
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BillingMultipliers {
+    #[serde(default = "default_multiplier")]
+    pub default_multiplier: f64,
+
+    /// 标签 -> 倍率
+    #[serde(default)]
+    pub tags: BTreeMap<String, f64>,
+
+    /// API Key ID -> 倍率
+    #[serde(default)]
+    pub keys: BTreeMap<String, f64>,
+
+    /// 模型系列 (模型名包含的片段，如 `opus`，不区分大小写) -> 倍率，多个匹配时取最长的
+    #[serde(default)]
+    pub model_families: BTreeMap<String, f64>,
+
+    #[serde(default)]
+    pub updated_by: Option<String>,
+
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl Default for BillingMultipliers {
+    fn default() -> Self {
+        Self {
+            default_multiplier: default_multiplier(),
+            tags: BTreeMap::new(),
+            keys: BTreeMap::new(),
+            model_families: BTreeMap::new(),
+            updated_by: None,
+            updated_at: None,
+        }
+    }
+}
+
+impl BillingMultipliers {
+    /// 指定 Key 和模型的最终倍率
+    pub fn resolve(&self, key_id: &str, tags: &[String], model: &str) -> f64 {
+        let base = self
+            .keys
+            .get(key_id)
+            .or_else(|| tags.iter().find_map(|tag| self.tags.get(tag)))
+            .copied()
+            .unwrap_or(self.default_multiplier);
+
+        base * self.model_family_multiplier(model)
+    }
+
+    fn model_family_multiplier(&self, model: &str) -> f64 {
+        let model = model.to_lowercase();
+        self.model_families
+            .iter()
+            .filter(|(family, _)| model.contains(family.as_str()))
+            .max_by_key(|(family, _)| family.len())
+            .map_or(1.0, |(_, multiplier)| *multiplier)
+    }
+}
+
+/// 更新计费倍率请求 (整体替换)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BillingMultipliersUpdate {
+    #[serde(default = "default_multiplier")]
+    pub default_multiplier: f64,
+    #[serde(default)]
+    pub tags: BTreeMap<String, f64>,
+    #[serde(default)]
+    pub keys: BTreeMap<String, f64>,
+    #[serde(default)]
+    pub model_families: BTreeMap<String, f64>,
+}
+
+impl BillingMultipliersUpdate {
+    /// 倍率必须是非负有限数 (0 表示免费)，名称不能为空
+    pub fn validate(&self) -> Result<(), String> {
+        check_multiplier("defaultMultiplier", self.default_multiplier)?;
+        for (field, entries) in [
+            ("tags", &self.tags),
+            ("keys", &self.keys),
+            ("modelFamilies", &self.model_families),
+        ] {
+            for (name, multiplier) in entries {
+                if name.trim().is_empty() {
+                    return Err(format!("{} contains an empty name", field));
+                }
+                check_multiplier(&format!("{}.{}", field, name), *multiplier)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 模型系列统一转为小写，便于匹配
+    pub fn into_multipliers(self, updated_by: &str) -> BillingMultipliers {
+        BillingMultipliers {
+            default_multiplier: self.default_multiplier,
+            tags: self.tags,
+            keys: self.keys,
+            model_families: self
+                .model_families
+                .into_iter()
+                .map(|(family, multiplier)| (family.trim().to_lowercase(), multiplier))
+                .collect(),
+            updated_by: Some(updated_by.to_string()),
+            updated_at: Some(Utc::now()),
+        }
+    }
+}
+
+fn check_multiplier(field: &str, multiplier: f64) -> Result<(), String> {
+    if !multiplier.is_finite() || multiplier < 0.0 {
+        return Err(format!("{} must be a non-negative number", field));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn multipliers(json: &str) -> BillingMultipliers {
+        let update: BillingMultipliersUpdate = serde_json::from_str(json).unwrap();
+        update.validate().unwrap();
+        update.into_multipliers("admin")
+    }
+
+    #[test]
+    fn test_resolve_precedence() {
+        let m = multipliers(
+            r#"{"defaultMultiplier":1.2,
+                "tags":{"vip":1.1,"team":1.5},
+                "keys":{"k1":2.0},
+                "modelFamilies":{"Opus":1.5,"opus-4-1":2.0}}"#,
+        );
+        let tags = vec!["team".to_string(), "vip".to_string()];
+
+        assert_eq!(m.resolve("k1", &tags, "claude-sonnet-4"), 2.0);
+        assert_eq!(m.resolve("k2", &tags, "claude-sonnet-4"), 1.5);
+        assert_eq!(m.resolve("k2", &[], "claude-sonnet-4"), 1.2);
+        assert_eq!(m.resolve("k1", &[], "claude-opus-4-20250514"), 3.0);
+        assert_eq!(m.resolve("k1", &[], "claude-opus-4-1-20250805"), 4.0);
+    }
+
+    #[test]
+    fn test_defaults_and_validation() {
+        assert_eq!(
+            BillingMultipliers::default().resolve("k1", &[], "claude-opus-4"),
+            1.0
+        );
+        let m: BillingMultipliers = serde_json::from_str("{}").unwrap();
+        assert_eq!(m.default_multiplier, 1.0);
+
+        let update: BillingMultipliersUpdate =
+            serde_json::from_str(r#"{"tags":{"vip":-1}}"#).unwrap();
+        assert!(update.validate().unwrap_err().contains("tags.vip"));
+
+        let update: BillingMultipliersUpdate =
+            serde_json::from_str(r#"{"modelFamilies":{" ":2}}"#).unwrap();
+        assert!(update.validate().unwrap_err().contains("empty"));
+    }
+}
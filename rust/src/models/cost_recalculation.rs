@@ -0,0 +1,184 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// 重算任务记录 `cost_recalculation:job:{id}`
+pub fn cost_recalculation_job_key(job_id: &str) -> String {
+    format!("cost_recalculation:job:{}", job_id)
+}
+
+/// 同一时间只允许一个重算任务，值为正在运行的任务 ID
+pub const COST_RECALCULATION_LOCK_KEY: &str = "cost_recalculation:lock";
+
+/// 重算选项 (同时作为管理接口的请求体)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CostRecalculationOptions {
+    /// 只计算差异，不写回
+    #[serde(default)]
+    pub dry_run: bool,
+    /// 只重算指定的 Key，为空表示全部 (含已删除)
+    #[serde(default)]
+    pub key_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CostRecalculationStatus {
+    Running,
+    Completed,
+    Failed,
+}
+
+/// 单个模型的成本变化 (累计统计)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelCostDiff {
+    pub model: String,
+    pub requests: i64,
+    pub old_cost: f64,
+    pub new_cost: f64,
+}
+
+/// 单个 API Key 的成本变化
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyCostDiff {
+    pub key_id: String,
+    pub key_name: String,
+    pub old_cost: f64,
+    pub new_cost: f64,
+    pub diff: f64,
+    pub models: Vec<ModelCostDiff>,
+}
+
+impl KeyCostDiff {
+    pub fn new(key_id: &str, key_name: &str, models: Vec<ModelCostDiff>) -> Self {
+        let old_cost: f64 = models.iter().map(|m| m.old_cost).sum();
+        let new_cost: f64 = models.iter().map(|m| m.new_cost).sum();
+        Self {
+            key_id: key_id.to_string(),
+            key_name: key_name.to_string(),
+            old_cost,
+            new_cost,
+            diff: new_cost - old_cost,
+            models,
+        }
+    }
+
+    pub fn has_changes(&self) -> bool {
+        self.models
+            .iter()
+            .any(|m| cost_changed(m.old_cost, m.new_cost))
+    }
+}
+
+/// 成本重算任务及其进度
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CostRecalculationJob {
+    pub id: String,
+    pub status: CostRecalculationStatus,
+    pub dry_run: bool,
+    /// 重算使用的定价数据版本
+    pub pricing_version: Option<String>,
+    pub requested_by: String,
+    pub total_keys: usize,
+    pub processed_keys: usize,
+    pub old_total_cost: f64,
+    pub new_total_cost: f64,
+    /// 成本有变化的 Key
+    pub keys: Vec<KeyCostDiff>,
+    /// 没有定价、保留原成本的模型
+    pub unpriced_models: BTreeSet<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+}
+
+impl CostRecalculationJob {
+    pub fn new(dry_run: bool, pricing_version: Option<String>, requested_by: &str) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            status: CostRecalculationStatus::Running,
+            dry_run,
+            pricing_version,
+            requested_by: requested_by.to_string(),
+            total_keys: 0,
+            processed_keys: 0,
+            old_total_cost: 0.0,
+            new_total_cost: 0.0,
+            keys: Vec::new(),
+            unpriced_models: BTreeSet::new(),
+            started_at: Utc::now(),
+            finished_at: None,
+            error: None,
+        }
+    }
+
+    /// 计入一个已处理的 Key
+    pub fn add_key(&mut self, diff: KeyCostDiff) {
+        self.processed_keys += 1;
+        self.old_total_cost += diff.old_cost;
+        self.new_total_cost += diff.new_cost;
+        if diff.has_changes() {
+            self.keys.push(diff);
+        }
+    }
+}
+
+/// 忽略浮点累加误差后成本是否变化
+pub fn cost_changed(old_cost: f64, new_cost: f64) -> bool {
+    (new_cost - old_cost).abs() > 1e-9
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(name: &str, old_cost: f64, new_cost: f64) -> ModelCostDiff {
+        ModelCostDiff {
+            model: name.to_string(),
+            requests: 1,
+            old_cost,
+            new_cost,
+        }
+    }
+
+    #[test]
+    fn test_job_only_keeps_changed_keys() {
+        let mut job = CostRecalculationJob::new(true, None, "admin");
+        job.add_key(KeyCostDiff::new(
+            "k1",
+            "unchanged",
+            vec![model("claude-sonnet-4", 1.0, 1.0)],
+        ));
+        job.add_key(KeyCostDiff::new(
+            "k2",
+            "changed",
+            vec![
+                model("claude-sonnet-4", 1.0, 1.5),
+                model("claude-opus-4", 2.0, 2.0),
+            ],
+        ));
+
+        assert_eq!(job.processed_keys, 2);
+        assert_eq!(job.keys.len(), 1);
+        assert_eq!(job.keys[0].key_id, "k2");
+        assert_eq!(job.keys[0].diff, 0.5);
+        assert_eq!(job.old_total_cost, 4.0);
+        assert_eq!(job.new_total_cost, 4.5);
+    }
+
+    #[test]
+    fn test_options_defaults() {
+        let options: CostRecalculationOptions = serde_json::from_str("{}").unwrap();
+        assert!(!options.dry_run);
+        assert!(options.key_ids.is_empty());
+
+        let options: CostRecalculationOptions =
+            serde_json::from_str(r#"{"dryRun":true,"keyIds":["k1"]}"#).unwrap();
+        assert!(options.dry_run);
+        assert_eq!(options.key_ids, ["k1"]);
+    }
+}
@@ -1,10 +1,41 @@
 pub mod account;
+pub mod account_usage;
+pub mod admin_token;
 pub mod api_key;
+pub mod audit;
+pub mod billing_event;
+pub mod billing_multiplier;
+pub mod cost_recalculation;
+pub mod oem;
+pub mod pricing_override;
+pub mod role;
+pub mod statement;
 pub mod usage_record;
+pub mod usage_trend;
+pub mod user;
+pub mod wallet;
 
 pub use account::{
     AccountStatus, AccountType, ClaudeAccount, ClaudeOAuthData, CreateClaudeAccountOptions,
     Platform, ProxyConfig, SubscriptionInfo,
 };
-pub use api_key::{ApiKey, ApiKeyCreateOptions, ApiKeyPermissions, ExpirationMode};
+pub use account_usage::{AccountUsageSummary, AccountUsageTotals, SessionWindowUsage};
+pub use admin_token::{AdminApiToken, CreateAdminApiTokenRequest};
+pub use api_key::{
+    ApiKey, ApiKeyBulkUpdate, ApiKeyCreateOptions, ApiKeyHistoryEntry, ApiKeyPermissions,
+    BulkOperationResult, ExpirationMode, RateLimitWindowState, UsagePeriod,
+};
+pub use audit::{AuditEvent, AuditLogEntry, AuditLogQuery};
+pub use billing_event::{BillingDetails, BillingEvent, BillingEventEntry, CostBreakdown};
+pub use billing_multiplier::{BillingMultipliers, BillingMultipliersUpdate};
+pub use cost_recalculation::{CostRecalculationJob, CostRecalculationOptions, KeyCostDiff};
+pub use oem::{FooterLink, OemSettings, OemSettingsUpdate};
+pub use pricing_override::{PricingOverride, PricingOverrideRequest, UnpricedModel};
+pub use role::{Permission, Role};
+pub use statement::{Statement, StatementFormat, StatementQuery, StatementScope};
 pub use usage_record::UsageRecord;
+pub use usage_trend::{TrendGranularity, TrendQuery, TrendRange, UsageBucket, UsageDimension};
+pub use user::{User, UserAuthSource, UserInvitation, UserManagementSettings};
+pub use wallet::{
+    LedgerEntry, RedemptionCode, RedemptionCodeStatus, Wallet, WalletOwner, WalletOwnerType,
+};
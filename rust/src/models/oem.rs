@@ -0,0 +1,287 @@
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 默认站点名称
+pub const DEFAULT_SITE_NAME: &str = "Claude Relay Service";
+
+/// 默认主题色
+pub const DEFAULT_THEME_COLOR: &str = "#6366f1";
+
+/// 上传图标解码后的大小上限
+pub const MAX_SITE_ICON_BYTES: usize = 350 * 1024;
+
+/// 页脚链接数量上限
+pub const MAX_FOOTER_LINKS: usize = 10;
+
+/// 允许上传的图标类型
+const ALLOWED_ICON_TYPES: [&str; 5] = [
+    "image/png",
+    "image/jpeg",
+    "image/x-icon",
+    "image/vnd.microsoft.icon",
+    "image/svg+xml",
+];
+
+/// OEM 品牌设置 (白标)
+///
+/// 存储在 `oem_settings`，公开接口直接返回给登录页和统计页
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OemSettings {
+    pub site_name: String,
+
+    /// 外部图标 URL
+    #[serde(default)]
+    pub site_icon: String,
+
+    /// 上传的图标 (`data:image/...;base64,...`)，优先于 `site_icon`
+    #[serde(default)]
+    pub site_icon_data: String,
+
+    pub theme_color: String,
+
+    #[serde(default)]
+    pub footer_links: Vec<FooterLink>,
+
+    /// 公开页面是否显示管理后台入口
+    pub show_admin_button: bool,
+
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl Default for OemSettings {
+    fn default() -> Self {
+        Self {
+            site_name: DEFAULT_SITE_NAME.to_string(),
+            site_icon: String::new(),
+            site_icon_data: String::new(),
+            theme_color: DEFAULT_THEME_COLOR.to_string(),
+            footer_links: Vec::new(),
+            show_admin_button: true,
+            updated_at: None,
+        }
+    }
+}
+
+impl OemSettings {
+    /// 合并部分更新 (未提供的字段保持不变)
+    pub fn apply(&mut self, update: OemSettingsUpdate) {
+        if let Some(site_name) = update.site_name {
+            self.site_name = site_name.trim().to_string();
+        }
+        if let Some(site_icon) = update.site_icon {
+            self.site_icon = site_icon.trim().to_string();
+        }
+        if let Some(site_icon_data) = update.site_icon_data {
+            self.site_icon_data = site_icon_data.trim().to_string();
+        }
+        if let Some(theme_color) = update.theme_color {
+            self.theme_color = theme_color.trim().to_string();
+        }
+        if let Some(footer_links) = update.footer_links {
+            self.footer_links = footer_links;
+        }
+        if let Some(show_admin_button) = update.show_admin_button {
+            self.show_admin_button = show_admin_button;
+        }
+    }
+
+    /// 校验所有字段，返回第一个错误
+    pub fn validate(&self) -> Result<(), String> {
+        let name_len = self.site_name.chars().count();
+        if name_len == 0 || name_len > 100 {
+            return Err("siteName must be 1-100 characters".to_string());
+        }
+        if !self.site_icon.is_empty() && !is_safe_url(&self.site_icon, true) {
+            return Err("siteIcon must be an http(s) URL or an absolute path".to_string());
+        }
+        if !self.site_icon_data.is_empty() {
+            validate_icon_data(&self.site_icon_data)?;
+        }
+        if !is_hex_color(&self.theme_color) {
+            return Err("themeColor must be a hex color like #6366f1".to_string());
+        }
+        if self.footer_links.len() > MAX_FOOTER_LINKS {
+            return Err(format!(
+                "At most {} footer links are allowed",
+                MAX_FOOTER_LINKS
+            ));
+        }
+        for link in &self.footer_links {
+            let label_len = link.label.trim().chars().count();
+            if label_len == 0 || label_len > 50 {
+                return Err("Footer link label must be 1-50 characters".to_string());
+            }
+            if !is_safe_url(&link.url, false) {
+                return Err(format!(
+                    "Footer link URL must be an http(s) URL: {}",
+                    link.url
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 用于审计日志的副本 (图标数据只保留类型和大小)
+    pub fn audit_view(&self) -> Self {
+        let mut view = self.clone();
+        if let Some((header, payload)) = view.site_icon_data.split_once(',') {
+            view.site_icon_data = format!("{},[{} chars]", header, payload.len());
+        }
+        view
+    }
+}
+
+/// 页脚链接
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FooterLink {
+    pub label: String,
+    pub url: String,
+}
+
+/// OEM 设置部分更新请求
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OemSettingsUpdate {
+    pub site_name: Option<String>,
+    pub site_icon: Option<String>,
+    pub site_icon_data: Option<String>,
+    pub theme_color: Option<String>,
+    pub footer_links: Option<Vec<FooterLink>>,
+    pub show_admin_button: Option<bool>,
+}
+
+/// 校验 base64 图标：类型白名单、解码后大小、文件头与声明的类型一致
+fn validate_icon_data(data: &str) -> Result<(), String> {
+    let (header, payload) = data
+        .strip_prefix("data:")
+        .and_then(|rest| rest.split_once(','))
+        .ok_or_else(|| "siteIconData must be a base64 data URL".to_string())?;
+    let mime = header
+        .strip_suffix(";base64")
+        .ok_or_else(|| "siteIconData must be base64 encoded".to_string())?;
+    if !ALLOWED_ICON_TYPES.contains(&mime) {
+        return Err(format!(
+            "Unsupported icon type {}, allowed: png, jpeg, ico, svg",
+            mime
+        ));
+    }
+
+    // 先按编码长度粗略判断，避免解码超大内容
+    if payload.len() / 4 * 3 > MAX_SITE_ICON_BYTES + 3 {
+        return Err(format!(
+            "Icon must not exceed {} KB",
+            MAX_SITE_ICON_BYTES / 1024
+        ));
+    }
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(payload)
+        .map_err(|_| "siteIconData contains invalid base64".to_string())?;
+    if bytes.len() > MAX_SITE_ICON_BYTES {
+        return Err(format!(
+            "Icon must not exceed {} KB",
+            MAX_SITE_ICON_BYTES / 1024
+        ));
+    }
+
+    let matches_type = match mime {
+        "image/png" => bytes.starts_with(b"\x89PNG\r\n\x1a\n"),
+        "image/jpeg" => bytes.starts_with(&[0xFF, 0xD8, 0xFF]),
+        "image/x-icon" | "image/vnd.microsoft.icon" => bytes.starts_with(&[0, 0, 1, 0]),
+        _ => {
+            let text = String::from_utf8_lossy(&bytes).to_lowercase();
+            text.contains("<svg") && !text.contains("<script") && !text.contains("javascript:")
+        }
+    };
+    if !matches_type {
+        return Err(format!("Icon content does not match type {}", mime));
+    }
+
+    Ok(())
+}
+
+/// http(s) URL；`allow_path` 时也允许站内绝对路径
+fn is_safe_url(url: &str, allow_path: bool) -> bool {
+    if url.len() > 2048 || url.chars().any(char::is_whitespace) {
+        return false;
+    }
+    let lower = url.to_lowercase();
+    lower.starts_with("https://")
+        || lower.starts_with("http://")
+        || (allow_path && url.starts_with('/') && !url.starts_with("//"))
+}
+
+fn is_hex_color(value: &str) -> bool {
+    value
+        .strip_prefix('#')
+        .is_some_and(|hex| matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn icon(mime: &str, bytes: &[u8]) -> String {
+        format!(
+            "data:{};base64,{}",
+            mime,
+            base64::engine::general_purpose::STANDARD.encode(bytes)
+        )
+    }
+
+    #[test]
+    fn test_apply_partial_update() {
+        let mut settings = OemSettings::default();
+        settings.apply(OemSettingsUpdate {
+            site_name: Some("  Acme AI  ".to_string()),
+            show_admin_button: Some(false),
+            ..Default::default()
+        });
+
+        assert_eq!(settings.site_name, "Acme AI");
+        assert!(!settings.show_admin_button);
+        assert_eq!(settings.theme_color, DEFAULT_THEME_COLOR);
+        assert!(settings.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_fields() {
+        let valid = OemSettings::default();
+
+        let mut settings = valid.clone();
+        settings.theme_color = "red".to_string();
+        assert!(settings.validate().is_err());
+
+        let mut settings = valid.clone();
+        settings.site_icon = "javascript:alert(1)".to_string();
+        assert!(settings.validate().is_err());
+
+        let mut settings = valid.clone();
+        settings.footer_links = vec![FooterLink {
+            label: "Docs".to_string(),
+            url: "https://docs.example.com".to_string(),
+        }];
+        assert!(settings.validate().is_ok());
+        settings.footer_links[0].url = "/docs".to_string();
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_icon_data() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        assert!(validate_icon_data(&icon("image/png", png)).is_ok());
+        assert!(validate_icon_data(&icon("image/svg+xml", b"<svg xmlns='x'></svg>")).is_ok());
+
+        // 类型与内容不符、不支持的类型、脚本、超出大小
+        assert!(validate_icon_data(&icon("image/jpeg", png)).is_err());
+        assert!(validate_icon_data(&icon("image/gif", b"GIF89a")).is_err());
+        assert!(validate_icon_data(&icon("image/svg+xml", b"<svg><script/></svg>")).is_err());
+        let mut large = png.to_vec();
+        large.resize(MAX_SITE_ICON_BYTES + 1, 0);
+        assert!(validate_icon_data(&icon("image/png", &large)).is_err());
+        assert!(validate_icon_data("data:image/png;base64,@@@").is_err());
+    }
+}
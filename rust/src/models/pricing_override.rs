@@ -0,0 +1,179 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 管理员配置的模型定价 Hash (field 为模型名，value 为 `PricingOverride` JSON)
+pub const PRICING_OVERRIDES_KEY: &str = "pricing_overrides";
+
+/// 流量中出现过但没有定价的模型 (ZSET，score 为最后出现时间)
+pub const UNPRICED_MODELS_KEY: &str = "pricing:unpriced_models";
+
+/// 没有定价的模型的请求数 (Hash)
+pub const UNPRICED_MODEL_REQUESTS_KEY: &str = "pricing:unpriced_models:requests";
+
+/// 长上下文档位的默认阈值 (输入 tokens，含缓存)
+pub const DEFAULT_LONG_CONTEXT_THRESHOLD: i64 = 200_000;
+
+fn default_long_context_threshold() -> i64 {
+    DEFAULT_LONG_CONTEXT_THRESHOLD
+}
+
+/// 长上下文档位：单次请求输入超过阈值时整个请求按该价格计算输入和输出
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LongContextTier {
+    #[serde(default = "default_long_context_threshold")]
+    pub threshold_tokens: i64,
+    pub input_cost_per_token: f64,
+    pub output_cost_per_token: f64,
+}
+
+/// 管理员配置的模型定价 (美元/token)，优先于价格镜像数据
+///
+/// 用于微调模型和价格镜像中没有的第三方模型
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PricingOverride {
+    pub model: String,
+    pub input_cost_per_token: f64,
+    pub output_cost_per_token: f64,
+    /// 5 分钟缓存写入
+    #[serde(default)]
+    pub cache_creation_input_token_cost: Option<f64>,
+    /// 1 小时缓存写入，未设置时使用内置的 1 小时缓存价格
+    #[serde(default, rename = "cacheCreation1hInputTokenCost")]
+    pub cache_creation_1h_input_token_cost: Option<f64>,
+    #[serde(default)]
+    pub cache_read_input_token_cost: Option<f64>,
+    #[serde(default)]
+    pub long_context: Option<LongContextTier>,
+    #[serde(default)]
+    pub description: String,
+    pub updated_by: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 创建或更新模型定价请求
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PricingOverrideRequest {
+    pub input_cost_per_token: f64,
+    pub output_cost_per_token: f64,
+    #[serde(default)]
+    pub cache_creation_input_token_cost: Option<f64>,
+    #[serde(default, rename = "cacheCreation1hInputTokenCost")]
+    pub cache_creation_1h_input_token_cost: Option<f64>,
+    #[serde(default)]
+    pub cache_read_input_token_cost: Option<f64>,
+    #[serde(default)]
+    pub long_context: Option<LongContextTier>,
+    #[serde(default)]
+    pub description: String,
+}
+
+impl PricingOverrideRequest {
+    /// 价格必须是非负有限数，长上下文阈值必须为正
+    pub fn validate(&self) -> Result<(), String> {
+        let mut prices = vec![
+            ("inputCostPerToken", Some(self.input_cost_per_token)),
+            ("outputCostPerToken", Some(self.output_cost_per_token)),
+            (
+                "cacheCreationInputTokenCost",
+                self.cache_creation_input_token_cost,
+            ),
+            (
+                "cacheCreation1hInputTokenCost",
+                self.cache_creation_1h_input_token_cost,
+            ),
+            ("cacheReadInputTokenCost", self.cache_read_input_token_cost),
+        ];
+        if let Some(tier) = &self.long_context {
+            if tier.threshold_tokens <= 0 {
+                return Err("longContext.thresholdTokens must be positive".to_string());
+            }
+            prices.push((
+                "longContext.inputCostPerToken",
+                Some(tier.input_cost_per_token),
+            ));
+            prices.push((
+                "longContext.outputCostPerToken",
+                Some(tier.output_cost_per_token),
+            ));
+        }
+
+        for (field, price) in prices {
+            if let Some(price) = price {
+                if !price.is_finite() || price < 0.0 {
+                    return Err(format!("{} must be a non-negative number", field));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn into_override(self, model: &str, updated_by: &str) -> PricingOverride {
+        PricingOverride {
+            model: model.to_string(),
+            input_cost_per_token: self.input_cost_per_token,
+            output_cost_per_token: self.output_cost_per_token,
+            cache_creation_input_token_cost: self.cache_creation_input_token_cost,
+            cache_creation_1h_input_token_cost: self.cache_creation_1h_input_token_cost,
+            cache_read_input_token_cost: self.cache_read_input_token_cost,
+            long_context: self.long_context,
+            description: self.description,
+            updated_by: updated_by.to_string(),
+            updated_at: Utc::now(),
+        }
+    }
+}
+
+/// 流量中出现过但没有定价的模型 (成本按 0 记录)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnpricedModel {
+    pub model: String,
+    pub requests: i64,
+    pub last_seen_at: Option<DateTime<Utc>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(json: &str) -> PricingOverrideRequest {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_request_defaults() {
+        let req = request(r#"{"inputCostPerToken":0.000001,"outputCostPerToken":0.000002}"#);
+        assert!(req.validate().is_ok());
+        assert!(req.cache_creation_input_token_cost.is_none());
+
+        let req = request(
+            r#"{"inputCostPerToken":0.000001,"outputCostPerToken":0.000002,
+                "cacheCreation1hInputTokenCost":0.000004,
+                "longContext":{"inputCostPerToken":0.000002,"outputCostPerToken":0.000004}}"#,
+        );
+        assert_eq!(req.cache_creation_1h_input_token_cost, Some(0.000004));
+        assert_eq!(
+            req.long_context.as_ref().unwrap().threshold_tokens,
+            DEFAULT_LONG_CONTEXT_THRESHOLD
+        );
+
+        let pricing = req.into_override("my-finetune", "admin");
+        assert_eq!(pricing.model, "my-finetune");
+        assert_eq!(pricing.updated_by, "admin");
+    }
+
+    #[test]
+    fn test_request_validation() {
+        let req = request(r#"{"inputCostPerToken":-1,"outputCostPerToken":0}"#);
+        assert!(req.validate().unwrap_err().contains("inputCostPerToken"));
+
+        let req = request(
+            r#"{"inputCostPerToken":0,"outputCostPerToken":0,
+                "longContext":{"thresholdTokens":0,"inputCostPerToken":0,"outputCostPerToken":0}}"#,
+        );
+        assert!(req.validate().unwrap_err().contains("thresholdTokens"));
+    }
+}
@@ -0,0 +1,144 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 内置超级管理员角色
+pub const ADMIN_ROLE_NAME: &str = "admin";
+
+/// 普通用户角色 (只能访问 /users 自助接口，没有任何管理权限)
+pub const USER_ROLE_NAME: &str = "user";
+
+/// 管理接口权限
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Permission {
+    #[serde(rename = "accounts:read")]
+    AccountsRead,
+    #[serde(rename = "accounts:write")]
+    AccountsWrite,
+    #[serde(rename = "keys:read")]
+    KeysRead,
+    #[serde(rename = "keys:write")]
+    KeysWrite,
+    #[serde(rename = "stats:read")]
+    StatsRead,
+    #[serde(rename = "settings:write")]
+    SettingsWrite,
+    #[serde(rename = "users:manage")]
+    UsersManage,
+    #[serde(rename = "audit:read")]
+    AuditRead,
+}
+
+impl Permission {
+    pub const ALL: [Permission; 8] = [
+        Permission::AccountsRead,
+        Permission::AccountsWrite,
+        Permission::KeysRead,
+        Permission::KeysWrite,
+        Permission::StatsRead,
+        Permission::SettingsWrite,
+        Permission::UsersManage,
+        Permission::AuditRead,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::AccountsRead => "accounts:read",
+            Permission::AccountsWrite => "accounts:write",
+            Permission::KeysRead => "keys:read",
+            Permission::KeysWrite => "keys:write",
+            Permission::StatsRead => "stats:read",
+            Permission::SettingsWrite => "settings:write",
+            Permission::UsersManage => "users:manage",
+            Permission::AuditRead => "audit:read",
+        }
+    }
+}
+
+/// 管理角色
+///
+/// 内置角色不可修改，自定义角色存储在 `admin_role:{name}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Role {
+    /// 角色名 (即 JWT 中的 role)
+    pub name: String,
+
+    #[serde(default)]
+    pub description: String,
+
+    pub permissions: Vec<Permission>,
+
+    #[serde(rename = "builtIn", default)]
+    pub built_in: bool,
+
+    #[serde(rename = "createdAt", skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+
+    #[serde(rename = "updatedAt", skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl Role {
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        self.permissions.contains(&permission)
+    }
+}
+
+/// 内置角色
+///
+/// - admin: 全部权限
+/// - operator: 管理账户，只读 API Key，不能查看费用统计
+/// - support: 只读 API Key
+pub fn builtin_roles() -> Vec<Role> {
+    let role = |name: &str, description: &str, permissions: &[Permission]| Role {
+        name: name.to_string(),
+        description: description.to_string(),
+        permissions: permissions.to_vec(),
+        built_in: true,
+        created_at: None,
+        updated_at: None,
+    };
+
+    vec![
+        role(ADMIN_ROLE_NAME, "Full access", &Permission::ALL),
+        role(
+            "operator",
+            "Manage upstream accounts, view API keys",
+            &[
+                Permission::AccountsRead,
+                Permission::AccountsWrite,
+                Permission::KeysRead,
+            ],
+        ),
+        role("support", "View API keys", &[Permission::KeysRead]),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_permission_serde() {
+        let json = serde_json::to_string(&Permission::AccountsWrite).unwrap();
+        assert_eq!(json, "\"accounts:write\"");
+
+        for permission in Permission::ALL {
+            let parsed: Permission =
+                serde_json::from_str(&format!("\"{}\"", permission.as_str())).unwrap();
+            assert_eq!(parsed, permission);
+        }
+        assert!(serde_json::from_str::<Permission>("\"billing:read\"").is_err());
+    }
+
+    #[test]
+    fn test_builtin_roles() {
+        let roles = builtin_roles();
+        let admin = roles.iter().find(|r| r.name == ADMIN_ROLE_NAME).unwrap();
+        assert!(Permission::ALL.iter().all(|p| admin.has_permission(*p)));
+
+        let operator = roles.iter().find(|r| r.name == "operator").unwrap();
+        assert!(operator.has_permission(Permission::AccountsWrite));
+        assert!(!operator.has_permission(Permission::StatsRead));
+        assert!(roles.iter().all(|r| r.name != USER_ROLE_NAME));
+    }
+}
@@ -0,0 +1,424 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::str::FromStr;
+
+use crate::models::api_key::ModelUsage;
+use crate::utils::escape_csv_field;
+
+/// 单份对账单的最大天数 (日模型统计只保留 32 天)
+pub const MAX_STATEMENT_DAYS: i64 = 31;
+
+/// 对账单对象
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatementScope {
+    /// 单个 API Key
+    Key,
+    /// 用户名下的全部 Key
+    User,
+    /// 带有该标签的全部 Key
+    Tag,
+}
+
+impl StatementScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Key => "key",
+            Self::User => "user",
+            Self::Tag => "tag",
+        }
+    }
+}
+
+impl FromStr for StatementScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "key" => Ok(Self::Key),
+            "user" => Ok(Self::User),
+            "tag" => Ok(Self::Tag),
+            other => Err(format!(
+                "Invalid scope: {}, expected key, user or tag",
+                other
+            )),
+        }
+    }
+}
+
+/// 对账单输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatementFormat {
+    #[default]
+    Json,
+    Csv,
+    /// 可打印的 HTML 文档
+    Html,
+}
+
+impl FromStr for StatementFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            "html" => Ok(Self::Html),
+            other => Err(format!(
+                "Invalid format: {}, expected json, csv or html",
+                other
+            )),
+        }
+    }
+}
+
+/// 对账单查询参数，日期为系统时区日期且包含两端
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatementQuery {
+    pub scope: String,
+    pub id: String,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    #[serde(default)]
+    pub format: Option<String>,
+}
+
+impl StatementQuery {
+    pub fn scope(&self) -> Result<StatementScope, String> {
+        self.scope.parse()
+    }
+
+    pub fn format(&self) -> Result<StatementFormat, String> {
+        self.format
+            .as_deref()
+            .map_or(Ok(StatementFormat::Json), str::parse)
+    }
+
+    /// 校验日期范围，返回 (第一天, 最后一天)
+    pub fn date_range(&self) -> Result<(NaiveDate, NaiveDate), String> {
+        if self.end_date < self.start_date {
+            return Err("endDate must not be before startDate".to_string());
+        }
+        if (self.end_date - self.start_date).num_days() >= MAX_STATEMENT_DAYS {
+            return Err(format!(
+                "Statement range cannot exceed {} days",
+                MAX_STATEMENT_DAYS
+            ));
+        }
+        Ok((self.start_date, self.end_date))
+    }
+}
+
+/// 对账单中的一行合计
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatementTotals {
+    pub requests: i64,
+    /// 失败的请求数
+    pub errors: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_creation_tokens: i64,
+    pub cache_read_tokens: i64,
+    /// 上游成本
+    pub cost: f64,
+    /// 计费成本 (上游成本 × 计费倍率)
+    pub billed_cost: f64,
+    /// 缓存读取相对按普通输入计费节省的成本
+    pub cache_savings: f64,
+}
+
+impl StatementTotals {
+    pub fn add_usage(&mut self, usage: &ModelUsage, cache_savings: f64) {
+        self.requests += usage.requests;
+        self.input_tokens += usage.input_tokens;
+        self.output_tokens += usage.output_tokens;
+        self.cache_creation_tokens += usage.cache_creation_tokens;
+        self.cache_read_tokens += usage.cache_read_tokens;
+        self.cost += usage.cost;
+        self.billed_cost += usage.billed_cost;
+        self.cache_savings += cache_savings;
+    }
+
+    pub fn add(&mut self, other: &StatementTotals) {
+        self.requests += other.requests;
+        self.errors += other.errors;
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_tokens += other.cache_creation_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+        self.cost += other.cost;
+        self.billed_cost += other.billed_cost;
+        self.cache_savings += other.cache_savings;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatementDay {
+    pub date: NaiveDate,
+    #[serde(flatten)]
+    pub totals: StatementTotals,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatementModel {
+    pub model: String,
+    #[serde(flatten)]
+    pub totals: StatementTotals,
+}
+
+/// 对账单包含的 Key
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatementKey {
+    pub id: String,
+    pub name: String,
+    pub is_deleted: bool,
+}
+
+/// 使用量对账单
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Statement {
+    pub scope: StatementScope,
+    /// Key ID、用户 ID 或标签
+    pub subject_id: String,
+    /// Key 名称、用户显示名或标签
+    pub subject_name: String,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub generated_at: DateTime<Utc>,
+    pub keys: Vec<StatementKey>,
+    /// 范围内的每一天 (没有使用量的日期合计为 0)
+    pub days: Vec<StatementDay>,
+    /// 按计费成本从高到低排序
+    pub models: Vec<StatementModel>,
+    pub totals: StatementTotals,
+}
+
+const TOTALS_HEADER: [&str; 9] = [
+    "requests",
+    "errors",
+    "inputTokens",
+    "outputTokens",
+    "cacheCreationTokens",
+    "cacheReadTokens",
+    "cost",
+    "billedCost",
+    "cacheSavings",
+];
+
+fn totals_fields(totals: &StatementTotals) -> [String; 9] {
+    [
+        totals.requests.to_string(),
+        totals.errors.to_string(),
+        totals.input_tokens.to_string(),
+        totals.output_tokens.to_string(),
+        totals.cache_creation_tokens.to_string(),
+        totals.cache_read_tokens.to_string(),
+        format!("{:.6}", totals.cost),
+        format!("{:.6}", totals.billed_cost),
+        format!("{:.6}", totals.cache_savings),
+    ]
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+impl Statement {
+    /// 导出为 CSV：`section` 为 `day`、`model` 或 `total`，`item` 为日期或模型
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!("section,item,{}\n", TOTALS_HEADER.join(","));
+        let rows = self
+            .days
+            .iter()
+            .map(|day| ("day", day.date.to_string(), &day.totals))
+            .chain(
+                self.models
+                    .iter()
+                    .map(|model| ("model", model.model.clone(), &model.totals)),
+            )
+            .chain(std::iter::once(("total", String::new(), &self.totals)));
+
+        for (section, item, totals) in rows {
+            let _ = writeln!(
+                csv,
+                "{},{},{}",
+                section,
+                escape_csv_field(&item),
+                totals_fields(totals).join(",")
+            );
+        }
+        csv
+    }
+
+    /// 导出为可打印的 HTML 文档
+    pub fn to_html(&self) -> String {
+        let title = format!(
+            "Usage statement: {} ({} to {})",
+            self.subject_name, self.start_date, self.end_date
+        );
+        let mut html = String::new();
+        let _ = writeln!(
+            html,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+             <style>\n\
+             body {{ font-family: sans-serif; margin: 2em; color: #222; }}\n\
+             table {{ border-collapse: collapse; width: 100%; margin-bottom: 2em; }}\n\
+             th, td {{ border: 1px solid #ccc; padding: 4px 8px; text-align: right; }}\n\
+             th:first-child, td:first-child {{ text-align: left; }}\n\
+             tfoot td {{ font-weight: bold; }}\n\
+             @media print {{ body {{ margin: 0; }} }}\n\
+             </style>\n</head>\n<body>\n<h1>{title}</h1>",
+            title = html_escape(&title)
+        );
+        let _ = writeln!(
+            html,
+            "<p>Scope: {} ({})<br>Generated at: {}<br>Keys: {}</p>",
+            self.scope.as_str(),
+            html_escape(&self.subject_id),
+            self.generated_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            html_escape(
+                &self
+                    .keys
+                    .iter()
+                    .map(|key| key.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        );
+
+        let days = self
+            .days
+            .iter()
+            .map(|day| (day.date.to_string(), &day.totals));
+        html_table(&mut html, "Daily usage", "Date", days, &self.totals);
+        let models = self
+            .models
+            .iter()
+            .map(|model| (model.model.clone(), &model.totals));
+        html_table(&mut html, "Usage by model", "Model", models, &self.totals);
+
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+
+fn html_table<'a>(
+    html: &mut String,
+    caption: &str,
+    item_header: &str,
+    rows: impl Iterator<Item = (String, &'a StatementTotals)>,
+    totals: &StatementTotals,
+) {
+    let _ = write!(
+        html,
+        "<h2>{}</h2>\n<table>\n<thead><tr><th>{}</th>",
+        caption, item_header
+    );
+    for header in TOTALS_HEADER {
+        let _ = write!(html, "<th>{}</th>", header);
+    }
+    html.push_str("</tr></thead>\n<tbody>\n");
+
+    let mut row = |item: &str, totals: &StatementTotals| {
+        let _ = write!(html, "<tr><td>{}</td>", html_escape(item));
+        for field in totals_fields(totals) {
+            let _ = write!(html, "<td>{}</td>", field);
+        }
+        html.push_str("</tr>\n");
+    };
+    for (item, row_totals) in rows {
+        row(&item, row_totals);
+    }
+    row("Total", totals);
+    html.push_str("</tbody>\n</table>\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statement() -> Statement {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 31).unwrap();
+        let totals = StatementTotals {
+            requests: 3,
+            errors: 1,
+            input_tokens: 100,
+            cost: 0.5,
+            billed_cost: 0.75,
+            ..Default::default()
+        };
+        Statement {
+            scope: StatementScope::Tag,
+            subject_id: "acme".to_string(),
+            subject_name: "acme".to_string(),
+            start_date: date,
+            end_date: date,
+            generated_at: "2025-02-01T00:00:00Z".parse().unwrap(),
+            keys: vec![StatementKey {
+                id: "k1".to_string(),
+                name: "<script>".to_string(),
+                is_deleted: false,
+            }],
+            days: vec![StatementDay {
+                date,
+                totals: totals.clone(),
+            }],
+            models: vec![StatementModel {
+                model: "claude,sonnet".to_string(),
+                totals: totals.clone(),
+            }],
+            totals,
+        }
+    }
+
+    #[test]
+    fn test_query_validation() {
+        let query = |json: &str| -> StatementQuery { serde_json::from_str(json).unwrap() };
+
+        let ok =
+            query(r#"{"scope":"key","id":"k1","startDate":"2025-01-01","endDate":"2025-01-31"}"#);
+        assert_eq!(ok.scope(), Ok(StatementScope::Key));
+        assert_eq!(ok.format(), Ok(StatementFormat::Json));
+        assert!(ok.date_range().is_ok());
+
+        let too_long =
+            query(r#"{"scope":"key","id":"k1","startDate":"2025-01-01","endDate":"2025-02-01"}"#);
+        assert!(too_long.date_range().is_err());
+        let reversed =
+            query(r#"{"scope":"team","id":"k1","startDate":"2025-01-02","endDate":"2025-01-01"}"#);
+        assert!(reversed.date_range().is_err());
+        assert!(reversed.scope().is_err());
+    }
+
+    #[test]
+    fn test_csv_and_html() {
+        let statement = statement();
+
+        let csv = statement.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("section,item,requests,errors"));
+        assert_eq!(
+            lines[1],
+            "day,2025-01-31,3,1,100,0,0,0,0.500000,0.750000,0.000000"
+        );
+        assert!(lines[2].starts_with("model,\"claude,sonnet\",3"));
+        assert!(lines[3].starts_with("total,,3,1"));
+
+        let html = statement.to_html();
+        assert!(html.contains("&lt;script&gt;"));
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("<td>2025-01-31</td>"));
+    }
+}
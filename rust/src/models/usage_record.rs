@@ -1,3 +1,5 @@
+use crate::models::billing_event::BillingDetails;
+
 /// 使用记录数据结构
 #[derive(Debug, Clone)]
 pub struct UsageRecord {
@@ -8,6 +10,10 @@ pub struct UsageRecord {
     pub cache_creation_tokens: i64,
     pub cache_read_tokens: i64,
     pub cost: f64,
+    /// 处理该请求的上游账户 (用于按账户的时间序列统计)
+    pub account_id: Option<String>,
+    /// 计费事件所需的明细 (请求 ID、平台、缓存 TTL 明细、成本明细、定价版本)
+    pub billing: Option<BillingDetails>,
 }
 
 impl UsageRecord {
@@ -30,6 +36,20 @@ impl UsageRecord {
             cache_creation_tokens,
             cache_read_tokens,
             cost,
+            account_id: None,
+            billing: None,
         }
     }
+
+    /// 设置处理该请求的上游账户
+    pub fn with_account(mut self, account_id: impl Into<String>) -> Self {
+        self.account_id = Some(account_id.into());
+        self
+    }
+
+    /// 附加计费明细
+    pub fn with_billing(mut self, billing: BillingDetails) -> Self {
+        self.billing = Some(billing);
+        self
+    }
 }
@@ -0,0 +1,619 @@
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, TimeZone, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{timezone_from_hours, UsageRetentionSettings};
+use crate::models::api_key::ModelUsage;
+use crate::utils::escape_csv_field;
+
+/// 小时统计桶的默认保留时间 (秒)，覆盖最长的小时粒度查询范围
+///
+/// 实际保留时间由 `UsageRetentionSettings::hourly_days` 配置
+pub const HOURLY_USAGE_RETENTION_SECONDS: i64 = 8 * 24 * 3600;
+
+/// 小时桶在保留期之外多保留的时间 (秒)，留给清理任务汇总进日桶
+pub const HOURLY_ROLLUP_GRACE_SECONDS: i64 = 24 * 3600;
+
+/// 小时粒度单次查询的最大范围 (小时)
+pub const MAX_HOURLY_TREND_HOURS: i64 = 7 * 24;
+
+/// 日粒度单次查询的最大天数
+pub const MAX_DAILY_TREND_DAYS: i64 = 90;
+
+/// 时间序列统计粒度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TrendGranularity {
+    Hour,
+    Day,
+}
+
+impl TrendGranularity {
+    /// 解析查询参数，同时接受 `hour`/`hourly` 和 `day`/`daily`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "hour" | "hourly" => Some(Self::Hour),
+            "day" | "daily" => Some(Self::Day),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Hour => "hour",
+            Self::Day => "day",
+        }
+    }
+
+    /// Redis 键中使用的名称
+    fn key_segment(&self) -> &'static str {
+        match self {
+            Self::Hour => "hourly",
+            Self::Day => "daily",
+        }
+    }
+
+    /// 统计桶的过期时间 (秒)
+    pub fn retention_seconds(&self, retention: &UsageRetentionSettings) -> i64 {
+        match self {
+            Self::Hour => retention.hourly_days * 24 * 3600 + HOURLY_ROLLUP_GRACE_SECONDS,
+            Self::Day => retention.daily_days * 24 * 3600,
+        }
+    }
+}
+
+/// 时间序列统计维度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsageDimension {
+    Global,
+    ApiKey,
+    Account,
+    Model,
+}
+
+impl UsageDimension {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Global => "global",
+            Self::ApiKey => "key",
+            Self::Account => "account",
+            Self::Model => "model",
+        }
+    }
+}
+
+/// 一个统计桶：小时桶按 UTC 整点划分 (`2025-01-31T16`)，日桶按系统时区日期划分 (`2025-01-31`)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UsageBucket {
+    pub granularity: TrendGranularity,
+    pub name: String,
+}
+
+impl UsageBucket {
+    pub fn hour(at: DateTime<Utc>) -> Self {
+        Self {
+            granularity: TrendGranularity::Hour,
+            name: at.format("%Y-%m-%dT%H").to_string(),
+        }
+    }
+
+    pub fn day(date: NaiveDate) -> Self {
+        Self {
+            granularity: TrendGranularity::Day,
+            name: date.format("%Y-%m-%d").to_string(),
+        }
+    }
+
+    /// 组成指定时区某一天的 24 个小时桶
+    pub fn hours_of_day(date: NaiveDate, timezone: FixedOffset) -> Vec<Self> {
+        let start = local_midnight(date, timezone);
+        (0..24)
+            .map(|h| Self::hour(start + Duration::hours(h)))
+            .collect()
+    }
+
+    /// 统计 Hash 的键
+    ///
+    /// 格式: `usage:global:{hourly|daily}:{bucket}` 或 `usage:{key|account|model}:{id}:{hourly|daily}:{bucket}`
+    pub fn usage_key(&self, dimension: UsageDimension, id: &str) -> String {
+        match dimension {
+            UsageDimension::Global => format!(
+                "usage:global:{}:{}",
+                self.granularity.key_segment(),
+                self.name
+            ),
+            _ => format!(
+                "usage:{}:{}:{}:{}",
+                dimension.as_str(),
+                id,
+                self.granularity.key_segment(),
+                self.name
+            ),
+        }
+    }
+
+    /// 桶内出现过的成员 (Key / 账户 / 模型) 集合，避免查询时扫描键
+    ///
+    /// 格式: `usage:members:{key|account|model}:{hourly|daily}:{bucket}`
+    pub fn members_key(&self, dimension: UsageDimension) -> String {
+        format!(
+            "usage:members:{}:{}:{}",
+            dimension.as_str(),
+            self.granularity.key_segment(),
+            self.name
+        )
+    }
+}
+
+/// 趋势中的一个时间点
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrendPoint {
+    /// 时间点开始时刻 (UTC)
+    pub start: DateTime<Utc>,
+    /// 在查询时区下的显示标签：小时粒度 `MM/DD HH:00`，日粒度 `YYYY-MM-DD`
+    pub label: String,
+    /// 组成该时间点的统计桶
+    pub buckets: Vec<UsageBucket>,
+}
+
+/// 解析后的趋势查询范围
+#[derive(Debug, Clone)]
+pub struct TrendRange {
+    pub granularity: TrendGranularity,
+    pub timezone: FixedOffset,
+    pub points: Vec<TrendPoint>,
+}
+
+impl TrendRange {
+    /// `start` 到 `end` 之间的每个整点 (含两端所在的小时)
+    pub fn hourly(
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        timezone: FixedOffset,
+    ) -> Result<Self, String> {
+        if end < start {
+            return Err("endDate must not be before startDate".to_string());
+        }
+        let first = start
+            .with_minute(0)
+            .and_then(|t| t.with_second(0))
+            .and_then(|t| t.with_nanosecond(0))
+            .unwrap_or(start);
+        let hours = (end - first).num_hours();
+        if hours >= MAX_HOURLY_TREND_HOURS {
+            return Err(format!(
+                "Hourly range cannot exceed {} hours",
+                MAX_HOURLY_TREND_HOURS
+            ));
+        }
+
+        let points = (0..=hours)
+            .map(|i| {
+                let at = first + Duration::hours(i);
+                TrendPoint {
+                    start: at,
+                    label: at
+                        .with_timezone(&timezone)
+                        .format("%m/%d %H:00")
+                        .to_string(),
+                    buckets: vec![UsageBucket::hour(at)],
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            granularity: TrendGranularity::Hour,
+            timezone,
+            points,
+        })
+    }
+
+    /// `first` 到 `last` 的每一天 (查询时区的日期)
+    ///
+    /// 日桶按系统时区划分；查询时区与系统时区不同时，由仍在保留期内的小时桶拼出当天，
+    /// 更早的日期只能退回到系统时区的日桶
+    pub fn daily(
+        first: NaiveDate,
+        last: NaiveDate,
+        timezone: FixedOffset,
+        system_timezone: FixedOffset,
+        now: DateTime<Utc>,
+    ) -> Result<Self, String> {
+        if last < first {
+            return Err("endDate must not be before startDate".to_string());
+        }
+        let days = (last - first).num_days() + 1;
+        if days > MAX_DAILY_TREND_DAYS {
+            return Err(format!(
+                "Daily range cannot exceed {} days",
+                MAX_DAILY_TREND_DAYS
+            ));
+        }
+
+        let hourly_since = now - Duration::seconds(HOURLY_USAGE_RETENTION_SECONDS);
+        let points = first
+            .iter_days()
+            .take(days as usize)
+            .map(|date| {
+                let start = local_midnight(date, timezone);
+                let buckets = if timezone == system_timezone || start < hourly_since {
+                    vec![UsageBucket::day(date)]
+                } else {
+                    UsageBucket::hours_of_day(date, timezone)
+                };
+                TrendPoint {
+                    start,
+                    label: date.format("%Y-%m-%d").to_string(),
+                    buckets,
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            granularity: TrendGranularity::Day,
+            timezone,
+            points,
+        })
+    }
+
+    /// 所有时间点涉及的统计桶
+    pub fn buckets(&self) -> impl Iterator<Item = &UsageBucket> {
+        self.points.iter().flat_map(|point| point.buckets.iter())
+    }
+}
+
+/// 趋势接口的通用查询参数
+///
+/// - `granularity`: `hour` / `day`，默认 `day`
+/// - `days`: 日粒度时查询最近 N 天 (含今天)，默认 7
+/// - `startDate` / `endDate`: ISO 8601 时间范围，优先于 `days`；小时粒度未提供时默认最近 24 小时
+/// - `timezone`: 查询时区 (相对 UTC 的小时数，可为 5.5 等 15 分钟的整数倍)，默认系统时区
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrendQuery {
+    pub granularity: Option<String>,
+    pub days: Option<i64>,
+    pub start_date: Option<DateTime<Utc>>,
+    pub end_date: Option<DateTime<Utc>>,
+    pub timezone: Option<f64>,
+}
+
+impl TrendQuery {
+    /// 未指定粒度时默认按天；但自定义范围不从 0 点开始且不超过小时粒度上限时 (如最近 24 小时) 按小时
+    pub fn granularity(&self, timezone: FixedOffset) -> Result<TrendGranularity, String> {
+        match (self.granularity.as_deref(), self.start_date, self.end_date) {
+            (Some(value), _, _) => TrendGranularity::parse(value)
+                .ok_or_else(|| format!("Invalid granularity: {}, expected hour or day", value)),
+            (None, Some(start), Some(end))
+                if start.with_timezone(&timezone).num_seconds_from_midnight() != 0
+                    && (end - start).num_hours() < MAX_HOURLY_TREND_HOURS =>
+            {
+                Ok(TrendGranularity::Hour)
+            }
+            (None, _, _) => Ok(TrendGranularity::Day),
+        }
+    }
+
+    pub fn timezone(&self, system_timezone: FixedOffset) -> Result<FixedOffset, String> {
+        match self.timezone {
+            None => Ok(system_timezone),
+            Some(hours) => timezone_from_hours(hours).ok_or_else(|| {
+                "timezone must be between -12 and 14 hours in steps of 15 minutes".to_string()
+            }),
+        }
+    }
+
+    pub fn resolve(
+        &self,
+        system_timezone: FixedOffset,
+        now: DateTime<Utc>,
+    ) -> Result<TrendRange, String> {
+        let timezone = self.timezone(system_timezone)?;
+
+        match self.granularity(timezone)? {
+            TrendGranularity::Hour => {
+                let (start, end) = match (self.start_date, self.end_date) {
+                    (Some(start), Some(end)) => (start, end),
+                    _ => (now - Duration::hours(24), now),
+                };
+                TrendRange::hourly(start, end, timezone)
+            }
+            TrendGranularity::Day => {
+                let (first, last) = match (self.start_date, self.end_date) {
+                    (Some(start), Some(end)) => (
+                        start.with_timezone(&timezone).date_naive(),
+                        end.with_timezone(&timezone).date_naive(),
+                    ),
+                    _ => {
+                        let days = self.days.unwrap_or(7).clamp(1, MAX_DAILY_TREND_DAYS);
+                        let today = now.with_timezone(&timezone).date_naive();
+                        (today - Duration::days(days - 1), today)
+                    }
+                };
+                TrendRange::daily(first, last, timezone, system_timezone, now)
+            }
+        }
+    }
+}
+
+/// 单次导出的最大范围 (天)
+pub const MAX_USAGE_EXPORT_DAYS: i64 = 366;
+
+/// 使用量导出查询参数
+///
+/// - `dimension`: `global` / `key` / `account` / `model`，默认 `key`
+/// - `id`: 只导出该成员，默认导出维度下的全部成员
+/// - `granularity`: `hour` (UTC 整点) / `day` (系统时区日期)，默认 `day`
+/// - `startDate` / `endDate`: 时间范围，默认最近 7 天
+/// - `format`: `csv` / `jsonl`，默认 `csv`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageExportQuery {
+    pub dimension: Option<String>,
+    pub id: Option<String>,
+    pub granularity: Option<String>,
+    pub start_date: Option<DateTime<Utc>>,
+    pub end_date: Option<DateTime<Utc>>,
+    pub format: Option<String>,
+}
+
+impl UsageExportQuery {
+    pub fn dimension(&self) -> Result<UsageDimension, String> {
+        match self.dimension.as_deref().unwrap_or("key") {
+            "global" => Ok(UsageDimension::Global),
+            "key" => Ok(UsageDimension::ApiKey),
+            "account" => Ok(UsageDimension::Account),
+            "model" => Ok(UsageDimension::Model),
+            other => Err(format!(
+                "Invalid dimension: {}, expected global, key, account or model",
+                other
+            )),
+        }
+    }
+
+    /// 是否导出为 JSONL (默认 CSV)
+    pub fn is_jsonl(&self) -> Result<bool, String> {
+        match self.format.as_deref().unwrap_or("csv") {
+            "csv" => Ok(false),
+            "jsonl" => Ok(true),
+            other => Err(format!("Invalid format: {}, expected csv or jsonl", other)),
+        }
+    }
+
+    /// 范围内按时间顺序的统计桶
+    pub fn buckets(
+        &self,
+        system_timezone: FixedOffset,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<UsageBucket>, String> {
+        let end = self.end_date.unwrap_or(now);
+        let start = self.start_date.unwrap_or(end - Duration::days(7));
+        if end < start {
+            return Err("endDate must not be before startDate".to_string());
+        }
+        if (end - start).num_days() >= MAX_USAGE_EXPORT_DAYS {
+            return Err(format!(
+                "Export range cannot exceed {} days",
+                MAX_USAGE_EXPORT_DAYS
+            ));
+        }
+
+        let granularity = match self.granularity.as_deref() {
+            None => TrendGranularity::Day,
+            Some(value) => TrendGranularity::parse(value)
+                .ok_or_else(|| format!("Invalid granularity: {}, expected hour or day", value))?,
+        };
+        Ok(match granularity {
+            TrendGranularity::Hour => {
+                let first = start
+                    .with_minute(0)
+                    .and_then(|t| t.with_second(0))
+                    .and_then(|t| t.with_nanosecond(0))
+                    .unwrap_or(start);
+                (0..=(end - first).num_hours())
+                    .map(|i| UsageBucket::hour(first + Duration::hours(i)))
+                    .collect()
+            }
+            TrendGranularity::Day => {
+                let first = start.with_timezone(&system_timezone).date_naive();
+                let last = end.with_timezone(&system_timezone).date_naive();
+                first
+                    .iter_days()
+                    .take_while(|date| *date <= last)
+                    .map(UsageBucket::day)
+                    .collect()
+            }
+        })
+    }
+}
+
+/// 导出的一行：某个统计桶中一个成员的使用量
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageExportRow {
+    /// 统计桶 (`2025-01-31T16` 或 `2025-01-31`)
+    pub bucket: String,
+    pub dimension: &'static str,
+    /// 全局统计时为空
+    pub id: String,
+    pub requests: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_creation_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub cost: f64,
+    pub billed_cost: f64,
+}
+
+impl UsageExportRow {
+    pub const CSV_HEADER: &'static str = "bucket,dimension,id,requests,inputTokens,outputTokens,\
+                                          cacheCreationTokens,cacheReadTokens,cost,billedCost";
+
+    pub fn new(
+        bucket: &UsageBucket,
+        dimension: UsageDimension,
+        id: &str,
+        usage: &ModelUsage,
+    ) -> Self {
+        Self {
+            bucket: bucket.name.clone(),
+            dimension: dimension.as_str(),
+            id: id.to_string(),
+            requests: usage.requests,
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+            cache_creation_tokens: usage.cache_creation_tokens,
+            cache_read_tokens: usage.cache_read_tokens,
+            cost: usage.cost,
+            billed_cost: usage.billed_cost,
+        }
+    }
+
+    pub fn to_csv(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{:.6},{:.6}",
+            self.bucket,
+            self.dimension,
+            escape_csv_field(&self.id),
+            self.requests,
+            self.input_tokens,
+            self.output_tokens,
+            self.cache_creation_tokens,
+            self.cache_read_tokens,
+            self.cost,
+            self.billed_cost
+        )
+    }
+}
+
+/// 查询时区中某天 0 点对应的 UTC 时刻
+fn local_midnight(date: NaiveDate, timezone: FixedOffset) -> DateTime<Utc> {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+    timezone
+        .from_local_datetime(&midnight)
+        .single()
+        .map(|t| t.with_timezone(&Utc))
+        .unwrap_or_else(|| Utc.from_utc_datetime(&midnight))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tz(hours: i32) -> FixedOffset {
+        FixedOffset::east_opt(hours * 3600).unwrap()
+    }
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_bucket_keys() {
+        let bucket = UsageBucket::hour(utc("2025-01-31T16:42:00Z"));
+        assert_eq!(bucket.name, "2025-01-31T16");
+        assert_eq!(
+            bucket.usage_key(UsageDimension::Global, ""),
+            "usage:global:hourly:2025-01-31T16"
+        );
+        assert_eq!(
+            bucket.usage_key(UsageDimension::Model, "claude-sonnet-4"),
+            "usage:model:claude-sonnet-4:hourly:2025-01-31T16"
+        );
+
+        let day = UsageBucket::day(NaiveDate::from_ymd_opt(2025, 1, 31).unwrap());
+        assert_eq!(
+            day.usage_key(UsageDimension::ApiKey, "k1"),
+            "usage:key:k1:daily:2025-01-31"
+        );
+        assert_eq!(
+            day.members_key(UsageDimension::Account),
+            "usage:members:account:daily:2025-01-31"
+        );
+    }
+
+    #[test]
+    fn test_hourly_range() {
+        let range = TrendRange::hourly(
+            utc("2025-01-31T16:30:00Z"),
+            utc("2025-01-31T18:05:00Z"),
+            tz(8),
+        )
+        .unwrap();
+
+        assert_eq!(range.points.len(), 3);
+        assert_eq!(range.points[0].start, utc("2025-01-31T16:00:00Z"));
+        assert_eq!(range.points[0].label, "02/01 00:00");
+        assert_eq!(range.points[2].buckets[0].name, "2025-01-31T18");
+
+        let too_long = TrendRange::hourly(
+            utc("2025-01-01T00:00:00Z"),
+            utc("2025-01-09T00:00:00Z"),
+            tz(8),
+        );
+        assert!(too_long.is_err());
+    }
+
+    #[test]
+    fn test_daily_range_timezones() {
+        let now = utc("2025-01-31T12:00:00Z");
+        let query = TrendQuery {
+            days: Some(3),
+            ..Default::default()
+        };
+
+        // 系统时区：直接读取日桶
+        let range = query.resolve(tz(8), now).unwrap();
+        let labels: Vec<_> = range.points.iter().map(|p| p.label.as_str()).collect();
+        assert_eq!(labels, ["2025-01-29", "2025-01-30", "2025-01-31"]);
+        assert_eq!(
+            range.points[0].buckets,
+            [UsageBucket::day(
+                NaiveDate::from_ymd_opt(2025, 1, 29).unwrap()
+            )]
+        );
+
+        // 其他时区：由小时桶拼出当天
+        let query = TrendQuery {
+            days: Some(1),
+            timezone: Some(-5.0),
+            ..Default::default()
+        };
+        let range = query.resolve(tz(8), now).unwrap();
+        assert_eq!(range.points[0].label, "2025-01-31");
+        assert_eq!(range.points[0].buckets.len(), 24);
+        assert_eq!(range.points[0].buckets[0].name, "2025-01-31T05");
+
+        assert!(TrendQuery {
+            timezone: Some(15.0),
+            ..Default::default()
+        }
+        .resolve(tz(8), now)
+        .is_err());
+        assert!(TrendQuery {
+            timezone: Some(5.6),
+            ..Default::default()
+        }
+        .resolve(tz(8), now)
+        .is_err());
+        // 未指定粒度的自定义范围：整天按天，其他按小时
+        let last_24h = TrendQuery {
+            start_date: Some(utc("2025-01-30T12:30:00Z")),
+            end_date: Some(now),
+            ..Default::default()
+        };
+        assert_eq!(last_24h.granularity(tz(8)), Ok(TrendGranularity::Hour));
+        let whole_days = TrendQuery {
+            start_date: Some(utc("2025-01-29T16:00:00Z")),
+            end_date: Some(utc("2025-01-31T15:59:59Z")),
+            ..Default::default()
+        };
+        assert_eq!(whole_days.granularity(tz(8)), Ok(TrendGranularity::Day));
+        assert_eq!(whole_days.resolve(tz(8), now).unwrap().points.len(), 2);
+
+        assert!(TrendQuery {
+            granularity: Some("week".to_string()),
+            ..Default::default()
+        }
+        .resolve(tz(8), now)
+        .is_err());
+    }
+}
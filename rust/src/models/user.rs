@@ -0,0 +1,173 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 普通用户默认可创建的 API Key 数量
+pub const DEFAULT_USER_MAX_API_KEYS: i64 = 5;
+
+/// 普通用户
+///
+/// 存储在 `user:{id}`，用户名索引为 `user_username:{username}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct User {
+    /// 用户 ID (UUID)
+    pub id: String,
+
+    /// 登录用户名 (唯一，不区分大小写)
+    pub username: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none", rename = "displayName")]
+    pub display_name: Option<String>,
+
+    /// Argon2 密码哈希，对外返回前通过 `sanitized` 移除
+    #[serde(skip_serializing_if = "Option::is_none", rename = "passwordHash")]
+    pub password_hash: Option<String>,
+
+    /// JWT 中的角色 (LDAP 用户为组映射得到的角色)
+    #[serde(default = "default_user_role")]
+    pub role: String,
+
+    /// 认证来源，LDAP 用户没有本地密码
+    #[serde(rename = "authSource", default)]
+    pub auth_source: UserAuthSource,
+
+    #[serde(rename = "isActive")]
+    pub is_active: bool,
+
+    /// 可创建的 API Key 数量上限 (0 表示不允许自行创建)
+    #[serde(rename = "maxApiKeys", default = "default_max_api_keys")]
+    pub max_api_keys: i64,
+
+    /// 自行创建的 Key 每日费用限制上限 (0 表示不限制)
+    #[serde(rename = "maxKeyDailyCostLimit", default)]
+    pub max_key_daily_cost_limit: f64,
+
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+
+    #[serde(rename = "updatedAt")]
+    pub updated_at: DateTime<Utc>,
+
+    #[serde(skip_serializing_if = "Option::is_none", rename = "lastLoginAt")]
+    pub last_login_at: Option<DateTime<Utc>>,
+
+    /// 邀请人 (管理员用户名)，开放注册时为 None
+    #[serde(skip_serializing_if = "Option::is_none", rename = "invitedBy")]
+    pub invited_by: Option<String>,
+}
+
+impl User {
+    /// 移除敏感字段，用于 API 响应
+    pub fn sanitized(&self) -> Self {
+        Self {
+            password_hash: None,
+            ..self.clone()
+        }
+    }
+}
+
+/// 用户认证来源
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UserAuthSource {
+    /// 本地注册，使用 Argon2 密码哈希
+    #[default]
+    Local,
+    /// 首次 LDAP 登录时自动创建
+    Ldap,
+}
+
+fn default_user_role() -> String {
+    "user".to_string()
+}
+
+fn default_max_api_keys() -> i64 {
+    DEFAULT_USER_MAX_API_KEYS
+}
+
+/// 用户注册邀请
+///
+/// 存储在 `user_invitation:{code}`，过期后自动删除
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserInvitation {
+    pub code: String,
+
+    /// 限定注册邮箱 (可选)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+
+    /// 覆盖默认的 Key 数量上限
+    #[serde(skip_serializing_if = "Option::is_none", rename = "maxApiKeys")]
+    pub max_api_keys: Option<i64>,
+
+    #[serde(rename = "createdBy")]
+    pub created_by: String,
+
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+
+    #[serde(rename = "expiresAt")]
+    pub expires_at: DateTime<Utc>,
+}
+
+/// 用户管理设置 (存储在 Redis，管理员可修改)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserManagementSettings {
+    /// 是否允许无邀请码注册
+    #[serde(rename = "allowRegistration", default)]
+    pub allow_registration: bool,
+
+    /// 新用户默认的 Key 数量上限
+    #[serde(rename = "defaultMaxApiKeys", default = "default_max_api_keys")]
+    pub default_max_api_keys: i64,
+
+    /// 新用户默认的 Key 每日费用限制上限 (0 表示不限制)
+    #[serde(rename = "defaultMaxKeyDailyCostLimit", default)]
+    pub default_max_key_daily_cost_limit: f64,
+}
+
+impl Default for UserManagementSettings {
+    fn default() -> Self {
+        Self {
+            allow_registration: false,
+            default_max_api_keys: DEFAULT_USER_MAX_API_KEYS,
+            default_max_key_daily_cost_limit: 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_sanitized_hides_password_hash() {
+        let now = Utc::now();
+        let user = User {
+            id: "u1".to_string(),
+            username: "alice".to_string(),
+            email: None,
+            display_name: None,
+            password_hash: Some("$argon2id$...".to_string()),
+            role: "user".to_string(),
+            auth_source: UserAuthSource::Local,
+            is_active: true,
+            max_api_keys: DEFAULT_USER_MAX_API_KEYS,
+            max_key_daily_cost_limit: 0.0,
+            created_at: now,
+            updated_at: now,
+            last_login_at: None,
+            invited_by: None,
+        };
+
+        let stored = serde_json::to_value(&user).unwrap();
+        assert!(stored.get("passwordHash").is_some());
+
+        let public = serde_json::to_value(user.sanitized()).unwrap();
+        assert!(public.get("passwordHash").is_none());
+        assert_eq!(public["isActive"], true);
+        assert_eq!(public["authSource"], "local");
+    }
+}
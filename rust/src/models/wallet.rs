@@ -0,0 +1,288 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// 每个钱包流水保留的条数上限，超过后丢弃最旧的记录 (充值和兑换另存于不截断的入账流水)
+pub const MAX_WALLET_LEDGER_ENTRIES: usize = 10_000;
+
+/// 单次查询返回的最大流水条数
+pub const MAX_WALLET_LEDGER_PAGE: usize = 500;
+
+/// 单批生成的兑换码数量上限
+pub const MAX_REDEMPTION_CODES_PER_BATCH: usize = 1000;
+
+/// 全部兑换码索引 (ZSET，score 为创建时间)
+pub const REDEMPTION_CODES_KEY: &str = "redemption_codes";
+
+/// 钱包归属
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WalletOwnerType {
+    /// 单个 API Key 的钱包
+    Key,
+    /// 用户的钱包，由该用户名下没有独立钱包的 Key 共用
+    User,
+}
+
+impl WalletOwnerType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Key => "key",
+            Self::User => "user",
+        }
+    }
+}
+
+impl FromStr for WalletOwnerType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "key" => Ok(Self::Key),
+            "user" => Ok(Self::User),
+            other => Err(format!("Invalid wallet owner type: {}", other)),
+        }
+    }
+}
+
+/// 钱包所有者 (Key 或用户)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WalletOwner {
+    pub owner_type: WalletOwnerType,
+    pub owner_id: String,
+}
+
+impl WalletOwner {
+    pub fn key(key_id: &str) -> Self {
+        Self {
+            owner_type: WalletOwnerType::Key,
+            owner_id: key_id.to_string(),
+        }
+    }
+
+    pub fn user(user_id: &str) -> Self {
+        Self {
+            owner_type: WalletOwnerType::User,
+            owner_id: user_id.to_string(),
+        }
+    }
+
+    /// 余额 Hash `wallet:{type}:{id}`
+    pub fn wallet_key(&self) -> String {
+        format!("wallet:{}", self)
+    }
+
+    /// 流水 List `wallet_ledger:{type}:{id}` (最新的在前)
+    pub fn ledger_key(&self) -> String {
+        format!("wallet_ledger:{}", self)
+    }
+
+    /// 入账流水 List `wallet_credits:{type}:{id}` (只含充值和兑换，不截断，最新的在前)
+    pub fn credit_ledger_key(&self) -> String {
+        format!("wallet_credits:{}", self)
+    }
+
+    /// 在途请求成本预占 Hash `wallet_reservation:{type}:{id}` (共用钱包的所有 Key 一起计算)
+    pub fn reservation_key(&self) -> String {
+        format!("wallet_reservation:{}", self)
+    }
+}
+
+impl fmt::Display for WalletOwner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.owner_type.as_str(), self.owner_id)
+    }
+}
+
+/// 预付费钱包
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Wallet {
+    pub owner: WalletOwner,
+    /// 余额 (USD)，单次请求的费用超过剩余余额时可能为负
+    pub balance: f64,
+    /// 累计充值和兑换
+    pub total_credited: f64,
+    /// 累计消费 (计费成本)
+    pub total_spent: f64,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LedgerEntryType {
+    /// 管理员充值
+    Topup,
+    /// 兑换码兑换
+    Redemption,
+    /// 请求消费
+    Usage,
+}
+
+/// 钱包流水，余额变更和流水在同一个 Lua 脚本中写入
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LedgerEntry {
+    pub id: String,
+    pub entry_type: LedgerEntryType,
+    /// 变动金额，消费为负
+    pub amount: f64,
+    /// 变动后的余额 (由脚本写入)
+    #[serde(default)]
+    pub balance_after: f64,
+    /// 充值的管理员或兑换的 Key ID
+    #[serde(default)]
+    pub operator: Option<String>,
+    /// 消费对应的请求 ID 或兑换码
+    #[serde(default)]
+    pub reference: Option<String>,
+    /// 消费的模型
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl LedgerEntry {
+    pub fn new(entry_type: LedgerEntryType, amount: f64) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            entry_type,
+            amount,
+            balance_after: 0.0,
+            operator: None,
+            reference: None,
+            model: None,
+            note: None,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// 管理员充值请求
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WalletTopUpRequest {
+    pub amount: f64,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+/// 兑换码 Hash `redemption_code:{code}`
+pub fn redemption_code_key(code: &str) -> String {
+    format!("redemption_code:{}", code)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RedemptionCodeStatus {
+    Active,
+    Redeemed,
+    Disabled,
+}
+
+impl FromStr for RedemptionCodeStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "active" => Ok(Self::Active),
+            "redeemed" => Ok(Self::Redeemed),
+            "disabled" => Ok(Self::Disabled),
+            other => Err(format!("Invalid redemption code status: {}", other)),
+        }
+    }
+}
+
+/// 兑换码
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RedemptionCode {
+    pub code: String,
+    pub amount: f64,
+    pub status: RedemptionCodeStatus,
+    pub batch_id: String,
+    pub note: Option<String>,
+    pub created_by: String,
+    pub created_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// 兑换到的钱包 (`key:{id}` 或 `user:{id}`)
+    pub redeemed_to: Option<String>,
+    pub redeemed_at: Option<DateTime<Utc>>,
+}
+
+/// 批量生成兑换码请求
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerateRedemptionCodesRequest {
+    pub count: usize,
+    pub amount: f64,
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+impl GenerateRedemptionCodesRequest {
+    pub fn validate(&self, now: DateTime<Utc>) -> Result<(), String> {
+        if self.count == 0 || self.count > MAX_REDEMPTION_CODES_PER_BATCH {
+            return Err(format!(
+                "count must be between 1 and {}",
+                MAX_REDEMPTION_CODES_PER_BATCH
+            ));
+        }
+        validate_amount(self.amount)?;
+        if self.expires_at.is_some_and(|at| at <= now) {
+            return Err("expiresAt must be in the future".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// 充值和兑换金额必须是正的有限数
+pub fn validate_amount(amount: f64) -> Result<(), String> {
+    if !amount.is_finite() || amount <= 0.0 {
+        return Err("amount must be a positive number".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wallet_owner_keys() {
+        let owner = WalletOwner::user("u1");
+        assert_eq!(owner.wallet_key(), "wallet:user:u1");
+        assert_eq!(owner.ledger_key(), "wallet_ledger:user:u1");
+        assert_eq!(owner.credit_ledger_key(), "wallet_credits:user:u1");
+        assert_eq!(owner.reservation_key(), "wallet_reservation:user:u1");
+        assert_eq!(WalletOwner::key("k1").to_string(), "key:k1");
+        assert_eq!("key".parse(), Ok(WalletOwnerType::Key));
+        assert!("team".parse::<WalletOwnerType>().is_err());
+    }
+
+    #[test]
+    fn test_generate_request_validation() {
+        let now: DateTime<Utc> = "2025-01-31T00:00:00Z".parse().unwrap();
+        let request =
+            |json: &str| -> GenerateRedemptionCodesRequest { serde_json::from_str(json).unwrap() };
+
+        assert!(request(r#"{"count":10,"amount":5}"#).validate(now).is_ok());
+        assert!(request(r#"{"count":0,"amount":5}"#).validate(now).is_err());
+        assert!(request(r#"{"count":1001,"amount":5}"#)
+            .validate(now)
+            .is_err());
+        assert!(request(r#"{"count":1,"amount":0}"#).validate(now).is_err());
+        assert!(
+            request(r#"{"count":1,"amount":5,"expiresAt":"2025-01-30T00:00:00Z"}"#)
+                .validate(now)
+                .is_err()
+        );
+    }
+}
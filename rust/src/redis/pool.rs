@@ -1,4 +1,4 @@
-use deadpool_redis::{Config, Connection, Pool, Runtime};
+use deadpool_redis::{Config, Connection, Pool, Runtime, Status};
 use redis::AsyncCommands;
 
 use crate::config::Settings;
@@ -31,6 +31,11 @@ impl RedisPool {
             .map_err(|e| AppError::RedisError(format!("Failed to get Redis connection: {}", e)))
     }
 
+    /// Current pool usage (size, available and waiting connections)
+    pub fn status(&self) -> Status {
+        self.pool.status()
+    }
+
     /// Ping Redis to check connectivity
     pub async fn ping(&self) -> Result<()> {
         let mut conn = self.get_connection().await?;
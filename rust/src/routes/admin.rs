@@ -1,18 +1,55 @@
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
+    body::Body,
+    extract::{ConnectInfo, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     routing::{delete, get, post, put},
     Json, Router,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::net::SocketAddr;
 use std::sync::Arc;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
-use crate::middleware::{authenticate_jwt, JwtAuthState};
-use crate::models::api_key::{ApiKeyCreateOptions, ApiKeyPermissions};
-use crate::services::{AdminService, ApiKeyService, LoginRequest};
+use crate::config::timezone_hours;
+use crate::middleware::{
+    audit_admin_request, authenticate_admin, require_permission, JwtAuthState, PermissionGuard,
+};
+use crate::models::admin_token::CreateAdminApiTokenRequest;
+use crate::models::api_key::{
+    ApiKey, ApiKeyBulkUpdate, ApiKeyCreateOptions, ApiKeyPermissions, ModelUsage,
+    DEFAULT_CONCURRENCY_QUEUE_MAX_SIZE, DEFAULT_CONCURRENCY_QUEUE_TIMEOUT_MS,
+};
+use crate::services::api_key::DEFAULT_ROTATION_GRACE_PERIOD_SECONDS;
+use crate::services::billing_events::MAX_BILLING_EVENTS_REPLAY;
+use crate::services::pricing_service::PricingService;
+use crate::models::audit::{AuditEvent, AuditLogQuery};
+use crate::models::billing_multiplier::BillingMultipliersUpdate;
+use crate::models::cost_recalculation::CostRecalculationOptions;
+use crate::models::oem::OemSettingsUpdate;
+use crate::models::pricing_override::PricingOverrideRequest;
+use crate::models::role::{Permission, USER_ROLE_NAME};
+use crate::models::statement::{StatementFormat, StatementQuery};
+use crate::models::usage_trend::{
+    TrendGranularity, TrendPoint, TrendQuery, TrendRange, UsageDimension, UsageExportQuery,
+    UsageExportRow,
+};
+use crate::models::user::UserManagementSettings;
+use crate::models::wallet::{
+    GenerateRedemptionCodesRequest, RedemptionCodeStatus, WalletOwner, WalletOwnerType,
+    WalletTopUpRequest,
+};
+use crate::services::user::DEFAULT_INVITATION_TTL_HOURS;
+use crate::services::{
+    AccountUsageService, AdminService, ApiKeyService, AuditService, CostRecalculationService,
+    DashboardService, PricingOverrideService,
+    LockoutScope, LoginGuardService, LoginRequest, LogoutRequest, OemSettingsService,
+    RefreshTokenRequest, RoleService, StatementService, TwoFactorService, UsageTrendService,
+    UserService, WebhookService,
+};
+use crate::utils::client_ip::extract_client_ip;
+use crate::utils::csv::escape_csv_field;
 use crate::utils::error::AppError;
 
 // ============================================================================
@@ -24,20 +61,20 @@ use crate::utils::error::AppError;
 pub struct AdminRouteState {
     pub admin_service: Arc<AdminService>,
     pub api_key_service: Arc<ApiKeyService>,
+    pub user_service: Arc<UserService>,
+    pub role_service: Arc<RoleService>,
+    pub audit_service: Arc<AuditService>,
+    pub login_guard: Arc<LoginGuardService>,
+    pub oem_service: Arc<OemSettingsService>,
+    pub dashboard_service: Arc<DashboardService>,
+    pub trend_service: Arc<UsageTrendService>,
+    pub account_usage_service: Arc<AccountUsageService>,
+    pub cost_recalculation: Arc<CostRecalculationService>,
+    pub pricing_overrides: Arc<PricingOverrideService>,
+    pub statement_service: Arc<StatementService>,
     pub redis: crate::RedisPool,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct OemSettings {
-    pub enabled: bool,
-    #[serde(rename = "companyName")]
-    pub company_name: Option<String>,
-    #[serde(rename = "logoUrl")]
-    pub logo_url: Option<String>,
-    #[serde(rename = "themeColor")]
-    pub theme_color: Option<String>,
-}
-
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ClaudeAccountRequest {
     pub name: String,
@@ -82,6 +119,12 @@ pub struct ApiKeyRequest {
     pub rate_limit_cost: Option<f64>,
     #[serde(rename = "concurrencyLimit")]
     pub concurrency_limit: Option<i32>,
+    #[serde(rename = "concurrencyQueueEnabled")]
+    pub concurrency_queue_enabled: Option<bool>,
+    #[serde(rename = "concurrencyQueueMaxSize")]
+    pub concurrency_queue_max_size: Option<i64>,
+    #[serde(rename = "concurrencyQueueTimeoutMs")]
+    pub concurrency_queue_timeout_ms: Option<i64>,
     #[serde(rename = "dailyCostLimit")]
     pub daily_cost_limit: Option<f64>,
     #[serde(rename = "totalCostLimit")]
@@ -118,6 +161,153 @@ pub struct ApiKeyRequest {
     pub owner_id: Option<String>,
 }
 
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct RotateApiKeyRequest {
+    #[serde(rename = "gracePeriodSeconds")]
+    pub grace_period_seconds: Option<i64>,
+    #[serde(rename = "notifyOnOldKeyUse", default)]
+    pub notify_on_old_key_use: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BatchCreateApiKeysRequest {
+    /// Key 模板，`name` 支持 `{n}` / `{n:3}` 序号占位符
+    #[serde(flatten)]
+    pub template: ApiKeyRequest,
+    pub count: usize,
+    #[serde(rename = "startIndex")]
+    pub start_index: Option<usize>,
+    /// 明文密钥导出格式: "json" (默认) 或 "csv"
+    #[serde(rename = "exportFormat")]
+    pub export_format: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BulkApiKeyRequest {
+    #[serde(default)]
+    pub ids: Vec<String>,
+    pub tag: Option<String>,
+    pub updates: Option<ApiKeyBulkUpdate>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CreateInvitationRequest {
+    pub email: Option<String>,
+    #[serde(rename = "maxApiKeys")]
+    pub max_api_keys: Option<i64>,
+    #[serde(rename = "ttlHours")]
+    pub ttl_hours: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UpdateUserStatusRequest {
+    #[serde(rename = "isActive")]
+    pub is_active: bool,
+    /// 是否同步启用/禁用该用户的 API Keys
+    #[serde(rename = "updateKeys", default = "default_true")]
+    pub update_keys: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UpdateUserQuotaRequest {
+    #[serde(rename = "maxApiKeys")]
+    pub max_api_keys: Option<i64>,
+    #[serde(rename = "maxKeyDailyCostLimit")]
+    pub max_key_daily_cost_limit: Option<f64>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ReassignKeysRequest {
+    /// 目标用户，为空时收回为管理员所有
+    #[serde(rename = "targetUserId")]
+    pub target_user_id: Option<String>,
+    /// 要转移的 Key，为空时转移全部
+    #[serde(rename = "keyIds", default)]
+    pub key_ids: Vec<String>,
+}
+
+/// 分配用户角色请求
+#[derive(Debug, Deserialize)]
+pub struct AssignUserRoleRequest {
+    pub role: String,
+}
+
+/// 创建自定义角色请求
+#[derive(Debug, Deserialize)]
+pub struct CreateRoleRequest {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub permissions: Vec<Permission>,
+}
+
+/// 更新自定义角色请求 (权限整体替换)
+#[derive(Debug, Deserialize)]
+pub struct UpdateRoleRequest {
+    pub description: Option<String>,
+    #[serde(default)]
+    pub permissions: Vec<Permission>,
+}
+
+/// 双因素认证验证码 (注册确认、重新生成恢复码和关闭时使用)
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorCodeRequest {
+    #[serde(rename = "totpCode")]
+    pub totp_code: Option<String>,
+    #[serde(rename = "recoveryCode")]
+    pub recovery_code: Option<String>,
+}
+
+/// 无定价模型查询参数
+#[derive(Debug, Deserialize)]
+pub struct UnpricedModelsQuery {
+    pub limit: Option<usize>,
+}
+
+/// 钱包流水查询参数
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WalletLedgerQuery {
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+    /// 只返回入账 (充值、兑换)，读取不截断的入账流水
+    pub credits_only: Option<bool>,
+}
+
+/// 兑换码查询参数
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RedemptionCodesQuery {
+    pub status: Option<RedemptionCodeStatus>,
+    pub batch_id: Option<String>,
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+/// 计费事件查询参数 (默认最近 24 小时)
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BillingEventsQuery {
+    pub start_time: Option<chrono::DateTime<chrono::Utc>>,
+    pub end_time: Option<chrono::DateTime<chrono::Utc>>,
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// 计费事件重放请求
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BillingEventsReplayRequest {
+    pub start_time: chrono::DateTime<chrono::Utc>,
+    pub end_time: chrono::DateTime<chrono::Utc>,
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct GenerateAuthUrlRequest {
     #[serde(rename = "proxyUrl")]
@@ -170,12 +360,16 @@ fn default_priority() -> u8 {
 /// # 路由
 ///
 /// - POST /admin/auth/login - 管理员登录
+/// - POST /admin/auth/refresh - 使用刷新令牌换取新的访问令牌
+/// - POST /admin/auth/logout - 登出 (撤销当前会话，可选注销全部会话)
 /// - GET /admin/profile - 获取管理员信息
 /// - GET /admin/auth/user - 获取当前用户信息
-/// - GET /admin/oem-settings - 获取OEM设置
-/// - PUT /admin/oem-settings - 更新OEM设置
+/// - GET /admin/oem-settings - 获取OEM设置 (公开)
+/// - PUT /admin/oem-settings - 更新OEM设置 (部分更新，校验图标、颜色和链接)
 /// - GET /admin/dashboard - 获取仪表板数据
-/// - GET /admin/claude-accounts - 获取Claude账户列表
+/// - GET /admin/claude-accounts - 获取Claude账户列表 (含使用量)
+/// - GET /admin/claude-accounts/usage - 各账户使用量和成本
+/// - GET /admin/accounts/:id/usage - 单个账户的使用量、按模型明细和会话窗口
 /// - POST /admin/claude-accounts - 创建Claude账户
 /// - PUT /admin/claude-accounts/:id - 更新Claude账户
 /// - DELETE /admin/claude-accounts/:id - 删除Claude账户
@@ -187,38 +381,148 @@ fn default_priority() -> u8 {
 /// - DELETE /admin/api-keys/:id - 删除API Key
 /// - PUT /admin/api-keys/:id/toggle - 启用/禁用API Key
 /// - GET /admin/stats/overview - 获取统计概览
+/// - GET /admin/users - 获取用户列表
+/// - PUT /admin/users/:id/role - 分配角色
+/// - GET/POST /admin/roles, PUT/DELETE /admin/roles/:name - 角色管理
+/// - GET /admin/two-factor - 双因素认证状态
+/// - POST /admin/two-factor/setup, /enable, /disable, /recovery-codes - 双因素认证自助管理
+/// - POST /admin/two-factor/reset/:username - 重置其他管理员的双因素认证
+/// - GET /admin/login-lockouts - 查看登录锁定
+/// - DELETE /admin/login-lockouts/:scope/:subject - 解除登录锁定 (scope: username/ip)
+/// - GET/POST /admin/api-tokens, DELETE /admin/api-tokens/:id - 管理 API 令牌
+/// - GET /admin/audit-logs - 查询审计日志
+/// - GET /admin/audit-logs/export - 导出审计日志 (JSONL)
+/// - GET /admin/billing-events - 按时间范围分页查询计费事件
+/// - POST /admin/billing-events/replay - 重放时间范围内的计费事件
+/// - POST /admin/cost-recalculation - 按当前定价重算历史成本 (后台任务)
+/// - GET /admin/cost-recalculation/:id - 重算进度和每个 Key 的成本差异
+/// - GET /admin/pricing/overrides, GET/PUT/DELETE /admin/pricing/overrides/*model - 管理员模型定价
+/// - GET /admin/pricing/unpriced-models - 流量中出现过但没有定价的模型
+/// - GET/PUT /admin/billing/multipliers - 计费倍率 (全局、标签、Key、模型系列)
+/// - GET /admin/wallets/:owner_type/:owner_id[/ledger] - 预付费钱包余额和流水 (key/user)
+/// - POST /admin/wallets/:owner_type/:owner_id/top-up - 充值
+/// - GET/POST /admin/redemption-codes, DELETE /admin/redemption-codes/:code - 兑换码
+/// - GET /admin/statements - Key、用户或标签的使用量对账单 (JSON/CSV/HTML)
+/// - GET /admin/usage/export - 按 Key/账户/模型/时间范围流式导出使用量 (CSV/JSONL)
 ///
+/// 除登录和 OEM 设置外，所有路由都要求 JWT 或管理 API 令牌 (`cra_` 前缀)，并按路由分组检查权限
+/// (JWT 取角色权限，API 令牌取其 scopes)：
+/// accounts:read/write、keys:read/write、stats:read、settings:write、users:manage、audit:read
+///
+/// 受保护路由上的所有写操作都会写入审计日志
 pub fn create_admin_routes(
     admin_service: Arc<AdminService>,
     api_key_service: Arc<ApiKeyService>,
     redis: crate::RedisPool,
 ) -> Router {
     // 创建共享状态
+    let user_service = Arc::new(UserService::new(
+        Arc::new(redis.clone()),
+        admin_service.clone(),
+        api_key_service.clone(),
+    ));
+    let role_service = Arc::new(RoleService::new(Arc::new(redis.clone())));
+    let audit_service = Arc::new(AuditService::new(Arc::new(redis.clone())));
+    let login_guard = Arc::new(
+        LoginGuardService::new(Arc::new(redis.clone()))
+            .with_webhook_service(Arc::new(WebhookService::new(Arc::new(redis.clone())))),
+    );
+    let dashboard_service = Arc::new(DashboardService::new(
+        Arc::new(redis.clone()),
+        api_key_service.clone(),
+    ));
     let shared_state = Arc::new(AdminRouteState {
         admin_service: admin_service.clone(),
-        api_key_service,
+        api_key_service: api_key_service.clone(),
+        user_service: user_service.clone(),
+        role_service: role_service.clone(),
+        audit_service: audit_service.clone(),
+        login_guard,
+        oem_service: Arc::new(OemSettingsService::new(Arc::new(redis.clone()))),
+        dashboard_service,
+        trend_service: Arc::new(UsageTrendService::new(Arc::new(redis.clone()))),
+        account_usage_service: Arc::new(AccountUsageService::new(
+            Arc::new(redis.clone()),
+            api_key_service.system_timezone(),
+        )),
+        cost_recalculation: Arc::new(CostRecalculationService::new(
+            Arc::new(redis.clone()),
+            api_key_service.clone(),
+        )),
+        pricing_overrides: Arc::new(PricingOverrideService::new(Arc::new(redis.clone()))),
+        statement_service: Arc::new(StatementService::new(
+            api_key_service.clone(),
+            user_service.clone(),
+        )),
         redis,
     });
 
     // 认证中间件工厂函数
     let auth_layer = |service: Arc<AdminService>| {
-        axum::middleware::from_fn_with_state(service, authenticate_jwt)
+        axum::middleware::from_fn_with_state(service, authenticate_admin)
+    };
+
+    // 权限中间件工厂函数，None 表示任意管理角色均可访问
+    let permission_layer = |permission: Option<Permission>| {
+        axum::middleware::from_fn_with_state(
+            PermissionGuard {
+                role_service: role_service.clone(),
+                permission,
+            },
+            require_permission,
+        )
     };
 
     // 公开路由 - 不需要认证（品牌化信息等）
     let public_routes = Router::new()
         .route("/auth/login", post(login_handler))
+        .route("/auth/refresh", post(refresh_token_handler))
         .route("/oem-settings", get(get_oem_settings_handler))
         .with_state(shared_state.clone());
 
-    // 受保护路由 - 需要JWT认证
-    let protected_routes = Router::new()
+    // 任意管理角色可访问
+    let common_routes = Router::new()
         .route("/profile", get(get_profile_handler))
         .route("/auth/user", get(get_profile_handler))
-        .route("/oem-settings", put(update_oem_settings_handler))
-        .route("/dashboard", get(get_dashboard_handler))
+        .route("/auth/logout", post(logout_handler))
+        // 客户端和分组管理
+        .route("/supported-clients", get(get_supported_clients_handler))
+        .route("/account-groups", get(get_account_groups_handler))
+        // Claude Code 版本管理
+        .route("/claude-code-version", get(get_claude_code_version_handler))
+        // 系统管理
+        .route("/check-updates", get(check_updates_handler))
+        // 双因素认证 (仅限当前登录的管理员自己)
+        .route("/two-factor", get(get_two_factor_status_handler))
+        .route("/two-factor/setup", post(setup_two_factor_handler))
+        .route("/two-factor/enable", post(enable_two_factor_handler))
+        .route("/two-factor/disable", post(disable_two_factor_handler))
+        .route(
+            "/two-factor/recovery-codes",
+            post(regenerate_recovery_codes_handler),
+        )
+        .route_layer(permission_layer(None));
+
+    // 账户查看 (accounts:read)
+    let accounts_read_routes = Router::new()
         // Claude Console 账户管理（重命名以匹配前端期望）
         .route("/claude-console-accounts", get(list_claude_accounts_handler))
+        // Claude账户别名路由（前端兼容性）
+        .route("/claude-accounts", get(list_claude_accounts_handler))
+        .route("/claude-accounts/usage", get(get_claude_accounts_usage_handler))
+        .route("/accounts/:id/usage", get(get_account_usage_handler))
+        // 其他账户类型管理（占位实现）
+        .route("/gemini-accounts", get(list_gemini_accounts_handler))
+        .route("/openai-accounts", get(list_openai_accounts_handler))
+        .route("/openai-responses-accounts", get(list_openai_responses_accounts_handler))
+        .route("/bedrock-accounts", get(list_bedrock_accounts_handler))
+        .route("/azure-openai-accounts", get(list_azure_openai_accounts_handler))
+        .route("/droid-accounts", get(list_droid_accounts_handler))
+        .route("/ccr-accounts", get(list_ccr_accounts_handler))
+        .route_layer(permission_layer(Some(Permission::AccountsRead)));
+
+    // 账户管理 (accounts:write)
+    let accounts_write_routes = Router::new()
         .route("/claude-console-accounts", post(create_claude_account_handler))
         .route("/claude-console-accounts/:id", put(update_claude_account_handler))
         .route(
@@ -233,8 +537,6 @@ pub fn create_admin_routes(
             "/claude-console-accounts/exchange-code",
             post(exchange_code_handler),
         )
-        // Claude账户别名路由（前端兼容性）
-        .route("/claude-accounts", get(list_claude_accounts_handler))
         .route("/claude-accounts", post(create_claude_account_handler))
         .route("/claude-accounts/:id", put(update_claude_account_handler))
         .route("/claude-accounts/:id", delete(delete_claude_account_handler))
@@ -246,43 +548,116 @@ pub fn create_admin_routes(
             "/claude-accounts/exchange-code",
             post(exchange_code_handler),
         )
-        .route("/claude-accounts/usage", get(get_claude_accounts_usage_handler))
-        // 其他账户类型管理（占位实现）
-        .route("/gemini-accounts", get(list_gemini_accounts_handler))
-        .route("/openai-accounts", get(list_openai_accounts_handler))
-        .route("/openai-responses-accounts", get(list_openai_responses_accounts_handler))
-        .route("/bedrock-accounts", get(list_bedrock_accounts_handler))
-        .route("/azure-openai-accounts", get(list_azure_openai_accounts_handler))
-        .route("/droid-accounts", get(list_droid_accounts_handler))
-        .route("/ccr-accounts", get(list_ccr_accounts_handler))
         .route("/ccr-accounts", post(create_ccr_account_handler))
-        // API Keys管理
+        .route_layer(permission_layer(Some(Permission::AccountsWrite)));
+
+    // API Key 查看 (keys:read)
+    let keys_read_routes = Router::new()
         .route("/api-keys", get(list_api_keys_handler))
-        .route("/api-keys", post(create_api_key_handler))
         .route("/api-keys/:id", get(get_api_key_handler)) // ISSUE-UI-009: 添加获取单个API Key详情
+        .route("/api-keys/:id/concurrency", get(get_api_key_concurrency_handler))
+        .route("/api-keys/:id/history", get(get_api_key_history_handler))
+        .route("/api-keys/tags", get(get_api_keys_tags_handler))
+        .route("/tags", get(get_api_keys_tags_handler)) // Alias for frontend compatibility (ISSUE-UI-004)
+        .route_layer(permission_layer(Some(Permission::KeysRead)));
+
+    // API Key 管理 (keys:write)
+    let keys_write_routes = Router::new()
+        .route("/api-keys", post(create_api_key_handler))
         .route("/api-keys/:id", put(update_api_key_handler))
         .route("/api-keys/:id", delete(delete_api_key_handler))
         .route("/api-keys/:id/toggle", put(toggle_api_key_handler))
-        .route("/api-keys/tags", get(get_api_keys_tags_handler))
-        .route("/tags", get(get_api_keys_tags_handler)) // Alias for frontend compatibility (ISSUE-UI-004)
-        // 客户端和分组管理
-        .route("/supported-clients", get(get_supported_clients_handler))
-        .route("/account-groups", get(get_account_groups_handler))
-        // Claude Code 版本管理
-        .route("/claude-code-version", get(get_claude_code_version_handler))
-        .route("/claude-code-version/clear", post(clear_claude_code_version_handler))
-        // 用户管理
-        .route("/users", get(get_users_handler))
-        // 统计
+        .route("/api-keys/:id/rotate", post(rotate_api_key_handler))
+        .route("/api-keys/batch", post(batch_create_api_keys_handler))
+        .route("/api-keys/batch/update", post(bulk_update_api_keys_handler))
+        .route("/api-keys/batch/enable", post(bulk_enable_api_keys_handler))
+        .route("/api-keys/batch/disable", post(bulk_disable_api_keys_handler))
+        .route("/api-keys/batch/delete", post(bulk_delete_api_keys_handler))
+        .route_layer(permission_layer(Some(Permission::KeysWrite)));
+
+    // 使用与费用统计 (stats:read)
+    let stats_routes = Router::new()
+        .route("/dashboard", get(get_dashboard_handler))
         .route("/stats/overview", get(get_stats_overview_handler))
         .route("/usage-costs", get(get_usage_costs_handler))
         .route("/usage-trend", get(get_usage_trend_handler))
         .route("/model-stats", get(get_model_stats_handler))
         .route("/account-usage-trend", get(get_account_usage_trend_handler))
         .route("/api-keys-usage-trend", get(get_api_keys_usage_trend_handler))
-        // 系统管理
-        .route("/check-updates", get(check_updates_handler))
-        // 应用认证中间件
+        .route("/billing-events", get(list_billing_events_handler))
+        .route("/cost-recalculation/:id", get(get_cost_recalculation_handler))
+        .route("/pricing/overrides", get(list_pricing_overrides_handler))
+        .route("/pricing/overrides/*model", get(get_pricing_override_handler))
+        .route("/pricing/unpriced-models", get(list_unpriced_models_handler))
+        .route("/billing/multipliers", get(get_billing_multipliers_handler))
+        .route("/wallets/:owner_type/:owner_id", get(get_wallet_handler))
+        .route("/wallets/:owner_type/:owner_id/ledger", get(get_wallet_ledger_handler))
+        .route("/statements", get(get_statement_handler))
+        .route("/usage/export", get(export_usage_handler))
+        .route_layer(permission_layer(Some(Permission::StatsRead)));
+
+    // 系统设置 (settings:write)
+    let settings_routes = Router::new()
+        .route("/oem-settings", put(update_oem_settings_handler))
+        .route("/claude-code-version/clear", post(clear_claude_code_version_handler))
+        .route("/billing-events/replay", post(replay_billing_events_handler))
+        .route("/cost-recalculation", post(start_cost_recalculation_handler))
+        .route("/pricing/overrides/*model", put(upsert_pricing_override_handler))
+        .route("/pricing/overrides/*model", delete(delete_pricing_override_handler))
+        .route("/billing/multipliers", put(update_billing_multipliers_handler))
+        .route("/wallets/:owner_type/:owner_id/top-up", post(top_up_wallet_handler))
+        .route("/redemption-codes", get(list_redemption_codes_handler))
+        .route("/redemption-codes", post(generate_redemption_codes_handler))
+        .route("/redemption-codes/:code", delete(disable_redemption_code_handler))
+        .route_layer(permission_layer(Some(Permission::SettingsWrite)));
+
+    // 用户与角色管理 (users:manage)
+    let users_routes = Router::new()
+        .route("/users", get(get_users_handler))
+        .route("/users/invitations", post(create_user_invitation_handler))
+        .route("/users/:id/status", put(update_user_status_handler))
+        .route("/users/:id/quota", put(update_user_quota_handler))
+        .route("/users/:id/role", put(assign_user_role_handler))
+        .route("/users/:id/api-keys", get(get_user_api_keys_handler))
+        .route("/users/:id/reassign-keys", post(reassign_user_keys_handler))
+        .route("/user-management/settings", get(get_user_settings_handler))
+        .route("/user-management/settings", put(update_user_settings_handler))
+        .route("/roles", get(list_roles_handler))
+        .route("/roles", post(create_role_handler))
+        .route("/roles/:name", put(update_role_handler))
+        .route("/roles/:name", delete(delete_role_handler))
+        .route("/two-factor/reset/:username", post(reset_two_factor_handler))
+        .route("/login-lockouts", get(list_login_lockouts_handler))
+        .route(
+            "/login-lockouts/:scope/:subject",
+            delete(clear_login_lockout_handler),
+        )
+        .route("/api-tokens", get(list_admin_api_tokens_handler))
+        .route("/api-tokens", post(create_admin_api_token_handler))
+        .route("/api-tokens/:id", delete(revoke_admin_api_token_handler))
+        .route_layer(permission_layer(Some(Permission::UsersManage)));
+
+    // 审计日志 (audit:read)
+    let audit_routes = Router::new()
+        .route("/audit-logs", get(list_audit_logs_handler))
+        .route("/audit-logs/export", get(export_audit_logs_handler))
+        .route_layer(permission_layer(Some(Permission::AuditRead)));
+
+    // 受保护路由 - 先验证 JWT，记录审计日志，再按分组检查权限
+    let protected_routes = Router::new()
+        .merge(common_routes)
+        .merge(accounts_read_routes)
+        .merge(accounts_write_routes)
+        .merge(keys_read_routes)
+        .merge(keys_write_routes)
+        .merge(stats_routes)
+        .merge(settings_routes)
+        .merge(users_routes)
+        .merge(audit_routes)
+        .layer(axum::middleware::from_fn_with_state(
+            audit_service,
+            audit_admin_request,
+        ))
         .layer(auth_layer(admin_service))
         .with_state(shared_state);
 
@@ -295,159 +670,547 @@ pub fn create_admin_routes(
 // ============================================================================
 
 /// 管理员登录处理器
+///
+/// 按用户名和客户端 IP 统计失败次数，超过阈值后指数退避并临时锁定 (429)。
+/// 只有凭据或双因素验证码错误计为失败
 async fn login_handler(
     State(state): State<Arc<AdminRouteState>>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
     Json(payload): Json<LoginRequest>,
-) -> Result<impl IntoResponse, AppError> {
-    info!("🔐 Admin login attempt: {}", payload.username);
+) -> Result<Response, AppError> {
+    let client_ip = extract_client_ip(&headers, connect_info.map(|ConnectInfo(addr)| addr));
+    info!(
+        "🔐 Admin login attempt: {} from {}",
+        payload.username, client_ip
+    );
+
+    guarded_login(
+        &state.login_guard,
+        &payload.username,
+        &client_ip,
+        authenticate_login(&state, &payload),
+    )
+    .await
+}
+
+/// 在登录失败保护下执行一次登录尝试 (管理后台和用户登录共用)
+///
+/// 处于锁定或退避期时直接返回 429；只有凭据或双因素验证码错误 (401) 计为失败，
+/// 登录成功后清除该用户名的失败计数
+pub(crate) async fn guarded_login(
+    login_guard: &LoginGuardService,
+    username: &str,
+    client_ip: &str,
+    attempt: impl std::future::Future<Output = Result<Response, AppError>>,
+) -> Result<Response, AppError> {
+    login_guard.check(username, client_ip).await?;
+
+    let result = attempt.await;
+    let succeeded = matches!(&result, Ok(response) if response.status() == StatusCode::OK);
+    let failed = matches!(&result, Err(AppError::Unauthorized(_)));
+    if succeeded {
+        if let Err(e) = login_guard.record_success(username).await {
+            error!("❌ Failed to reset login failures: {}", e);
+        }
+    } else if failed {
+        warn!("🔒 Login failed: {} from {}", username, client_ip);
+        if let Err(e) = login_guard.record_failure(username, client_ip).await {
+            error!("❌ Failed to record login failure: {}", e);
+        }
+    }
+
+    result
+}
+
+/// 已启用双因素认证时在签发 JWT 之前校验验证码或恢复码
+///
+/// `username` 为凭据校验得到的规范用户名 (JWT sub)，不能使用请求中原样的用户名。
+/// 未提供验证码时返回要求输入验证码的 401 响应 (不计为失败)
+pub(crate) async fn verify_login_two_factor(
+    service: &AdminService,
+    username: &str,
+    payload: &LoginRequest,
+) -> Result<Option<Response>, AppError> {
+    let Some(two_factor) = service.two_factor_service() else {
+        return Ok(None);
+    };
+    let totp_code = payload.totp_code.as_deref().filter(|c| !c.trim().is_empty());
+    let recovery_code = payload.recovery_code.as_deref().filter(|c| !c.trim().is_empty());
+
+    if totp_code.is_none()
+        && recovery_code.is_none()
+        && two_factor.is_enabled(username).await?
+    {
+        info!("🔐 Two-factor code required for: {}", username);
+        return Ok(Some(
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(json!({
+                    "success": false,
+                    "requiresTwoFactor": true,
+                    "message": "Two-factor code required"
+                })),
+            )
+                .into_response(),
+        ));
+    }
+
+    two_factor.verify(username, totp_code, recovery_code).await?;
+
+    Ok(None)
+}
+
+/// 校验凭据和双因素验证码并签发登录令牌
+async fn authenticate_login(
+    state: &AdminRouteState,
+    payload: &LoginRequest,
+) -> Result<Response, AppError> {
     let service = &state.admin_service;
 
-    let response = service
-        .authenticate(&payload.username, &payload.password)
-        .await?;
+    let staff_user = match service
+        .verify_credentials(&payload.username, &payload.password)
+        .await
+    {
+        Ok(()) => None,
+        // 本地管理员凭据不匹配时尝试被分配了管理角色的用户 (含 LDAP 用户)
+        Err(AppError::Unauthorized(_)) => Some(
+            state
+                .user_service
+                .verify_staff_credentials(&payload.username, &payload.password)
+                .await?,
+        ),
+        Err(e) => return Err(e),
+    };
+
+    // 双因素认证在签发 JWT 之前校验，按凭据校验得到的规范用户名 (JWT sub) 查找
+    let username = staff_user
+        .as_ref()
+        .map_or(payload.username.as_str(), |user| user.username.as_str());
+    if let Some(response) = verify_login_two_factor(service, username, payload).await? {
+        return Ok(response);
+    }
+
+    let response = match staff_user {
+        None => service.issue_login(&payload.username).await?,
+        Some(user) => state.user_service.issue_staff_login(user).await?,
+    };
 
     info!("✅ Admin login successful: {}", payload.username);
 
+    Ok((StatusCode::OK, Json(response)).into_response())
+}
+
+/// 刷新访问令牌 (刷新令牌同时轮换，旧刷新令牌立即失效)
+async fn refresh_token_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    Json(request): Json<RefreshTokenRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = state
+        .admin_service
+        .refresh_session(&request.refresh_token)
+        .await?;
+
     Ok((StatusCode::OK, Json(response)))
 }
 
+/// 登出处理器
+///
+/// 撤销当前会话；`allSessions: true` 时递增令牌版本，注销该账号的所有会话
+async fn logout_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    body: Option<Json<LogoutRequest>>,
+) -> Result<impl IntoResponse, AppError> {
+    let request = body.map(|Json(r)| r).unwrap_or_default();
+    if request.all_sessions {
+        state
+            .admin_service
+            .bump_token_version(&jwt_state.claims.sub)
+            .await?;
+    }
+    state
+        .admin_service
+        .revoke_session(&jwt_state.claims.sid)
+        .await?;
+
+    info!("👋 Admin logged out: {}", jwt_state.claims.sub);
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "success": true, "message": "已登出" })),
+    ))
+}
+
 /// 获取管理员资料处理器
 async fn get_profile_handler(
+    State(state): State<Arc<AdminRouteState>>,
     jwt_state: axum::Extension<JwtAuthState>,
 ) -> Result<impl IntoResponse, AppError> {
     let claims = &jwt_state.claims;
+    let permissions = state.role_service.permissions_for(&claims.role).await?;
 
     Ok((
         StatusCode::OK,
         Json(json!({
             "username": claims.sub,
             "role": claims.role,
+            "permissions": permissions,
         })),
     ))
 }
 
 // ============================================================================
-// OEM Settings Handlers
+// Two-Factor Authentication Handlers
 // ============================================================================
 
-/// 获取OEM设置（Mock实现）
-async fn get_oem_settings_handler() -> Result<impl IntoResponse, AppError> {
-    info!("📝 Getting OEM settings");
+fn two_factor_service(state: &AdminRouteState) -> Result<Arc<TwoFactorService>, AppError> {
+    state.admin_service.two_factor_service().ok_or_else(|| {
+        AppError::BadRequest("Two-factor authentication is not configured".to_string())
+    })
+}
 
-    // Mock数据 - 返回默认设置
-    let settings = OemSettings {
-        enabled: false,
-        company_name: Some("Claude Relay Service".to_string()),
-        logo_url: None,
-        theme_color: Some("#6366f1".to_string()),
-    };
+fn required_totp_code(request: &TwoFactorCodeRequest) -> Result<&str, AppError> {
+    request
+        .totp_code
+        .as_deref()
+        .filter(|c| !c.trim().is_empty())
+        .ok_or_else(|| AppError::BadRequest("Missing totpCode".to_string()))
+}
+
+/// 获取当前管理员的双因素认证状态
+async fn get_two_factor_status_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+) -> Result<impl IntoResponse, AppError> {
+    let status = two_factor_service(&state)?
+        .status(&jwt_state.claims.sub)
+        .await?;
 
-    Ok((StatusCode::OK, Json(settings)))
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "success": true, "data": status })),
+    ))
 }
 
-/// 更新OEM设置（Mock实现）
-async fn update_oem_settings_handler(
-    Json(settings): Json<OemSettings>,
+/// 开始注册双因素认证，返回密钥和 otpauth URI
+///
+/// 需要调用 /two-factor/enable 提交验证码后才会生效
+async fn setup_two_factor_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+) -> Result<impl IntoResponse, AppError> {
+    let enrollment = two_factor_service(&state)?
+        .begin_enrollment(&jwt_state.claims.sub)
+        .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "success": true, "data": enrollment })),
+    ))
+}
+
+/// 提交验证码完成注册，恢复码仅在此时返回一次
+async fn enable_two_factor_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Json(request): Json<TwoFactorCodeRequest>,
 ) -> Result<impl IntoResponse, AppError> {
-    info!("💾 Updating OEM settings: {:?}", settings);
+    let recovery_codes = two_factor_service(&state)?
+        .confirm_enrollment(&jwt_state.claims.sub, required_totp_code(&request)?)
+        .await?;
+    let audit = AuditEvent::new("two_factor.enable", "admin", Some(&jwt_state.claims.sub));
 
-    // Mock实现 - 直接返回接收到的设置
     Ok((
         StatusCode::OK,
+        axum::Extension(audit),
         Json(json!({
             "success": true,
-            "message": "OEM设置已更新",
-            "settings": settings
+            "message": "双因素认证已启用，请妥善保存恢复码",
+            "data": { "recoveryCodes": recovery_codes }
         })),
     ))
 }
 
-// ============================================================================
-// Dashboard Handlers
-// ============================================================================
+/// 关闭双因素认证 (需要验证码或恢复码)
+async fn disable_two_factor_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Json(request): Json<TwoFactorCodeRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    two_factor_service(&state)?
+        .disable(
+            &jwt_state.claims.sub,
+            request.totp_code.as_deref(),
+            request.recovery_code.as_deref(),
+        )
+        .await?;
+    let audit = AuditEvent::new("two_factor.disable", "admin", Some(&jwt_state.claims.sub));
 
-/// 获取仪表板数据（Mock实现）
-async fn get_dashboard_handler() -> Result<impl IntoResponse, AppError> {
-    info!("📊 Getting dashboard data");
+    Ok((
+        StatusCode::OK,
+        axum::Extension(audit),
+        Json(json!({ "success": true, "message": "双因素认证已关闭" })),
+    ))
+}
 
-    // Mock数据 - 返回符合前端期望的完整数据结构
-    // 前端期望: data.overview, data.recentActivity, data.systemAverages, data.realtimeMetrics, data.systemHealth, data.systemTimezone
-    let dashboard = json!({
-        "success": true,
-        "data": {
-            "overview": {
-                // API Keys 统计
-                "totalApiKeys": 0,
-                "activeApiKeys": 0,
-                // 账户统计
-                "totalAccounts": 0,
-                "normalAccounts": 0,
-                "abnormalAccounts": 0,
-                "pausedAccounts": 0,
-                "activeAccounts": 0,
-                "rateLimitedAccounts": 0,
-                "accountsByPlatform": {
-                    "claude": 0,
-                    "gemini": 0,
-                    "openai": 0,
-                    "bedrock": 0,
-                    "azure": 0
-                },
-                // 请求统计
-                "totalRequestsUsed": 0,
-                // Token 统计
-                "totalTokensUsed": 0,
-                "totalInputTokensUsed": 0,
-                "totalOutputTokensUsed": 0,
-                "totalCacheCreateTokensUsed": 0,
-                "totalCacheReadTokensUsed": 0
-            },
-            "recentActivity": {
-                // 今日请求
-                "requestsToday": 0,
-                // 今日 Token
-                "tokensToday": 0,
-                "inputTokensToday": 0,
-                "outputTokensToday": 0,
-                "cacheCreateTokensToday": 0,
-                "cacheReadTokensToday": 0
-            },
-            "systemAverages": {
-                "rpm": 0,
-                "tpm": 0
-            },
-            "realtimeMetrics": {
-                "rpm": 0,
-                "tpm": 0,
-                "windowMinutes": 5,
-                "isHistorical": false
-            },
-            "systemHealth": {
-                "redisConnected": true,
-                "uptime": 0
-            },
-            "systemTimezone": 8
-        }
-    });
+/// 重新生成恢复码，旧恢复码全部失效
+async fn regenerate_recovery_codes_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Json(request): Json<TwoFactorCodeRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let recovery_codes = two_factor_service(&state)?
+        .regenerate_recovery_codes(&jwt_state.claims.sub, required_totp_code(&request)?)
+        .await?;
+    let audit = AuditEvent::new(
+        "two_factor.recovery_codes",
+        "admin",
+        Some(&jwt_state.claims.sub),
+    );
+
+    Ok((
+        StatusCode::OK,
+        axum::Extension(audit),
+        Json(json!({ "success": true, "data": { "recoveryCodes": recovery_codes } })),
+    ))
+}
+
+/// 重置其他管理员的双因素认证 (丢失验证器且没有恢复码时使用)
+async fn reset_two_factor_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Path(username): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let existed = two_factor_service(&state)?
+        .reset(&username, &jwt_state.claims.sub)
+        .await?;
+    if !existed {
+        return Err(AppError::NotFound(format!(
+            "Two-factor authentication is not configured for {}",
+            username
+        )));
+    }
+    let audit = AuditEvent::new("two_factor.reset", "admin", Some(&username));
 
-    Ok((StatusCode::OK, Json(dashboard)))
+    Ok((
+        StatusCode::OK,
+        axum::Extension(audit),
+        Json(json!({ "success": true, "message": "双因素认证已重置" })),
+    ))
 }
 
 // ============================================================================
-// Claude Accounts Handlers
+// Login Lockout Handlers
 // ============================================================================
 
-/// 获取Claude账户列表（真实Redis实现）
-async fn list_claude_accounts_handler(
+/// 查看当前的登录锁定
+async fn list_login_lockouts_handler(
     State(state): State<Arc<AdminRouteState>>,
 ) -> Result<impl IntoResponse, AppError> {
-    info!("📋 Listing Claude accounts");
+    let lockouts = state.login_guard.list_lockouts().await?;
 
-    let mut conn = state.redis.get_connection().await?;
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "success": true, "data": lockouts })),
+    ))
+}
 
-    // 查询所有 Claude 账户 (包括 Console 和 Official)
-    let pattern = "claude_account:*";
-    let keys: Vec<String> = redis::cmd("KEYS")
+/// 解除登录锁定 (同时清除失败计数和退避)
+async fn clear_login_lockout_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    Path((scope, subject)): Path<(String, String)>,
+) -> Result<impl IntoResponse, AppError> {
+    let scope = LockoutScope::parse(&scope).ok_or_else(|| {
+        AppError::BadRequest("scope must be 'username' or 'ip'".to_string())
+    })?;
+    let existed = state.login_guard.clear_lockout(scope, &subject).await?;
+    if !existed {
+        return Err(AppError::NotFound(format!(
+            "No active login lockout for {} {}",
+            scope.as_str(),
+            subject
+        )));
+    }
+    info!("🔓 Login lockout cleared: {} {}", scope.as_str(), subject);
+    let audit = AuditEvent::new(
+        "login_lockout.clear",
+        scope.as_str(),
+        Some(&subject),
+    );
+
+    Ok((
+        StatusCode::OK,
+        axum::Extension(audit),
+        Json(json!({ "success": true, "message": "登录锁定已解除" })),
+    ))
+}
+
+// ============================================================================
+// Admin API Token Handlers
+// ============================================================================
+
+/// 列出管理 API 令牌 (不含明文和哈希)
+async fn list_admin_api_tokens_handler(
+    State(state): State<Arc<AdminRouteState>>,
+) -> Result<impl IntoResponse, AppError> {
+    let tokens: Vec<_> = state
+        .admin_service
+        .api_token_service()
+        .list_tokens()
+        .await?
+        .iter()
+        .map(|token| token.public_view())
+        .collect();
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "success": true, "data": tokens })),
+    ))
+}
+
+/// 创建管理 API 令牌
+///
+/// scopes 不能超出调用者自身的权限，明文令牌只在响应中返回一次。
+/// 令牌的有效性跟随创建者账号，因此不能用 API 令牌创建新令牌
+async fn create_admin_api_token_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Json(request): Json<CreateAdminApiTokenRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    if jwt_state.scopes.is_some() {
+        return Err(AppError::Forbidden(
+            "Admin API tokens cannot create other API tokens".to_string(),
+        ));
+    }
+    let granted = state
+        .role_service
+        .permissions_for(&jwt_state.claims.role)
+        .await?;
+    let (token, plaintext) = state
+        .admin_service
+        .api_token_service()
+        .create_token(request, &granted, &jwt_state.claims.sub)
+        .await?;
+    let token = token.public_view();
+    let audit = AuditEvent::new("admin_api_token.create", "admin_api_token", Some(&token.id))
+        .after(&token);
+
+    Ok((
+        StatusCode::OK,
+        axum::Extension(audit),
+        Json(json!({
+            "success": true,
+            "data": token,
+            "token": plaintext,
+            "message": "请立即保存令牌，之后将无法再次查看"
+        })),
+    ))
+}
+
+/// 撤销管理 API 令牌 (立即生效)
+async fn revoke_admin_api_token_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let token = state
+        .admin_service
+        .api_token_service()
+        .revoke_token(&id, &jwt_state.claims.sub)
+        .await?
+        .public_view();
+    let audit = AuditEvent::new("admin_api_token.revoke", "admin_api_token", Some(&id))
+        .before(&token);
+
+    Ok((
+        StatusCode::OK,
+        axum::Extension(audit),
+        Json(json!({ "success": true, "message": "令牌已撤销" })),
+    ))
+}
+
+// ============================================================================
+// OEM Settings Handlers
+// ============================================================================
+
+/// 获取OEM设置 (公开，供登录页和统计页使用)
+async fn get_oem_settings_handler(
+    State(state): State<Arc<AdminRouteState>>,
+) -> Result<impl IntoResponse, AppError> {
+    let settings = state.oem_service.get_settings().await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "success": true, "data": settings })),
+    ))
+}
+
+/// 更新OEM设置
+///
+/// 只更新请求中提供的字段；图标限制为 350KB 以内的 png/jpeg/ico/svg
+async fn update_oem_settings_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Json(update): Json<OemSettingsUpdate>,
+) -> Result<impl IntoResponse, AppError> {
+    let (before, settings) = state
+        .oem_service
+        .update_settings(update, &jwt_state.claims.sub)
+        .await?;
+    let audit = AuditEvent::new("oem_settings.update", "settings", Some("oem"))
+        .before(&before.audit_view())
+        .after(&settings.audit_view());
+
+    Ok((
+        StatusCode::OK,
+        axum::Extension(audit),
+        Json(json!({
+            "success": true,
+            "message": "OEM设置已更新",
+            "data": settings
+        })),
+    ))
+}
+
+// ============================================================================
+// Dashboard Handlers
+// ============================================================================
+
+/// 获取仪表板数据
+///
+/// API Key、各平台账户健康状态、累计/今日使用量、实时 RPM/TPM 和系统状态
+async fn get_dashboard_handler(
+    State(state): State<Arc<AdminRouteState>>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("📊 Getting dashboard data");
+
+    let dashboard = state.dashboard_service.get_dashboard().await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "success": true,
+            "data": dashboard
+        })),
+    ))
+}
+
+// ============================================================================
+// Claude Accounts Handlers
+// ============================================================================
+
+/// 获取Claude账户列表（真实Redis实现）
+async fn list_claude_accounts_handler(
+    State(state): State<Arc<AdminRouteState>>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("📋 Listing Claude accounts");
+
+    let mut conn = state.redis.get_connection().await?;
+
+    // 查询所有 Claude 账户 (包括 Console 和 Official)
+    let pattern = "claude_account:*";
+    let keys: Vec<String> = redis::cmd("KEYS")
         .arg(pattern)
         .query_async(&mut conn)
         .await
@@ -468,6 +1231,21 @@ async fn list_claude_accounts_handler(
         }
     }
 
+    // 附加每个账户的今日/累计使用量和会话窗口用量
+    let account_ids: Vec<String> = accounts
+        .iter()
+        .map(|account| account["id"].as_str().unwrap_or_default().to_string())
+        .collect();
+    let summaries = state
+        .account_usage_service
+        .get_accounts_usage(&account_ids)
+        .await?;
+    for (account, summary) in accounts.iter_mut().zip(summaries) {
+        if let Some(account) = account.as_object_mut() {
+            account.insert("usage".to_string(), json!(summary));
+        }
+    }
+
     info!("✅ Found {} Claude accounts", accounts.len());
 
     Ok((StatusCode::OK, Json(json!({
@@ -476,7 +1254,9 @@ async fn list_claude_accounts_handler(
     }))))
 }
 
-/// 获取Claude账户使用统计数据（简化实现）
+/// 获取Claude账户使用统计数据
+///
+/// 返回 `{ accountId: usage }`，包含当日/当月/累计用量和成本、按模型明细以及当前 5 小时会话窗口用量
 async fn get_claude_accounts_usage_handler(
     State(state): State<Arc<AdminRouteState>>,
 ) -> Result<impl IntoResponse, AppError> {
@@ -495,19 +1275,20 @@ async fn get_claude_accounts_usage_handler(
             AppError::InternalError("Failed to fetch accounts".to_string())
         })?;
 
-    // 构建 usage map: { accountId: usageData }
-    // 目前返回空对象，因为使用数据未实现完整的 OAuth usage API 调用
-    let mut usage_map = serde_json::Map::new();
-
-    for key in keys {
-        // 从 key 中提取 account ID
-        // key 格式: "claude_account:claude_acc_xxx" 或 "claude_account:xxx"
-        let account_id = key.strip_prefix("claude_account:").unwrap_or(&key);
+    // key 格式: "claude_account:claude_acc_xxx" 或 "claude_account:xxx"
+    let account_ids: Vec<String> = keys
+        .iter()
+        .map(|key| key.strip_prefix("claude_account:").unwrap_or(key).to_string())
+        .collect();
+    let summaries = state
+        .account_usage_service
+        .get_accounts_usage(&account_ids)
+        .await?;
 
-        // 目前返回 null，前端会显示"暂无统计"
-        // 未来可以从 Redis 读取缓存的 usage 数据
-        usage_map.insert(account_id.to_string(), serde_json::Value::Null);
-    }
+    let usage_map: serde_json::Map<String, serde_json::Value> = summaries
+        .into_iter()
+        .map(|summary| (summary.account_id.clone(), json!(summary)))
+        .collect();
 
     info!("✅ Retrieved usage data for {} Claude accounts", usage_map.len());
 
@@ -517,6 +1298,22 @@ async fn get_claude_accounts_usage_handler(
     }))))
 }
 
+/// 获取单个账户 (任意平台) 的使用量和成本汇总
+async fn get_account_usage_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    Path(account_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let summary = state
+        .account_usage_service
+        .get_account_usage(&account_id)
+        .await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": summary
+    })))
+}
+
 /// 创建Claude账户（真实Redis实现）
 async fn create_claude_account_handler(
     State(state): State<Arc<AdminRouteState>>,
@@ -562,10 +1359,10 @@ async fn create_claude_account_handler(
                 "API URL is required when using API key".to_string()
             ));
         }
-    } else if request.account_type == "claude-official" {
-        if request.session_token.is_none() {
-            return Err(AppError::BadRequest("Session token is required for Claude Official".to_string()));
-        }
+    } else if request.account_type == "claude-official" && request.session_token.is_none() {
+        return Err(AppError::BadRequest(
+            "Session token is required for Claude Official".to_string(),
+        ));
     }
 
     // 生成账户 ID (UUID 类型，不是字符串!)
@@ -636,7 +1433,10 @@ async fn create_claude_account_handler(
 
     info!("✅ Claude account created successfully: {}", account_id);
 
-    Ok((StatusCode::OK, Json(json!({
+    let audit = AuditEvent::new("account.create", "account", Some(&account_id))
+        .after(&account_data);
+
+    Ok((StatusCode::OK, axum::Extension(audit), Json(json!({
         "success": true,
         "message": "Claude账户创建成功",
         "account": {
@@ -766,13 +1566,101 @@ async fn get_api_key_handler(
     Ok((StatusCode::OK, Json(response)))
 }
 
-/// 创建API Key
-async fn create_api_key_handler(
+/// 获取 API Key 并发及排队统计
+async fn get_api_key_concurrency_handler(
     State(state): State<Arc<AdminRouteState>>,
-    Json(key_request): Json<ApiKeyRequest>,
+    Path(id): Path<String>,
 ) -> Result<impl IntoResponse, AppError> {
-    info!("➕ Creating API key: {}", key_request.name);
+    info!("📊 Getting concurrency stats for API key: {}", id);
+
+    let api_key = state.api_key_service.get_key(&id).await?;
+    let stats = state
+        .api_key_service
+        .get_concurrency_queue_stats(&id)
+        .await?;
+
+    let response = json!({
+        "success": true,
+        "data": {
+            "concurrencyLimit": api_key.concurrency_limit,
+            "queueEnabled": api_key.concurrency_queue_enabled,
+            "queueMaxSize": api_key.concurrency_queue_max_size,
+            "queueTimeoutMs": api_key.concurrency_queue_timeout_ms,
+            "stats": stats
+        }
+    });
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 轮换 API Key 密钥
+///
+/// 保留 Key ID、统计和绑定，生成新密钥；旧密钥在宽限期内仍可使用
+async fn rotate_api_key_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Path(id): Path<String>,
+    body: Option<Json<RotateApiKeyRequest>>,
+) -> Result<impl IntoResponse, AppError> {
+    let request = body.map(|Json(r)| r).unwrap_or_default();
+    let grace_period_seconds = request
+        .grace_period_seconds
+        .unwrap_or(DEFAULT_ROTATION_GRACE_PERIOD_SECONDS);
+    info!(
+        "🔄 Rotating API key: {} (grace period: {}s) by user: {}",
+        id, grace_period_seconds, jwt_state.claims.sub
+    );
+
+    let (raw_key, grace_expires_at, api_key) = state
+        .api_key_service
+        .rotate_key(
+            &id,
+            grace_period_seconds,
+            request.notify_on_old_key_use,
+            &jwt_state.claims.sub,
+        )
+        .await?;
+
+    let audit = AuditEvent::new("api_key.rotate", "api_key", Some(&id)).after(&json!({
+        "gracePeriodSeconds": grace_period_seconds,
+        "graceExpiresAt": grace_expires_at
+    }));
+
+    // 新密钥仅在轮换时返回一次
+    let mut response_key = api_key;
+    response_key.key = Some(raw_key);
+
+    let response = json!({
+        "success": true,
+        "message": "API Key密钥已轮换",
+        "data": response_key,
+        "graceExpiresAt": grace_expires_at
+    });
+
+    Ok((StatusCode::OK, axum::Extension(audit), Json(response)))
+}
 
+/// 获取 API Key 历史记录
+async fn get_api_key_history_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("📜 Getting history for API key: {}", id);
+
+    let history = state.api_key_service.get_key_history(&id).await?;
+
+    let response = json!({
+        "success": true,
+        "data": history
+    });
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 将前端请求转换为 API Key 创建选项
+fn build_api_key_create_options(
+    key_request: &ApiKeyRequest,
+) -> Result<ApiKeyCreateOptions, AppError> {
     // 解析permissions字符串为枚举
     let permissions = match key_request.permissions.as_deref() {
         Some("all") | None => ApiKeyPermissions::All,
@@ -804,6 +1692,13 @@ async fn create_api_key_handler(
         // 其他可选字段
         token_limit: key_request.token_limit.unwrap_or(0),
         concurrency_limit: key_request.concurrency_limit.map(|v| v as i64).unwrap_or(0),
+        concurrency_queue_enabled: key_request.concurrency_queue_enabled.unwrap_or(false),
+        concurrency_queue_max_size: key_request
+            .concurrency_queue_max_size
+            .unwrap_or(DEFAULT_CONCURRENCY_QUEUE_MAX_SIZE),
+        concurrency_queue_timeout_ms: key_request
+            .concurrency_queue_timeout_ms
+            .unwrap_or(DEFAULT_CONCURRENCY_QUEUE_TIMEOUT_MS),
         rate_limit_window: key_request.rate_limit_window.map(|v| v as i64),
         rate_limit_requests: key_request.rate_limit_requests.map(|v| v as i64),
         rate_limit_cost: key_request.rate_limit_cost,
@@ -817,8 +1712,21 @@ async fn create_api_key_handler(
         ..Default::default()
     };
 
+    Ok(options)
+}
+
+/// 创建API Key
+async fn create_api_key_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    Json(key_request): Json<ApiKeyRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("➕ Creating API key: {}", key_request.name);
+
+    let options = build_api_key_create_options(&key_request)?;
+
     // 使用真实服务生成API Key
     let (raw_key, api_key) = state.api_key_service.generate_key(options).await?;
+    let audit = AuditEvent::new("api_key.create", "api_key", Some(&api_key.id)).after(&api_key);
 
     // 返回包含原始key的响应（仅在创建时返回一次）
     let mut response_key = api_key;
@@ -830,76 +1738,298 @@ async fn create_api_key_handler(
         "data": response_key  // 改为 data 字段，与前端期待的字段名一致
     });
 
-    Ok((StatusCode::OK, Json(response)))
+    Ok((StatusCode::OK, axum::Extension(audit), Json(response)))
 }
 
-/// 更新API Key
-async fn update_api_key_handler(
+/// 批量创建API Key
+///
+/// 新生成的明文密钥只在本次响应中返回一次，`exportFormat: "csv"` 时以 CSV 文件形式返回
+async fn batch_create_api_keys_handler(
     State(state): State<Arc<AdminRouteState>>,
-    Path(id): Path<String>,
-    Json(key_request): Json<ApiKeyRequest>,
-) -> Result<impl IntoResponse, AppError> {
-    info!("🔄 Updating API key: {} with name: {}", id, key_request.name);
+    jwt_state: axum::Extension<JwtAuthState>,
+    Json(batch_request): Json<BatchCreateApiKeysRequest>,
+) -> Result<Response, AppError> {
+    info!(
+        "➕ Batch creating {} API keys from template: {} by user: {}",
+        batch_request.count, batch_request.template.name, jwt_state.claims.sub
+    );
 
-    // 调用 ApiKeyService 的更新方法
-    // 支持更新所有字段：名称、状态、账户绑定、限制、标签、模型/客户端限制
-    let updated_key = state
+    let export_csv = match batch_request.export_format.as_deref() {
+        Some("json") | None => false,
+        Some("csv") => true,
+        Some(other) => {
+            return Err(AppError::BadRequest(format!("Invalid export format: {}", other)))
+        }
+    };
+
+    let mut template = build_api_key_create_options(&batch_request.template)?;
+    template.created_by = Some(jwt_state.claims.sub.clone());
+
+    let created = state
         .api_key_service
-        .update_key(
-            &id,
-            Some(key_request.name.clone()),
-            key_request.is_active,
-            key_request.account_id.clone(),
-            key_request.claude_account_id.clone().map(Some),
-            key_request.claude_console_account_id.clone().map(Some),
-            key_request.gemini_account_id.clone().map(Some),
-            key_request.openai_account_id.clone().map(Some),
-            key_request.bedrock_account_id.clone().map(Some),
-            key_request.droid_account_id.clone().map(Some),
-            key_request.rate_limit_window,
-            key_request.rate_limit_requests,
-            key_request.rate_limit_cost,
-            key_request.concurrency_limit,
-            key_request.daily_cost_limit,
-            key_request.total_cost_limit,
-            key_request.weekly_opus_cost_limit,
-            Some(key_request.tags.clone()),
-            key_request.enable_model_restriction,
-            Some(key_request.restricted_models.clone()),
-            key_request.enable_client_restriction,
-            Some(key_request.allowed_clients.clone()),
+        .generate_keys_batch(
+            template,
+            batch_request.count,
+            batch_request.start_index.unwrap_or(1),
         )
         .await?;
 
+    let response_keys: Vec<_> = created
+        .into_iter()
+        .map(|(raw_key, mut api_key)| {
+            api_key.key = Some(raw_key);
+            api_key
+        })
+        .collect();
+    let created_ids: Vec<&str> = response_keys.iter().map(|k| k.id.as_str()).collect();
+    let audit = axum::Extension(
+        AuditEvent::new("api_key.batch_create", "api_key", None)
+            .after(&json!({ "ids": created_ids, "template": batch_request.template })),
+    );
+
+    if export_csv {
+        let filename = format!("api-keys-{}.csv", chrono::Utc::now().format("%Y%m%d%H%M%S"));
+        return Ok((
+            StatusCode::OK,
+            audit,
+            [
+                (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{}\"", filename),
+                ),
+            ],
+            api_keys_to_csv(&response_keys),
+        )
+            .into_response());
+    }
+
     let response = json!({
         "success": true,
-        "message": "API Key更新成功",
-        "data": updated_key  // 修复 ISSUE-UI-007: 与其他端点保持一致，使用 data 字段
+        "message": format!("已创建 {} 个API Key", response_keys.len()),
+        "data": response_keys
     });
 
-    Ok((StatusCode::OK, Json(response)))
+    Ok((StatusCode::OK, audit, Json(response)).into_response())
 }
 
-/// 删除API Key（软删除）
-async fn delete_api_key_handler(
+/// 将新创建的 API Keys 导出为 CSV (含明文密钥)
+fn api_keys_to_csv(api_keys: &[ApiKey]) -> String {
+    let mut csv = String::from("id,name,key,tags,expiresAt,createdAt\n");
+    for api_key in api_keys {
+        let row = [
+            escape_csv_field(&api_key.id),
+            escape_csv_field(&api_key.name),
+            escape_csv_field(api_key.key.as_deref().unwrap_or_default()),
+            escape_csv_field(&api_key.tags.join(";")),
+            api_key.expires_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
+            api_key.created_at.to_rfc3339(),
+        ];
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// 批量更新API Key（按ID列表或标签）
+async fn bulk_update_api_keys_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Json(bulk_request): Json<BulkApiKeyRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let update = bulk_request
+        .updates
+        .clone()
+        .ok_or_else(|| AppError::BadRequest("Missing updates".to_string()))?;
+    let key_ids = resolve_bulk_targets(&state, &bulk_request).await?;
+    info!(
+        "📝 Bulk updating {} API keys by user: {}",
+        key_ids.len(),
+        jwt_state.claims.sub
+    );
+
+    let result = state
+        .api_key_service
+        .bulk_update_keys(&key_ids, &update, &jwt_state.claims.sub)
+        .await;
+    let audit = AuditEvent::new("api_key.bulk_update", "api_key", None)
+        .after(&json!({ "updates": update, "result": result }));
+
+    Ok((
+        StatusCode::OK,
+        axum::Extension(audit),
+        Json(json!({ "success": true, "data": result })),
+    ))
+}
+
+/// 批量启用API Key
+async fn bulk_enable_api_keys_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Json(bulk_request): Json<BulkApiKeyRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let key_ids = resolve_bulk_targets(&state, &bulk_request).await?;
+    info!(
+        "✅ Bulk enabling {} API keys by user: {}",
+        key_ids.len(),
+        jwt_state.claims.sub
+    );
+
+    let result = state
+        .api_key_service
+        .bulk_set_active(&key_ids, true, &jwt_state.claims.sub)
+        .await;
+    let audit = AuditEvent::new("api_key.bulk_enable", "api_key", None).after(&result);
+
+    Ok((
+        StatusCode::OK,
+        axum::Extension(audit),
+        Json(json!({ "success": true, "data": result })),
+    ))
+}
+
+/// 批量禁用API Key
+async fn bulk_disable_api_keys_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Json(bulk_request): Json<BulkApiKeyRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let key_ids = resolve_bulk_targets(&state, &bulk_request).await?;
+    info!(
+        "⏸️  Bulk disabling {} API keys by user: {}",
+        key_ids.len(),
+        jwt_state.claims.sub
+    );
+
+    let result = state
+        .api_key_service
+        .bulk_set_active(&key_ids, false, &jwt_state.claims.sub)
+        .await;
+    let audit = AuditEvent::new("api_key.bulk_disable", "api_key", None).after(&result);
+
+    Ok((
+        StatusCode::OK,
+        axum::Extension(audit),
+        Json(json!({ "success": true, "data": result })),
+    ))
+}
+
+/// 批量删除API Key（软删除）
+async fn bulk_delete_api_keys_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Json(bulk_request): Json<BulkApiKeyRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let key_ids = resolve_bulk_targets(&state, &bulk_request).await?;
+    info!(
+        "🗑️  Bulk deleting {} API keys by user: {}",
+        key_ids.len(),
+        jwt_state.claims.sub
+    );
+
+    let result = state
+        .api_key_service
+        .bulk_delete_keys(&key_ids, &jwt_state.claims.sub)
+        .await;
+    let audit = AuditEvent::new("api_key.bulk_delete", "api_key", None).after(&result);
+
+    Ok((
+        StatusCode::OK,
+        axum::Extension(audit),
+        Json(json!({ "success": true, "data": result })),
+    ))
+}
+
+async fn resolve_bulk_targets(
+    state: &AdminRouteState,
+    bulk_request: &BulkApiKeyRequest,
+) -> Result<Vec<String>, AppError> {
+    state
+        .api_key_service
+        .resolve_bulk_targets(&bulk_request.ids, bulk_request.tag.as_deref())
+        .await
+}
+
+/// 更新API Key
+async fn update_api_key_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    Path(id): Path<String>,
+    Json(key_request): Json<ApiKeyRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("🔄 Updating API key: {} with name: {}", id, key_request.name);
+
+    let before = state.api_key_service.get_key(&id).await?;
+
+    // 调用 ApiKeyService 的更新方法
+    // 支持更新所有字段：名称、状态、账户绑定、限制、标签、模型/客户端限制
+    let updated_key = state
+        .api_key_service
+        .update_key(
+            &id,
+            Some(key_request.name.clone()),
+            key_request.is_active,
+            key_request.account_id.clone(),
+            key_request.claude_account_id.clone().map(Some),
+            key_request.claude_console_account_id.clone().map(Some),
+            key_request.gemini_account_id.clone().map(Some),
+            key_request.openai_account_id.clone().map(Some),
+            key_request.bedrock_account_id.clone().map(Some),
+            key_request.droid_account_id.clone().map(Some),
+            key_request.rate_limit_window,
+            key_request.rate_limit_requests,
+            key_request.rate_limit_cost,
+            key_request.concurrency_limit,
+            key_request.concurrency_queue_enabled,
+            key_request.concurrency_queue_max_size,
+            key_request.concurrency_queue_timeout_ms,
+            key_request.daily_cost_limit,
+            key_request.total_cost_limit,
+            key_request.weekly_opus_cost_limit,
+            Some(key_request.tags.clone()),
+            key_request.enable_model_restriction,
+            Some(key_request.restricted_models.clone()),
+            key_request.enable_client_restriction,
+            Some(key_request.allowed_clients.clone()),
+        )
+        .await?;
+
+    let audit = AuditEvent::new("api_key.update", "api_key", Some(&id))
+        .before(&before)
+        .after(&updated_key);
+
+    let response = json!({
+        "success": true,
+        "message": "API Key更新成功",
+        "data": updated_key  // 修复 ISSUE-UI-007: 与其他端点保持一致，使用 data 字段
+    });
+
+    Ok((StatusCode::OK, axum::Extension(audit), Json(response)))
+}
+
+/// 删除API Key（软删除）
+async fn delete_api_key_handler(
     State(state): State<Arc<AdminRouteState>>,
     jwt_state: axum::Extension<JwtAuthState>,
     Path(id): Path<String>,
 ) -> Result<impl IntoResponse, AppError> {
     info!("🗑️  Deleting API key: {} by user: {}", id, jwt_state.claims.sub);
 
+    let before = state.api_key_service.get_key(&id).await?;
+
     // 调用 ApiKeyService 的软删除方法
     state
         .api_key_service
         .delete_key(&id, &jwt_state.claims.sub)
         .await?;
 
+    let audit = AuditEvent::new("api_key.delete", "api_key", Some(&id)).before(&before);
+
     let response = json!({
         "success": true,
         "message": "API Key删除成功"
     });
 
-    Ok((StatusCode::OK, Json(response)))
+    Ok((StatusCode::OK, axum::Extension(audit), Json(response)))
 }
 
 /// 启用/禁用API Key（Mock实现）
@@ -962,21 +2092,30 @@ async fn get_api_keys_tags_handler(
 ///
 /// 返回系统中所有用户的列表，供前端下拉选择使用
 async fn get_users_handler(
-    State(_state): State<Arc<AdminRouteState>>,
+    State(state): State<Arc<AdminRouteState>>,
 ) -> Result<impl IntoResponse, AppError> {
     info!("📋 Fetching users list");
 
-    // 目前只返回默认的 admin 用户
-    // 未来可以扩展为从 UserService 获取完整的用户列表
-    let users = vec![
-        serde_json::json!({
-            "id": "admin",
-            "username": "admin",
-            "displayName": "Admin",
-            "email": "",
-            "role": "admin"
-        })
-    ];
+    // 管理员始终排在第一位，供 Key 归属下拉选择使用
+    let mut users = vec![json!({
+        "id": "admin",
+        "username": "admin",
+        "displayName": "Admin",
+        "email": "",
+        "role": "admin"
+    })];
+
+    let api_keys = state.api_key_service.get_all_keys(false).await?;
+    for user in state.user_service.list_users().await? {
+        let api_key_count = api_keys
+            .iter()
+            .filter(|k| k.user_id.as_deref() == Some(user.id.as_str()))
+            .count();
+        let mut entry = serde_json::to_value(user.sanitized())
+            .map_err(|e| AppError::InternalError(format!("序列化失败: {}", e)))?;
+        entry["apiKeyCount"] = json!(api_key_count);
+        users.push(entry);
+    }
 
     info!("📋 Retrieved {} users", users.len());
 
@@ -988,227 +2127,1463 @@ async fn get_users_handler(
     Ok((StatusCode::OK, Json(response)))
 }
 
-// ============================================================================
-// Statistics Handlers
-// ============================================================================
+/// 创建用户注册邀请
+async fn create_user_invitation_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Json(request): Json<CreateInvitationRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let invitation = state
+        .user_service
+        .create_invitation(
+            &jwt_state.claims.sub,
+            request.email,
+            request.max_api_keys,
+            request.ttl_hours.unwrap_or(DEFAULT_INVITATION_TTL_HOURS),
+        )
+        .await?;
 
-/// 获取统计概览
-///
-/// 聚合所有 API Keys 的使用统计数据，返回总体概览
-async fn get_stats_overview_handler(
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "success": true, "data": invitation })),
+    ))
+}
+
+/// 启用/禁用用户
+async fn update_user_status_handler(
     State(state): State<Arc<AdminRouteState>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Path(id): Path<String>,
+    Json(request): Json<UpdateUserStatusRequest>,
 ) -> Result<impl IntoResponse, AppError> {
-    info!("📊 Fetching stats overview");
+    let before = state.user_service.get_user(&id).await?.sanitized();
+    let user = state
+        .user_service
+        .set_user_active(
+            &id,
+            request.is_active,
+            request.update_keys,
+            &jwt_state.claims.sub,
+        )
+        .await?;
+    let audit = AuditEvent::new("user.status", "user", Some(&id))
+        .before(&before)
+        .after(&user.sanitized());
 
-    // 1. 获取所有 API Keys（不包括已删除）
-    let all_keys = state.api_key_service.get_all_keys(false).await?;
+    Ok((
+        StatusCode::OK,
+        axum::Extension(audit),
+        Json(json!({ "success": true, "data": user.sanitized() })),
+    ))
+}
 
-    // 2. 统计活跃 API Keys 数量
-    let total_api_keys = all_keys.len() as i64;
-    let active_api_keys = all_keys.iter().filter(|k| k.is_active && !k.is_deleted).count() as i64;
+/// 更新用户 API Key 配额
+async fn update_user_quota_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    Path(id): Path<String>,
+    Json(request): Json<UpdateUserQuotaRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("📏 Updating quota for user: {}", id);
 
-    // 3. 聚合所有 API Keys 的使用量
-    let mut total_requests = 0i64;
-    let mut total_input_tokens = 0i64;
-    let mut total_output_tokens = 0i64;
-    let mut total_cache_creation_tokens = 0i64;
-    let mut total_cache_read_tokens = 0i64;
-    let mut total_cost = 0.0f64;
+    let before = state.user_service.get_user(&id).await?.sanitized();
+    let user = state
+        .user_service
+        .update_quota(&id, request.max_api_keys, request.max_key_daily_cost_limit)
+        .await?;
+    let audit = AuditEvent::new("user.quota", "user", Some(&id))
+        .before(&before)
+        .after(&user.sanitized());
 
-    for api_key in &all_keys {
-        // 获取每个 key 的使用统计
-        if let Ok(usage_stats) = state.api_key_service.get_usage_stats(&api_key.id).await {
-            total_requests += usage_stats.total_requests;
-            total_input_tokens += usage_stats.total_input_tokens;
-            total_output_tokens += usage_stats.total_output_tokens;
-            total_cache_creation_tokens += usage_stats.total_cache_creation_tokens;
-            total_cache_read_tokens += usage_stats.total_cache_read_tokens;
-            total_cost += usage_stats.total_cost;
-        }
-    }
+    Ok((
+        StatusCode::OK,
+        axum::Extension(audit),
+        Json(json!({ "success": true, "data": user.sanitized() })),
+    ))
+}
 
-    // 4. 构建响应
-    let stats = serde_json::json!({
-        "success": true,
-        "stats": {
-            "totalApiKeys": total_api_keys,
-            "activeApiKeys": active_api_keys,
-            "totalUsage": {
-                "requests": total_requests,
-                "inputTokens": total_input_tokens,
-                "outputTokens": total_output_tokens,
-                "cacheCreationTokens": total_cache_creation_tokens,
-                "cacheReadTokens": total_cache_read_tokens,
-                "totalCost": total_cost
-            }
-        }
-    });
+/// 获取用户的 API Keys
+async fn get_user_api_keys_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    state.user_service.get_user(&id).await?;
+    let api_keys = state.user_service.list_user_keys(&id).await?;
 
-    info!("📊 Stats overview: {} total keys, {} active keys, {} total requests",
-          total_api_keys, active_api_keys, total_requests);
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "success": true, "data": api_keys })),
+    ))
+}
 
-    Ok((StatusCode::OK, Json(stats)))
+/// 将用户的 API Keys 转移给其他用户或收回
+async fn reassign_user_keys_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Path(id): Path<String>,
+    Json(request): Json<ReassignKeysRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    info!(
+        "🔀 Reassigning keys of user {} to {} by: {}",
+        id,
+        request.target_user_id.as_deref().unwrap_or("admin"),
+        jwt_state.claims.sub
+    );
+
+    let result = state
+        .user_service
+        .reassign_keys(&id, request.target_user_id.as_deref(), &request.key_ids)
+        .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "success": true, "data": result })),
+    ))
 }
 
-/// 获取使用成本统计
+/// 获取用户管理设置
+async fn get_user_settings_handler(
+    State(state): State<Arc<AdminRouteState>>,
+) -> Result<impl IntoResponse, AppError> {
+    let settings = state.user_service.get_settings().await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "success": true, "data": settings })),
+    ))
+}
+
+/// 更新用户管理设置
+async fn update_user_settings_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    Json(settings): Json<UserManagementSettings>,
+) -> Result<impl IntoResponse, AppError> {
+    let before = state.user_service.get_settings().await?;
+    let settings = state.user_service.update_settings(settings).await?;
+    let audit = AuditEvent::new("settings.user_management", "settings", None)
+        .before(&before)
+        .after(&settings);
+
+    Ok((
+        StatusCode::OK,
+        axum::Extension(audit),
+        Json(json!({ "success": true, "data": settings })),
+    ))
+}
+
+/// 分配用户角色
 ///
-/// 按时间维度（today/week/month）聚合所有 API Keys 的成本数据
-async fn get_usage_costs_handler(
+/// `user` 表示收回管理权限，其他角色必须存在。
+/// 调用者必须拥有新角色和用户当前角色的全部权限，不能借此提升自己或他人的权限
+async fn assign_user_role_handler(
     State(state): State<Arc<AdminRouteState>>,
-    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Path(id): Path<String>,
+    Json(request): Json<AssignUserRoleRequest>,
 ) -> Result<impl IntoResponse, AppError> {
-    let period = params.get("period").map(|s| s.as_str()).unwrap_or("today");
-    info!("📊 Fetching usage costs for period: {}", period);
+    let new_permissions = if request.role == USER_ROLE_NAME {
+        Vec::new()
+    } else {
+        state
+            .role_service
+            .get_role(&request.role)
+            .await?
+            .ok_or_else(|| AppError::BadRequest(format!("Role {} does not exist", request.role)))?
+            .permissions
+    };
+    require_grantable(&state, &jwt_state, &new_permissions).await?;
+
+    let before = state.user_service.get_user(&id).await?.sanitized();
+    let current_permissions = state.role_service.permissions_for(&before.role).await?;
+    require_grantable(&state, &jwt_state, &current_permissions).await?;
+    let user = state
+        .user_service
+        .set_user_role(&id, &request.role, &jwt_state.claims.sub)
+        .await?;
+    let audit = AuditEvent::new("user.role", "user", Some(&id))
+        .before(&before)
+        .after(&user.sanitized());
 
-    // 1. 获取所有 API Keys（不包括已删除）
-    let all_keys = state.api_key_service.get_all_keys(false).await?;
+    Ok((
+        StatusCode::OK,
+        axum::Extension(audit),
+        Json(json!({ "success": true, "data": user.sanitized() })),
+    ))
+}
 
-    // 2. 根据时间维度聚合数据
-    let mut total_cost = 0.0f64;
-    let mut total_input_tokens = 0i64;
-    let mut total_output_tokens = 0i64;
-    let mut total_requests = 0i64;
+// ============================================================================
+// Role Management Handlers
+// ============================================================================
+
+/// 获取所有角色及可用权限
+async fn list_roles_handler(
+    State(state): State<Arc<AdminRouteState>>,
+) -> Result<impl IntoResponse, AppError> {
+    let roles = state.role_service.list_roles().await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "success": true,
+            "data": {
+                "roles": roles,
+                "permissions": Permission::ALL,
+            }
+        })),
+    ))
+}
+
+/// 调用者当前拥有的权限 (管理 API 令牌为角色权限与 scopes 的交集)
+async fn caller_permissions(
+    state: &AdminRouteState,
+    jwt_state: &JwtAuthState,
+) -> Result<Vec<Permission>, AppError> {
+    let mut granted = state
+        .role_service
+        .permissions_for(&jwt_state.claims.role)
+        .await?;
+    if let Some(scopes) = &jwt_state.scopes {
+        granted.retain(|permission| scopes.contains(permission));
+    }
+    Ok(granted)
+}
+
+/// 调用者只能授予自己拥有的权限 (与管理 API 令牌的 scopes 规则一致)
+async fn require_grantable(
+    state: &AdminRouteState,
+    jwt_state: &JwtAuthState,
+    permissions: &[Permission],
+) -> Result<(), AppError> {
+    let granted = caller_permissions(state, jwt_state).await?;
+    if let Some(permission) = permissions.iter().find(|p| !granted.contains(p)) {
+        return Err(AppError::Forbidden(format!(
+            "Cannot grant permission {} that you do not have",
+            permission.as_str()
+        )));
+    }
+    Ok(())
+}
+
+/// 创建自定义角色 (权限不能超出调用者自身的权限)
+async fn create_role_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Json(request): Json<CreateRoleRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_grantable(&state, &jwt_state, &request.permissions).await?;
+    let role = state
+        .role_service
+        .create_role(
+            &request.name,
+            request.description,
+            request.permissions,
+            &jwt_state.claims.sub,
+        )
+        .await?;
+    let audit = AuditEvent::new("role.create", "role", Some(&role.name)).after(&role);
+
+    Ok((
+        StatusCode::OK,
+        axum::Extension(audit),
+        Json(json!({ "success": true, "data": role })),
+    ))
+}
+
+/// 更新自定义角色
+///
+/// 调用者必须拥有角色现有和更新后的全部权限
+async fn update_role_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Path(name): Path<String>,
+    Json(request): Json<UpdateRoleRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let before = state.role_service.get_role(&name).await?;
+    if let Some(role) = &before {
+        require_grantable(&state, &jwt_state, &role.permissions).await?;
+    }
+    require_grantable(&state, &jwt_state, &request.permissions).await?;
+    let role = state
+        .role_service
+        .update_role(
+            &name,
+            request.description,
+            request.permissions,
+            &jwt_state.claims.sub,
+        )
+        .await?;
+    let audit = AuditEvent::new("role.update", "role", Some(&name))
+        .before(&before)
+        .after(&role);
+
+    Ok((
+        StatusCode::OK,
+        axum::Extension(audit),
+        Json(json!({ "success": true, "data": role })),
+    ))
+}
+
+/// 删除自定义角色 (仍有用户使用时拒绝)
+async fn delete_role_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let in_use = state
+        .user_service
+        .list_users()
+        .await?
+        .iter()
+        .filter(|user| user.role == name)
+        .count();
+    if in_use > 0 {
+        return Err(AppError::BadRequest(format!(
+            "Role {} is still assigned to {} users",
+            name, in_use
+        )));
+    }
+
+    let before = state.role_service.get_role(&name).await?;
+    state
+        .role_service
+        .delete_role(&name, &jwt_state.claims.sub)
+        .await?;
+    let audit = AuditEvent::new("role.delete", "role", Some(&name)).before(&before);
+
+    Ok((
+        StatusCode::OK,
+        axum::Extension(audit),
+        Json(json!({ "success": true, "message": "角色已删除" })),
+    ))
+}
+
+// ============================================================================
+// Audit Log Handlers
+// ============================================================================
+
+/// 查询审计日志 (时间倒序)
+///
+/// 支持 actor、action (前缀)、targetType、targetId、since、until、limit、offset 过滤
+async fn list_audit_logs_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    Query(query): Query<AuditLogQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let entries = state.audit_service.query(&query).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "success": true, "data": entries })),
+    ))
+}
+
+/// 导出审计日志 (JSONL，每行一条记录，过滤条件同查询接口)
+async fn export_audit_logs_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Query(query): Query<AuditLogQuery>,
+) -> Result<Response, AppError> {
+    let entries = state.audit_service.export(&query).await?;
+    info!(
+        "📤 Exporting {} audit log entries by user: {}",
+        entries.len(),
+        jwt_state.claims.sub
+    );
+
+    let mut body = String::new();
+    for entry in &entries {
+        body.push_str(&serde_json::to_string(entry)?);
+        body.push('\n');
+    }
+
+    let filename = format!(
+        "audit-log-{}.jsonl",
+        chrono::Utc::now().format("%Y%m%d%H%M%S")
+    );
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "application/x-ndjson".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+// ============================================================================
+// Statistics Handlers
+// ============================================================================
+
+/// 获取统计概览
+///
+/// 聚合所有 API Keys 的使用统计数据，返回总体概览
+async fn get_stats_overview_handler(
+    State(state): State<Arc<AdminRouteState>>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("📊 Fetching stats overview");
+
+    // 1. 获取所有 API Keys（不包括已删除）
+    let all_keys = state.api_key_service.get_all_keys(false).await?;
+
+    // 2. 统计活跃 API Keys 数量
+    let total_api_keys = all_keys.len() as i64;
+    let active_api_keys = all_keys.iter().filter(|k| k.is_active && !k.is_deleted).count() as i64;
+
+    // 3. 聚合所有 API Keys 的使用量
+    let mut total_requests = 0i64;
+    let mut total_input_tokens = 0i64;
+    let mut total_output_tokens = 0i64;
+    let mut total_cache_creation_tokens = 0i64;
+    let mut total_cache_read_tokens = 0i64;
+    let mut total_cost = 0.0f64;
+
+    for api_key in &all_keys {
+        // 获取每个 key 的使用统计
+        if let Ok(usage_stats) = state.api_key_service.get_usage_stats(&api_key.id).await {
+            total_requests += usage_stats.total_requests;
+            total_input_tokens += usage_stats.total_input_tokens;
+            total_output_tokens += usage_stats.total_output_tokens;
+            total_cache_creation_tokens += usage_stats.total_cache_creation_tokens;
+            total_cache_read_tokens += usage_stats.total_cache_read_tokens;
+            total_cost += usage_stats.total_cost;
+        }
+    }
+
+    // 4. 构建响应
+    let stats = serde_json::json!({
+        "success": true,
+        "stats": {
+            "totalApiKeys": total_api_keys,
+            "activeApiKeys": active_api_keys,
+            "totalUsage": {
+                "requests": total_requests,
+                "inputTokens": total_input_tokens,
+                "outputTokens": total_output_tokens,
+                "cacheCreationTokens": total_cache_creation_tokens,
+                "cacheReadTokens": total_cache_read_tokens,
+                "totalCost": total_cost
+            }
+        }
+    });
+
+    info!("📊 Stats overview: {} total keys, {} active keys, {} total requests",
+          total_api_keys, active_api_keys, total_requests);
+
+    Ok((StatusCode::OK, Json(stats)))
+}
+
+/// 获取使用成本统计
+///
+/// 按时间维度（today/week/month）聚合所有 API Keys 的成本数据
+async fn get_usage_costs_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+) -> Result<impl IntoResponse, AppError> {
+    let period = params.get("period").map(|s| s.as_str()).unwrap_or("today");
+    info!("📊 Fetching usage costs for period: {}", period);
+
+    // 1. 获取所有 API Keys（不包括已删除）
+    let all_keys = state.api_key_service.get_all_keys(false).await?;
+
+    // 2. 根据时间维度聚合数据
+    let mut total_cost = 0.0f64;
+    let mut total_input_tokens = 0i64;
+    let mut total_output_tokens = 0i64;
+    let mut total_requests = 0i64;
+
+    for api_key in &all_keys {
+        if let Ok(usage_stats) = state.api_key_service.get_usage_stats(&api_key.id).await {
+            // 根据 period 参数选择对应的统计字段
+            match period {
+                "today" => {
+                    // 使用每日成本
+                    total_cost += usage_stats.daily_cost;
+                    // 注意：当前 ApiKeyUsageStats 没有每日 tokens 字段，使用总量作为近似
+                    // 完整实现需要在 Redis 中按日期存储 tokens
+                    total_input_tokens += usage_stats.total_input_tokens;
+                    total_output_tokens += usage_stats.total_output_tokens;
+                    total_requests += usage_stats.total_requests;
+                }
+                "week" => {
+                    // 使用每周成本
+                    total_cost += usage_stats.weekly_opus_cost;
+                    total_input_tokens += usage_stats.total_input_tokens;
+                    total_output_tokens += usage_stats.total_output_tokens;
+                    total_requests += usage_stats.total_requests;
+                }
+                _ => {
+                    // 默认使用总成本（month/all）
+                    total_cost += usage_stats.total_cost;
+                    total_input_tokens += usage_stats.total_input_tokens;
+                    total_output_tokens += usage_stats.total_output_tokens;
+                    total_requests += usage_stats.total_requests;
+                }
+            }
+        }
+    }
+
+    // 3. 构建响应（匹配前端期望的结构）
+    let costs = serde_json::json!({
+        "success": true,
+        "period": period,
+        "data": {
+            "totalCosts": {
+                "totalCost": total_cost,
+                "inputTokens": total_input_tokens,
+                "outputTokens": total_output_tokens,
+                "requests": total_requests,
+                "formatted": {
+                    "totalCost": format!("${:.6}", total_cost)
+                }
+            }
+        }
+    });
+
+    info!("📊 Usage costs for period '{}': ${:.4}, {} requests",
+          period, total_cost, total_requests);
+
+    Ok((StatusCode::OK, Json(costs)))
+}
+
+/// 获取全局使用趋势
+///
+/// 查询参数见 `TrendQuery` (granularity / days / startDate / endDate / timezone)
+async fn get_usage_trend_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    Query(query): Query<TrendQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let range = resolve_trend_range(&state, &query)?;
+    info!(
+        "📊 Fetching usage trend: granularity={}, points={}",
+        range.granularity.as_str(),
+        range.points.len()
+    );
+
+    let series = state
+        .trend_service
+        .get_series(UsageDimension::Global, "", &range)
+        .await?;
+    let data: Vec<serde_json::Value> = range
+        .points
+        .iter()
+        .zip(&series)
+        .map(|(point, usage)| {
+            let mut item = trend_point_json(&range, point);
+            item.extend(usage_json(usage));
+            serde_json::Value::Object(item)
+        })
+        .collect();
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "success": true,
+            "granularity": range.granularity.as_str(),
+            "timezone": timezone_hours(range.timezone),
+            "data": data
+        })),
+    ))
+}
+
+/// 获取模型统计
+///
+/// `period=daily` 为今天，`period=monthly` 为本月；提供 startDate/endDate 时按自定义范围统计
+async fn get_model_stats_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    Query(query): Query<TrendQuery>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
+) -> Result<impl IntoResponse, AppError> {
+    let period = params.get("period").map(|s| s.as_str()).unwrap_or("daily");
+    info!("📊 Fetching model stats for period: {}", period);
+
+    let custom = query.start_date.is_some() && query.end_date.is_some();
+    let range = if custom {
+        resolve_trend_range(&state, &query)?
+    } else {
+        let system_timezone = state.api_key_service.system_timezone();
+        let timezone = query
+            .timezone(system_timezone)
+            .map_err(AppError::ValidationError)?;
+        let now = chrono::Utc::now();
+        let today = now.with_timezone(&timezone).date_naive();
+        let first = match period {
+            "monthly" => chrono::Datelike::with_day(&today, 1).unwrap_or(today),
+            _ => today,
+        };
+        TrendRange::daily(first, today, timezone, system_timezone, now)
+            .map_err(AppError::ValidationError)?
+    };
+
+    let totals = state
+        .trend_service
+        .get_member_totals(UsageDimension::Model, &range)
+        .await?;
+    let mut models: Vec<(String, ModelUsage)> = totals.into_iter().collect();
+    models.sort_by(|a, b| b.1.cost.total_cmp(&a.1.cost));
+
+    let period = if custom { "custom" } else { period };
+    let data: Vec<serde_json::Value> = models
+        .iter()
+        .map(|(model, usage)| {
+            json!({
+                "model": model,
+                "period": period,
+                "requests": usage.requests,
+                "inputTokens": usage.input_tokens,
+                "outputTokens": usage.output_tokens,
+                "cacheCreateTokens": usage.cache_creation_tokens,
+                "cacheReadTokens": usage.cache_read_tokens,
+                "allTokens": usage.total_tokens(),
+                "usage": usage_json(usage),
+                "costs": { "total": usage.cost },
+                "formatted": { "total": format!("${:.6}", usage.cost) }
+            })
+        })
+        .collect();
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "success": true,
+            "period": period,
+            "data": data
+        })),
+    ))
+}
+
+/// 获取账号使用趋势
+///
+/// `group`: claude (含 Console / Bedrock / CCR)、openai (含 Azure)、gemini、droid
+async fn get_account_usage_trend_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    Query(query): Query<TrendQuery>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
+) -> Result<impl IntoResponse, AppError> {
+    let group = params.get("group").map(|s| s.as_str()).unwrap_or("claude");
+    let (group_label, platforms): (&str, &[&str]) = match group {
+        "claude" => ("Claude账户", &["claude", "claudeconsole", "bedrock", "ccr"]),
+        "openai" => ("OpenAI账户", &["openai", "azure"]),
+        "gemini" => ("Gemini账户", &["gemini"]),
+        "droid" => ("Droid账户", &["droid"]),
+        _ => {
+            return Err(AppError::ValidationError(format!(
+                "Invalid account group: {}",
+                group
+            )))
+        }
+    };
+    let range = resolve_trend_range(&state, &query)?;
+    info!(
+        "📊 Fetching account usage trend: group={}, granularity={}, points={}",
+        group,
+        range.granularity.as_str(),
+        range.points.len()
+    );
+
+    let series = state
+        .trend_service
+        .get_member_series(UsageDimension::Account, &range)
+        .await?;
+
+    // 只保留该分组平台下仍存在的账户
+    let mut account_ids: Vec<&String> = series.iter().flat_map(|point| point.keys()).collect();
+    account_ids.sort();
+    account_ids.dedup();
+    let accounts: std::collections::HashMap<String, String> =
+        load_account_names(&state, &account_ids)
+            .await?
+            .into_iter()
+            .filter(|(_, (_, platform))| platforms.contains(&platform.as_str()))
+            .map(|(id, (name, _))| (id, name))
+            .collect();
+
+    let mut cost_totals: std::collections::HashMap<&str, f64> = std::collections::HashMap::new();
+    let data: Vec<serde_json::Value> = range
+        .points
+        .iter()
+        .zip(&series)
+        .map(|(point, usages)| {
+            let mut item = trend_point_json(&range, point);
+            let mut point_accounts = serde_json::Map::new();
+            for (account_id, usage) in usages {
+                let Some(name) = accounts.get(account_id) else {
+                    continue;
+                };
+                *cost_totals.entry(account_id.as_str()).or_default() += usage.cost;
+                point_accounts.insert(
+                    account_id.clone(),
+                    json!({
+                        "name": name,
+                        "cost": usage.cost,
+                        "formattedCost": format!("${:.6}", usage.cost),
+                        "requests": usage.requests
+                    }),
+                );
+            }
+            item.insert("accounts".to_string(), point_accounts.into());
+            serde_json::Value::Object(item)
+        })
+        .collect();
+
+    let top_accounts = top_members(&cost_totals, 20);
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "success": true,
+            "group": group,
+            "groupLabel": group_label,
+            "granularity": range.granularity.as_str(),
+            "timezone": timezone_hours(range.timezone),
+            "data": data,
+            "topAccounts": top_accounts,
+            "totalAccounts": cost_totals.len()
+        })),
+    ))
+}
+
+/// 获取 API Keys 使用趋势
+///
+/// `metric` (requests / tokens / cost) 决定 topApiKeys 的排序依据
+async fn get_api_keys_usage_trend_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    Query(query): Query<TrendQuery>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
+) -> Result<impl IntoResponse, AppError> {
+    let metric = params.get("metric").map(|s| s.as_str()).unwrap_or("requests");
+    let range = resolve_trend_range(&state, &query)?;
+    info!(
+        "📊 Fetching API keys usage trend: metric={}, granularity={}, points={}",
+        metric,
+        range.granularity.as_str(),
+        range.points.len()
+    );
+
+    let series = state
+        .trend_service
+        .get_member_series(UsageDimension::ApiKey, &range)
+        .await?;
+    let key_names: std::collections::HashMap<String, String> = state
+        .api_key_service
+        .get_all_keys(true)
+        .await?
+        .into_iter()
+        .map(|key| (key.id, key.name))
+        .collect();
+
+    let mut metric_totals: std::collections::HashMap<&str, f64> = std::collections::HashMap::new();
+    let data: Vec<serde_json::Value> = range
+        .points
+        .iter()
+        .zip(&series)
+        .map(|(point, usages)| {
+            let mut item = trend_point_json(&range, point);
+            let mut point_keys = serde_json::Map::new();
+            for (key_id, usage) in usages {
+                let Some(name) = key_names.get(key_id) else {
+                    continue;
+                };
+                let value = match metric {
+                    "tokens" => usage.total_tokens() as f64,
+                    "cost" => usage.cost,
+                    _ => usage.requests as f64,
+                };
+                *metric_totals.entry(key_id.as_str()).or_default() += value;
+                point_keys.insert(
+                    key_id.clone(),
+                    json!({
+                        "name": name,
+                        "tokens": usage.total_tokens(),
+                        "requests": usage.requests,
+                        "cost": usage.cost,
+                        "formattedCost": format!("${:.6}", usage.cost)
+                    }),
+                );
+            }
+            item.insert("apiKeys".to_string(), point_keys.into());
+            serde_json::Value::Object(item)
+        })
+        .collect();
+
+    let top_api_keys = top_members(&metric_totals, 10);
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "success": true,
+            "metric": metric,
+            "granularity": range.granularity.as_str(),
+            "timezone": timezone_hours(range.timezone),
+            "data": data,
+            "topApiKeys": top_api_keys,
+            "totalApiKeys": metric_totals.len()
+        })),
+    ))
+}
+
+/// 按系统时区解析趋势查询范围
+fn resolve_trend_range(state: &AdminRouteState, query: &TrendQuery) -> Result<TrendRange, AppError> {
+    query
+        .resolve(state.api_key_service.system_timezone(), chrono::Utc::now())
+        .map_err(AppError::ValidationError)
+}
+
+/// 趋势数据点的时间字段：小时粒度为 `hour` (ISO 时间) 和 `label`，日粒度为 `date`
+fn trend_point_json(
+    range: &TrendRange,
+    point: &TrendPoint,
+) -> serde_json::Map<String, serde_json::Value> {
+    let mut item = serde_json::Map::new();
+    match range.granularity {
+        TrendGranularity::Hour => {
+            item.insert("hour".to_string(), json!(point.start.to_rfc3339()));
+            item.insert("label".to_string(), json!(point.label));
+        }
+        TrendGranularity::Day => {
+            item.insert("date".to_string(), json!(point.label));
+        }
+    }
+    item
+}
+
+/// 使用量字段 (与前端趋势图一致的命名)
+fn usage_json(usage: &ModelUsage) -> serde_json::Map<String, serde_json::Value> {
+    let value = json!({
+        "requests": usage.requests,
+        "inputTokens": usage.input_tokens,
+        "outputTokens": usage.output_tokens,
+        "cacheCreateTokens": usage.cache_creation_tokens,
+        "cacheReadTokens": usage.cache_read_tokens,
+        "totalTokens": usage.total_tokens(),
+        "cost": usage.cost,
+        "formattedCost": format!("${:.6}", usage.cost)
+    });
+    match value {
+        serde_json::Value::Object(map) => map,
+        _ => serde_json::Map::new(),
+    }
+}
+
+/// 按合计值倒序取前 `limit` 个成员
+fn top_members(totals: &std::collections::HashMap<&str, f64>, limit: usize) -> Vec<String> {
+    let mut members: Vec<(&str, f64)> = totals.iter().map(|(id, v)| (*id, *v)).collect();
+    members.sort_by(|a, b| b.1.total_cmp(&a.1));
+    members
+        .into_iter()
+        .take(limit)
+        .map(|(id, _)| id.to_string())
+        .collect()
+}
+
+/// 读取账户名称和平台 (已删除的账户不返回)
+async fn load_account_names(
+    state: &AdminRouteState,
+    account_ids: &[&String],
+) -> Result<std::collections::HashMap<String, (String, String)>, AppError> {
+    let mut accounts = std::collections::HashMap::new();
+    if account_ids.is_empty() {
+        return Ok(accounts);
+    }
+
+    let mut pipe = redis::pipe();
+    for id in account_ids {
+        pipe.cmd("GET").arg(format!("claude_account:{}", id));
+    }
+    let mut conn = state.redis.get_connection().await?;
+    let rows: Vec<Option<String>> = pipe
+        .query_async(&mut conn)
+        .await
+        .map_err(|e| AppError::RedisError(format!("Failed to fetch accounts: {}", e)))?;
+
+    for (id, json) in account_ids.iter().zip(rows) {
+        let Some(account) = json.and_then(|j| serde_json::from_str::<serde_json::Value>(&j).ok())
+        else {
+            continue;
+        };
+        let field = |name: &str| account.get(name).and_then(|v| v.as_str()).unwrap_or_default();
+        accounts.insert(
+            id.to_string(),
+            (field("name").to_string(), field("platform").to_string()),
+        );
+    }
+
+    Ok(accounts)
+}
+
+// ============================================================================
+// Billing Event Handlers
+// ============================================================================
+
+/// 按时间范围分页查询计费事件 (按 Stream ID 升序)
+///
+/// 返回的 `nextCursor` 不为空时，作为下一页的 `cursor` 参数
+async fn list_billing_events_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    Query(query): Query<BillingEventsQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let end = query.end_time.unwrap_or_else(chrono::Utc::now);
+    let start = query
+        .start_time
+        .unwrap_or_else(|| end - chrono::Duration::hours(24));
+    validate_billing_range(start, end)?;
+
+    let page = state
+        .api_key_service
+        .billing_events()
+        .list_events(start, end, query.cursor.as_deref(), query.limit.unwrap_or(100))
+        .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "success": true, "data": page })),
+    ))
+}
+
+/// 把时间范围内的计费事件重新发布到 Stream，供外部计费系统重新消费
+///
+/// 单次最多重放 `MAX_BILLING_EVENTS_REPLAY` 条，`truncated` 为 true 时用 `nextCursor` 继续
+async fn replay_billing_events_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Json(request): Json<BillingEventsReplayRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    validate_billing_range(request.start_time, request.end_time)?;
+
+    let result = state
+        .api_key_service
+        .billing_events()
+        .replay(
+            request.start_time,
+            request.end_time,
+            request.cursor.as_deref(),
+            request.limit.unwrap_or(MAX_BILLING_EVENTS_REPLAY),
+        )
+        .await?;
+    info!(
+        "🔁 Billing events replayed by {}: {} events",
+        jwt_state.claims.sub, result.replayed
+    );
+    let audit = AuditEvent::new("billing_events.replay", "billing_events", None)
+        .after(&json!({ "request": request, "result": result }));
+
+    Ok((
+        StatusCode::OK,
+        axum::Extension(audit),
+        Json(json!({ "success": true, "data": result })),
+    ))
+}
+
+fn validate_billing_range(
+    start: chrono::DateTime<chrono::Utc>,
+    end: chrono::DateTime<chrono::Utc>,
+) -> Result<(), AppError> {
+    if start >= end {
+        return Err(AppError::ValidationError(
+            "startTime must be earlier than endTime".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Cost Recalculation Handlers
+// ============================================================================
+
+/// 按当前定价重算历史成本
+///
+/// 读取运行中服务维护的定价文件，在后台执行，立即返回任务 (202)；
+/// 同一时间只能有一个重算任务
+async fn start_cost_recalculation_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Json(options): Json<CostRecalculationOptions>,
+) -> Result<impl IntoResponse, AppError> {
+    let pricing = load_pricing(&state).await?;
+    let job = state
+        .cost_recalculation
+        .start_job(&pricing, &options, &jwt_state.claims.sub)
+        .await?;
+    let audit = AuditEvent::new("cost_recalculation.start", "cost_recalculation", Some(&job.id))
+        .after(&options);
+
+    let service = state.cost_recalculation.clone();
+    let background_job = job.clone();
+    tokio::spawn(async move {
+        service.run_job(&pricing, background_job, &options).await;
+    });
+
+    Ok((
+        StatusCode::ACCEPTED,
+        axum::Extension(audit),
+        Json(json!({ "success": true, "data": job })),
+    ))
+}
+
+/// 加载运行中服务维护的定价文件和管理员定价
+async fn load_pricing(state: &AdminRouteState) -> Result<PricingService, AppError> {
+    let pricing = PricingService::new(Arc::new(reqwest::Client::new()))
+        .with_overrides(Arc::new(state.redis.clone()));
+    pricing
+        .load_local()
+        .await
+        .map_err(|e| AppError::InternalError(format!("Failed to load pricing data: {}", e)))?;
+    Ok(pricing)
+}
+
+/// 获取重算任务的进度和结果
+async fn get_cost_recalculation_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let job = state.cost_recalculation.get_job(&id).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "success": true, "data": job })),
+    ))
+}
+
+// ============================================================================
+// Pricing Override Handlers
+// ============================================================================
+
+/// 获取全部管理员模型定价
+async fn list_pricing_overrides_handler(
+    State(state): State<Arc<AdminRouteState>>,
+) -> Result<impl IntoResponse, AppError> {
+    let overrides = state.pricing_overrides.list().await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "success": true, "data": overrides })),
+    ))
+}
+
+/// 获取单个模型的管理员定价 (模型名可包含 `/`)
+async fn get_pricing_override_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    Path(model): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let pricing = state.pricing_overrides.get(&model).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "success": true, "data": pricing })),
+    ))
+}
+
+/// 创建或替换模型定价 (优先于价格镜像数据，30 秒内生效)
+async fn upsert_pricing_override_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Path(model): Path<String>,
+    Json(request): Json<PricingOverrideRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let (before, pricing) = state
+        .pricing_overrides
+        .upsert(&model, request, &jwt_state.claims.sub)
+        .await?;
+    let mut audit = AuditEvent::new("pricing_override.upsert", "pricing", Some(&pricing.model))
+        .after(&pricing);
+    if let Some(before) = &before {
+        audit = audit.before(before);
+    }
+
+    Ok((
+        StatusCode::OK,
+        axum::Extension(audit),
+        Json(json!({
+            "success": true,
+            "message": "模型定价已保存",
+            "data": pricing
+        })),
+    ))
+}
+
+/// 删除模型定价 (恢复使用价格镜像数据)
+async fn delete_pricing_override_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Path(model): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let before = state
+        .pricing_overrides
+        .delete(&model, &jwt_state.claims.sub)
+        .await?;
+    let audit = AuditEvent::new("pricing_override.delete", "pricing", Some(&model)).before(&before);
+
+    Ok((
+        StatusCode::OK,
+        axum::Extension(audit),
+        Json(json!({ "success": true, "message": "模型定价已删除" })),
+    ))
+}
+
+/// 流量中出现过、目前仍没有定价的模型 (成本按 0 记录)
+async fn list_unpriced_models_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    Query(query): Query<UnpricedModelsQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let pricing = load_pricing(&state).await?;
+    let models = state
+        .pricing_overrides
+        .unpriced_models(&pricing, query.limit.unwrap_or(100))
+        .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "success": true, "data": models })),
+    ))
+}
+
+/// 获取计费倍率
+async fn get_billing_multipliers_handler(
+    State(state): State<Arc<AdminRouteState>>,
+) -> Result<impl IntoResponse, AppError> {
+    let multipliers = state.api_key_service.billing_multipliers().get().await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "success": true, "data": multipliers })),
+    ))
+}
+
+/// 替换计费倍率
+///
+/// 只影响之后记录的用量，已记录的计费成本不变
+async fn update_billing_multipliers_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Json(update): Json<BillingMultipliersUpdate>,
+) -> Result<impl IntoResponse, AppError> {
+    let (before, multipliers) = state
+        .api_key_service
+        .billing_multipliers()
+        .update(update, &jwt_state.claims.sub)
+        .await?;
+    let audit = AuditEvent::new("billing_multipliers.update", "settings", Some("billing"))
+        .before(&before)
+        .after(&multipliers);
+
+    Ok((
+        StatusCode::OK,
+        axum::Extension(audit),
+        Json(json!({ "success": true, "data": multipliers })),
+    ))
+}
+
+/// 使用量对账单
+///
+/// `format=csv` 和 `format=html` 返回文件，HTML 为可直接打印的文档
+async fn get_statement_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    Query(query): Query<StatementQuery>,
+) -> Result<Response, AppError> {
+    let scope = query.scope().map_err(AppError::BadRequest)?;
+    let format = query.format().map_err(AppError::BadRequest)?;
+    let (first, last) = query.date_range().map_err(AppError::ValidationError)?;
+    info!(
+        "🧾 Generating {} statement for {}: {} to {}",
+        scope.as_str(),
+        query.id,
+        first,
+        last
+    );
+
+    let statement = state
+        .statement_service
+        .generate(scope, &query.id, first, last)
+        .await?;
+
+    let subject: String = statement
+        .subject_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect();
+    let filename = format!("statement-{}-{}-{}-{}", scope.as_str(), subject, first, last);
+    let (content_type, disposition, body) = match format {
+        StatementFormat::Json => {
+            return Ok((
+                StatusCode::OK,
+                Json(json!({ "success": true, "data": statement })),
+            )
+                .into_response());
+        }
+        StatementFormat::Csv => (
+            "text/csv; charset=utf-8",
+            format!("attachment; filename=\"{}.csv\"", filename),
+            statement.to_csv(),
+        ),
+        StatementFormat::Html => (
+            "text/html; charset=utf-8",
+            format!("inline; filename=\"{}.html\"", filename),
+            statement.to_html(),
+        ),
+    };
+
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    )
+        .into_response())
+}
 
-    for api_key in &all_keys {
-        if let Ok(usage_stats) = state.api_key_service.get_usage_stats(&api_key.id).await {
-            // 根据 period 参数选择对应的统计字段
-            match period {
-                "today" => {
-                    // 使用每日成本
-                    total_cost += usage_stats.daily_cost;
-                    // 注意：当前 ApiKeyUsageStats 没有每日 tokens 字段，使用总量作为近似
-                    // 完整实现需要在 Redis 中按日期存储 tokens
-                    total_input_tokens += usage_stats.total_input_tokens;
-                    total_output_tokens += usage_stats.total_output_tokens;
-                    total_requests += usage_stats.total_requests;
-                }
-                "week" => {
-                    // 使用每周成本
-                    total_cost += usage_stats.weekly_opus_cost;
-                    total_input_tokens += usage_stats.total_input_tokens;
-                    total_output_tokens += usage_stats.total_output_tokens;
-                    total_requests += usage_stats.total_requests;
-                }
-                _ => {
-                    // 默认使用总成本（month/all）
-                    total_cost += usage_stats.total_cost;
-                    total_input_tokens += usage_stats.total_input_tokens;
-                    total_output_tokens += usage_stats.total_output_tokens;
-                    total_requests += usage_stats.total_requests;
+/// 流式导出使用量统计
+///
+/// 按统计桶逐个读取 Redis 并写出，导出范围较大时不会在内存中拼接完整文件
+async fn export_usage_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    Query(query): Query<UsageExportQuery>,
+) -> Result<Response, AppError> {
+    use futures::StreamExt;
+
+    let dimension = query.dimension().map_err(AppError::BadRequest)?;
+    let jsonl = query.is_jsonl().map_err(AppError::BadRequest)?;
+    let buckets = query
+        .buckets(state.api_key_service.system_timezone(), chrono::Utc::now())
+        .map_err(AppError::ValidationError)?;
+    let id = query.id.clone().filter(|id| !id.trim().is_empty());
+    info!(
+        "📤 Exporting {} usage: {} buckets, id={}",
+        dimension.as_str(),
+        buckets.len(),
+        id.as_deref().unwrap_or("*")
+    );
+
+    let lines = state
+        .trend_service
+        .export_rows(dimension, id, buckets)
+        .map(move |rows| {
+            let mut chunk = String::new();
+            for row in rows? {
+                if jsonl {
+                    chunk.push_str(&serde_json::to_string(&row)?);
+                } else {
+                    chunk.push_str(&row.to_csv());
                 }
+                chunk.push('\n');
             }
-        }
+            Ok::<_, AppError>(chunk)
+        })
+        .scan(false, move |failed, chunk| {
+            if *failed {
+                return futures::future::ready(None);
+            }
+            // 中途读取失败时写入错误标记行后结束，响应已是 200，不能让截断的文件看起来完整
+            let chunk = chunk.unwrap_or_else(|e| {
+                warn!("⚠️ Usage export aborted: {}", e);
+                *failed = true;
+                export_error_line(jsonl, &e)
+            });
+            futures::future::ready(Some(Ok::<_, std::convert::Infallible>(chunk)))
+        });
+    let body = if jsonl {
+        Body::from_stream(lines)
+    } else {
+        let header = format!("{}\n", UsageExportRow::CSV_HEADER);
+        Body::from_stream(futures::stream::once(async { Ok(header) }).chain(lines))
+    };
+
+    let (content_type, extension) = if jsonl {
+        ("application/x-ndjson", "jsonl")
+    } else {
+        ("text/csv; charset=utf-8", "csv")
+    };
+    let filename = format!(
+        "usage-{}-{}.{}",
+        dimension.as_str(),
+        chrono::Utc::now().format("%Y%m%d%H%M%S"),
+        extension
+    );
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// 导出中途失败时写在文件末尾的错误标记行
+///
+/// CSV 为 `# export failed: ...`，JSONL 为 `{"error":"export failed: ..."}`
+fn export_error_line(jsonl: bool, error: &AppError) -> String {
+    let message = format!("export failed: {}", error);
+    if jsonl {
+        format!("{}\n", json!({ "error": message }))
+    } else {
+        format!("# {}\n", message.replace('\n', " "))
     }
+}
 
-    // 3. 构建响应（匹配前端期望的结构）
-    let costs = serde_json::json!({
-        "success": true,
-        "period": period,
-        "data": {
-            "totalCosts": {
-                "totalCost": total_cost,
-                "inputTokens": total_input_tokens,
-                "outputTokens": total_output_tokens,
-                "requests": total_requests,
-                "formatted": {
-                    "totalCost": format!("${:.6}", total_cost)
-                }
-            }
+/// 解析并校验钱包所有者 (Key 或用户必须存在)
+async fn wallet_owner(
+    state: &AdminRouteState,
+    owner_type: &str,
+    owner_id: &str,
+) -> Result<WalletOwner, AppError> {
+    let owner_type: WalletOwnerType = owner_type.parse().map_err(AppError::BadRequest)?;
+    match owner_type {
+        WalletOwnerType::Key => {
+            state.api_key_service.get_key(owner_id).await?;
+            Ok(WalletOwner::key(owner_id))
         }
-    });
+        WalletOwnerType::User => {
+            state.user_service.get_user(owner_id).await?;
+            Ok(WalletOwner::user(owner_id))
+        }
+    }
+}
 
-    info!("📊 Usage costs for period '{}': ${:.4}, {} requests",
-          period, total_cost, total_requests);
+/// 获取钱包余额
+async fn get_wallet_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    Path((owner_type, owner_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse, AppError> {
+    let owner = wallet_owner(&state, &owner_type, &owner_id).await?;
+    let wallet = state
+        .api_key_service
+        .wallets()
+        .get_wallet(&owner)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Wallet not found: {}", owner)))?;
 
-    Ok((StatusCode::OK, Json(costs)))
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "success": true, "data": wallet })),
+    ))
 }
 
-/// 获取使用趋势
-async fn get_usage_trend_handler(
-    State(_state): State<Arc<AdminRouteState>>,
-    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+/// 钱包流水 (最新的在前)
+async fn get_wallet_ledger_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    Path((owner_type, owner_id)): Path<(String, String)>,
+    Query(query): Query<WalletLedgerQuery>,
 ) -> Result<impl IntoResponse, AppError> {
-    let granularity = params.get("granularity").map(|s| s.as_str()).unwrap_or("day");
-    let days = params.get("days").and_then(|s| s.parse::<i32>().ok()).unwrap_or(7);
-    info!("📊 Fetching usage trend: granularity={}, days={}", granularity, days);
-
-    // 占位数据 - 返回空趋势数组
-    // TODO: 从 Redis 聚合时间序列数据
-    let trend = serde_json::json!({
-        "success": true,
-        "granularity": granularity,
-        "data": []
-    });
+    let owner = wallet_owner(&state, &owner_type, &owner_id).await?;
+    let (entries, total) = state
+        .api_key_service
+        .wallets()
+        .get_ledger(
+            &owner,
+            query.offset.unwrap_or(0),
+            query.limit.unwrap_or(100),
+            query.credits_only.unwrap_or(false),
+        )
+        .await?;
 
-    Ok((StatusCode::OK, Json(trend)))
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "success": true,
+            "data": { "entries": entries, "total": total }
+        })),
+    ))
 }
 
-/// 获取模型统计
-async fn get_model_stats_handler(
-    State(_state): State<Arc<AdminRouteState>>,
-    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+/// 充值 (钱包不存在时创建)
+async fn top_up_wallet_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Path((owner_type, owner_id)): Path<(String, String)>,
+    Json(request): Json<WalletTopUpRequest>,
 ) -> Result<impl IntoResponse, AppError> {
-    let period = params.get("period").map(|s| s.as_str()).unwrap_or("monthly");
-    info!("📊 Fetching model stats for period: {}", period);
-
-    // 占位数据 - 返回空模型统计
-    // 前端期望: response.data (数组)
-    // TODO: 按模型维度聚合 Redis 数据
-    let stats = serde_json::json!({
-        "success": true,
-        "period": period,
-        "data": []  // ← 字段名从 "models" 改为 "data" 以匹配前端期望
-    });
+    let owner = wallet_owner(&state, &owner_type, &owner_id).await?;
+    let entry = state
+        .api_key_service
+        .wallets()
+        .top_up(&owner, request.amount, request.note, &jwt_state.claims.sub)
+        .await?;
+    let audit =
+        AuditEvent::new("wallet.top_up", "wallet", Some(&owner.to_string())).after(&entry);
 
-    Ok((StatusCode::OK, Json(stats)))
+    Ok((
+        StatusCode::OK,
+        axum::Extension(audit),
+        Json(json!({ "success": true, "data": entry })),
+    ))
 }
 
-/// 获取账号使用趋势
-async fn get_account_usage_trend_handler(
-    State(_state): State<Arc<AdminRouteState>>,
-    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+/// 兑换码列表
+async fn list_redemption_codes_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    Query(query): Query<RedemptionCodesQuery>,
 ) -> Result<impl IntoResponse, AppError> {
-    let granularity = params.get("granularity").map(|s| s.as_str()).unwrap_or("day");
-    let days = params.get("days").and_then(|s| s.parse::<i32>().ok()).unwrap_or(7);
-    let group = params.get("group").map(|s| s.as_str()).unwrap_or("claude");
-    info!("📊 Fetching account usage trend: group={}, granularity={}, days={}", group, granularity, days);
-
-    // 占位数据 - 返回符合前端期望的结构
-    // 前端期望: data, topAccounts, totalAccounts, group, groupLabel
-    // TODO: 按账号维度聚合 Redis 数据
-    let trend = serde_json::json!({
-        "success": true,
-        "group": group,
-        "granularity": granularity,
-        "data": [],           // 前端期望 response.data
-        "topAccounts": [],    // 前端期望 response.topAccounts
-        "totalAccounts": 0,   // 前端期望 response.totalAccounts
-        "groupLabel": ""      // 前端期望 response.groupLabel
-    });
+    let (codes, total) = state
+        .api_key_service
+        .wallets()
+        .list_codes(
+            query.status,
+            query.batch_id.as_deref(),
+            query.offset.unwrap_or(0),
+            query.limit.unwrap_or(100),
+        )
+        .await?;
 
-    Ok((StatusCode::OK, Json(trend)))
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "success": true,
+            "data": { "codes": codes, "total": total }
+        })),
+    ))
 }
 
-/// 获取 API Keys 使用趋势
-async fn get_api_keys_usage_trend_handler(
-    State(_state): State<Arc<AdminRouteState>>,
-    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+/// 批量生成兑换码
+///
+/// 兑换码只在响应和列表接口中返回，审计日志只记录批次信息
+async fn generate_redemption_codes_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Json(request): Json<GenerateRedemptionCodesRequest>,
 ) -> Result<impl IntoResponse, AppError> {
-    let granularity = params.get("granularity").map(|s| s.as_str()).unwrap_or("day");
-    let days = params.get("days").and_then(|s| s.parse::<i32>().ok()).unwrap_or(7);
-    let metric = params.get("metric").map(|s| s.as_str()).unwrap_or("requests");
-    info!("📊 Fetching API keys usage trend: metric={}, granularity={}, days={}", metric, granularity, days);
+    let (batch_id, codes) = state
+        .api_key_service
+        .wallets()
+        .generate_codes(&request, &jwt_state.claims.sub)
+        .await?;
+    let audit = AuditEvent::new("redemption_codes.generate", "redemption_code", Some(&batch_id))
+        .after(&json!({
+            "count": codes.len(),
+            "amount": request.amount,
+            "expiresAt": request.expires_at,
+            "note": request.note,
+        }));
 
-    // 占位数据 - 返回空 API Key 趋势
-    // TODO: 按 API Key 维度聚合 Redis 数据
-    let trend = serde_json::json!({
-        "success": true,
-        "metric": metric,
-        "granularity": granularity,
-        "apiKeys": []
-    });
+    Ok((
+        StatusCode::CREATED,
+        axum::Extension(audit),
+        Json(json!({
+            "success": true,
+            "data": { "batchId": batch_id, "codes": codes }
+        })),
+    ))
+}
+
+/// 停用未兑换的兑换码
+async fn disable_redemption_code_handler(
+    State(state): State<Arc<AdminRouteState>>,
+    Path(code): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    state.api_key_service.wallets().disable_code(&code).await?;
+    let audit = AuditEvent::new("redemption_code.disable", "redemption_code", Some(&code));
 
-    Ok((StatusCode::OK, Json(trend)))
+    Ok((
+        StatusCode::OK,
+        axum::Extension(audit),
+        Json(json!({ "success": true, "message": "兑换码已停用" })),
+    ))
 }
 
 // ============================================================================
@@ -1642,6 +4017,7 @@ mod tests {
     use tower::ServiceExt;
 
     #[tokio::test]
+    #[ignore] // 登录锁定检查需要 Redis
     async fn test_login_route() {
         let settings = Settings::new().expect("Failed to create test settings");
         let redis = Arc::new(RedisPool::new(&settings).expect("Failed to create Redis pool"));
@@ -1651,7 +4027,7 @@ mod tests {
         ));
         let api_key_service = Arc::new(ApiKeyService::new((*redis).clone(), settings.clone()));
 
-        let app = create_admin_routes(admin_service, api_key_service);
+        let app = create_admin_routes(admin_service, api_key_service, (*redis).clone());
 
         let request = Request::builder()
             .uri("/auth/login")
@@ -1666,4 +4042,313 @@ mod tests {
             response.status() == StatusCode::OK || response.status() == StatusCode::UNAUTHORIZED
         );
     }
+
+    #[test]
+    fn test_export_error_line() {
+        let error = AppError::RedisError("connection reset".to_string());
+        let csv = export_error_line(false, &error);
+        assert!(csv.starts_with("# export failed: "));
+        assert!(csv.contains("connection reset"));
+        assert!(csv.ends_with('\n'));
+
+        let jsonl = export_error_line(true, &error);
+        let value: serde_json::Value = serde_json::from_str(jsonl.trim_end()).unwrap();
+        assert!(value["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("export failed: "));
+    }
+
+    #[tokio::test]
+    #[ignore] // 会话校验需要 Redis
+    async fn test_route_permissions() {
+        let settings = Settings::new().expect("Failed to create test settings");
+        let redis = Arc::new(RedisPool::new(&settings).expect("Failed to create Redis pool"));
+        let admin_service = Arc::new(AdminService::new(
+            redis.clone(),
+            "test_secret_key_at_least_32_chars_long".to_string(),
+        ));
+        let api_key_service = Arc::new(ApiKeyService::new((*redis).clone(), settings.clone()));
+        let app = create_admin_routes(admin_service.clone(), api_key_service, (*redis).clone());
+
+        let mut tokens = std::collections::HashMap::new();
+        for role in ["support", "user", "operator"] {
+            let login = admin_service.create_session("tester", role).await.unwrap();
+            tokens.insert(role, login.token);
+        }
+        let get = |uri: &str, role: &str| {
+            Request::builder()
+                .uri(uri)
+                .header("authorization", format!("Bearer {}", tokens[role]))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        // 内置角色无需查询角色存储
+        let response = app
+            .clone()
+            .oneshot(get("/supported-clients", "support"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // support 只有 keys:read，不能查看费用统计
+        let response = app
+            .clone()
+            .oneshot(get("/stats/overview", "support"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        // 普通用户没有任何管理权限
+        let response = app
+            .clone()
+            .oneshot(get("/supported-clients", "user"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = app
+            .clone()
+            .oneshot(get("/roles", "operator"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        // 审计日志需要 audit:read
+        let response = app.oneshot(get("/audit-logs", "operator")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    #[ignore] // 需要 Redis
+    async fn test_roles_cannot_grant_missing_permissions() {
+        let settings = Settings::new().expect("Failed to create test settings");
+        let redis = Arc::new(RedisPool::new(&settings).expect("Failed to create Redis pool"));
+        let admin_service = Arc::new(AdminService::new(
+            redis.clone(),
+            "test_secret_key_at_least_32_chars_long".to_string(),
+        ));
+        let api_key_service = Arc::new(ApiKeyService::new((*redis).clone(), settings.clone()));
+        let app = create_admin_routes(admin_service.clone(), api_key_service, (*redis).clone());
+
+        // 只有 users:manage 的自定义角色
+        let role_service = RoleService::new(redis.clone());
+        let suffix = &uuid::Uuid::new_v4().simple().to_string()[..8];
+        let manager_role = format!("manager-{}", suffix);
+        role_service
+            .create_role(&manager_role, None, vec![Permission::UsersManage], "tester")
+            .await
+            .unwrap();
+        let login = admin_service
+            .create_session("role-tester", &manager_role)
+            .await
+            .unwrap();
+        let send = |method: &str, uri: &str, body: serde_json::Value| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("authorization", format!("Bearer {}", login.token))
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        let escalated = format!("escalated-{}", suffix);
+        let response = app
+            .clone()
+            .oneshot(send(
+                "POST",
+                "/roles",
+                json!({ "name": escalated, "permissions": ["users:manage", "settings:write"] }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = app
+            .clone()
+            .oneshot(send(
+                "PUT",
+                &format!("/roles/{}", manager_role),
+                json!({ "permissions": ["users:manage", "audit:read"] }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = app
+            .clone()
+            .oneshot(send(
+                "PUT",
+                "/users/any-user/role",
+                json!({ "role": "admin" }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        // 不超出自身权限的角色可以创建
+        let allowed = format!("allowed-{}", suffix);
+        let response = app
+            .oneshot(send(
+                "POST",
+                "/roles",
+                json!({ "name": allowed, "permissions": ["users:manage"] }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        for name in [&manager_role, &allowed] {
+            role_service.delete_role(name, "tester").await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_token_without_session_rejected() {
+        let settings = Settings::new().expect("Failed to create test settings");
+        let redis = Arc::new(RedisPool::new(&settings).expect("Failed to create Redis pool"));
+        let admin_service = Arc::new(AdminService::new(
+            redis.clone(),
+            "test_secret_key_at_least_32_chars_long".to_string(),
+        ));
+        let api_key_service = Arc::new(ApiKeyService::new((*redis).clone(), settings.clone()));
+        let app = create_admin_routes(admin_service.clone(), api_key_service, (*redis).clone());
+
+        // 旧版无会话的令牌不再被接受
+        let token = admin_service
+            .generate_token("tester", "admin", "", 0)
+            .unwrap();
+        let request = Request::builder()
+            .uri("/supported-clients")
+            .header("authorization", format!("Bearer {}", token))
+            .body(Body::empty())
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    #[ignore] // 需要 Redis
+    async fn test_logout_revokes_session() {
+        let settings = Settings::new().expect("Failed to create test settings");
+        let redis = Arc::new(RedisPool::new(&settings).expect("Failed to create Redis pool"));
+        let admin_service = Arc::new(AdminService::new(
+            redis.clone(),
+            "test_secret_key_at_least_32_chars_long".to_string(),
+        ));
+        let api_key_service = Arc::new(ApiKeyService::new((*redis).clone(), settings.clone()));
+        let app = create_admin_routes(admin_service.clone(), api_key_service, (*redis).clone());
+
+        let login = admin_service
+            .create_session("logout-tester", "support")
+            .await
+            .unwrap();
+        let request = |method: &str, uri: &str| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("authorization", format!("Bearer {}", login.token))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(request("GET", "/supported-clients"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .clone()
+            .oneshot(request("POST", "/auth/logout"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .oneshot(request("GET", "/supported-clients"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // 会话删除后刷新令牌同样失效
+        assert!(admin_service
+            .refresh_session(&login.refresh_token)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    #[ignore] // 需要 Redis
+    async fn test_admin_api_token_scopes() {
+        let settings = Settings::new().expect("Failed to create test settings");
+        let redis = Arc::new(RedisPool::new(&settings).expect("Failed to create Redis pool"));
+        let admin_service = Arc::new(AdminService::new(
+            redis.clone(),
+            "test_secret_key_at_least_32_chars_long".to_string(),
+        ));
+        let api_key_service = Arc::new(ApiKeyService::new((*redis).clone(), settings.clone()));
+        let app = create_admin_routes(admin_service.clone(), api_key_service, (*redis).clone());
+
+        let tokens = admin_service.api_token_service();
+        let (token, plaintext) = tokens
+            .create_token(
+                CreateAdminApiTokenRequest {
+                    name: "ci".to_string(),
+                    description: None,
+                    scopes: vec![Permission::KeysRead],
+                    expires_at: None,
+                    ip_allowlist: Vec::new(),
+                },
+                &Permission::ALL,
+                "admin",
+            )
+            .await
+            .unwrap();
+        let request = |uri: &str| {
+            Request::builder()
+                .uri(uri)
+                .header("authorization", format!("Bearer {}", plaintext))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(request("/api-keys")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // 超出 scopes 的分组被拒绝
+        let response = app.clone().oneshot(request("/audit-logs")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let used = tokens.get_token(&token.id).await.unwrap().unwrap();
+        assert!(used.last_used_at.is_some());
+
+        tokens.revoke_token(&token.id, "admin").await.unwrap();
+        let response = app.clone().oneshot(request("/api-keys")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(tokens.get_token(&token.id).await.unwrap().is_none());
+
+        // 创建者的令牌版本变化 (禁用、降级、重置密码) 后令牌失效
+        let (_, plaintext) = tokens
+            .create_token(
+                CreateAdminApiTokenRequest {
+                    name: "ci-2".to_string(),
+                    description: None,
+                    scopes: vec![Permission::KeysRead],
+                    expires_at: None,
+                    ip_allowlist: Vec::new(),
+                },
+                &Permission::ALL,
+                "admin",
+            )
+            .await
+            .unwrap();
+        assert!(tokens.authenticate(&plaintext, "127.0.0.1").await.is_ok());
+        admin_service.bump_token_version("admin").await.unwrap();
+        assert!(tokens.authenticate(&plaintext, "127.0.0.1").await.is_err());
+    }
 }
@@ -31,17 +31,18 @@ use tracing::{info, warn};
 
 use crate::config::Settings;
 use crate::middleware::auth::AuthState;
-use crate::models::{ApiKey, ApiKeyPermissions, UsageRecord};
+use crate::models::{ApiKey, ApiKeyPermissions};
 use crate::redis::RedisPool;
 use crate::services::{
     account::ClaudeAccountService,
     account_scheduler::AccountScheduler,
     api_key::ApiKeyService,
     bedrock_relay::BedrockRelayService,
-    claude_relay::{ClaudeRelayService, ClaudeRequest},
+    claude_relay::{ClaudeRelayService, ClaudeRequest, Usage},
     pricing_service::PricingService,
-    relay_trait::{RelayRequest, RelayService},
+    relay_trait::{RelayRequest, RelayService, UsageStats},
     unified_claude_scheduler::{SchedulerAccountVariant, UnifiedClaudeScheduler},
+    usage_settlement::UsageSettlement,
 };
 use crate::utils::error::{AppError, Result};
 use crate::utils::session_helper;
@@ -87,6 +88,11 @@ pub fn create_router(state: ApiState) -> Router {
             state.api_key_service.clone(),
             crate::middleware::auth::authenticate_api_key,
         ))
+        // 请求指标 (包括认证失败的请求)
+        .layer(middleware::from_fn_with_state(
+            "claude",
+            crate::middleware::metrics::track_relay_metrics,
+        ))
         .with_state(state)
 }
 
@@ -159,7 +165,52 @@ async fn handle_messages(
     let model = request.model.clone();
     let stream = request.stream.unwrap_or(false);
 
-    // 5. 使用统一调度器选择账户
+    // 5. 请求前成本限制检查 (输入 tokens + max_tokens 预估，预占额度直到实际用量结算)
+    let estimated_cost = state
+        .pricing_service
+        .estimate_cost(
+            &model,
+            estimate_tokens(&request) as i64,
+            request.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS) as i64,
+        )
+        .await;
+    let settlement = UsageSettlement::reserve(
+        state.api_key_service.clone(),
+        state.pricing_service.clone(),
+        &api_key,
+        &model,
+        estimated_cost,
+    )
+    .await?;
+
+    // 转发失败时记录错误并释放预占 (成功时在记录实际用量后释放)
+    settlement
+        .release_on_error(
+            relay_messages(
+                &state,
+                &api_key,
+                request,
+                session_hash,
+                stream,
+                settlement.clone(),
+            )
+            .await,
+        )
+        .await
+}
+
+/// 调度账户并转发 messages 请求
+async fn relay_messages(
+    state: &ApiState,
+    api_key: &ApiKey,
+    request: ClaudeRequest,
+    session_hash: Option<String>,
+    stream: bool,
+    settlement: Arc<UsageSettlement>,
+) -> Result<Response> {
+    let model = settlement.model().to_string();
+
+    // 6. 使用统一调度器选择账户
     // TODO: 需要在 UnifiedClaudeScheduler 中添加 API Key 专属账户绑定支持
     // Node.js 版本: selectAccountForApiKey(apiKeyData, sessionHash, requestedModel)
     // 当前简化版本: select_account(sessionHash, requestedModel)
@@ -167,6 +218,8 @@ async fn handle_messages(
         .unified_claude_scheduler
         .select_account(session_hash.as_deref(), Some(&model))
         .await?;
+    settlement.bind_account(selected.account.id.to_string());
+    settlement.set_platform(selected.account_variant.as_str());
 
     info!(
         "🎯 Selected account: {} (type: {}) for API key: {}",
@@ -175,8 +228,8 @@ async fn handle_messages(
         api_key.name
     );
 
-    // 6. 根据账户类型和流式标志选择转发服务
-    // 6.1 流式请求处理
+    // 7. 根据账户类型和流式标志选择转发服务
+    // 7.1 流式请求处理
     if stream {
         info!("🌊 Processing streaming request");
         return match selected.account_variant {
@@ -194,29 +247,36 @@ async fn handle_messages(
 
                 // 将 StreamChunk 转换为 SSE 事件格式
                 use crate::services::claude_relay::StreamChunk;
-                let sse_stream = stream.map(|chunk_result| {
-                    match chunk_result {
-                        Ok(chunk) => match chunk {
-                            StreamChunk::Data(data) => {
-                                // 原始 SSE 数据，直接传递
-                                Ok::<_, std::convert::Infallible>(data)
-                            }
-                            StreamChunk::Usage(_usage) => {
-                                // Usage 已经在 Data 中发送，这里跳过
-                                // (ClaudeRelayService 已经在流的最后发送了 message_stop 事件)
-                                Ok(bytes::Bytes::new())
+                let usage_settlement = settlement.clone();
+                let sse_stream = stream
+                    .then(move |chunk_result| {
+                        let settlement = usage_settlement.clone();
+                        async move {
+                            match chunk_result {
+                                Ok(chunk) => match chunk {
+                                    StreamChunk::Data(data) => {
+                                        // 原始 SSE 数据，直接传递
+                                        Ok::<_, std::convert::Infallible>(data)
+                                    }
+                                    StreamChunk::Usage(usage) => {
+                                        // Usage 已经在 Data 中发送，这里只记录实际用量
+                                        // (ClaudeRelayService 已经在流的最后发送了 message_stop 事件)
+                                        settlement.settle(Some(&usage)).await;
+                                        Ok(bytes::Bytes::new())
+                                    }
+                                },
+                                Err(e) => {
+                                    // 发送错误事件
+                                    Ok(format!(
+                                        "event: error\ndata: {}\n\n",
+                                        serde_json::json!({"error": e.to_string()})
+                                    )
+                                    .into())
+                                }
                             }
-                        },
-                        Err(e) => {
-                            // 发送错误事件
-                            Ok(format!(
-                                "event: error\ndata: {}\n\n",
-                                serde_json::json!({"error": e.to_string()})
-                            )
-                            .into())
                         }
-                    }
-                });
+                    })
+                    .chain(settlement.release_on_stream_end());
 
                 // 创建 SSE 响应
                 Ok(Response::builder()
@@ -249,36 +309,43 @@ async fn handle_messages(
 
                 // 将 GenericStreamChunk 转换为 SSE 事件格式
                 use crate::services::relay_trait::GenericStreamChunk;
-                let sse_stream = stream.map(|chunk_result| {
-                    match chunk_result {
-                        Ok(chunk) => match chunk {
-                            GenericStreamChunk::Data(data) => {
-                                // 原始 SSE 数据，直接传递
-                                Ok::<_, std::convert::Infallible>(data)
+                let usage_settlement = settlement.clone();
+                let sse_stream = stream
+                    .then(move |chunk_result| {
+                        let settlement = usage_settlement.clone();
+                        async move {
+                            match chunk_result {
+                                Ok(chunk) => match chunk {
+                                    GenericStreamChunk::Data(data) => {
+                                        // 原始 SSE 数据，直接传递
+                                        Ok::<_, std::convert::Infallible>(data)
+                                    }
+                                    GenericStreamChunk::Usage(stats) => {
+                                        // Usage 已经在 Data 中发送，这里只记录实际用量
+                                        settlement.settle(Some(&stats)).await;
+                                        Ok(bytes::Bytes::new())
+                                    }
+                                    GenericStreamChunk::Error(err) => {
+                                        // 错误事件
+                                        Ok(format!(
+                                            "event: error\ndata: {}\n\n",
+                                            serde_json::json!({"error": err})
+                                        )
+                                        .into())
+                                    }
+                                },
+                                Err(e) => {
+                                    // 发送错误事件
+                                    Ok(format!(
+                                        "event: error\ndata: {}\n\n",
+                                        serde_json::json!({"error": e.to_string()})
+                                    )
+                                    .into())
+                                }
                             }
-                            GenericStreamChunk::Usage(_usage) => {
-                                // Usage 已经在 Data 中发送
-                                Ok(bytes::Bytes::new())
-                            }
-                            GenericStreamChunk::Error(err) => {
-                                // 错误事件
-                                Ok(format!(
-                                    "event: error\ndata: {}\n\n",
-                                    serde_json::json!({"error": err})
-                                )
-                                .into())
-                            }
-                        },
-                        Err(e) => {
-                            // 发送错误事件
-                            Ok(format!(
-                                "event: error\ndata: {}\n\n",
-                                serde_json::json!({"error": e.to_string()})
-                            )
-                            .into())
                         }
-                    }
-                });
+                    })
+                    .chain(settlement.release_on_stream_end());
 
                 // 创建 SSE 响应
                 Ok(Response::builder()
@@ -293,7 +360,7 @@ async fn handle_messages(
         };
     }
 
-    // 6.2 非流式请求处理
+    // 7.2 非流式请求处理
     let relay_response = match selected.account_variant {
         SchedulerAccountVariant::ClaudeOfficial => {
             info!("🔄 Using ClaudeRelayService for claude-official account");
@@ -322,19 +389,14 @@ async fn handle_messages(
             let generic_response = state.bedrock_service.relay_request(relay_request).await?;
 
             // 将 GenericRelayResponse 转换为 RelayResponse
-            use crate::services::claude_relay::{RelayResponse, Usage};
+            use crate::services::claude_relay::RelayResponse;
             RelayResponse {
                 status_code: generic_response.status_code,
                 headers: generic_response.headers,
                 body: generic_response.body,
                 account_id: generic_response.account_id,
                 account_type: generic_response.account_type,
-                usage: generic_response.usage.map(|stats| Usage {
-                    input_tokens: stats.input_tokens,
-                    output_tokens: stats.output_tokens,
-                    cache_creation_input_tokens: stats.cache_creation_tokens,
-                    cache_read_input_tokens: stats.cache_read_tokens,
-                }),
+                usage: generic_response.usage.map(usage_from_stats),
             }
         }
         SchedulerAccountVariant::Ccr => {
@@ -347,48 +409,11 @@ async fn handle_messages(
         }
     };
 
-    // 6. 记录使用量并计算成本
-    if let Some(ref usage) = relay_response.usage {
-        // 将 Claude Usage 转换为 PricingService Usage
-        let cache_creation = usage.cache_creation_input_tokens.map(|tokens| {
-            // 简化版本: 假设所有缓存创建 tokens 都是 1h ephemeral
-            crate::services::pricing_service::CacheCreation {
-                ephemeral_5m_input_tokens: 0,
-                ephemeral_1h_input_tokens: tokens as i64,
-            }
-        });
-
-        let pricing_usage = crate::services::pricing_service::Usage {
-            input_tokens: usage.input_tokens as i64,
-            output_tokens: usage.output_tokens as i64,
-            cache_creation_input_tokens: usage.cache_creation_input_tokens.unwrap_or(0) as i64,
-            cache_read_input_tokens: usage.cache_read_input_tokens.unwrap_or(0) as i64,
-            cache_creation,
-        };
-
-        // 计算实际成本
-        let cost_result = state
-            .pricing_service
-            .calculate_cost(&pricing_usage, &model)
-            .await;
-
-        let cost = cost_result.total_cost;
-
-        state
-            .api_key_service
-            .record_usage(UsageRecord::new(
-                api_key.id.clone(),
-                model.clone(),
-                usage.input_tokens as i64,
-                usage.output_tokens as i64,
-                usage.cache_creation_input_tokens.unwrap_or(0) as i64,
-                usage.cache_read_input_tokens.unwrap_or(0) as i64,
-                cost,
-            ))
-            .await?;
-    }
+    // 8. 记录使用量并计算成本，释放成本预占
+    settlement.bind_account(relay_response.account_id.clone());
+    settlement.settle(relay_response.usage.as_ref()).await;
 
-    // 7. 返回响应
+    // 9. 返回响应
     Ok((
         StatusCode::from_u16(relay_response.status_code).unwrap(),
         relay_response.body,
@@ -461,6 +486,10 @@ async fn handle_key_info(
 
     // 获取使用统计
     let stats = state.api_key_service.get_usage_stats(&api_key.id).await?;
+    let queue_stats = state
+        .api_key_service
+        .get_concurrency_queue_stats(&api_key.id)
+        .await?;
 
     Ok(Json(json!({
         "id": api_key.id,
@@ -472,6 +501,15 @@ async fn handle_key_info(
             "output_tokens": stats.total_output_tokens,
             "cache_creation_tokens": stats.total_cache_creation_tokens,
             "cache_read_tokens": stats.total_cache_read_tokens,
+        },
+        "concurrency": {
+            "limit": api_key.concurrency_limit,
+            "queue_enabled": api_key.concurrency_queue_enabled,
+            "active_requests": queue_stats.active_requests,
+            "queue_depth": queue_stats.queue_depth,
+            "avg_wait_ms": queue_stats.avg_wait_ms,
+            "max_wait_ms": queue_stats.max_wait_ms,
+            "total_timeouts": queue_stats.total_timeouts,
         }
     })))
 }
@@ -581,6 +619,20 @@ fn estimate_tokens(request: &ClaudeRequest) -> u32 {
     (total_chars / 4) as u32
 }
 
+/// 请求未指定 max_tokens 时用于成本预估的输出 tokens 上限
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// 将通用使用统计转换为 Claude Usage
+fn usage_from_stats(stats: UsageStats) -> Usage {
+    Usage {
+        input_tokens: stats.input_tokens,
+        output_tokens: stats.output_tokens,
+        cache_creation_input_tokens: stats.cache_creation_tokens,
+        cache_read_input_tokens: stats.cache_read_tokens,
+        cache_creation: None,
+    }
+}
+
 /// 使用统计查询参数
 #[derive(Debug, Deserialize)]
 struct UsageQuery {
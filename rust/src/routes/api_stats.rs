@@ -0,0 +1,375 @@
+// API Key 自助统计路由
+//
+// 供 Key 持有者在无需管理员权限的情况下查询自己的使用情况：
+// - POST /api/get-key-id - 通过密钥获取 Key ID
+// - POST /api/user-stats - 使用量、费用限制、速率限制、并发和过期信息
+// - POST /api/user-model-stats - 按模型的日/月使用统计
+// - POST /api/redeem - 兑换码充值到预付费钱包
+//
+// 注意：这些路由会被 nest 到 /apiStats 前缀下，且按客户端 IP 限流
+
+use axum::{
+    extract::{ConnectInfo, State},
+    http::HeaderMap,
+    routing::post,
+    Json, Router,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::models::api_key::{ModelUsage, UsagePeriod};
+use crate::models::ApiKey;
+use crate::redis::RedisPool;
+use crate::services::api_key::ApiKeyService;
+use crate::utils::client_ip::extract_client_ip;
+use crate::utils::error::{AppError, Result};
+
+/// 每个 IP 在限流窗口内允许的查询次数
+const STATS_RATE_LIMIT_REQUESTS: i64 = 30;
+
+/// 限流窗口 (秒)
+const STATS_RATE_LIMIT_WINDOW_SECONDS: i64 = 60;
+
+/// 自助统计路由器状态
+#[derive(Clone)]
+pub struct ApiStatsState {
+    pub redis: Arc<RedisPool>,
+    pub api_key_service: Arc<ApiKeyService>,
+}
+
+/// 自助统计请求
+#[derive(Debug, Deserialize)]
+pub struct UserStatsRequest {
+    #[serde(rename = "apiKey")]
+    pub api_key: String,
+    /// 可选的 Key ID，提供时必须与密钥匹配
+    #[serde(rename = "apiId")]
+    pub api_id: Option<String>,
+    /// 按模型统计的周期 (仅 user-model-stats 使用)
+    #[serde(default)]
+    pub period: UsagePeriod,
+    /// 兑换码 (仅 redeem 使用)
+    #[serde(default)]
+    pub code: Option<String>,
+}
+
+/// 创建自助统计路由 (无需认证)
+pub fn create_router(state: ApiStatsState) -> Router {
+    Router::new()
+        .route("/api/get-key-id", post(handle_get_key_id))
+        .route("/api/user-stats", post(handle_user_stats))
+        .route("/api/user-model-stats", post(handle_user_model_stats))
+        .route("/api/redeem", post(handle_redeem))
+        .with_state(state)
+}
+
+/// POST /apiStats/api/get-key-id - 通过密钥获取 Key ID
+async fn handle_get_key_id(
+    State(state): State<ApiStatsState>,
+    headers: HeaderMap,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    Json(request): Json<UserStatsRequest>,
+) -> Result<Json<JsonValue>> {
+    let api_key = authorize_stats_request(&state, &headers, connect_info, &request).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": { "id": api_key.id }
+    })))
+}
+
+/// POST /apiStats/api/user-stats - Key 使用量、限制与状态
+///
+/// 对 Key 持有者展示的费用均为计费成本 (上游成本 × 计费倍率)
+async fn handle_user_stats(
+    State(state): State<ApiStatsState>,
+    headers: HeaderMap,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    Json(request): Json<UserStatsRequest>,
+) -> Result<Json<JsonValue>> {
+    let api_key = authorize_stats_request(&state, &headers, connect_info, &request).await?;
+    info!("📊 Self-service stats requested for key: {}", api_key.name);
+
+    let service = &state.api_key_service;
+    let stats = service.get_usage_stats(&api_key.id).await?;
+    let daily_usage = service
+        .get_period_model_usage(&api_key.id, UsagePeriod::Daily, None)
+        .await?;
+    let monthly_usage = service
+        .get_period_model_usage(&api_key.id, UsagePeriod::Monthly, None)
+        .await?;
+    let rate_limit = service.get_rate_limit_state(&api_key).await?;
+    let queue_stats = service.get_concurrency_queue_stats(&api_key.id).await?;
+    let wallet = match service.wallets().resolve_wallet(&api_key).await? {
+        Some(owner) => service.wallets().get_wallet(&owner).await?,
+        None => None,
+    };
+
+    let is_expired = api_key
+        .expires_at
+        .map(|expires_at| Utc::now() > expires_at)
+        .unwrap_or(false);
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "id": api_key.id,
+            "name": api_key.name,
+            "description": api_key.description,
+            "isActive": api_key.is_active,
+            "permissions": api_key.permissions,
+            "createdAt": api_key.created_at,
+            "lastUsedAt": stats.last_used_at.or(api_key.last_used_at),
+            "expiration": {
+                "mode": api_key.expiration_mode,
+                "expiresAt": api_key.expires_at,
+                "activatedAt": api_key.activated_at,
+                "activationDays": api_key.activation_days,
+                "activationUnit": api_key.activation_unit,
+                "isExpired": is_expired,
+            },
+            "usage": {
+                "total": {
+                    "requests": stats.total_requests,
+                    "inputTokens": stats.total_input_tokens,
+                    "outputTokens": stats.total_output_tokens,
+                    "cacheCreateTokens": stats.total_cache_creation_tokens,
+                    "cacheReadTokens": stats.total_cache_read_tokens,
+                    "cost": stats.total_billed_cost,
+                },
+                "daily": summarize_model_usage(&daily_usage),
+                "monthly": summarize_model_usage(&monthly_usage),
+            },
+            "limits": {
+                "tokenLimit": api_key.token_limit,
+                "dailyCostLimit": api_key.daily_cost_limit,
+                "currentDailyCost": stats.daily_billed_cost,
+                "totalCostLimit": api_key.total_cost_limit,
+                "currentTotalCost": stats.total_billed_cost,
+                "weeklyOpusCostLimit": api_key.weekly_opus_cost_limit,
+                "currentWeeklyOpusCost": stats.weekly_opus_billed_cost,
+            },
+            "rateLimit": rate_limit.map(|state| json!({
+                "windowSeconds": state.window_seconds,
+                "maxRequests": state.max_requests,
+                "currentRequests": state.current_requests,
+                "remainingRequests": (state.max_requests - state.current_requests).max(0),
+                "windowStart": state.window_start,
+                "resetAt": state.reset_at,
+                "costLimit": api_key.rate_limit_cost,
+            })),
+            "concurrency": {
+                "limit": api_key.concurrency_limit,
+                "activeRequests": queue_stats.active_requests,
+                "queueEnabled": api_key.concurrency_queue_enabled,
+                "queueDepth": queue_stats.queue_depth,
+            },
+            "wallet": wallet,
+            "restrictions": {
+                "enableModelRestriction": api_key.enable_model_restriction,
+                "restrictedModels": api_key.restricted_models,
+                "enableClientRestriction": api_key.enable_client_restriction,
+                "allowedClients": api_key.allowed_clients,
+            },
+        }
+    })))
+}
+
+/// POST /apiStats/api/user-model-stats - 按模型的日/月使用统计
+async fn handle_user_model_stats(
+    State(state): State<ApiStatsState>,
+    headers: HeaderMap,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    Json(request): Json<UserStatsRequest>,
+) -> Result<Json<JsonValue>> {
+    let api_key = authorize_stats_request(&state, &headers, connect_info, &request).await?;
+
+    let usage = state
+        .api_key_service
+        .get_period_model_usage(&api_key.id, request.period, None)
+        .await?;
+
+    let mut models: Vec<JsonValue> = usage
+        .iter()
+        .map(|(model, usage)| {
+            json!({
+                "model": model,
+                "requests": usage.requests,
+                "inputTokens": usage.input_tokens,
+                "outputTokens": usage.output_tokens,
+                "cacheCreateTokens": usage.cache_creation_tokens,
+                "cacheReadTokens": usage.cache_read_tokens,
+                "cost": usage.billed_cost,
+            })
+        })
+        .collect();
+    models.sort_by(|a, b| {
+        let cost = |v: &JsonValue| v["cost"].as_f64().unwrap_or(0.0);
+        cost(b).total_cmp(&cost(a))
+    });
+
+    Ok(Json(json!({
+        "success": true,
+        "period": request.period,
+        "bucket": request
+            .period
+            .bucket(Utc::now(), state.api_key_service.system_timezone()),
+        "data": models
+    })))
+}
+
+/// POST /apiStats/api/redeem - 兑换码充值到预付费钱包
+///
+/// 与查询接口共用按 IP 的限流，防止枚举兑换码
+async fn handle_redeem(
+    State(state): State<ApiStatsState>,
+    headers: HeaderMap,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    Json(request): Json<UserStatsRequest>,
+) -> Result<Json<JsonValue>> {
+    let api_key = authorize_stats_request(&state, &headers, connect_info, &request).await?;
+    let code = request
+        .code
+        .as_deref()
+        .ok_or_else(|| AppError::BadRequest("code is required".to_string()))?;
+
+    let entry = state
+        .api_key_service
+        .wallets()
+        .redeem(&api_key, code)
+        .await
+        .inspect_err(|e| warn!("🎟️ Redemption failed for key {}: {}", api_key.name, e))?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "amount": entry.amount,
+            "balance": entry.balance_after,
+            "redeemedAt": entry.created_at,
+        }
+    })))
+}
+
+/// 限流并校验查询请求中的密钥
+async fn authorize_stats_request(
+    state: &ApiStatsState,
+    headers: &HeaderMap,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    request: &UserStatsRequest,
+) -> Result<ApiKey> {
+    let client_ip = extract_client_ip(headers, connect_info.map(|ConnectInfo(addr)| addr));
+    check_stats_rate_limit(&state.redis, &client_ip).await?;
+
+    if request.api_key.trim().is_empty() {
+        return Err(AppError::BadRequest("apiKey is required".to_string()));
+    }
+
+    let api_key = state
+        .api_key_service
+        .find_key_by_secret(request.api_key.trim())
+        .await
+        .inspect_err(|_| {
+            warn!(
+                "🔒 Invalid self-service stats lookup from IP: {}",
+                client_ip
+            )
+        })?;
+
+    if let Some(api_id) = &request.api_id {
+        if api_id != &api_key.id {
+            warn!(
+                "🔒 Self-service stats key ID mismatch from IP: {}",
+                client_ip
+            );
+            return Err(AppError::Unauthorized("Invalid API Key".to_string()));
+        }
+    }
+
+    Ok(api_key)
+}
+
+/// 按客户端 IP 的固定窗口限流
+async fn check_stats_rate_limit(redis: &RedisPool, client_ip: &str) -> Result<()> {
+    let key = format!("api_stats_rate_limit:{}", client_ip);
+    let count = redis.incr(&key).await?;
+    if count == 1 {
+        redis.expire(&key, STATS_RATE_LIMIT_WINDOW_SECONDS).await?;
+    }
+
+    if count > STATS_RATE_LIMIT_REQUESTS {
+        return Err(AppError::RateLimitExceeded(format!(
+            "Too many stats requests, please retry in {} seconds",
+            STATS_RATE_LIMIT_WINDOW_SECONDS
+        )));
+    }
+
+    Ok(())
+}
+
+/// 汇总各模型的使用量
+fn summarize_model_usage(usage: &HashMap<String, ModelUsage>) -> JsonValue {
+    let total = usage.values().fold(ModelUsage::default(), |mut acc, u| {
+        acc.requests += u.requests;
+        acc.input_tokens += u.input_tokens;
+        acc.output_tokens += u.output_tokens;
+        acc.cache_creation_tokens += u.cache_creation_tokens;
+        acc.cache_read_tokens += u.cache_read_tokens;
+        acc.billed_cost += u.billed_cost;
+        acc
+    });
+
+    json!({
+        "requests": total.requests,
+        "inputTokens": total.input_tokens,
+        "outputTokens": total.output_tokens,
+        "cacheCreateTokens": total.cache_creation_tokens,
+        "cacheReadTokens": total.cache_read_tokens,
+        "cost": total.billed_cost,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize_model_usage() {
+        let mut usage = HashMap::new();
+        usage.insert(
+            "claude-3-5-sonnet".to_string(),
+            ModelUsage {
+                requests: 2,
+                input_tokens: 100,
+                output_tokens: 50,
+                cost: 0.25,
+                billed_cost: 0.5,
+                ..Default::default()
+            },
+        );
+        usage.insert(
+            "claude-3-opus".to_string(),
+            ModelUsage {
+                requests: 1,
+                input_tokens: 10,
+                cache_read_tokens: 5,
+                cost: 1.0,
+                billed_cost: 1.25,
+                ..Default::default()
+            },
+        );
+
+        let summary = summarize_model_usage(&usage);
+        assert_eq!(summary["requests"], 3);
+        assert_eq!(summary["inputTokens"], 110);
+        assert_eq!(summary["cacheReadTokens"], 5);
+        assert_eq!(summary["cost"], 1.75);
+
+        let empty = summarize_model_usage(&HashMap::new());
+        assert_eq!(empty["requests"], 0);
+    }
+}
@@ -22,12 +22,17 @@ use tracing::{info, warn};
 
 use crate::config::Settings;
 use crate::middleware::auth::AuthState;
-use crate::models::{ApiKey, ApiKeyPermissions, UsageRecord};
+use crate::models::{ApiKey, ApiKeyPermissions};
 use crate::redis::RedisPool;
 use crate::services::{
-    account::ClaudeAccountService, account_scheduler::AccountScheduler, api_key::ApiKeyService,
-    gemini_relay::GeminiRelayService, pricing_service::PricingService, relay_trait::RelayService,
+    account::ClaudeAccountService,
+    account_scheduler::AccountScheduler,
+    api_key::ApiKeyService,
+    gemini_relay::GeminiRelayService,
+    pricing_service::PricingService,
+    relay_trait::RelayService,
     unified_gemini_scheduler::UnifiedGeminiScheduler,
+    usage_settlement::UsageSettlement,
 };
 use crate::utils::error::{AppError, Result};
 use crate::utils::session_helper;
@@ -63,6 +68,11 @@ pub fn create_router(state: GeminiState) -> Router {
             state.api_key_service.clone(),
             crate::middleware::auth::authenticate_api_key,
         ))
+        // 请求指标 (包括认证失败的请求)
+        .layer(middleware::from_fn_with_state(
+            "gemini",
+            crate::middleware::metrics::track_relay_metrics,
+        ))
         .with_state(state)
 }
 
@@ -203,19 +213,27 @@ async fn handle_messages(
         session_hash.as_deref().unwrap_or("none")
     );
 
-    // 5. 使用统一调度器选择账户
+    // 5. 请求前成本限制检查
+    let settlement = reserve_request_cost(&state, &api_key, &model, &request).await?;
+
+    // 6. 使用统一调度器选择账户
     // TODO: 需要在 UnifiedGeminiScheduler 中添加 API Key 专属账户绑定支持
-    let selected = state
-        .unified_gemini_scheduler
-        .select_account(&api_key, session_hash.as_deref(), Some(&model))
+    let selected = settlement
+        .release_on_error(
+            state
+                .unified_gemini_scheduler
+                .select_account(&api_key, session_hash.as_deref(), Some(&model))
+                .await,
+        )
         .await?;
+    settlement.bind_account(selected.account_id.clone());
 
     info!(
         "🎯 Selected Gemini account: {} (id: {}) for API key: {}",
         selected.account.name, selected.account_id, api_key.name
     );
 
-    // 6. 创建 RelayRequest
+    // 7. 创建 RelayRequest
     use crate::services::relay_trait::RelayRequest;
     let relay_request = RelayRequest {
         model: model.clone(),
@@ -224,56 +242,22 @@ async fn handle_messages(
         stream,
     };
 
-    // 7. 调用转发服务
+    // 8. 调用转发服务
     if stream {
         // 流式响应 - TODO: 实现 SSE 流式传输
+        settlement.release().await;
         Err(AppError::InternalError("流式响应暂未实现".to_string()))
     } else {
         // 非流式响应
-        let relay_response = state.gemini_service.relay_request(relay_request).await?;
-
-        // 7. 记录使用量并计算成本
-        if let Some(ref usage) = relay_response.usage {
-            // 将 Gemini Usage 转换为 PricingService Usage
-            // Note: Gemini 使用 cache_creation_tokens 和 cache_read_tokens
-            let cache_creation = usage.cache_creation_tokens.map(|tokens| {
-                crate::services::pricing_service::CacheCreation {
-                    ephemeral_5m_input_tokens: 0,
-                    ephemeral_1h_input_tokens: tokens as i64,
-                }
-            });
-
-            let pricing_usage = crate::services::pricing_service::Usage {
-                input_tokens: usage.input_tokens as i64,
-                output_tokens: usage.output_tokens as i64,
-                cache_creation_input_tokens: usage.cache_creation_tokens.unwrap_or(0) as i64,
-                cache_read_input_tokens: usage.cache_read_tokens.unwrap_or(0) as i64,
-                cache_creation,
-            };
-
-            // 计算实际成本
-            let cost_result = state
-                .pricing_service
-                .calculate_cost(&pricing_usage, &model)
-                .await;
-
-            let cost = cost_result.total_cost;
+        let relay_response = settlement
+            .release_on_error(state.gemini_service.relay_request(relay_request).await)
+            .await?;
 
-            state
-                .api_key_service
-                .record_usage(UsageRecord::new(
-                    api_key.id.clone(),
-                    model.clone(),
-                    usage.input_tokens as i64,
-                    usage.output_tokens as i64,
-                    usage.cache_creation_tokens.unwrap_or(0) as i64,
-                    usage.cache_read_tokens.unwrap_or(0) as i64,
-                    cost,
-                ))
-                .await?;
-        }
+        // 9. 记录使用量并计算成本，释放成本预占
+        settlement.bind_account(relay_response.account_id.clone());
+        settlement.settle(relay_response.usage.as_ref()).await;
 
-        // 8. 返回响应
+        // 10. 返回响应
         Ok((
             StatusCode::from_u16(relay_response.status_code).unwrap(),
             relay_response.body,
@@ -439,12 +423,20 @@ async fn handle_generate_content_impl(
     // 生成会话 Hash
     let session_hash = generate_session_hash(&request);
 
+    // 请求前成本限制检查
+    let settlement = reserve_request_cost(&state, &api_key, &model, &request).await?;
+
     // 使用统一调度器选择账户
     // TODO: 需要在 UnifiedGeminiScheduler 中添加 API Key 专属账户绑定支持
-    let _selected = state
-        .unified_gemini_scheduler
-        .select_account(&api_key, session_hash.as_deref(), Some(&model))
+    let selected = settlement
+        .release_on_error(
+            state
+                .unified_gemini_scheduler
+                .select_account(&api_key, session_hash.as_deref(), Some(&model))
+                .await,
+        )
         .await?;
+    settlement.bind_account(selected.account_id.clone());
 
     // 创建 RelayRequest
     use crate::services::relay_trait::RelayRequest;
@@ -456,47 +448,13 @@ async fn handle_generate_content_impl(
     };
 
     // 调用转发服务
-    let relay_response = state.gemini_service.relay_request(relay_request).await?;
-
-    // 记录使用量并计算成本
-    if let Some(ref usage) = relay_response.usage {
-        // 将 Gemini Usage 转换为 PricingService Usage
-        let cache_creation = usage.cache_creation_tokens.map(|tokens| {
-            crate::services::pricing_service::CacheCreation {
-                ephemeral_5m_input_tokens: 0,
-                ephemeral_1h_input_tokens: tokens as i64,
-            }
-        });
-
-        let pricing_usage = crate::services::pricing_service::Usage {
-            input_tokens: usage.input_tokens as i64,
-            output_tokens: usage.output_tokens as i64,
-            cache_creation_input_tokens: usage.cache_creation_tokens.unwrap_or(0) as i64,
-            cache_read_input_tokens: usage.cache_read_tokens.unwrap_or(0) as i64,
-            cache_creation,
-        };
-
-        // 计算实际成本
-        let cost_result = state
-            .pricing_service
-            .calculate_cost(&pricing_usage, &model)
-            .await;
-
-        let cost = cost_result.total_cost;
-
-        state
-            .api_key_service
-            .record_usage(UsageRecord::new(
-                api_key.id.clone(),
-                model.clone(),
-                usage.input_tokens as i64,
-                usage.output_tokens as i64,
-                usage.cache_creation_tokens.unwrap_or(0) as i64,
-                usage.cache_read_tokens.unwrap_or(0) as i64,
-                cost,
-            ))
-            .await?;
-    }
+    let relay_response = settlement
+        .release_on_error(state.gemini_service.relay_request(relay_request).await)
+        .await?;
+
+    // 记录使用量并计算成本，释放成本预占
+    settlement.bind_account(relay_response.account_id.clone());
+    settlement.settle(relay_response.usage.as_ref()).await;
 
     // 返回响应
     Ok((
@@ -541,11 +499,19 @@ async fn handle_stream_generate_content_impl(
         session_hash.as_deref().unwrap_or("none")
     );
 
+    // 请求前成本限制检查
+    let settlement = reserve_request_cost(&state, &api_key, &model, &request).await?;
+
     // 使用统一调度器选择账户
-    let selected = state
-        .unified_gemini_scheduler
-        .select_account(&api_key, session_hash.as_deref(), Some(&model))
+    let selected = settlement
+        .release_on_error(
+            state
+                .unified_gemini_scheduler
+                .select_account(&api_key, session_hash.as_deref(), Some(&model))
+                .await,
+        )
         .await?;
+    settlement.bind_account(selected.account_id.clone());
 
     info!(
         "🎯 Selected Gemini account: {} for API key: {}",
@@ -565,9 +531,13 @@ async fn handle_stream_generate_content_impl(
     use futures::stream::StreamExt;
     use tokio_stream::wrappers::ReceiverStream;
 
-    let stream_rx = state
-        .gemini_service
-        .relay_request_stream(relay_request)
+    let stream_rx = settlement
+        .release_on_error(
+            state
+                .gemini_service
+                .relay_request_stream(relay_request)
+                .await,
+        )
         .await?;
 
     // 将 mpsc::Receiver 转换为 Stream
@@ -575,36 +545,44 @@ async fn handle_stream_generate_content_impl(
 
     // 将 GenericStreamChunk 转换为 SSE 事件格式
     use crate::services::relay_trait::GenericStreamChunk;
-    let sse_stream = stream.map(|chunk_result| {
-        match chunk_result {
-            Ok(chunk) => match chunk {
-                GenericStreamChunk::Data(data) => {
-                    // 原始 SSE 数据，直接传递
-                    Ok::<_, std::convert::Infallible>(data)
+    let usage_settlement = settlement.clone();
+    let sse_stream = stream
+        .then(move |chunk_result| {
+            let settlement = usage_settlement.clone();
+            async move {
+                match chunk_result {
+                    Ok(chunk) => match chunk {
+                        GenericStreamChunk::Data(data) => {
+                            // 原始 SSE 数据，直接传递
+                            Ok::<_, std::convert::Infallible>(data)
+                        }
+                        GenericStreamChunk::Usage(usage) => {
+                            // Usage 已经在 Data 中发送，这里只记录实际用量
+                            settlement.settle(Some(&usage)).await;
+                            Ok(bytes::Bytes::new())
+                        }
+                        GenericStreamChunk::Error(err) => {
+                            // 错误事件
+                            Ok(format!(
+                                "event: error\ndata: {}\n\n",
+                                serde_json::json!({"error": err})
+                            )
+                            .into())
+                        }
+                    },
+                    Err(e) => {
+                        // 发送错误事件
+                        Ok(format!(
+                            "event: error\ndata: {}\n\n",
+                            serde_json::json!({"error": e.to_string()})
+                        )
+                        .into())
+                    }
                 }
-                GenericStreamChunk::Usage(_usage) => {
-                    // Usage 已经在 Data 中发送
-                    Ok(bytes::Bytes::new())
-                }
-                GenericStreamChunk::Error(err) => {
-                    // 错误事件
-                    Ok(format!(
-                        "event: error\ndata: {}\n\n",
-                        serde_json::json!({"error": err})
-                    )
-                    .into())
-                }
-            },
-            Err(e) => {
-                // 发送错误事件
-                Ok(format!(
-                    "event: error\ndata: {}\n\n",
-                    serde_json::json!({"error": e.to_string()})
-                )
-                .into())
             }
-        }
-    });
+        })
+        // 上游未返回 usage 时在流结束时兜底释放预占
+        .chain(settlement.release_on_stream_end());
 
     // 创建 SSE 响应
     use axum::{body::Body, http::StatusCode};
@@ -632,3 +610,49 @@ async fn handle_stream_generate_content_impl(
 fn generate_session_hash(request: &JsonValue) -> Option<String> {
     session_helper::generate_session_hash(request)
 }
+
+/// 未指定 maxOutputTokens 时用于成本预估的输出 tokens 上限
+const DEFAULT_MAX_OUTPUT_TOKENS: i64 = 8192;
+
+/// 请求前成本限制检查
+///
+/// 按请求体长度估算输入 tokens (4 chars ≈ 1 token)，按 maxOutputTokens 估算输出上限，
+/// 预占成本直到实际用量结算
+async fn reserve_request_cost(
+    state: &GeminiState,
+    api_key: &ApiKey,
+    model: &str,
+    request: &JsonValue,
+) -> Result<Arc<UsageSettlement>> {
+    let input_tokens = (request.to_string().len() / 4) as i64;
+    let max_output_tokens = extract_max_output_tokens(request).unwrap_or(DEFAULT_MAX_OUTPUT_TOKENS);
+
+    let estimated_cost = state
+        .pricing_service
+        .estimate_cost(model, input_tokens, max_output_tokens)
+        .await;
+
+    let settlement = UsageSettlement::reserve(
+        state.api_key_service.clone(),
+        state.pricing_service.clone(),
+        api_key,
+        model,
+        estimated_cost,
+    )
+    .await?;
+    settlement.set_platform("gemini");
+
+    Ok(settlement)
+}
+
+/// 提取请求中的输出 tokens 上限
+///
+/// 支持 generationConfig.maxOutputTokens、v1internal 的 request.generationConfig.maxOutputTokens
+/// 以及 messages 格式的 max_tokens
+fn extract_max_output_tokens(request: &JsonValue) -> Option<i64> {
+    request
+        .pointer("/generationConfig/maxOutputTokens")
+        .or_else(|| request.pointer("/request/generationConfig/maxOutputTokens"))
+        .or_else(|| request.get("max_tokens"))
+        .and_then(|v| v.as_i64())
+}
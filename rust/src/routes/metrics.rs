@@ -0,0 +1,160 @@
+// Prometheus 指标路由
+//
+// - GET /metrics - Prometheus 文本格式的指标
+//
+// 配置了 metrics.token 时需要 `Authorization: Bearer <token>`
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap},
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+
+use crate::redis::RedisPool;
+use crate::utils::metrics;
+use crate::utils::{AppError, CryptoService, Result};
+
+/// Prometheus 文本格式的 Content-Type
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// 指标路由器状态
+#[derive(Clone)]
+pub struct MetricsState {
+    pub redis: RedisPool,
+    pub token: Option<String>,
+}
+
+/// 创建指标路由器
+pub fn create_router(state: MetricsState) -> Router {
+    Router::new()
+        .route("/metrics", get(handle_metrics))
+        .with_state(state)
+}
+
+/// GET /metrics - 输出请求、上游、用量、并发、调度等指标以及 Redis 连接池和解密缓存状态
+async fn handle_metrics(
+    State(state): State<MetricsState>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse> {
+    if let Some(expected) = state.token.as_deref() {
+        let provided = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim);
+        if provided != Some(expected) {
+            return Err(AppError::Unauthorized(
+                "Invalid or missing metrics token".to_string(),
+            ));
+        }
+    }
+
+    let mut body = metrics::render();
+
+    let pool = state.redis.status();
+    metrics::write_gauge(
+        &mut body,
+        "relay_redis_pool_max_size",
+        "Maximum number of Redis pool connections",
+        pool.max_size as f64,
+    );
+    metrics::write_gauge(
+        &mut body,
+        "relay_redis_pool_size",
+        "Redis pool connections currently open",
+        pool.size as f64,
+    );
+    metrics::write_gauge(
+        &mut body,
+        "relay_redis_pool_available",
+        "Idle Redis pool connections",
+        pool.available as f64,
+    );
+    metrics::write_gauge(
+        &mut body,
+        "relay_redis_pool_waiting",
+        "Tasks waiting for a Redis pool connection",
+        pool.waiting as f64,
+    );
+
+    let (size, hits, misses, hit_rate) = CryptoService::cache_stats()?;
+    metrics::write_gauge(
+        &mut body,
+        "relay_decrypt_cache_size",
+        "Entries in the decrypt cache",
+        size as f64,
+    );
+    metrics::write_counter(
+        &mut body,
+        "relay_decrypt_cache_hits_total",
+        "Decrypt cache hits",
+        hits as f64,
+    );
+    metrics::write_counter(
+        &mut body,
+        "relay_decrypt_cache_misses_total",
+        "Decrypt cache misses",
+        misses as f64,
+    );
+    metrics::write_gauge(
+        &mut body,
+        "relay_decrypt_cache_hit_ratio",
+        "Decrypt cache hit ratio since startup (0-1)",
+        hit_rate / 100.0,
+    );
+
+    Ok(([(header::CONTENT_TYPE, CONTENT_TYPE)], body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Settings;
+    use axum::{body::Body, http::Request, http::StatusCode};
+    use tower::ServiceExt;
+
+    fn test_router(token: Option<&str>) -> Router {
+        let settings = Settings::new().expect("default settings");
+        create_router(MetricsState {
+            redis: RedisPool::new(&settings).expect("pool"),
+            token: token.map(str::to_string),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_metrics_token() {
+        let request = |auth: Option<&str>| {
+            let mut builder = Request::builder().uri("/metrics");
+            if let Some(auth) = auth {
+                builder = builder.header(header::AUTHORIZATION, auth);
+            }
+            builder.body(Body::empty()).unwrap()
+        };
+
+        let router = test_router(Some("secret"));
+        let response = router.clone().oneshot(request(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = router
+            .clone()
+            .oneshot(request(Some("Bearer nope")))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = router
+            .oneshot(request(Some("Bearer secret")))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], CONTENT_TYPE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+        assert!(text.contains("# TYPE relay_requests_total counter"));
+        assert!(text.contains("relay_redis_pool_max_size "));
+        assert!(text.contains("relay_decrypt_cache_hit_ratio "));
+    }
+}
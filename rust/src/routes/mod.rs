@@ -1,11 +1,17 @@
 pub mod admin;
 pub mod api;
+pub mod api_stats;
 pub mod gemini;
 pub mod health;
+pub mod metrics;
 pub mod openai;
+pub mod user;
 
 pub use admin::create_admin_routes;
 pub use api::{create_router as create_api_router, ApiState};
+pub use api_stats::{create_router as create_api_stats_router, ApiStatsState};
 pub use gemini::{create_router as create_gemini_router, GeminiState};
 pub use health::{health_check, ping, AppState};
+pub use metrics::{create_router as create_metrics_router, MetricsState};
 pub use openai::{create_router as create_openai_router, OpenAIState};
+pub use user::create_user_routes;
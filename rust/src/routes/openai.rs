@@ -22,7 +22,8 @@ use crate::models::{ApiKey, ApiKeyPermissions};
 use crate::redis::RedisPool;
 use crate::services::{
     account::ClaudeAccountService, account_scheduler::AccountScheduler, api_key::ApiKeyService,
-    unified_openai_scheduler::UnifiedOpenAIScheduler,
+    pricing_service::PricingService, unified_openai_scheduler::UnifiedOpenAIScheduler,
+    usage_settlement::UsageSettlement,
 };
 use crate::utils::error::{AppError, Result};
 use crate::utils::session_helper;
@@ -36,6 +37,7 @@ pub struct OpenAIState {
     pub api_key_service: Arc<ApiKeyService>,
     pub scheduler: Arc<AccountScheduler>,
     pub unified_openai_scheduler: Arc<UnifiedOpenAIScheduler>,
+    pub pricing_service: Arc<PricingService>,
 }
 
 /// 创建 OpenAI API 路由
@@ -54,6 +56,11 @@ pub fn create_router(state: OpenAIState) -> Router {
             state.api_key_service.clone(),
             crate::middleware::auth::authenticate_api_key,
         ))
+        // 请求指标 (包括认证失败的请求)
+        .layer(middleware::from_fn_with_state(
+            "openai",
+            crate::middleware::metrics::track_relay_metrics,
+        ))
         .with_state(state)
 }
 
@@ -121,12 +128,35 @@ async fn handle_responses(
         session_hash.as_deref().unwrap_or("none")
     );
 
-    // 5. 使用统一调度器选择账户
+    // 5. 请求前成本限制检查 (按 prompt 长度 + max_output_tokens 预估)
+    let estimated_cost = state
+        .pricing_service
+        .estimate_cost(
+            &model,
+            (request.to_string().len() / 4) as i64,
+            extract_max_output_tokens(&request).unwrap_or(DEFAULT_MAX_OUTPUT_TOKENS),
+        )
+        .await;
+    let settlement = UsageSettlement::reserve(
+        state.api_key_service.clone(),
+        state.pricing_service.clone(),
+        &api_key,
+        &model,
+        estimated_cost,
+    )
+    .await?;
+
+    // 6. 使用统一调度器选择账户
     // TODO: 需要在 UnifiedOpenAIScheduler 中添加 API Key 专属账户绑定支持
     let selected = state
         .unified_openai_scheduler
         .select_account(&api_key, session_hash.as_deref(), Some(&model))
-        .await?;
+        .await;
+
+    // 转发逻辑尚未实现，不产生实际用量，直接释放预占
+    // TODO: 实现转发后在记录实际用量时释放
+    settlement.release().await;
+    let selected = selected?;
 
     info!(
         "🎯 Selected OpenAI account: {} (type: {}) for API key: {}",
@@ -197,6 +227,17 @@ async fn handle_key_info(
 // 辅助函数
 // ============================================================================
 
+/// 未指定输出上限时用于成本预估的输出 tokens 数
+const DEFAULT_MAX_OUTPUT_TOKENS: i64 = 4096;
+
+/// 提取请求中的输出 tokens 上限 (max_output_tokens 或 max_tokens)
+fn extract_max_output_tokens(request: &JsonValue) -> Option<i64> {
+    request
+        .get("max_output_tokens")
+        .or_else(|| request.get("max_tokens"))
+        .and_then(|v| v.as_i64())
+}
+
 /// 生成会话 Hash (用于粘性会话)
 ///
 /// 使用智能会话哈希生成逻辑：
@@ -0,0 +1,389 @@
+// 用户路由
+//
+// 普通用户注册、登录以及管理自己的 API Keys：
+// - POST /users/register - 注册 (需要邀请码，除非管理员开放注册)
+// - POST /users/login - 登录，签发 user 角色的 JWT (与管理后台共用失败锁定和双因素认证)
+// - POST /users/auth/refresh - 使用刷新令牌换取新的访问令牌
+// - POST /users/auth/logout - 登出 (撤销当前会话)
+// - GET /users/profile - 当前用户信息
+// - GET /users/api-keys - 我的 API Keys
+// - POST /users/api-keys - 创建 API Key (受配额限制)
+// - PUT /users/api-keys/:id - 修改名称或停用 (停用后只能由管理员重新启用)
+// - DELETE /users/api-keys/:id - 删除 API Key
+
+use axum::{
+    extract::{ConnectInfo, Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::middleware::{authenticate_jwt, JwtAuthState};
+use crate::models::api_key::{ApiKeyCreateOptions, ApiKeyPermissions};
+use crate::models::user::User;
+use crate::routes::admin::{guarded_login, verify_login_two_factor};
+use crate::services::{
+    AdminService, LoginGuardService, LoginRequest, LogoutRequest, RefreshTokenRequest,
+    RegisterUserRequest, UserService,
+};
+use crate::utils::client_ip::extract_client_ip;
+use crate::utils::error::AppError;
+
+/// 用户登录路由状态
+#[derive(Clone)]
+pub struct UserLoginState {
+    pub user_service: Arc<UserService>,
+    pub admin_service: Arc<AdminService>,
+    pub login_guard: Arc<LoginGuardService>,
+}
+
+/// 用户创建 API Key 请求
+///
+/// 只开放基础字段，限制类设置由管理员配额决定
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UserApiKeyRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub permissions: Option<ApiKeyPermissions>,
+    #[serde(rename = "dailyCostLimit")]
+    pub daily_cost_limit: Option<f64>,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// 用户修改 API Key 请求
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UserApiKeyUpdateRequest {
+    pub name: Option<String>,
+    #[serde(rename = "isActive")]
+    pub is_active: Option<bool>,
+}
+
+/// 创建用户路由
+pub fn create_user_routes(
+    user_service: Arc<UserService>,
+    admin_service: Arc<AdminService>,
+    login_guard: Arc<LoginGuardService>,
+) -> Router {
+    let public_routes = Router::new()
+        .route("/register", post(register_handler))
+        .with_state(user_service.clone())
+        .merge(
+            Router::new()
+                .route("/login", post(login_handler))
+                .with_state(UserLoginState {
+                    user_service: user_service.clone(),
+                    admin_service: admin_service.clone(),
+                    login_guard,
+                }),
+        );
+
+    let session_routes = Router::new()
+        .route("/auth/refresh", post(refresh_handler))
+        .merge(
+            Router::new()
+                .route("/auth/logout", post(logout_handler))
+                .layer(axum::middleware::from_fn_with_state(
+                    admin_service.clone(),
+                    authenticate_jwt,
+                )),
+        )
+        .with_state(admin_service.clone());
+
+    let protected_routes = Router::new()
+        .route("/profile", get(get_profile_handler))
+        .route("/api-keys", get(list_api_keys_handler))
+        .route("/api-keys", post(create_api_key_handler))
+        .route("/api-keys/:id", put(update_api_key_handler))
+        .route("/api-keys/:id", delete(delete_api_key_handler))
+        .layer(axum::middleware::from_fn_with_state(
+            admin_service,
+            authenticate_jwt,
+        ))
+        .with_state(user_service);
+
+    public_routes.merge(session_routes).merge(protected_routes)
+}
+
+/// 获取当前登录用户
+async fn current_user(service: &UserService, jwt_state: &JwtAuthState) -> Result<User, AppError> {
+    service
+        .get_active_user(&jwt_state.claims.sub, &jwt_state.claims.role)
+        .await
+}
+
+/// 用户注册
+async fn register_handler(
+    State(service): State<Arc<UserService>>,
+    Json(request): Json<RegisterUserRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("📝 User registration attempt: {}", request.username);
+
+    let user = service.register(request).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "success": true, "data": user.sanitized() })),
+    ))
+}
+
+/// 用户登录
+///
+/// 与管理后台登录相同，按用户名和客户端 IP 统计失败次数并校验双因素验证码
+async fn login_handler(
+    State(state): State<UserLoginState>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    Json(payload): Json<LoginRequest>,
+) -> Result<Response, AppError> {
+    let client_ip = extract_client_ip(&headers, connect_info.map(|ConnectInfo(addr)| addr));
+    info!(
+        "🔐 User login attempt: {} from {}",
+        payload.username, client_ip
+    );
+
+    guarded_login(
+        &state.login_guard,
+        &payload.username,
+        &client_ip,
+        authenticate_user_login(&state, &payload),
+    )
+    .await
+}
+
+/// 校验凭据和双因素验证码并签发 user 角色的登录令牌
+async fn authenticate_user_login(
+    state: &UserLoginState,
+    payload: &LoginRequest,
+) -> Result<Response, AppError> {
+    let user = state
+        .user_service
+        .verify_credentials(&payload.username, &payload.password)
+        .await?;
+
+    // 按注册时的用户名 (JWT sub) 校验，与登录时输入的大小写无关
+    if let Some(response) =
+        verify_login_two_factor(&state.admin_service, &user.username, payload).await?
+    {
+        return Ok(response);
+    }
+
+    let response = state.user_service.issue_user_login(user).await?;
+
+    Ok((StatusCode::OK, Json(response)).into_response())
+}
+
+/// 刷新访问令牌 (刷新令牌同时轮换)
+async fn refresh_handler(
+    State(service): State<Arc<AdminService>>,
+    Json(request): Json<RefreshTokenRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let response = service.refresh_session(&request.refresh_token).await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// 登出
+async fn logout_handler(
+    State(service): State<Arc<AdminService>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    body: Option<Json<LogoutRequest>>,
+) -> Result<impl IntoResponse, AppError> {
+    let request = body.map(|Json(r)| r).unwrap_or_default();
+    if request.all_sessions {
+        service.bump_token_version(&jwt_state.claims.sub).await?;
+    }
+    service.revoke_session(&jwt_state.claims.sid).await?;
+
+    info!("👋 User logged out: {}", jwt_state.claims.sub);
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "success": true, "message": "已登出" })),
+    ))
+}
+
+/// 当前用户信息
+async fn get_profile_handler(
+    State(service): State<Arc<UserService>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+) -> Result<impl IntoResponse, AppError> {
+    let user = current_user(&service, &jwt_state).await?;
+    let key_count = service.list_user_keys(&user.id).await?.len();
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "success": true,
+            "data": {
+                "user": user.sanitized(),
+                "quota": {
+                    "maxApiKeys": user.max_api_keys,
+                    "usedApiKeys": key_count,
+                    "maxKeyDailyCostLimit": user.max_key_daily_cost_limit,
+                }
+            }
+        })),
+    ))
+}
+
+/// 我的 API Keys
+async fn list_api_keys_handler(
+    State(service): State<Arc<UserService>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+) -> Result<impl IntoResponse, AppError> {
+    let user = current_user(&service, &jwt_state).await?;
+    let api_keys = service.list_user_keys(&user.id).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "success": true, "data": api_keys })),
+    ))
+}
+
+/// 创建 API Key
+async fn create_api_key_handler(
+    State(service): State<Arc<UserService>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Json(request): Json<UserApiKeyRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let user = current_user(&service, &jwt_state).await?;
+
+    let options = ApiKeyCreateOptions {
+        name: request.name,
+        description: request.description,
+        permissions: request.permissions.unwrap_or_default(),
+        daily_cost_limit: request.daily_cost_limit.unwrap_or(0.0),
+        expires_at: request.expires_at,
+        ..Default::default()
+    };
+
+    let (raw_key, mut api_key) = service.create_user_key(&user, options).await?;
+    api_key.key = Some(raw_key);
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "success": true,
+            "message": "API Key创建成功",
+            "data": api_key
+        })),
+    ))
+}
+
+/// 修改 API Key
+async fn update_api_key_handler(
+    State(service): State<Arc<UserService>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Path(id): Path<String>,
+    Json(request): Json<UserApiKeyUpdateRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let user = current_user(&service, &jwt_state).await?;
+    let api_key = service
+        .update_user_key(&user, &id, request.name, request.is_active)
+        .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "success": true, "data": api_key })),
+    ))
+}
+
+/// 删除 API Key
+async fn delete_api_key_handler(
+    State(service): State<Arc<UserService>>,
+    jwt_state: axum::Extension<JwtAuthState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let user = current_user(&service, &jwt_state).await?;
+    service.delete_user_key(&user, &id).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "success": true, "message": "API Key已删除" })),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Settings;
+    use crate::services::two_factor::{current_totp, TwoFactorService};
+    use crate::services::ApiKeyService;
+    use crate::RedisPool;
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    #[ignore] // 需要 Redis
+    async fn test_login_two_factor_ignores_username_case() {
+        let settings = Settings::new().expect("Failed to create test settings");
+        let redis = Arc::new(RedisPool::new(&settings).expect("Failed to create Redis pool"));
+        let two_factor = Arc::new(TwoFactorService::new(
+            redis.clone(),
+            "test-encryption-key-32-chars!!".to_string(),
+        ));
+        let admin_service = Arc::new(
+            AdminService::new(
+                redis.clone(),
+                "test_secret_key_at_least_32_chars_long".to_string(),
+            )
+            .with_two_factor_service(two_factor.clone()),
+        );
+        let api_key_service = Arc::new(ApiKeyService::new((*redis).clone(), settings.clone()));
+        let user_service = Arc::new(UserService::new(
+            redis.clone(),
+            admin_service.clone(),
+            api_key_service,
+        ));
+        let login_guard = Arc::new(LoginGuardService::new(redis.clone()));
+        let app = create_user_routes(user_service.clone(), admin_service, login_guard);
+
+        let username = format!("Case{}", &uuid::Uuid::new_v4().simple().to_string()[..8]);
+        let invitation = user_service
+            .create_invitation("tester", None, None, 1)
+            .await
+            .unwrap();
+        user_service
+            .register(RegisterUserRequest {
+                username: username.clone(),
+                password: "password123".to_string(),
+                email: None,
+                display_name: None,
+                invitation_code: Some(invitation.code),
+            })
+            .await
+            .unwrap();
+        let enrollment = two_factor.begin_enrollment(&username).await.unwrap();
+        two_factor
+            .confirm_enrollment(&username, &current_totp(&enrollment.secret))
+            .await
+            .unwrap();
+
+        // 大小写和首尾空白不同的用户名仍然需要双因素验证码
+        for login_name in [username.to_uppercase(), format!(" {} ", username.to_lowercase())] {
+            let request = Request::builder()
+                .uri("/login")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({ "username": login_name, "password": "password123" }).to_string(),
+                ))
+                .unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body["requiresTwoFactor"], true);
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use crate::models::account::{AccountType, ClaudeAccount, Platform};
 use crate::redis::RedisPool;
 use crate::services::ClaudeAccountService;
-use crate::utils::{AppError, Result};
+use crate::utils::{metrics, AppError, Result};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
@@ -496,13 +496,16 @@ impl AccountScheduler {
         let mut conn = self.redis.get_connection().await?;
 
         // 添加到 Sorted Set（score 为过期时间）
-        redis::cmd("ZADD")
+        let added: i64 = redis::cmd("ZADD")
             .arg(&key)
             .arg(expiry_time)
             .arg(request_id)
-            .query_async::<_, ()>(&mut conn)
+            .query_async(&mut conn)
             .await
             .map_err(|e| AppError::RedisError(format!("Failed to increment concurrency: {}", e)))?;
+        if added > 0 {
+            metrics::ACCOUNT_CONCURRENCY.add(&[account_id], 1.0);
+        }
 
         // 设置 key 过期时间（避免 key 永久存在）
         redis::cmd("EXPIRE")
@@ -529,12 +532,15 @@ impl AccountScheduler {
         let key = format!("concurrency:{}", account_id);
         let mut conn = self.redis.get_connection().await?;
 
-        redis::cmd("ZREM")
+        let removed: i64 = redis::cmd("ZREM")
             .arg(&key)
             .arg(request_id)
-            .query_async::<_, ()>(&mut conn)
+            .query_async(&mut conn)
             .await
             .map_err(|e| AppError::RedisError(format!("Failed to decrement concurrency: {}", e)))?;
+        if removed > 0 {
+            metrics::ACCOUNT_CONCURRENCY.add(&[account_id], -1.0);
+        }
 
         Ok(())
     }
@@ -559,6 +565,7 @@ impl AccountScheduler {
             .map_err(|e| AppError::RedisError(format!("Failed to cleanup concurrency: {}", e)))?;
 
         if removed > 0 {
+            metrics::ACCOUNT_CONCURRENCY.add(&[account_id], -(removed as f64));
             tracing::debug!(
                 "🧹 Cleaned up {} expired concurrency records for account {}",
                 removed,
@@ -0,0 +1,285 @@
+use chrono::{DateTime, Duration, FixedOffset, TimeZone, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use crate::models::account_usage::{
+    account_model_usage_key, account_models_key, account_usage_key, session_window_for,
+    session_window_key, AccountUsageSummary, AccountUsageTotals, SessionWindowUsage, UsageAverages,
+    SESSION_WINDOW_HOURS,
+};
+use crate::models::api_key::{ModelUsage, UsagePeriod};
+use crate::models::usage_trend::{UsageBucket, UsageDimension};
+use crate::redis::RedisPool;
+use crate::services::api_key::parse_model_usage;
+use crate::utils::error::{AppError, Result};
+
+/// 把一次使用量记到处理请求的上游账户上
+///
+/// 写入累计统计、日/月按模型统计，并在没有活跃会话窗口时开启新窗口。
+/// 窗口内的用量从 `record_usage_buckets` 写入的账户小时桶汇总
+pub(crate) fn record_account_usage(
+    pipe: &mut redis::Pipeline,
+    account_id: &str,
+    model: &str,
+    usage: &ModelUsage,
+    now: DateTime<Utc>,
+    system_timezone: FixedOffset,
+) {
+    let total_key = account_usage_key(account_id);
+    incr_usage(pipe, &total_key, usage);
+    pipe.cmd("HSETNX")
+        .arg(&total_key)
+        .arg("first_used_at")
+        .arg(now.timestamp())
+        .hset(&total_key, "last_used_at", now.timestamp());
+
+    for period in [UsagePeriod::Daily, UsagePeriod::Monthly] {
+        let bucket = period.bucket(now, system_timezone);
+        let retention = period.retention_seconds();
+        let model_key = account_model_usage_key(account_id, period, &bucket, model);
+        incr_usage(pipe, &model_key, usage);
+        pipe.expire(&model_key, retention);
+
+        let models_key = account_models_key(account_id, period, &bucket);
+        pipe.sadd(&models_key, model).expire(&models_key, retention);
+    }
+
+    // 已有窗口时 NX 不生效，窗口结束时键自动过期
+    let (window_start, window_end) = session_window_for(now);
+    pipe.cmd("SET")
+        .arg(session_window_key(account_id))
+        .arg(window_start.timestamp())
+        .arg("NX")
+        .arg("EXAT")
+        .arg(window_end.timestamp());
+}
+
+fn incr_usage(pipe: &mut redis::Pipeline, key: &str, usage: &ModelUsage) {
+    pipe.hincr(key, "requests", usage.requests)
+        .hincr(key, "input_tokens", usage.input_tokens)
+        .hincr(key, "output_tokens", usage.output_tokens)
+        .hincr(key, "cache_creation_tokens", usage.cache_creation_tokens)
+        .hincr(key, "cache_read_tokens", usage.cache_read_tokens)
+        .cmd("HINCRBYFLOAT")
+        .arg(key)
+        .arg("cost")
+        .arg(usage.cost)
+        .cmd("HINCRBYFLOAT")
+        .arg(key)
+        .arg("billed_cost")
+        .arg(usage.billed_cost);
+}
+
+/// 账户使用量查询服务
+pub struct AccountUsageService {
+    redis: Arc<RedisPool>,
+    system_timezone: FixedOffset,
+}
+
+impl AccountUsageService {
+    pub fn new(redis: Arc<RedisPool>, system_timezone: FixedOffset) -> Self {
+        Self {
+            redis,
+            system_timezone,
+        }
+    }
+
+    /// 单个账户的使用量汇总
+    pub async fn get_account_usage(&self, account_id: &str) -> Result<AccountUsageSummary> {
+        let mut summaries = self.get_accounts_usage(&[account_id.to_string()]).await?;
+        Ok(summaries.remove(0))
+    }
+
+    /// 批量获取账户使用量汇总 (顺序与 `account_ids` 一致)
+    pub async fn get_accounts_usage(
+        &self,
+        account_ids: &[String],
+    ) -> Result<Vec<AccountUsageSummary>> {
+        if account_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let now = Utc::now();
+        let daily_bucket = UsagePeriod::Daily.bucket(now, self.system_timezone);
+        let monthly_bucket = UsagePeriod::Monthly.bucket(now, self.system_timezone);
+        let mut conn = self.redis.get_connection().await?;
+
+        // 1. 累计统计、当日/当月用过的模型、会话窗口起点
+        let mut pipe = redis::pipe();
+        for id in account_ids {
+            pipe.cmd("HGETALL")
+                .arg(account_usage_key(id))
+                .cmd("SMEMBERS")
+                .arg(account_models_key(id, UsagePeriod::Daily, &daily_bucket))
+                .cmd("SMEMBERS")
+                .arg(account_models_key(
+                    id,
+                    UsagePeriod::Monthly,
+                    &monthly_bucket,
+                ))
+                .cmd("GET")
+                .arg(session_window_key(id));
+        }
+        type Row = (
+            HashMap<String, String>,
+            Vec<String>,
+            Vec<String>,
+            Option<i64>,
+        );
+        let rows: Vec<Row> = pipe
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to get account usage: {}", e)))?;
+
+        // 2. 按模型统计和会话窗口覆盖的小时桶
+        let mut pipe = redis::pipe();
+        for (id, (_, daily_models, monthly_models, window_start)) in account_ids.iter().zip(&rows) {
+            for model in daily_models {
+                pipe.cmd("HGETALL").arg(account_model_usage_key(
+                    id,
+                    UsagePeriod::Daily,
+                    &daily_bucket,
+                    model,
+                ));
+            }
+            for model in monthly_models {
+                pipe.cmd("HGETALL").arg(account_model_usage_key(
+                    id,
+                    UsagePeriod::Monthly,
+                    &monthly_bucket,
+                    model,
+                ));
+            }
+            for hour in window_start.and_then(window_hours).into_iter().flatten() {
+                pipe.cmd("HGETALL")
+                    .arg(UsageBucket::hour(hour).usage_key(UsageDimension::Account, id));
+            }
+        }
+        let hashes: Vec<HashMap<String, String>> = pipe
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to get account usage: {}", e)))?;
+        let mut usages = hashes.iter().map(parse_model_usage);
+
+        let mut summaries = Vec::with_capacity(account_ids.len());
+        for (id, (total, daily_models, monthly_models, window_start)) in
+            account_ids.iter().zip(rows)
+        {
+            let daily_models = collect_models(daily_models, usages.by_ref());
+            let monthly_models = collect_models(monthly_models, usages.by_ref());
+            let session_window = window_start
+                .and_then(|ts| Utc.timestamp_opt(ts, 0).single())
+                .map(|start| {
+                    let usage = sum(usages.by_ref().take(SESSION_WINDOW_HOURS as usize));
+                    SessionWindowUsage::new(start, &usage, now)
+                });
+
+            let timestamp = |field: &str| {
+                total
+                    .get(field)
+                    .and_then(|v| v.parse().ok())
+                    .and_then(|ts| Utc.timestamp_opt(ts, 0).single())
+            };
+            let total_usage = parse_model_usage(&total);
+            let first_used_at = timestamp("first_used_at");
+
+            summaries.push(AccountUsageSummary {
+                account_id: id.clone(),
+                daily: AccountUsageTotals::from(&sum(daily_models.values().cloned())),
+                monthly: AccountUsageTotals::from(&sum(monthly_models.values().cloned())),
+                total: AccountUsageTotals::from(&total_usage),
+                averages: UsageAverages::since(&total_usage, first_used_at, now),
+                session_window,
+                daily_models: totals_by_model(daily_models),
+                monthly_models: totals_by_model(monthly_models),
+                last_used_at: timestamp("last_used_at"),
+            });
+        }
+
+        Ok(summaries)
+    }
+}
+
+/// 会话窗口覆盖的每个整点
+fn window_hours(start: i64) -> Option<Vec<DateTime<Utc>>> {
+    let start = Utc.timestamp_opt(start, 0).single()?;
+    Some(
+        (0..SESSION_WINDOW_HOURS)
+            .map(|hour| start + Duration::hours(hour))
+            .collect(),
+    )
+}
+
+fn collect_models(
+    models: Vec<String>,
+    usages: &mut impl Iterator<Item = ModelUsage>,
+) -> BTreeMap<String, ModelUsage> {
+    models.into_iter().zip(usages).collect()
+}
+
+fn totals_by_model(models: BTreeMap<String, ModelUsage>) -> BTreeMap<String, AccountUsageTotals> {
+    models
+        .iter()
+        .map(|(model, usage)| (model.clone(), AccountUsageTotals::from(usage)))
+        .collect()
+}
+
+fn sum(usages: impl Iterator<Item = ModelUsage>) -> ModelUsage {
+    usages.fold(ModelUsage::default(), |mut total, usage| {
+        total.add(&usage);
+        total
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_account_usage_keys() {
+        let mut pipe = redis::pipe();
+        let now: DateTime<Utc> = "2025-01-31T16:42:00Z".parse().unwrap();
+        let usage = ModelUsage {
+            requests: 1,
+            input_tokens: 100,
+            cost: 0.01,
+            ..Default::default()
+        };
+        let utc = FixedOffset::east_opt(0).unwrap();
+        record_account_usage(&mut pipe, "a1", "claude-sonnet-4", &usage, now, utc);
+
+        let packed = String::from_utf8_lossy(&pipe.get_packed_pipeline()).to_string();
+        for key in [
+            "account_usage:a1",
+            "account_usage:model:daily:a1:2025-01-31:claude-sonnet-4",
+            "account_usage:model:monthly:a1:2025-01:claude-sonnet-4",
+            "account_usage:models:daily:a1:2025-01-31",
+            "session_window:a1",
+        ] {
+            assert!(packed.contains(key), "missing {}", key);
+        }
+        // 窗口从 16:00 开始，21:00 过期
+        let window_end: DateTime<Utc> = "2025-01-31T21:00:00Z".parse().unwrap();
+        assert!(packed.contains(&window_end.timestamp().to_string()));
+    }
+
+    #[test]
+    fn test_window_hours() {
+        let start: DateTime<Utc> = "2025-01-31T22:00:00Z".parse().unwrap();
+        let hours = window_hours(start.timestamp()).unwrap();
+        let names: Vec<String> = hours
+            .into_iter()
+            .map(|h| UsageBucket::hour(h).name)
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "2025-01-31T22",
+                "2025-01-31T23",
+                "2025-02-01T00",
+                "2025-02-01T01",
+                "2025-02-01T02"
+            ]
+        );
+    }
+}
@@ -6,12 +6,17 @@ use argon2::{
 use chrono::{DateTime, Utc};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use rand::rngs::OsRng;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::fs;
 use std::path::Path;
 use std::sync::Arc;
 use tracing::{info, warn};
 
+use crate::services::admin_token::AdminApiTokenService;
+use crate::services::ldap::LdapAuthProvider;
+use crate::services::two_factor::TwoFactorService;
 use crate::utils::error::AppError;
 use crate::RedisPool;
 
@@ -39,6 +44,21 @@ pub struct InitData {
     pub updated_at: Option<DateTime<Utc>>,
 }
 
+/// 访问令牌有效期 (秒)
+pub const ACCESS_TOKEN_TTL_SECONDS: i64 = 15 * 60;
+
+/// 刷新令牌 (会话) 有效期 (秒)，每次刷新后重新计算
+pub const REFRESH_TOKEN_TTL_SECONDS: i64 = 7 * 24 * 3600;
+
+/// 会话未被登出或其他刷新修改时才写入轮换后的会话 (会话已删除时同样失败)
+const ROTATE_SESSION_LUA: &str = r#"
+if redis.call('GET', KEYS[1]) ~= ARGV[1] then
+    return 0
+end
+redis.call('SET', KEYS[1], ARGV[2], 'EX', ARGV[3])
+return 1
+"#;
+
 /// JWT Claims
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
@@ -46,6 +66,25 @@ pub struct Claims {
     pub role: String, // "admin" or "user"
     pub exp: usize,   // expiration time (Unix timestamp)
     pub iat: usize,   // issued at (Unix timestamp)
+    /// 会话 ID，登出后会话被删除，令牌随之失效
+    #[serde(default)]
+    pub sid: String,
+    /// 签发时的令牌版本，修改密码或角色后版本递增，旧令牌全部失效
+    #[serde(default)]
+    pub ver: u64,
+}
+
+/// 登录会话 (存储在 `admin_session:{id}`，TTL 为刷新令牌有效期)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Session {
+    id: String,
+    username: String,
+    role: String,
+    /// 当前刷新令牌的 SHA-256 哈希，每次刷新后轮换
+    refresh_token_hash: String,
+    token_version: u64,
+    created_at: DateTime<Utc>,
+    refreshed_at: Option<DateTime<Utc>>,
 }
 
 /// 登录请求
@@ -53,13 +92,41 @@ pub struct Claims {
 pub struct LoginRequest {
     pub username: String,
     pub password: String,
+    /// 已启用双因素认证时需要的 TOTP 验证码
+    #[serde(rename = "totpCode", default)]
+    pub totp_code: Option<String>,
+    /// 无法使用验证器时的一次性恢复码
+    #[serde(rename = "recoveryCode", default)]
+    pub recovery_code: Option<String>,
+}
+
+/// 刷新令牌请求
+#[derive(Debug, Deserialize)]
+pub struct RefreshTokenRequest {
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: String,
+}
+
+/// 登出请求
+#[derive(Debug, Default, Deserialize)]
+pub struct LogoutRequest {
+    /// 同时注销该账号在其他设备上的所有会话
+    #[serde(rename = "allSessions", default)]
+    pub all_sessions: bool,
 }
 
 /// 登录响应
 #[derive(Debug, Serialize)]
 pub struct LoginResponse {
     pub success: bool,
+    /// 短期访问令牌 (JWT)
     pub token: String,
+    /// 一次性刷新令牌，用于换取新的访问令牌
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: String,
+    /// 访问令牌剩余有效期 (秒)
+    #[serde(rename = "expiresIn")]
+    pub expires_in: i64,
     pub user: UserInfo,
 }
 
@@ -74,12 +141,48 @@ pub struct UserInfo {
 pub struct AdminService {
     redis: Arc<RedisPool>,
     jwt_secret: String,
+    ldap: Option<Arc<LdapAuthProvider>>,
+    two_factor: Option<Arc<TwoFactorService>>,
+    api_tokens: Arc<AdminApiTokenService>,
 }
 
 impl AdminService {
     /// 创建新的管理员服务实例
     pub fn new(redis: Arc<RedisPool>, jwt_secret: String) -> Self {
-        Self { redis, jwt_secret }
+        Self {
+            api_tokens: Arc::new(AdminApiTokenService::new(redis.clone())),
+            redis,
+            jwt_secret,
+            ldap: None,
+            two_factor: None,
+        }
+    }
+
+    /// 启用 LDAP 认证 (本地凭据校验失败时回退到目录)
+    pub fn with_ldap_provider(mut self, provider: Arc<LdapAuthProvider>) -> Self {
+        self.ldap = Some(provider);
+        self
+    }
+
+    /// 已配置的 LDAP 认证提供者
+    pub fn ldap_provider(&self) -> Option<Arc<LdapAuthProvider>> {
+        self.ldap.clone()
+    }
+
+    /// 启用 TOTP 双因素认证 (管理员可自行注册)
+    pub fn with_two_factor_service(mut self, service: Arc<TwoFactorService>) -> Self {
+        self.two_factor = Some(service);
+        self
+    }
+
+    /// 已配置的双因素认证服务
+    pub fn two_factor_service(&self) -> Option<Arc<TwoFactorService>> {
+        self.two_factor.clone()
+    }
+
+    /// 长期有效的管理 API 令牌
+    pub fn api_token_service(&self) -> Arc<AdminApiTokenService> {
+        self.api_tokens.clone()
     }
 
     /// 从 data/init.json 加载管理员凭据
@@ -144,6 +247,12 @@ impl AdminService {
         username: &str,
         password: &str,
     ) -> Result<LoginResponse, AppError> {
+        self.verify_credentials(username, password).await?;
+        self.issue_login(username).await
+    }
+
+    /// 校验管理员用户名和密码 (不签发 token)
+    pub async fn verify_credentials(&self, username: &str, password: &str) -> Result<(), AppError> {
         // 从 Redis 获取管理员凭据
         let credentials = self.get_admin_credentials().await?;
 
@@ -162,32 +271,212 @@ impl AdminService {
             .verify_password(password.as_bytes(), &parsed_hash)
             .map_err(|_| AppError::Unauthorized("Invalid credentials".to_string()))?;
 
-        // 生成 JWT token
-        let token = self.generate_token(username, "admin")?;
+        Ok(())
+    }
+
+    /// 为已通过校验的管理员签发 JWT
+    pub async fn issue_login(&self, username: &str) -> Result<LoginResponse, AppError> {
+        let response = self.create_session(username, "admin").await?;
 
         // 更新最后登录时间
         self.update_last_login(username).await?;
 
+        Ok(response)
+    }
+
+    /// 创建登录会话，签发访问令牌和刷新令牌
+    pub async fn create_session(
+        &self,
+        username: &str,
+        role: &str,
+    ) -> Result<LoginResponse, AppError> {
+        let session_id = uuid::Uuid::new_v4().to_string();
+        let (refresh_token, refresh_token_hash) = generate_refresh_token(&session_id);
+
+        let session = Session {
+            id: session_id,
+            username: username.to_string(),
+            role: role.to_string(),
+            refresh_token_hash,
+            token_version: self.token_version(username).await?,
+            created_at: Utc::now(),
+            refreshed_at: None,
+        };
+        self.save_session(&session).await?;
+
+        self.session_response(&session, refresh_token)
+    }
+
+    /// 使用刷新令牌换取新的访问令牌，刷新令牌同时轮换
+    ///
+    /// 已轮换的旧刷新令牌再次出现视为泄露，整个会话被撤销。
+    /// 轮换通过比较写入完成，同一刷新令牌的并发请求只有一个成功，登出后的刷新也会失败
+    pub async fn refresh_session(&self, refresh_token: &str) -> Result<LoginResponse, AppError> {
+        let invalid = || AppError::Unauthorized("Invalid refresh token".to_string());
+
+        let (session_id, _) = refresh_token.split_once('.').ok_or_else(invalid)?;
+        let (original, mut session) = self.get_session(session_id).await?.ok_or_else(invalid)?;
+
+        if session.refresh_token_hash != hash_token(refresh_token) {
+            warn!(
+                "⚠️  Refresh token reuse detected for {}, revoking session {}",
+                session.username, session.id
+            );
+            self.revoke_session(&session.id).await?;
+            return Err(invalid());
+        }
+        if session.token_version != self.token_version(&session.username).await? {
+            self.revoke_session(&session.id).await?;
+            return Err(AppError::Unauthorized(
+                "Session has been revoked".to_string(),
+            ));
+        }
+
+        let (refresh_token, refresh_token_hash) = generate_refresh_token(&session.id);
+        session.refresh_token_hash = refresh_token_hash;
+        session.refreshed_at = Some(Utc::now());
+        if !self.rotate_session(&original, &session).await? {
+            return Err(invalid());
+        }
+
+        self.session_response(&session, refresh_token)
+    }
+
+    /// 会话仍为 `original` 时写入轮换后的会话，返回是否写入成功
+    async fn rotate_session(&self, original: &str, session: &Session) -> Result<bool, AppError> {
+        let json = serde_json::to_string(session)
+            .map_err(|e| AppError::InternalError(format!("Failed to serialize session: {}", e)))?;
+
+        let mut conn = self.redis.get_connection().await?;
+        let rotated: i32 = redis::Script::new(ROTATE_SESSION_LUA)
+            .key(session_key(&session.id))
+            .arg(original)
+            .arg(json)
+            .arg(REFRESH_TOKEN_TTL_SECONDS)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to rotate session: {}", e)))?;
+
+        Ok(rotated == 1)
+    }
+
+    /// 撤销单个会话 (登出)
+    pub async fn revoke_session(&self, session_id: &str) -> Result<(), AppError> {
+        self.redis.del(&session_key(session_id)).await?;
+        Ok(())
+    }
+
+    /// 校验访问令牌对应的会话仍然有效 (未登出且令牌版本未变化)
+    pub async fn validate_session(&self, claims: &Claims) -> Result<(), AppError> {
+        if claims.sid.is_empty() {
+            return Err(AppError::Unauthorized(
+                "Session expired, please log in again".to_string(),
+            ));
+        }
+
+        let mut conn = self.redis.get_connection().await?;
+        let (session_exists, version): (bool, Option<u64>) = redis::pipe()
+            .exists(session_key(&claims.sid))
+            .get(token_version_key(&claims.sub))
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to check session: {}", e)))?;
+
+        if !session_exists || version.unwrap_or(0) != claims.ver {
+            return Err(AppError::Unauthorized(
+                "Session has been revoked".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// 当前令牌版本
+    pub async fn token_version(&self, username: &str) -> Result<u64, AppError> {
+        Ok(self
+            .redis
+            .get::<u64>(&token_version_key(username))
+            .await?
+            .unwrap_or(0))
+    }
+
+    /// 递增令牌版本，使该用户所有已签发的令牌和会话失效
+    pub async fn bump_token_version(&self, username: &str) -> Result<u64, AppError> {
+        let version = self.redis.incr(&token_version_key(username)).await? as u64;
+        info!(
+            "🔒 Sessions revoked for: {} (token version {})",
+            username, version
+        );
+        Ok(version)
+    }
+
+    /// 读取会话及其原始 JSON (用于比较写入)
+    async fn get_session(&self, session_id: &str) -> Result<Option<(String, Session)>, AppError> {
+        Ok(self
+            .redis
+            .get::<String>(&session_key(session_id))
+            .await?
+            .and_then(|json| {
+                let session = serde_json::from_str(&json).ok()?;
+                Some((json, session))
+            }))
+    }
+
+    async fn save_session(&self, session: &Session) -> Result<(), AppError> {
+        let json = serde_json::to_string(session)
+            .map_err(|e| AppError::InternalError(format!("Failed to serialize session: {}", e)))?;
+        self.redis
+            .setex(
+                &session_key(&session.id),
+                &json,
+                REFRESH_TOKEN_TTL_SECONDS as u64,
+            )
+            .await?;
+        Ok(())
+    }
+
+    fn session_response(
+        &self,
+        session: &Session,
+        refresh_token: String,
+    ) -> Result<LoginResponse, AppError> {
+        let token = self.generate_token(
+            &session.username,
+            &session.role,
+            &session.id,
+            session.token_version,
+        )?;
+
         Ok(LoginResponse {
             success: true,
             token,
+            refresh_token,
+            expires_in: ACCESS_TOKEN_TTL_SECONDS,
             user: UserInfo {
-                username: username.to_string(),
-                role: "admin".to_string(),
+                username: session.username.clone(),
+                role: session.role.clone(),
             },
         })
     }
 
     /// 生成 JWT token
-    pub fn generate_token(&self, username: &str, role: &str) -> Result<String, AppError> {
+    pub fn generate_token(
+        &self,
+        username: &str,
+        role: &str,
+        session_id: &str,
+        token_version: u64,
+    ) -> Result<String, AppError> {
         let now = Utc::now();
-        let expiration = now + chrono::Duration::hours(24); // 24 小时有效期
+        let expiration = now + chrono::Duration::seconds(ACCESS_TOKEN_TTL_SECONDS);
 
         let claims = Claims {
             sub: username.to_string(),
             role: role.to_string(),
             exp: expiration.timestamp() as usize,
             iat: now.timestamp() as usize,
+            sid: session_id.to_string(),
+            ver: token_version,
         };
 
         let token = encode(
@@ -212,6 +501,15 @@ impl AdminService {
         Ok(token_data.claims)
     }
 
+    /// 管理员用户名 (尚未初始化管理员时返回 None)
+    pub async fn admin_username(&self) -> Result<Option<String>, AppError> {
+        match self.get_admin_credentials().await {
+            Ok(credentials) => Ok(Some(credentials.username)),
+            Err(AppError::Unauthorized(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
     /// 获取管理员凭据
     async fn get_admin_credentials(&self) -> Result<AdminCredentials, AppError> {
         let mut conn = self.redis.get_connection().await?;
@@ -402,12 +700,35 @@ impl AdminService {
             .await
             .map_err(|e| AppError::InternalError(format!("Failed to update credentials: {}", e)))?;
 
+        // 修改密码后所有已登录会话失效
+        self.bump_token_version(username).await?;
+
         info!("✅ Admin password reset for: {}", username);
 
         Ok(())
     }
 }
 
+fn session_key(session_id: &str) -> String {
+    format!("admin_session:{}", session_id)
+}
+
+pub(crate) fn token_version_key(username: &str) -> String {
+    format!("token_version:{}", username)
+}
+
+/// 生成刷新令牌 (`{session_id}.{random}`) 及其哈希
+fn generate_refresh_token(session_id: &str) -> (String, String) {
+    let secret: [u8; 32] = rand::thread_rng().gen();
+    let token = format!("{}.{}", session_id, hex::encode(secret));
+    let hash = hash_token(&token);
+    (token, hash)
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -420,7 +741,7 @@ mod tests {
             AdminService::new(redis, "test_secret_key_at_least_32_chars_long".to_string());
 
         let token = service
-            .generate_token("admin", "admin")
+            .generate_token("admin", "admin", "session-1", 3)
             .expect("Failed to generate token");
 
         let claims = service
@@ -429,6 +750,20 @@ mod tests {
 
         assert_eq!(claims.sub, "admin");
         assert_eq!(claims.role, "admin");
+        assert_eq!(claims.sid, "session-1");
+        assert_eq!(claims.ver, 3);
+        assert_eq!(claims.exp - claims.iat, ACCESS_TOKEN_TTL_SECONDS as usize);
+    }
+
+    #[test]
+    fn test_refresh_token_format() {
+        let (token, hash) = generate_refresh_token("session-1");
+        let (session_id, secret) = token.split_once('.').unwrap();
+
+        assert_eq!(session_id, "session-1");
+        assert_eq!(secret.len(), 64);
+        assert_eq!(hash, hash_token(&token));
+        assert_ne!(generate_refresh_token("session-1").0, token);
     }
 
     #[tokio::test]
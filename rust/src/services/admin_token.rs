@@ -0,0 +1,246 @@
+use chrono::{Duration, Utc};
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::models::admin_token::{
+    is_valid_ip_entry, AdminApiToken, CreateAdminApiTokenRequest, ADMIN_API_TOKEN_PREFIX,
+};
+use crate::models::role::Permission;
+use crate::redis::RedisPool;
+use crate::services::admin::token_version_key;
+use crate::utils::error::{AppError, Result};
+
+/// 最后使用时间的写入间隔 (避免每个请求都写 Redis)
+const LAST_USED_UPDATE_INTERVAL_SECONDS: i64 = 60;
+
+/// 管理 API 令牌服务
+///
+/// - `admin_api_token:{id}` - 令牌记录
+/// - `admin_api_token_hash:{sha256}` - 哈希到 ID 的索引
+pub struct AdminApiTokenService {
+    redis: Arc<RedisPool>,
+}
+
+impl AdminApiTokenService {
+    pub fn new(redis: Arc<RedisPool>) -> Self {
+        Self { redis }
+    }
+
+    /// 创建令牌，返回记录和只显示一次的明文
+    ///
+    /// `granted` 为创建者自身拥有的权限，令牌的 scopes 不能超出该范围
+    pub async fn create_token(
+        &self,
+        request: CreateAdminApiTokenRequest,
+        granted: &[Permission],
+        created_by: &str,
+    ) -> Result<(AdminApiToken, String)> {
+        let name = request.name.trim();
+        if name.is_empty() || name.len() > 100 {
+            return Err(AppError::ValidationError(
+                "Token name must be 1-100 characters".to_string(),
+            ));
+        }
+        if request.scopes.is_empty() {
+            return Err(AppError::ValidationError(
+                "At least one scope is required".to_string(),
+            ));
+        }
+        if let Some(scope) = request.scopes.iter().find(|s| !granted.contains(s)) {
+            return Err(AppError::Forbidden(format!(
+                "Cannot grant scope {} that you do not have",
+                scope.as_str()
+            )));
+        }
+        if request.expires_at.is_some_and(|at| at <= Utc::now()) {
+            return Err(AppError::ValidationError(
+                "expiresAt must be in the future".to_string(),
+            ));
+        }
+        if let Some(entry) = request
+            .ip_allowlist
+            .iter()
+            .find(|entry| !is_valid_ip_entry(entry.trim()))
+        {
+            return Err(AppError::ValidationError(format!(
+                "Invalid IP allowlist entry: {}",
+                entry
+            )));
+        }
+
+        let mut scopes = Vec::new();
+        for scope in request.scopes {
+            if !scopes.contains(&scope) {
+                scopes.push(scope);
+            }
+        }
+
+        let secret: [u8; 32] = rand::thread_rng().gen();
+        let plaintext = format!("{}{}", ADMIN_API_TOKEN_PREFIX, hex::encode(secret));
+        let token = AdminApiToken {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            description: request.description.unwrap_or_default(),
+            scopes,
+            token_hash: hash_token(&plaintext),
+            token_prefix: plaintext[..ADMIN_API_TOKEN_PREFIX.len() + 8].to_string(),
+            ip_allowlist: request
+                .ip_allowlist
+                .iter()
+                .map(|entry| entry.trim().to_string())
+                .collect(),
+            created_by: created_by.to_string(),
+            creator_token_version: self.creator_token_version(created_by).await?,
+            created_at: Utc::now(),
+            expires_at: request.expires_at,
+            last_used_at: None,
+            last_used_ip: None,
+        };
+
+        let json = serde_json::to_string(&token)?;
+        self.redis.set(&token_key(&token.id), &json).await?;
+        self.redis
+            .set(&hash_key(&token.token_hash), &token.id)
+            .await?;
+
+        info!(
+            "🔑 Admin API token {} ({}) created by: {}",
+            token.name, token.id, created_by
+        );
+
+        Ok((token, plaintext))
+    }
+
+    /// 所有令牌 (按创建时间倒序)
+    pub async fn list_tokens(&self) -> Result<Vec<AdminApiToken>> {
+        let mut tokens = Vec::new();
+        for key in self.redis.keys("admin_api_token:*").await? {
+            if let Some(json) = self.redis.get::<String>(&key).await? {
+                match serde_json::from_str::<AdminApiToken>(&json) {
+                    Ok(token) => tokens.push(token),
+                    Err(e) => warn!("⚠️  Skipping malformed admin API token {}: {}", key, e),
+                }
+            }
+        }
+
+        tokens.sort_by_key(|token| std::cmp::Reverse(token.created_at));
+        Ok(tokens)
+    }
+
+    pub async fn get_token(&self, id: &str) -> Result<Option<AdminApiToken>> {
+        match self.redis.get::<String>(&token_key(id)).await? {
+            Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// 撤销 (删除) 令牌，立即生效
+    pub async fn revoke_token(&self, id: &str, revoked_by: &str) -> Result<AdminApiToken> {
+        let token = self
+            .get_token(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Admin API token {} not found", id)))?;
+
+        self.redis.del(&hash_key(&token.token_hash)).await?;
+        self.redis.del(&token_key(id)).await?;
+
+        info!(
+            "🗑️  Admin API token {} ({}) revoked by: {}",
+            token.name, token.id, revoked_by
+        );
+
+        Ok(token)
+    }
+
+    /// 校验令牌明文：存在、未过期、创建者的令牌版本未变化、客户端 IP 在白名单内，
+    /// 并更新最后使用信息
+    pub async fn authenticate(&self, plaintext: &str, client_ip: &str) -> Result<AdminApiToken> {
+        let invalid = || AppError::Unauthorized("Invalid admin API token".to_string());
+
+        let id = self
+            .redis
+            .get::<String>(&hash_key(&hash_token(plaintext)))
+            .await?
+            .ok_or_else(invalid)?;
+        let mut token = self.get_token(&id).await?.ok_or_else(invalid)?;
+
+        if token.is_expired() {
+            return Err(AppError::Unauthorized(
+                "Admin API token has expired".to_string(),
+            ));
+        }
+        if self.creator_token_version(&token.created_by).await? != token.creator_token_version {
+            warn!(
+                "🔒 Admin API token {} rejected: creator {} was disabled, demoted or reset",
+                token.id, token.created_by
+            );
+            return Err(AppError::Unauthorized(
+                "Admin API token was revoked with its creator's sessions".to_string(),
+            ));
+        }
+        if !token.allows_ip(client_ip) {
+            warn!(
+                "🔒 Admin API token {} used from disallowed IP: {}",
+                token.id, client_ip
+            );
+            return Err(AppError::Forbidden(
+                "Client IP is not allowed for this token".to_string(),
+            ));
+        }
+
+        let now = Utc::now();
+        let stale = token
+            .last_used_at
+            .is_none_or(|at| now - at >= Duration::seconds(LAST_USED_UPDATE_INTERVAL_SECONDS));
+        if stale || token.last_used_ip.as_deref() != Some(client_ip) {
+            token.last_used_at = Some(now);
+            token.last_used_ip = Some(client_ip.to_string());
+            if let Err(e) = self.update_last_used(&token).await {
+                warn!("⚠️  Failed to update admin API token last use: {}", e);
+            }
+        }
+
+        Ok(token)
+    }
+
+    /// 创建者当前的令牌版本 (与登录会话共用)
+    async fn creator_token_version(&self, created_by: &str) -> Result<u64> {
+        Ok(self
+            .redis
+            .get::<u64>(&token_version_key(created_by))
+            .await?
+            .unwrap_or(0))
+    }
+
+    /// 写入最后使用信息 (SET XX，并发撤销后不会重新创建记录)
+    async fn update_last_used(&self, token: &AdminApiToken) -> Result<()> {
+        let json = serde_json::to_string(token)?;
+        let mut conn = self.redis.get_connection().await?;
+        redis::cmd("SET")
+            .arg(token_key(&token.id))
+            .arg(json)
+            .arg("XX")
+            .query_async::<_, Option<String>>(&mut conn)
+            .await
+            .map_err(|e| {
+                AppError::RedisError(format!("Failed to update admin API token: {}", e))
+            })?;
+
+        Ok(())
+    }
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn token_key(id: &str) -> String {
+    format!("admin_api_token:{}", id)
+}
+
+fn hash_key(hash: &str) -> String {
+    format!("admin_api_token_hash:{}", hash)
+}
@@ -1,24 +1,333 @@
 use crate::config::Settings;
-use crate::models::api_key::{ApiKey, ApiKeyCreateOptions, ApiKeyUsageStats, ModelUsage};
+use crate::models::api_key::{
+    ApiKey, ApiKeyBulkUpdate, ApiKeyCreateOptions, ApiKeyHistoryEntry, ApiKeyUsageStats,
+    BulkOperationFailure, BulkOperationResult, ConcurrencyQueueStats, ModelUsage,
+    RateLimitWindowState, UsagePeriod,
+};
 use crate::models::usage_record::UsageRecord;
+use crate::models::usage_trend::{TrendGranularity, UsageBucket, UsageDimension};
+use crate::models::wallet::WalletOwner;
 use crate::redis::RedisPool;
+use crate::services::account_usage::record_account_usage;
+use crate::services::billing_events::{billing_event, BillingEventService};
+use crate::services::billing_multiplier::BillingMultiplierService;
+use crate::services::usage_trend::{record_usage_buckets, UsageTarget};
+use crate::services::wallet::WalletService;
+use crate::services::webhook::WebhookService;
 use crate::utils::error::{AppError, Result};
-use chrono::Utc;
+use crate::utils::metrics;
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tracing::{info, warn};
 use uuid::Uuid;
 
+/// 成本预占的过期时间（秒），与并发计数的过期时间保持一致
+const COST_RESERVATION_TTL_SECONDS: i64 = 600;
+
+/// 并发计数记录的过期时间（秒）
+const CONCURRENCY_TTL_SECONDS: i64 = 600;
+
+/// 并发排队轮询间隔（毫秒）
+const CONCURRENCY_QUEUE_POLL_INTERVAL_MS: u64 = 100;
+
+/// 排队记录超过最长等待时间后的清理宽限期（毫秒）
+const CONCURRENCY_QUEUE_STALE_GRACE_MS: i64 = 5_000;
+
+/// 全局每分钟统计的保留时间（秒），覆盖最长的实时统计窗口
+const GLOBAL_MINUTE_USAGE_TTL_SECONDS: i64 = 2 * 3600;
+
+/// 模型统计中 1 小时缓存写入 tokens 的字段 (包含在 `cache_creation_tokens` 内)
+pub(crate) const EPHEMERAL_1H_TOKENS_FIELD: &str = "ephemeral_1h_tokens";
+
+/// 更新 `last_used_at` 的最大重试次数
+const MAX_TOUCH_ATTEMPTS: usize = 3;
+
+/// Key 仍为读取时的 JSON 时写入新值并保留 TTL，返回是否写入
+const TOUCH_KEY_LUA: &str = r#"
+if redis.call('GET', KEYS[1]) ~= ARGV[1] then
+    return 0
+end
+local ttl = redis.call('PTTL', KEYS[1])
+if ttl > 0 then
+    redis.call('SET', KEYS[1], ARGV[2], 'PX', ttl)
+else
+    redis.call('SET', KEYS[1], ARGV[2])
+end
+return 1
+"#;
+
+/// 首次记录计费成本时用上游成本初始化计费字段
+///
+/// 引入计费倍率之前的统计按 1 倍计费，避免已有用量在限额检查中被清零。
+/// KEYS[1] 为 `api_key_usage:{key_id}`，其余为按模型的统计键
+const INIT_BILLED_COST_SCRIPT: &str = r#"
+local function init(key, raw_field, billed_field)
+    if redis.call('HEXISTS', key, billed_field) == 0 then
+        local value = redis.call('HGET', key, raw_field)
+        if value then
+            redis.call('HSET', key, billed_field, value)
+        end
+    end
+end
+
+init(KEYS[1], 'total_cost', 'total_billed_cost')
+init(KEYS[1], 'daily_cost', 'daily_billed_cost')
+init(KEYS[1], 'weekly_opus_cost', 'weekly_opus_billed_cost')
+for i = 2, #KEYS do
+    init(KEYS[i], 'cost', 'billed_cost')
+end
+return 1
+"#;
+
+/// 实时统计窗口上限（分钟）
+pub const MAX_REALTIME_WINDOW_MINUTES: i64 = 60;
+
+/// Key 的成本预占 Hash (字段为请求 ID)
+fn cost_reservation_key(key_id: &str) -> String {
+    format!("cost_reservation:{}", key_id)
+}
+
+/// 解析成本预占记录
+///
+/// 格式: `{cost}|{expires_at_ms}|{is_opus}`，使用钱包时追加 `|{钱包预占 Hash}`
+fn parse_cost_reservation(value: &str) -> Option<(f64, i64, bool)> {
+    let mut parts = value.split('|');
+    let cost = parts.next()?.parse().ok()?;
+    let expires_at = parts.next()?.parse().ok()?;
+    let is_opus = parts.next()? == "1";
+    Some((cost, expires_at, is_opus))
+}
+
+/// 成本预占记录中的钱包预占 Hash，未使用钱包时返回 None
+fn reservation_wallet_key(value: &str) -> Option<&str> {
+    value.splitn(4, '|').nth(3).filter(|key| !key.is_empty())
+}
+
+/// 密钥轮换默认宽限期（秒）
+pub const DEFAULT_ROTATION_GRACE_PERIOD_SECONDS: i64 = 24 * 3600;
+
+/// 每个 Key 保留的历史记录条数
+const KEY_HISTORY_MAX_ENTRIES: isize = 100;
+
+/// 旧密钥使用通知的最小间隔（秒）
+const OLD_KEY_NOTIFY_INTERVAL_SECONDS: u64 = 3600;
+
+/// 单次批量创建的最大 Key 数量
+pub const MAX_BATCH_CREATE_COUNT: usize = 500;
+
+/// 批量创建名称模板中序号补零的最大宽度
+pub const MAX_BATCH_NAME_WIDTH: usize = 10;
+
+/// 根据名称模板渲染批量创建的 Key 名称
+///
+/// 支持 `{n}` (序号) 和 `{n:3}` (补零到指定宽度，最大 `MAX_BATCH_NAME_WIDTH`)；
+/// 模板中没有占位符时在末尾追加序号
+fn render_batch_key_name(pattern: &str, index: usize) -> Result<String> {
+    if !pattern.contains("{n") {
+        return Ok(format!("{} {}", pattern, index));
+    }
+
+    let mut result = String::with_capacity(pattern.len() + 4);
+    let mut rest = pattern;
+    while let Some(start) = rest.find("{n") {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find('}') else {
+            result.push_str(&rest[start..]);
+            return Ok(result);
+        };
+
+        let spec = &after[..end];
+        let width = spec
+            .strip_prefix(':')
+            .and_then(|width| width.parse::<usize>().ok());
+        if width.is_some_and(|width| width > MAX_BATCH_NAME_WIDTH) {
+            return Err(AppError::BadRequest(format!(
+                "Name pattern width must be at most {}",
+                MAX_BATCH_NAME_WIDTH
+            )));
+        }
+        let rendered = if spec.is_empty() {
+            Some(index.to_string())
+        } else {
+            width.map(|width| format!("{:0width$}", index, width = width))
+        };
+
+        match rendered {
+            Some(value) => {
+                result.push_str(&value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                result.push_str("{n");
+                rest = after;
+            }
+        }
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// 按周期的模型统计键
+///
+/// 格式: `api_key_usage:model:{period}:{key_id}:{bucket}:{model}`
+fn period_model_usage_key(key_id: &str, period: UsagePeriod, bucket: &str, model: &str) -> String {
+    format!(
+        "api_key_usage:model:{}:{}:{}:{}",
+        period.as_str(),
+        key_id,
+        bucket,
+        model
+    )
+}
+
+/// 按日的失败请求计数 (Hash，模型 -> 次数)
+///
+/// 格式: `api_key_usage:errors:{key_id}:{date}`，日期为系统时区日期，保留时间与日模型统计相同
+fn daily_error_key(key_id: &str, date: &str) -> String {
+    format!("api_key_usage:errors:{}:{}", key_id, date)
+}
+
+/// 累加 Prometheus token 和成本计数器
+fn record_usage_metrics(key_id: &str, model: &str, usage: &ModelUsage) {
+    let key = metrics::key_label(key_id);
+    for (kind, tokens) in [
+        ("input", usage.input_tokens),
+        ("output", usage.output_tokens),
+        ("cache_creation", usage.cache_creation_tokens),
+        ("cache_read", usage.cache_read_tokens),
+    ] {
+        metrics::TOKENS.add(&[model, kind, key], tokens as f64);
+    }
+    metrics::COST.add(&[model, key], usage.cost);
+    metrics::BILLED_COST.add(&[model, key], usage.billed_cost);
+}
+
+/// 永久删除 Key 时需要清理的使用统计键模式 (`api_key_usage:{key_id}` 本身单独删除)
+fn usage_key_patterns(key_id: &str) -> Vec<String> {
+    let mut patterns = vec![
+        format!("api_key_usage:model:{}:*", key_id),
+        format!("api_key_usage:errors:{}:*", key_id),
+        format!("usage:key:{}:*", key_id),
+    ];
+    for period in [UsagePeriod::Daily, UsagePeriod::Monthly] {
+        patterns.push(period_model_usage_key(key_id, period, "*", "*"));
+    }
+    patterns
+}
+
+/// 全局每分钟统计键 (Unix 分钟)，用于实时 RPM/TPM
+fn global_minute_usage_key(minute: i64) -> String {
+    format!("usage:global:minute:{}", minute)
+}
+
+/// 从 Redis Hash 解析模型使用统计
+///
+/// 引入计费倍率之前的记录没有 `billed_cost`，按 1 倍计费即等于上游成本
+pub(crate) fn parse_model_usage(hash: &std::collections::HashMap<String, String>) -> ModelUsage {
+    let int_field = |name: &str| hash.get(name).and_then(|v| v.parse().ok()).unwrap_or(0);
+    let cost_field = |name: &str| hash.get(name).and_then(|v| v.parse::<f64>().ok());
+    let cost = cost_field("cost").unwrap_or(0.0);
+
+    ModelUsage {
+        requests: int_field("requests"),
+        input_tokens: int_field("input_tokens"),
+        output_tokens: int_field("output_tokens"),
+        cache_creation_tokens: int_field("cache_creation_tokens"),
+        cache_read_tokens: int_field("cache_read_tokens"),
+        cost,
+        billed_cost: cost_field("billed_cost").unwrap_or(cost),
+    }
+}
+
+/// 轮换后仍处于宽限期的旧密钥记录
+///
+/// 存储在 `api_key_rotated_hash:{old_hash}`，过期时间与宽限期一致
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RotatedKeyRecord {
+    key_id: String,
+    rotated_at: DateTime<Utc>,
+    grace_expires_at: DateTime<Utc>,
+    notify_on_use: bool,
+}
+
+/// 并发排队凭证
+///
+/// 未获取到槽位就被丢弃时 (超时、出错或请求被取消)，异步将请求从队列中移除
+struct QueueTicket {
+    redis: RedisPool,
+    queue_key: String,
+    request_id: String,
+    acquired: bool,
+}
+
+impl Drop for QueueTicket {
+    fn drop(&mut self) {
+        if self.acquired {
+            return;
+        }
+
+        let redis = self.redis.clone();
+        let queue_key = std::mem::take(&mut self.queue_key);
+        let request_id = std::mem::take(&mut self.request_id);
+        tokio::spawn(async move {
+            if let Err(e) = redis.zrem(&queue_key, &request_id).await {
+                warn!(
+                    "⚠️ Failed to remove {} from concurrency queue: {}",
+                    request_id, e
+                );
+            }
+        });
+    }
+}
+
 /// API Key 服务
 #[derive(Clone)]
 pub struct ApiKeyService {
     redis: RedisPool,
     config: Settings,
+    webhook_service: Option<Arc<WebhookService>>,
+    billing_events: BillingEventService,
+    billing_multipliers: BillingMultiplierService,
+    wallets: WalletService,
 }
 
 impl ApiKeyService {
     /// 创建新的 API Key 服务实例
     pub fn new(redis: RedisPool, config: Settings) -> Self {
-        Self { redis, config }
+        Self {
+            billing_events: BillingEventService::new(redis.clone(), &config.billing),
+            billing_multipliers: BillingMultiplierService::new(redis.clone()),
+            wallets: WalletService::new(redis.clone()),
+            redis,
+            config,
+            webhook_service: None,
+        }
+    }
+
+    /// 计费事件服务 (`billing:events` Stream)
+    pub fn billing_events(&self) -> &BillingEventService {
+        &self.billing_events
+    }
+
+    /// 计费倍率服务 (`billing_multipliers`)
+    pub fn billing_multipliers(&self) -> &BillingMultiplierService {
+        &self.billing_multipliers
+    }
+
+    /// 预付费钱包服务
+    pub fn wallets(&self) -> &WalletService {
+        &self.wallets
+    }
+
+    /// 设置 Webhook 服务 (用于旧密钥使用通知)
+    pub fn with_webhook_service(mut self, webhook_service: Arc<WebhookService>) -> Self {
+        self.webhook_service = Some(webhook_service);
+        self
     }
 
     /// 生成随机 API Key
@@ -116,6 +425,9 @@ impl ApiKeyService {
             permissions: options.permissions,
             token_limit: options.token_limit,
             concurrency_limit: options.concurrency_limit,
+            concurrency_queue_enabled: options.concurrency_queue_enabled,
+            concurrency_queue_max_size: options.concurrency_queue_max_size,
+            concurrency_queue_timeout_ms: options.concurrency_queue_timeout_ms,
             rate_limit_window: options.rate_limit_window,
             rate_limit_requests: options.rate_limit_requests,
             rate_limit_cost: options.rate_limit_cost,
@@ -148,6 +460,65 @@ impl ApiKeyService {
         Ok((raw_key, api_key))
     }
 
+    /// 按模板批量生成 API Keys
+    ///
+    /// # 参数
+    ///
+    /// * `template` - Key 模板，`name` 作为名称模板 (见 `render_batch_key_name`)，标签等设置由所有 Key 共享
+    /// * `count` - 生成数量 (1 ~ `MAX_BATCH_CREATE_COUNT`)
+    /// * `start_index` - 名称序号的起始值
+    ///
+    /// # 返回
+    ///
+    /// 返回 (原始key, ApiKey) 列表；任意一个 Key 创建失败时，已创建的 Key 会被回滚
+    pub async fn generate_keys_batch(
+        &self,
+        template: ApiKeyCreateOptions,
+        count: usize,
+        start_index: usize,
+    ) -> Result<Vec<(String, ApiKey)>> {
+        if count == 0 || count > MAX_BATCH_CREATE_COUNT {
+            return Err(AppError::BadRequest(format!(
+                "Batch count must be between 1 and {}",
+                MAX_BATCH_CREATE_COUNT
+            )));
+        }
+
+        let mut created: Vec<(String, ApiKey)> = Vec::with_capacity(count);
+        for offset in 0..count {
+            let mut options = template.clone();
+            options.name = render_batch_key_name(&template.name, start_index + offset)?;
+
+            match self.generate_key(options).await {
+                Ok(result) => created.push(result),
+                Err(e) => {
+                    warn!(
+                        "⚠️  Batch key creation failed after {} keys, rolling back: {}",
+                        created.len(),
+                        e
+                    );
+                    for (_, api_key) in &created {
+                        if let Err(rollback_err) = self.permanent_delete(&api_key.id).await {
+                            warn!(
+                                "⚠️  Failed to roll back batch key {}: {}",
+                                api_key.id, rollback_err
+                            );
+                        }
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        info!(
+            "🔑 Batch created {} API keys from template: {}",
+            created.len(),
+            template.name
+        );
+
+        Ok(created)
+    }
+
     /// 存储 API Key 到 Redis
     ///
     /// 存储结构:
@@ -222,6 +593,11 @@ impl ApiKeyService {
         let api_key: ApiKey = serde_json::from_str(&key_json)
             .map_err(|e| AppError::InternalError(format!("反序列化失败: {}", e)))?;
 
+        // 轮换后宽限期内的旧密钥
+        if api_key.key_hash != key_hash {
+            self.handle_rotated_key_use(&api_key, &key_hash).await;
+        }
+
         // 验证状态
         if api_key.is_deleted {
             return Err(AppError::Unauthorized(
@@ -325,6 +701,43 @@ impl ApiKeyService {
         Ok(api_keys)
     }
 
+    /// 获取属于指定用户的 API Keys (不含已删除)
+    pub async fn get_keys_by_user(&self, user_id: &str) -> Result<Vec<ApiKey>> {
+        Ok(self
+            .get_all_keys(false)
+            .await?
+            .into_iter()
+            .filter(|api_key| api_key.user_id.as_deref() == Some(user_id))
+            .collect())
+    }
+
+    /// 设置 API Key 的所属用户
+    ///
+    /// # 参数
+    ///
+    /// * `key_id` - API Key ID
+    /// * `user_id` - 新的所属用户，None 表示收回为管理员所有
+    pub async fn set_key_owner(&self, key_id: &str, user_id: Option<String>) -> Result<ApiKey> {
+        let mut api_key = self.get_key(key_id).await?;
+
+        if api_key.is_deleted {
+            return Err(AppError::BadRequest(
+                "Cannot update deleted API Key".to_string(),
+            ));
+        }
+
+        api_key.user_id = user_id;
+        api_key.updated_at = Utc::now();
+
+        let key = format!("api_key:{}", key_id);
+        let key_json = serde_json::to_string(&api_key)
+            .map_err(|e| AppError::InternalError(format!("序列化失败: {}", e)))?;
+
+        self.redis.set(&key, &key_json).await?;
+
+        Ok(api_key)
+    }
+
     /// 更新 API Key
     ///
     /// # 参数
@@ -353,6 +766,9 @@ impl ApiKeyService {
         rate_limit_requests: Option<i32>,
         rate_limit_cost: Option<f64>,
         concurrency_limit: Option<i32>,
+        concurrency_queue_enabled: Option<bool>,
+        concurrency_queue_max_size: Option<i64>,
+        concurrency_queue_timeout_ms: Option<i64>,
         daily_cost_limit: Option<f64>,
         total_cost_limit: Option<f64>,
         weekly_opus_cost_limit: Option<f64>,
@@ -428,6 +844,18 @@ impl ApiKeyService {
             api_key.concurrency_limit = limit as i64;
         }
 
+        if let Some(enabled) = concurrency_queue_enabled {
+            api_key.concurrency_queue_enabled = enabled;
+        }
+
+        if let Some(max_size) = concurrency_queue_max_size {
+            api_key.concurrency_queue_max_size = max_size;
+        }
+
+        if let Some(timeout_ms) = concurrency_queue_timeout_ms {
+            api_key.concurrency_queue_timeout_ms = timeout_ms;
+        }
+
         if let Some(limit) = daily_cost_limit {
             api_key.daily_cost_limit = limit;
         }
@@ -476,6 +904,201 @@ impl ApiKeyService {
         Ok(api_key)
     }
 
+    /// 轮换 API Key 密钥
+    ///
+    /// # 参数
+    ///
+    /// * `key_id` - API Key ID
+    /// * `grace_period_seconds` - 旧密钥宽限期 (秒)，0 表示旧密钥立即失效
+    /// * `notify_on_old_key_use` - 宽限期内使用旧密钥时是否发送 webhook 通知
+    /// * `rotated_by` - 操作者
+    ///
+    /// # 返回
+    ///
+    /// 返回元组 (新的原始key, 宽限期截止时间, ApiKey对象)
+    ///
+    /// 保留 Key ID、使用统计和账户绑定，只替换密钥；旧的 `api_key_hash:*` 映射在宽限期内仍然有效
+    pub async fn rotate_key(
+        &self,
+        key_id: &str,
+        grace_period_seconds: i64,
+        notify_on_old_key_use: bool,
+        rotated_by: &str,
+    ) -> Result<(String, DateTime<Utc>, ApiKey)> {
+        if grace_period_seconds < 0 {
+            return Err(AppError::ValidationError(
+                "Grace period must not be negative".to_string(),
+            ));
+        }
+
+        let mut api_key = self.get_key(key_id).await?;
+        if api_key.is_deleted {
+            return Err(AppError::BadRequest(
+                "Cannot rotate deleted API Key".to_string(),
+            ));
+        }
+
+        let raw_key = self.generate_random_key();
+        let new_hash = self.hash_key(&raw_key);
+        let old_hash = std::mem::replace(&mut api_key.key_hash, new_hash.clone());
+        let now = Utc::now();
+        let grace_expires_at = now + chrono::Duration::seconds(grace_period_seconds);
+        api_key.updated_at = now;
+
+        // 写入新密钥映射和更新后的 Key
+        self.store_api_key(&api_key, &new_hash).await?;
+
+        // 旧密钥映射: 宽限期内保留，否则立即删除
+        let old_hash_key = format!("api_key_hash:{}", old_hash);
+        if grace_period_seconds > 0 {
+            let record = RotatedKeyRecord {
+                key_id: key_id.to_string(),
+                rotated_at: now,
+                grace_expires_at,
+                notify_on_use: notify_on_old_key_use,
+            };
+            let record_json = serde_json::to_string(&record)
+                .map_err(|e| AppError::InternalError(format!("序列化失败: {}", e)))?;
+
+            self.redis
+                .expire(&old_hash_key, grace_period_seconds)
+                .await?;
+            self.redis
+                .setex(
+                    &format!("api_key_rotated_hash:{}", old_hash),
+                    &record_json,
+                    grace_period_seconds as u64,
+                )
+                .await?;
+        } else {
+            self.redis.del(&old_hash_key).await?;
+        }
+
+        self.append_history(
+            key_id,
+            ApiKeyHistoryEntry {
+                action: "rotated".to_string(),
+                timestamp: now,
+                actor: Some(rotated_by.to_string()),
+                details: serde_json::json!({
+                    "gracePeriodSeconds": grace_period_seconds,
+                    "graceExpiresAt": grace_expires_at,
+                    "notifyOnOldKeyUse": notify_on_old_key_use,
+                }),
+            },
+        )
+        .await?;
+
+        info!(
+            "🔄 Rotated API key {} (grace period: {}s) by {}",
+            key_id, grace_period_seconds, rotated_by
+        );
+
+        Ok((raw_key, grace_expires_at, api_key))
+    }
+
+    /// 处理宽限期内旧密钥的使用
+    ///
+    /// 记录警告日志，并在轮换时开启通知的情况下发送 webhook (每小时最多一次)
+    async fn handle_rotated_key_use(&self, api_key: &ApiKey, old_hash: &str) {
+        warn!(
+            "⚠️ API key {} used with a rotated secret (still in grace period)",
+            api_key.id
+        );
+
+        let Some(webhook_service) = self.webhook_service.clone() else {
+            return;
+        };
+
+        let record: Option<RotatedKeyRecord> = self
+            .redis
+            .get(&format!("api_key_rotated_hash:{}", old_hash))
+            .await
+            .ok()
+            .flatten()
+            .and_then(|json: String| serde_json::from_str(&json).ok());
+
+        let Some(record) = record.filter(|r| r.notify_on_use) else {
+            return;
+        };
+
+        // 限制通知频率
+        let throttle_key = format!("api_key_rotated_notified:{}", old_hash);
+        let first_notice = async {
+            let mut conn = self.redis.get_connection().await?;
+            redis::cmd("SET")
+                .arg(&throttle_key)
+                .arg(1)
+                .arg("NX")
+                .arg("EX")
+                .arg(OLD_KEY_NOTIFY_INTERVAL_SECONDS)
+                .query_async::<_, Option<String>>(&mut conn)
+                .await
+                .map_err(|e| AppError::RedisError(e.to_string()))
+        }
+        .await;
+
+        if !matches!(first_notice, Ok(Some(_))) {
+            return;
+        }
+
+        let data = serde_json::json!({
+            "keyId": api_key.id,
+            "keyName": api_key.name,
+            "rotatedAt": record.rotated_at,
+            "graceExpiresAt": record.grace_expires_at,
+        });
+        tokio::spawn(async move {
+            if let Err(e) = webhook_service
+                .trigger_event("api_key.rotated_key_used", data)
+                .await
+            {
+                warn!("⚠️ Failed to send rotated key webhook: {}", e);
+            }
+        });
+    }
+
+    /// 追加 API Key 历史记录
+    async fn append_history(&self, key_id: &str, entry: ApiKeyHistoryEntry) -> Result<()> {
+        let history_key = format!("api_key_history:{}", key_id);
+        let entry_json = serde_json::to_string(&entry)
+            .map_err(|e| AppError::InternalError(format!("序列化失败: {}", e)))?;
+
+        let mut conn = self.redis.get_connection().await?;
+        redis::pipe()
+            .atomic()
+            .lpush(&history_key, &entry_json)
+            .ltrim(&history_key, 0, KEY_HISTORY_MAX_ENTRIES - 1)
+            .query_async::<_, ()>(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to append key history: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// 获取 API Key 历史记录 (最新的在前)
+    ///
+    /// # 参数
+    ///
+    /// * `key_id` - API Key ID
+    pub async fn get_key_history(&self, key_id: &str) -> Result<Vec<ApiKeyHistoryEntry>> {
+        let history_key = format!("api_key_history:{}", key_id);
+        let mut conn = self.redis.get_connection().await?;
+
+        let entries: Vec<String> = redis::cmd("LRANGE")
+            .arg(&history_key)
+            .arg(0)
+            .arg(-1)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to get key history: {}", e)))?;
+
+        Ok(entries
+            .iter()
+            .filter_map(|json| serde_json::from_str(json).ok())
+            .collect())
+    }
+
     /// 删除 API Key (软删除)
     ///
     /// # 参数
@@ -572,13 +1195,261 @@ impl ApiKeyService {
         let hash_key = format!("api_key_hash:{}", api_key.key_hash);
         self.redis.del(&hash_key).await?;
 
-        // TODO: 删除相关的使用统计数据
-        // let usage_key = format!("api_key_usage:{}", key_id);
-        // self.redis.del(&usage_key).await?;
+        // 删除历史记录
+        let history_key = format!("api_key_history:{}", key_id);
+        self.redis.del(&history_key).await?;
+
+        // 删除宽限期内的旧密钥映射，轮换前的密钥不能再解析到已删除的 Key
+        self.delete_rotated_hashes(key_id).await?;
+
+        // 删除限流、并发、排队和成本预占状态
+        let runtime_keys = [
+            format!("rate_limit:requests:{}", key_id),
+            format!("rate_limit:window_start:{}", key_id),
+            format!("concurrency:{}", key_id),
+            format!("concurrency_queue:{}", key_id),
+            format!("concurrency_queue_stats:{}", key_id),
+            cost_reservation_key(key_id),
+        ];
+        for runtime_key in &runtime_keys {
+            self.redis.del(runtime_key).await?;
+        }
+
+        // 删除使用统计 (累计、按模型、日/月模型统计、失败计数和趋势桶)
+        let mut usage_keys = vec![format!("api_key_usage:{}", key_id)];
+        for pattern in usage_key_patterns(key_id) {
+            usage_keys.extend(self.redis.keys(&pattern).await?);
+        }
+        let mut conn = self.redis.get_connection().await?;
+        for chunk in usage_keys.chunks(500) {
+            redis::cmd("DEL")
+                .arg(chunk)
+                .query_async::<_, ()>(&mut conn)
+                .await
+                .map_err(|e| AppError::RedisError(format!("Failed to delete usage: {}", e)))?;
+        }
+
+        // 从趋势桶的成员集合中移除，排行和趋势查询不再返回已删除的 Key
+        let members_pattern = format!("usage:members:{}:*", UsageDimension::ApiKey.as_str());
+        let members_keys = self.redis.keys(&members_pattern).await?;
+        for chunk in members_keys.chunks(500) {
+            let mut pipe = redis::pipe();
+            for members_key in chunk {
+                pipe.srem(members_key, key_id).ignore();
+            }
+            pipe.query_async::<_, ()>(&mut conn).await.map_err(|e| {
+                AppError::RedisError(format!("Failed to remove usage members: {}", e))
+            })?;
+        }
+        info!(
+            "🗑️ Permanently deleted API key {} and {} usage keys",
+            key_id,
+            usage_keys.len()
+        );
+
+        Ok(())
+    }
+
+    /// 删除 Key 仍处于宽限期的旧密钥记录和哈希映射
+    async fn delete_rotated_hashes(&self, key_id: &str) -> Result<()> {
+        let record_keys = self.redis.keys("api_key_rotated_hash:*").await?;
+        let mut conn = self.redis.get_connection().await?;
+
+        for chunk in record_keys.chunks(500) {
+            let records: Vec<Option<String>> = redis::cmd("MGET")
+                .arg(chunk)
+                .query_async(&mut conn)
+                .await
+                .map_err(|e| AppError::RedisError(format!("Failed to get rotated keys: {}", e)))?;
+
+            let mut stale = Vec::new();
+            for (record_key, json) in chunk.iter().zip(records) {
+                let record =
+                    json.and_then(|json| serde_json::from_str::<RotatedKeyRecord>(&json).ok());
+                if record.is_none_or(|record| record.key_id != key_id) {
+                    continue;
+                }
+                let old_hash = record_key.trim_start_matches("api_key_rotated_hash:");
+                stale.push(record_key.clone());
+                stale.push(format!("api_key_hash:{}", old_hash));
+                stale.push(format!("api_key_rotated_notified:{}", old_hash));
+            }
+            if !stale.is_empty() {
+                redis::cmd("DEL")
+                    .arg(&stale)
+                    .query_async::<_, ()>(&mut conn)
+                    .await
+                    .map_err(|e| {
+                        AppError::RedisError(format!("Failed to delete rotated keys: {}", e))
+                    })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // ========================================
+    // 批量操作相关方法
+    // ========================================
+
+    /// 解析批量操作的目标 Key
+    ///
+    /// # 参数
+    ///
+    /// * `ids` - 显式指定的 Key ID 列表
+    /// * `tag` - 按标签选择 (不含已删除的 Key)
+    ///
+    /// # 返回
+    ///
+    /// 返回去重后的 Key ID 列表，两者都未提供时返回错误
+    pub async fn resolve_bulk_targets(
+        &self,
+        ids: &[String],
+        tag: Option<&str>,
+    ) -> Result<Vec<String>> {
+        let tag = tag.map(str::trim).filter(|t| !t.is_empty());
+        if ids.is_empty() && tag.is_none() {
+            return Err(AppError::BadRequest(
+                "Either ids or tag must be provided".to_string(),
+            ));
+        }
+
+        let mut key_ids: Vec<String> = Vec::new();
+        for id in ids {
+            if !key_ids.contains(id) {
+                key_ids.push(id.clone());
+            }
+        }
+
+        if let Some(tag) = tag {
+            for api_key in self.get_all_keys(false).await? {
+                if api_key.tags.iter().any(|t| t == tag) && !key_ids.contains(&api_key.id) {
+                    key_ids.push(api_key.id);
+                }
+            }
+        }
+
+        Ok(key_ids)
+    }
+
+    /// 批量更新 API Keys
+    ///
+    /// # 参数
+    ///
+    /// * `key_ids` - Key ID 列表
+    /// * `update` - 更新内容
+    /// * `updated_by` - 操作者
+    ///
+    /// # 返回
+    ///
+    /// 返回每个 Key 的处理结果，单个 Key 失败不影响其他 Key
+    pub async fn bulk_update_keys(
+        &self,
+        key_ids: &[String],
+        update: &ApiKeyBulkUpdate,
+        updated_by: &str,
+    ) -> BulkOperationResult {
+        let mut result = BulkOperationResult::default();
+
+        for key_id in key_ids {
+            match self.apply_bulk_update(key_id, update, updated_by).await {
+                Ok(()) => result.succeeded.push(key_id.clone()),
+                Err(e) => result.failed.push(BulkOperationFailure {
+                    id: key_id.clone(),
+                    error: e.to_string(),
+                }),
+            }
+        }
+
+        info!(
+            "📝 Bulk updated API keys: {} succeeded, {} failed",
+            result.succeeded.len(),
+            result.failed.len()
+        );
+
+        result
+    }
+
+    async fn apply_bulk_update(
+        &self,
+        key_id: &str,
+        update: &ApiKeyBulkUpdate,
+        updated_by: &str,
+    ) -> Result<()> {
+        let mut api_key = self.get_key(key_id).await?;
+
+        if api_key.is_deleted {
+            return Err(AppError::BadRequest(
+                "Cannot update deleted API Key".to_string(),
+            ));
+        }
+
+        update.apply(&mut api_key);
+        api_key.updated_at = Utc::now();
+
+        let key = format!("api_key:{}", key_id);
+        let key_json = serde_json::to_string(&api_key)
+            .map_err(|e| AppError::InternalError(format!("序列化失败: {}", e)))?;
+
+        self.redis.set(&key, &key_json).await?;
+
+        let entry = ApiKeyHistoryEntry {
+            action: "bulk_updated".to_string(),
+            timestamp: api_key.updated_at,
+            actor: Some(updated_by.to_string()),
+            details: serde_json::to_value(update).unwrap_or_default(),
+        };
+        if let Err(e) = self.append_history(key_id, entry).await {
+            warn!(
+                "⚠️  Failed to record bulk update history for {}: {}",
+                key_id, e
+            );
+        }
 
         Ok(())
     }
 
+    /// 批量启用/禁用 API Keys
+    pub async fn bulk_set_active(
+        &self,
+        key_ids: &[String],
+        is_active: bool,
+        updated_by: &str,
+    ) -> BulkOperationResult {
+        let update = ApiKeyBulkUpdate {
+            is_active: Some(is_active),
+            ..Default::default()
+        };
+        self.bulk_update_keys(key_ids, &update, updated_by).await
+    }
+
+    /// 批量删除 API Keys (软删除)
+    pub async fn bulk_delete_keys(
+        &self,
+        key_ids: &[String],
+        deleted_by: &str,
+    ) -> BulkOperationResult {
+        let mut result = BulkOperationResult::default();
+
+        for key_id in key_ids {
+            match self.delete_key(key_id, deleted_by).await {
+                Ok(()) => result.succeeded.push(key_id.clone()),
+                Err(e) => result.failed.push(BulkOperationFailure {
+                    id: key_id.clone(),
+                    error: e.to_string(),
+                }),
+            }
+        }
+
+        info!(
+            "🗑️  Bulk deleted API keys: {} succeeded, {} failed",
+            result.succeeded.len(),
+            result.failed.len()
+        );
+
+        result
+    }
+
     // ========================================
     // 使用统计相关方法
     // ========================================
@@ -594,7 +1465,14 @@ impl ApiKeyService {
     /// 成功返回 Ok(())
     ///
     /// 使用 Redis Hash + 原子操作实现并发安全的使用记录
+    ///
+    /// `cost` 为上游成本，按计费倍率同时累计计费成本 (`*_billed_cost`/`billed_cost`)，
+    /// 成本限额按计费成本检查
     pub async fn record_usage(&self, usage_record: UsageRecord) -> Result<()> {
+        let billing_record = self
+            .billing_events
+            .is_enabled()
+            .then(|| usage_record.clone());
         let UsageRecord {
             key_id,
             model,
@@ -603,12 +1481,43 @@ impl ApiKeyService {
             cache_creation_tokens,
             cache_read_tokens,
             cost,
+            account_id,
+            billing,
         } = usage_record;
+        // 1 小时缓存写入单独累计，重算成本时据此区分 5 分钟/1 小时价格
+        let ephemeral_1h_tokens = billing.as_ref().map_or(0, |b| b.ephemeral_1h_tokens);
         let usage_key = format!("api_key_usage:{}", key_id);
         let model_key = format!("api_key_usage:model:{}:{}", key_id, model);
 
+        let api_key = self.get_key(&key_id).await?;
+        let multiplier = self
+            .billing_multipliers
+            .multiplier_for(&api_key, &model)
+            .await?;
+        let billed_cost = cost * multiplier;
+
+        let now = Utc::now();
+        let now_timestamp = now.timestamp();
+        let period_keys: Vec<(UsagePeriod, String)> = [UsagePeriod::Daily, UsagePeriod::Monthly]
+            .into_iter()
+            .map(|period| {
+                let bucket = period.bucket(now, self.system_timezone());
+                let key = period_model_usage_key(&key_id, period, &bucket, &model);
+                (period, key)
+            })
+            .collect();
+
         let mut conn = self.redis.get_connection().await?;
-        let now_timestamp = Utc::now().timestamp();
+        let init_billed_script = redis::Script::new(INIT_BILLED_COST_SCRIPT);
+        let mut init_billed = init_billed_script.prepare_invoke();
+        init_billed.key(&usage_key).key(&model_key);
+        for (_, period_key) in &period_keys {
+            init_billed.key(period_key);
+        }
+        init_billed
+            .invoke_async::<_, i32>(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to init billed cost: {}", e)))?;
 
         // 使用 Redis Pipeline 执行原子操作
         // 1. 更新主要统计（使用 HINCRBY 和 HINCRBYFLOAT）
@@ -631,6 +1540,14 @@ impl ApiKeyService {
             .arg(&usage_key)
             .arg("daily_cost")
             .arg(cost)
+            .cmd("HINCRBYFLOAT")
+            .arg(&usage_key)
+            .arg("total_billed_cost")
+            .arg(billed_cost)
+            .cmd("HINCRBYFLOAT")
+            .arg(&usage_key)
+            .arg("daily_billed_cost")
+            .arg(billed_cost)
             .hset(&usage_key, "last_used_at", now_timestamp);
 
         // 如果是 Opus 模型，更新 weekly_opus_cost
@@ -638,7 +1555,11 @@ impl ApiKeyService {
             pipe.cmd("HINCRBYFLOAT")
                 .arg(&usage_key)
                 .arg("weekly_opus_cost")
-                .arg(cost);
+                .arg(cost)
+                .cmd("HINCRBYFLOAT")
+                .arg(&usage_key)
+                .arg("weekly_opus_billed_cost")
+                .arg(billed_cost);
         }
 
         // 2. 更新按模型的统计
@@ -650,24 +1571,141 @@ impl ApiKeyService {
             .cmd("HINCRBYFLOAT")
             .arg(&model_key)
             .arg("cost")
-            .arg(cost);
+            .arg(cost)
+            .cmd("HINCRBYFLOAT")
+            .arg(&model_key)
+            .arg("billed_cost")
+            .arg(billed_cost);
+        if ephemeral_1h_tokens > 0 {
+            pipe.hincr(&model_key, EPHEMERAL_1H_TOKENS_FIELD, ephemeral_1h_tokens);
+        }
+
+        // 3. 更新按周期 (日/月) 的模型统计
+        for (period, period_key) in &period_keys {
+            pipe.hincr(period_key, "requests", 1)
+                .hincr(period_key, "input_tokens", input_tokens)
+                .hincr(period_key, "output_tokens", output_tokens)
+                .hincr(period_key, "cache_creation_tokens", cache_creation_tokens)
+                .hincr(period_key, "cache_read_tokens", cache_read_tokens)
+                .cmd("HINCRBYFLOAT")
+                .arg(period_key)
+                .arg("cost")
+                .arg(cost)
+                .cmd("HINCRBYFLOAT")
+                .arg(period_key)
+                .arg("billed_cost")
+                .arg(billed_cost)
+                .expire(period_key, period.retention_seconds());
+            if ephemeral_1h_tokens > 0 {
+                pipe.hincr(period_key, EPHEMERAL_1H_TOKENS_FIELD, ephemeral_1h_tokens);
+            }
+        }
+
+        // 4. 时间序列：全局/Key/账户/模型的小时桶和日桶、账户统计，以及全局每分钟计数
+        let usage = ModelUsage {
+            requests: 1,
+            input_tokens,
+            output_tokens,
+            cache_creation_tokens,
+            cache_read_tokens,
+            cost,
+            billed_cost,
+        };
+        let target = UsageTarget {
+            key_id: &key_id,
+            model: &model,
+            account_id: account_id.as_deref(),
+        };
+        record_usage_buckets(
+            &mut pipe,
+            &target,
+            &usage,
+            now,
+            self.system_timezone(),
+            &self.config.usage_retention,
+        );
+        if let Some(account_id) = &account_id {
+            record_account_usage(
+                &mut pipe,
+                account_id,
+                &model,
+                &usage,
+                now,
+                self.system_timezone(),
+            );
+        }
+        let minute_key = global_minute_usage_key(now.timestamp() / 60);
+        pipe.hincr(&minute_key, "requests", 1)
+            .hincr(&minute_key, "tokens", usage.total_tokens())
+            .expire(&minute_key, GLOBAL_MINUTE_USAGE_TTL_SECONDS);
 
         // 执行所有操作
         pipe.query_async::<_, ()>(&mut conn)
             .await
             .map_err(|e| AppError::RedisError(format!("Failed to record usage: {}", e)))?;
+        record_usage_metrics(&key_id, &model, &usage);
 
         // 更新 API Key 的 last_used_at (这个可以容忍最终一致性)
-        let mut api_key = self.get_key(&key_id).await?;
-        api_key.last_used_at = Some(Utc::now());
-        api_key.updated_at = Utc::now();
+        self.touch_last_used(&key_id).await?;
+
+        // 预付费钱包按计费成本扣费
+        if billed_cost > 0.0 {
+            if let Some(owner) = self.wallets.resolve_wallet(&api_key).await? {
+                let request_id = billing.map(|b| b.request_id);
+                self.wallets
+                    .debit_usage(&owner, billed_cost, request_id, &model)
+                    .await?;
+            }
+        }
+
+        // 发布计费事件 (失败不影响使用量记录)
+        if let Some(record) = billing_record {
+            let event = billing_event(&record, &api_key, multiplier, now);
+            if let Err(e) = self.billing_events.publish(&event).await {
+                warn!(
+                    "⚠️ Failed to publish billing event for key {}: {}",
+                    key_id, e
+                );
+            }
+        }
 
+        Ok(())
+    }
+
+    /// 只更新 Key 的 `last_used_at`
+    ///
+    /// 对读取时的 JSON 做比较写入并保留 TTL：请求期间 Key 被禁用、轮换或修改时重新读取，
+    /// 不会用请求开始时的旧副本覆盖。多次冲突后放弃本次更新
+    async fn touch_last_used(&self, key_id: &str) -> Result<()> {
         let key = format!("api_key:{}", key_id);
-        let key_json = serde_json::to_string(&api_key)
-            .map_err(|e| AppError::InternalError(format!("序列化失败: {}", e)))?;
+        let mut conn = self.redis.get_connection().await?;
 
-        self.redis.set(&key, &key_json).await?;
+        for _ in 0..MAX_TOUCH_ATTEMPTS {
+            let Some(original) = self.redis.get::<String>(&key).await? else {
+                return Ok(());
+            };
+            let mut api_key: ApiKey = serde_json::from_str(&original)
+                .map_err(|e| AppError::InternalError(format!("反序列化失败: {}", e)))?;
+            api_key.last_used_at = Some(Utc::now());
+            let key_json = serde_json::to_string(&api_key)
+                .map_err(|e| AppError::InternalError(format!("序列化失败: {}", e)))?;
+
+            let saved: i32 = redis::Script::new(TOUCH_KEY_LUA)
+                .key(&key)
+                .arg(&original)
+                .arg(&key_json)
+                .invoke_async(&mut conn)
+                .await
+                .map_err(|e| AppError::RedisError(format!("Failed to update key: {}", e)))?;
+            if saved == 1 {
+                return Ok(());
+            }
+        }
 
+        warn!(
+            "⚠️ Skipped last_used_at update for key {} after concurrent changes",
+            key_id
+        );
         Ok(())
     }
 
@@ -739,92 +1777,597 @@ impl ApiKeyService {
             .and_then(|v| v.parse::<f64>().ok())
             .unwrap_or(0.0);
 
-        let last_used_at = hash_data
-            .get("last_used_at")
-            .and_then(|v| v.parse::<i64>().ok())
-            .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0));
+        // 还没有计费成本字段的旧统计按 1 倍计费
+        let billed_field = |name: &str, raw: f64| {
+            hash_data
+                .get(name)
+                .and_then(|v| v.parse::<f64>().ok())
+                .unwrap_or(raw)
+        };
+        let total_billed_cost = billed_field("total_billed_cost", total_cost);
+        let daily_billed_cost = billed_field("daily_billed_cost", daily_cost);
+        let weekly_opus_billed_cost = billed_field("weekly_opus_billed_cost", weekly_opus_cost);
+
+        let last_used_at = hash_data
+            .get("last_used_at")
+            .and_then(|v| v.parse::<i64>().ok())
+            .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0));
+
+        // 获取按模型的统计
+        let model_pattern = format!("api_key_usage:model:{}:*", key_id);
+        let model_keys: Vec<String> = redis::cmd("KEYS")
+            .arg(&model_pattern)
+            .query_async(&mut conn)
+            .await
+            .unwrap_or_default();
+
+        let mut usage_by_model = std::collections::HashMap::new();
+        for model_key in model_keys {
+            // 从 key 中提取模型名
+            if let Some(model_name) = model_key.split(':').next_back() {
+                let model_hash: std::collections::HashMap<String, String> = redis::cmd("HGETALL")
+                    .arg(&model_key)
+                    .query_async(&mut conn)
+                    .await
+                    .unwrap_or_default();
+
+                if !model_hash.is_empty() {
+                    usage_by_model.insert(model_name.to_string(), parse_model_usage(&model_hash));
+                }
+            }
+        }
+
+        Ok(ApiKeyUsageStats {
+            total_requests,
+            total_input_tokens,
+            total_output_tokens,
+            total_cache_creation_tokens,
+            total_cache_read_tokens,
+            total_cost,
+            daily_cost,
+            weekly_opus_cost,
+            total_billed_cost,
+            daily_billed_cost,
+            weekly_opus_billed_cost,
+            last_used_at,
+            usage_by_model,
+        })
+    }
+
+    /// 系统时区 (用于划分统计日期)
+    pub fn system_timezone(&self) -> FixedOffset {
+        self.config.server.timezone()
+    }
+
+    /// 系统时区的当前日期 (`YYYY-MM-DD`)
+    pub fn system_today(&self) -> String {
+        Utc::now()
+            .with_timezone(&self.system_timezone())
+            .format("%Y-%m-%d")
+            .to_string()
+    }
+
+    /// 多个 API Key 的累计使用量之和 (只读取汇总 Hash，不扫描模型统计)
+    pub async fn get_total_usage(&self, key_ids: &[String]) -> Result<ModelUsage> {
+        let mut totals = ModelUsage::default();
+        if key_ids.is_empty() {
+            return Ok(totals);
+        }
+
+        let mut pipe = redis::pipe();
+        for key_id in key_ids {
+            pipe.cmd("HMGET")
+                .arg(format!("api_key_usage:{}", key_id))
+                .arg("total_requests")
+                .arg("total_input_tokens")
+                .arg("total_output_tokens")
+                .arg("total_cache_creation_tokens")
+                .arg("total_cache_read_tokens")
+                .arg("total_cost");
+        }
+        let mut conn = self.redis.get_connection().await?;
+        let rows: Vec<Vec<Option<String>>> = pipe
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to get usage totals: {}", e)))?;
+
+        for row in rows {
+            let int_field = |index: usize| {
+                row.get(index)
+                    .cloned()
+                    .flatten()
+                    .and_then(|v| v.parse::<i64>().ok())
+                    .unwrap_or(0)
+            };
+            totals.requests += int_field(0);
+            totals.input_tokens += int_field(1);
+            totals.output_tokens += int_field(2);
+            totals.cache_creation_tokens += int_field(3);
+            totals.cache_read_tokens += int_field(4);
+            totals.cost += row
+                .get(5)
+                .cloned()
+                .flatten()
+                .and_then(|v| v.parse::<f64>().ok())
+                .unwrap_or(0.0);
+        }
+
+        Ok(totals)
+    }
+
+    /// 全局某日 (系统时区) 的使用量
+    pub async fn get_global_daily_usage(&self, date: &str) -> Result<ModelUsage> {
+        let mut conn = self.redis.get_connection().await?;
+        let hash: std::collections::HashMap<String, String> = redis::cmd("HGETALL")
+            .arg(
+                UsageBucket {
+                    granularity: TrendGranularity::Day,
+                    name: date.to_string(),
+                }
+                .usage_key(UsageDimension::Global, ""),
+            )
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to get daily usage: {}", e)))?;
+
+        Ok(parse_model_usage(&hash))
+    }
+
+    /// 最近 `window_minutes` 分钟 (不含当前未结束的分钟) 的全局请求数和 Token 数
+    pub async fn get_realtime_usage(&self, window_minutes: i64) -> Result<(i64, i64)> {
+        let window_minutes = window_minutes.clamp(1, MAX_REALTIME_WINDOW_MINUTES);
+        let current_minute = Utc::now().timestamp() / 60;
+
+        let mut pipe = redis::pipe();
+        for minute in (current_minute - window_minutes)..current_minute {
+            pipe.cmd("HMGET")
+                .arg(global_minute_usage_key(minute))
+                .arg("requests")
+                .arg("tokens");
+        }
+        let mut conn = self.redis.get_connection().await?;
+        let rows: Vec<(Option<i64>, Option<i64>)> = pipe
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to get realtime usage: {}", e)))?;
+
+        Ok(rows.into_iter().fold((0, 0), |(requests, tokens), (r, t)| {
+            (requests + r.unwrap_or(0), tokens + t.unwrap_or(0))
+        }))
+    }
+
+    /// 获取 API Key 在指定周期内按模型的使用统计
+    ///
+    /// # 参数
+    ///
+    /// * `key_id` - API Key ID
+    /// * `period` - 统计周期 (日/月)
+    /// * `bucket` - 统计桶 (如 `2025-01-31` / `2025-01`)，为 None 时使用当前周期
+    pub async fn get_period_model_usage(
+        &self,
+        key_id: &str,
+        period: UsagePeriod,
+        bucket: Option<&str>,
+    ) -> Result<std::collections::HashMap<String, ModelUsage>> {
+        let bucket = bucket
+            .map(str::to_string)
+            .unwrap_or_else(|| period.bucket(Utc::now(), self.system_timezone()));
+        let prefix = period_model_usage_key(key_id, period, &bucket, "");
+        let mut conn = self.redis.get_connection().await?;
+
+        let model_keys: Vec<String> = redis::cmd("KEYS")
+            .arg(format!("{}*", prefix))
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to get period usage: {}", e)))?;
+
+        let mut usage_by_model = std::collections::HashMap::new();
+        for model_key in model_keys {
+            let model_hash: std::collections::HashMap<String, String> = redis::cmd("HGETALL")
+                .arg(&model_key)
+                .query_async(&mut conn)
+                .await
+                .unwrap_or_default();
+
+            if let Some(model_name) = model_key.strip_prefix(&prefix) {
+                if !model_hash.is_empty() {
+                    usage_by_model.insert(model_name.to_string(), parse_model_usage(&model_hash));
+                }
+            }
+        }
+
+        Ok(usage_by_model)
+    }
+
+    /// 日期范围内每天 (系统时区) 各模型的使用量
+    ///
+    /// 日模型统计只保留 32 天，更早的日期没有数据
+    pub async fn get_daily_model_usage(
+        &self,
+        key_id: &str,
+        first: NaiveDate,
+        last: NaiveDate,
+    ) -> Result<BTreeMap<(NaiveDate, String), ModelUsage>> {
+        let prefix = format!("api_key_usage:model:daily:{}:", key_id);
+        let mut conn = self.redis.get_connection().await?;
+
+        let keys: Vec<String> = redis::cmd("KEYS")
+            .arg(format!("{}*", prefix))
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to get daily usage: {}", e)))?;
+
+        let mut targets = Vec::new();
+        let mut pipe = redis::pipe();
+        for key in &keys {
+            let Some((date, model)) = key
+                .strip_prefix(&prefix)
+                .and_then(|rest| rest.split_once(':'))
+            else {
+                continue;
+            };
+            let Ok(date) = NaiveDate::parse_from_str(date, "%Y-%m-%d") else {
+                continue;
+            };
+            if date < first || date > last {
+                continue;
+            }
+            targets.push((date, model.to_string()));
+            pipe.cmd("HGETALL").arg(key);
+        }
+        if targets.is_empty() {
+            return Ok(BTreeMap::new());
+        }
+
+        let hashes: Vec<std::collections::HashMap<String, String>> = pipe
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to get daily usage: {}", e)))?;
+
+        Ok(targets
+            .into_iter()
+            .zip(hashes)
+            .filter(|(_, hash)| !hash.is_empty())
+            .map(|(target, hash)| (target, parse_model_usage(&hash)))
+            .collect())
+    }
+
+    /// 记录一次失败的请求 (转发出错或被上游拒绝)
+    pub async fn record_error(&self, key_id: &str, model: &str) -> Result<()> {
+        let today = UsagePeriod::Daily.bucket(Utc::now(), self.system_timezone());
+        let key = daily_error_key(key_id, &today);
+        let mut conn = self.redis.get_connection().await?;
+
+        redis::pipe()
+            .hincr(&key, model, 1)
+            .expire(&key, UsagePeriod::Daily.retention_seconds())
+            .query_async::<_, ()>(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to record error: {}", e)))
+    }
+
+    /// 日期范围内每天 (系统时区) 各模型的失败请求数
+    pub async fn get_daily_errors(
+        &self,
+        key_id: &str,
+        first: NaiveDate,
+        last: NaiveDate,
+    ) -> Result<BTreeMap<(NaiveDate, String), i64>> {
+        let dates: Vec<NaiveDate> = first.iter_days().take_while(|d| *d <= last).collect();
+        let mut pipe = redis::pipe();
+        for date in &dates {
+            pipe.cmd("HGETALL").arg(daily_error_key(
+                key_id,
+                &date.format("%Y-%m-%d").to_string(),
+            ));
+        }
+
+        let mut conn = self.redis.get_connection().await?;
+        let hashes: Vec<std::collections::HashMap<String, i64>> = pipe
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to get daily errors: {}", e)))?;
+
+        Ok(dates
+            .into_iter()
+            .zip(hashes)
+            .flat_map(|(date, hash)| {
+                hash.into_iter()
+                    .map(move |(model, count)| ((date, model), count))
+            })
+            .collect())
+    }
+
+    /// 按原始密钥查找 API Key (用于自助查询)
+    ///
+    /// 与 `validate_key` 不同，已禁用或已过期的 Key 仍可查询，只有已删除的 Key 会被拒绝
+    pub async fn find_key_by_secret(&self, key: &str) -> Result<ApiKey> {
+        let key_hash = self.hash_key(key);
+        let hash_key = format!("api_key_hash:{}", key_hash);
+
+        let key_id: String = self
+            .redis
+            .get(&hash_key)
+            .await?
+            .ok_or_else(|| AppError::Unauthorized("Invalid API Key".to_string()))?;
+
+        let api_key = self
+            .get_key(&key_id)
+            .await
+            .map_err(|_| AppError::Unauthorized("Invalid API Key".to_string()))?;
+
+        if api_key.is_deleted {
+            return Err(AppError::Unauthorized("Invalid API Key".to_string()));
+        }
+
+        Ok(api_key)
+    }
+
+    /// 获取 API Key 当前速率限制窗口状态
+    ///
+    /// 未配置速率限制时返回 None
+    pub async fn get_rate_limit_state(
+        &self,
+        api_key: &ApiKey,
+    ) -> Result<Option<RateLimitWindowState>> {
+        let (Some(window), Some(max_requests)) =
+            (api_key.rate_limit_window, api_key.rate_limit_requests)
+        else {
+            return Ok(None);
+        };
+
+        let request_count_key = format!("rate_limit:requests:{}", api_key.id);
+        let window_start_key = format!("rate_limit:window_start:{}", api_key.id);
+
+        let window_start = self
+            .redis
+            .get::<i64>(&window_start_key)
+            .await?
+            .and_then(|ts| DateTime::from_timestamp(ts, 0))
+            .filter(|start| (Utc::now() - *start).num_seconds() < window);
+
+        // 窗口已过期时计数视为 0
+        let current_requests = match window_start {
+            Some(_) => self
+                .redis
+                .get::<i64>(&request_count_key)
+                .await?
+                .unwrap_or(0),
+            None => 0,
+        };
+
+        Ok(Some(RateLimitWindowState {
+            window_seconds: window,
+            max_requests,
+            current_requests,
+            window_start,
+            reset_at: window_start.map(|start| start + chrono::Duration::seconds(window)),
+        }))
+    }
+
+    /// 检查成本限制
+    ///
+    /// # 参数
+    ///
+    /// * `key_id` - API Key ID
+    /// * `estimated_cost` - 预估计费成本 (已乘计费倍率)
+    ///
+    /// # 返回
+    ///
+    /// 如果超过限制返回 Err,否则返回 Ok(())
+    ///
+    /// 已预占但尚未结算的在途请求费用也会计入
+    pub async fn check_cost_limits(&self, key_id: &str, estimated_cost: f64) -> Result<()> {
+        let api_key = self.get_key(key_id).await?;
+        let stats = self.get_usage_stats(key_id).await?;
+        let (pending_cost, pending_opus_cost) = self
+            .get_pending_costs(&cost_reservation_key(key_id))
+            .await?;
+
+        Self::evaluate_cost_limits(
+            &api_key,
+            &stats,
+            pending_cost + estimated_cost,
+            Some(pending_opus_cost + estimated_cost),
+        )
+    }
+
+    /// 预占请求成本（请求前成本限制检查）
+    ///
+    /// # 参数
+    ///
+    /// * `api_key` - API Key 对象
+    /// * `request_id` - 请求 ID
+    /// * `model` - 请求模型
+    /// * `estimated_cost` - 预估上游成本（输入 tokens + max_tokens 定价），预占时乘以计费倍率
+    ///
+    /// # 返回
+    ///
+    /// 如果加上在途请求后超过限制返回 Err,否则返回 Ok(())
+    ///
+    /// 先写入预占再检查，保证并发请求彼此可见；实际用量记录后需调用
+    /// `release_cost_reservation` 释放。预占带过期时间，异常中断的请求不会永久占用额度
+    ///
+    /// 使用预付费钱包的 Key 同时预占到钱包所有者名下，余额扣除共用该钱包的所有 Key
+    /// 的其他在途请求预占后用尽时返回 `InsufficientBalance`
+    pub async fn reserve_cost(
+        &self,
+        api_key: &ApiKey,
+        request_id: &str,
+        model: &str,
+        estimated_cost: f64,
+    ) -> Result<()> {
+        let wallet = self.wallets.resolve_wallet(api_key).await?;
+
+        // 未设置任何成本限制且没有钱包时无需预占
+        if api_key.total_cost_limit <= 0.0
+            && api_key.daily_cost_limit <= 0.0
+            && api_key.weekly_opus_cost_limit <= 0.0
+            && wallet.is_none()
+        {
+            return Ok(());
+        }
+
+        let estimated_cost = estimated_cost
+            * self
+                .billing_multipliers
+                .multiplier_for(api_key, model)
+                .await?;
+        let is_opus = model.to_lowercase().contains("opus");
+        let reservation_key = cost_reservation_key(&api_key.id);
+        let wallet_reservation_key = wallet.as_ref().map(WalletOwner::reservation_key);
+        let expires_at = Utc::now().timestamp_millis() + COST_RESERVATION_TTL_SECONDS * 1000;
+        let mut value = format!("{}|{}|{}", estimated_cost, expires_at, is_opus as u8);
+        if let Some(wallet_reservation_key) = &wallet_reservation_key {
+            value = format!("{}|{}", value, wallet_reservation_key);
+        }
 
-        // 获取按模型的统计
-        let model_pattern = format!("api_key_usage:model:{}:*", key_id);
-        let model_keys: Vec<String> = redis::cmd("KEYS")
-            .arg(&model_pattern)
-            .query_async(&mut conn)
+        let mut conn = self.redis.get_connection().await?;
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .hset(&reservation_key, request_id, &value)
+            .expire(&reservation_key, COST_RESERVATION_TTL_SECONDS + 60);
+        if let Some(wallet_reservation_key) = &wallet_reservation_key {
+            pipe.hset(wallet_reservation_key, request_id, &value)
+                .expire(wallet_reservation_key, COST_RESERVATION_TTL_SECONDS + 60);
+        }
+        pipe.query_async::<_, ()>(&mut conn)
             .await
-            .unwrap_or_default();
-
-        let mut usage_by_model = std::collections::HashMap::new();
-        for model_key in model_keys {
-            // 从 key 中提取模型名
-            if let Some(model_name) = model_key.split(':').next_back() {
-                let model_hash: std::collections::HashMap<String, String> = redis::cmd("HGETALL")
-                    .arg(&model_key)
-                    .query_async(&mut conn)
-                    .await
-                    .unwrap_or_default();
+            .map_err(|e| AppError::RedisError(format!("Failed to reserve cost: {}", e)))?;
+
+        let stats = self.get_usage_stats(&api_key.id).await?;
+        // 预占已写入，在途费用中已包含本次请求
+        let (pending_cost, pending_opus_cost) = self.get_pending_costs(&reservation_key).await?;
+        let pending_opus_cost = is_opus.then_some(pending_opus_cost);
+
+        let mut result =
+            Self::evaluate_cost_limits(api_key, &stats, pending_cost, pending_opus_cost);
+        if let (Ok(()), Some(owner), Some(wallet_reservation_key)) =
+            (&result, &wallet, &wallet_reservation_key)
+        {
+            let balance = self
+                .wallets
+                .get_wallet(owner)
+                .await?
+                .map_or(0.0, |w| w.balance);
+            let (wallet_pending_cost, _) = self.get_pending_costs(wallet_reservation_key).await?;
+            result = Self::evaluate_wallet_balance(balance, wallet_pending_cost - estimated_cost);
+        }
 
-                if !model_hash.is_empty() {
-                    let model_usage = ModelUsage {
-                        requests: model_hash
-                            .get("requests")
-                            .and_then(|v| v.parse().ok())
-                            .unwrap_or(0),
-                        input_tokens: model_hash
-                            .get("input_tokens")
-                            .and_then(|v| v.parse().ok())
-                            .unwrap_or(0),
-                        output_tokens: model_hash
-                            .get("output_tokens")
-                            .and_then(|v| v.parse().ok())
-                            .unwrap_or(0),
-                        cache_creation_tokens: model_hash
-                            .get("cache_creation_tokens")
-                            .and_then(|v| v.parse().ok())
-                            .unwrap_or(0),
-                        cache_read_tokens: model_hash
-                            .get("cache_read_tokens")
-                            .and_then(|v| v.parse().ok())
-                            .unwrap_or(0),
-                        cost: model_hash
-                            .get("cost")
-                            .and_then(|v| v.parse().ok())
-                            .unwrap_or(0.0),
-                    };
-                    usage_by_model.insert(model_name.to_string(), model_usage);
-                }
-            }
+        if let Err(e) = result {
+            self.release_cost_reservation(&api_key.id, request_id)
+                .await?;
+            return Err(e);
         }
 
-        Ok(ApiKeyUsageStats {
-            total_requests,
-            total_input_tokens,
-            total_output_tokens,
-            total_cache_creation_tokens,
-            total_cache_read_tokens,
-            total_cost,
-            daily_cost,
-            weekly_opus_cost,
-            last_used_at,
-            usage_by_model,
-        })
+        Ok(())
     }
 
-    /// 检查成本限制
+    /// 余额扣除其他在途请求的预占后是否仍有剩余
+    ///
+    /// 只要还有余额就放行，单次请求的实际费用可能使余额变为负数
+    fn evaluate_wallet_balance(balance: f64, pending_cost: f64) -> Result<()> {
+        let available = balance - pending_cost;
+        if available <= 0.0 {
+            return Err(AppError::InsufficientBalance(format!(
+                "Prepaid balance exhausted: balance {:.6}, reserved by in-flight requests {:.6}",
+                balance, pending_cost
+            )));
+        }
+        Ok(())
+    }
+
+    /// 释放成本预占 (同时释放钱包所有者名下的预占)
     ///
     /// # 参数
     ///
     /// * `key_id` - API Key ID
-    /// * `estimated_cost` - 预估成本
+    /// * `request_id` - 请求 ID
     ///
     /// # 返回
     ///
-    /// 如果超过限制返回 Err,否则返回 Ok(())
-    pub async fn check_cost_limits(&self, key_id: &str, estimated_cost: f64) -> Result<()> {
-        let api_key = self.get_key(key_id).await?;
-        let stats = self.get_usage_stats(key_id).await?;
+    /// 成功返回 Ok(())
+    pub async fn release_cost_reservation(&self, key_id: &str, request_id: &str) -> Result<()> {
+        let reservation_key = cost_reservation_key(key_id);
+        let mut conn = self.redis.get_connection().await?;
+
+        let (value, _): (Option<String>, ()) = redis::pipe()
+            .atomic()
+            .hget(&reservation_key, request_id)
+            .hdel(&reservation_key, request_id)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to release cost: {}", e)))?;
+
+        if let Some(wallet_reservation_key) = value.as_deref().and_then(reservation_wallet_key) {
+            redis::cmd("HDEL")
+                .arg(wallet_reservation_key)
+                .arg(request_id)
+                .query_async::<_, ()>(&mut conn)
+                .await
+                .map_err(|e| AppError::RedisError(format!("Failed to release cost: {}", e)))?;
+        }
+
+        Ok(())
+    }
+
+    /// 获取在途请求的预占费用
+    ///
+    /// `reservation_key` 为 Key 或钱包的预占 Hash。
+    /// 返回 (全部预占费用, Opus 模型预占费用)，同时清理已过期的预占
+    async fn get_pending_costs(&self, reservation_key: &str) -> Result<(f64, f64)> {
+        let mut conn = self.redis.get_connection().await?;
+
+        let reservations: std::collections::HashMap<String, String> = redis::cmd("HGETALL")
+            .arg(reservation_key)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to get reservations: {}", e)))?;
+
+        let now = Utc::now().timestamp_millis();
+        let mut expired = Vec::new();
+        let mut pending_cost = 0.0;
+        let mut pending_opus_cost = 0.0;
+
+        for (request_id, value) in &reservations {
+            match parse_cost_reservation(value) {
+                Some((cost, expires_at, is_opus)) if expires_at > now => {
+                    pending_cost += cost;
+                    if is_opus {
+                        pending_opus_cost += cost;
+                    }
+                }
+                _ => expired.push(request_id.clone()),
+            }
+        }
+
+        if !expired.is_empty() {
+            redis::cmd("HDEL")
+                .arg(reservation_key)
+                .arg(&expired)
+                .query_async::<_, ()>(&mut conn)
+                .await
+                .map_err(|e| {
+                    AppError::RedisError(format!("Failed to cleanup reservations: {}", e))
+                })?;
+        }
+
+        Ok((pending_cost, pending_opus_cost))
+    }
 
+    /// 根据已用费用和新增费用判断是否超过成本限制
+    ///
+    /// 已用费用和新增费用均为计费成本 (上游成本 × 计费倍率)。
+    /// `additional_opus_cost` 为 None 时表示非 Opus 请求，跳过每周 Opus 限制
+    fn evaluate_cost_limits(
+        api_key: &ApiKey,
+        stats: &ApiKeyUsageStats,
+        additional_cost: f64,
+        additional_opus_cost: Option<f64>,
+    ) -> Result<()> {
         // 检查总成本限制
         if api_key.total_cost_limit > 0.0 {
-            let new_total = stats.total_cost + estimated_cost;
+            let new_total = stats.total_billed_cost + additional_cost;
             if new_total > api_key.total_cost_limit {
                 return Err(AppError::RateLimitExceeded(format!(
                     "Total cost limit exceeded: {} > {}",
@@ -835,7 +2378,7 @@ impl ApiKeyService {
 
         // 检查每日成本限制
         if api_key.daily_cost_limit > 0.0 {
-            let new_daily = stats.daily_cost + estimated_cost;
+            let new_daily = stats.daily_billed_cost + additional_cost;
             if new_daily > api_key.daily_cost_limit {
                 return Err(AppError::RateLimitExceeded(format!(
                     "Daily cost limit exceeded: {} > {}",
@@ -844,10 +2387,12 @@ impl ApiKeyService {
             }
         }
 
-        // 检查每周 Opus 成本限制 (假设模型名包含 "opus")
-        if api_key.weekly_opus_cost_limit > 0.0 {
-            let new_weekly_opus = stats.weekly_opus_cost + estimated_cost;
-            if new_weekly_opus > api_key.weekly_opus_cost_limit {
+        // 检查每周 Opus 成本限制
+        if let Some(additional_opus_cost) = additional_opus_cost {
+            let new_weekly_opus = stats.weekly_opus_billed_cost + additional_opus_cost;
+            if api_key.weekly_opus_cost_limit > 0.0
+                && new_weekly_opus > api_key.weekly_opus_cost_limit
+            {
                 return Err(AppError::RateLimitExceeded(format!(
                     "Weekly Opus cost limit exceeded: {} > {}",
                     new_weekly_opus, api_key.weekly_opus_cost_limit
@@ -959,62 +2504,271 @@ impl ApiKeyService {
     /// # 返回
     ///
     /// 如果超过并发限制返回 Err,否则返回 Ok(())
+    ///
+    /// Key 启用并发排队时，超过限制的请求按 FIFO 顺序排队等待槽位释放，
+    /// 仅在队列已满或等待超时时返回 Err
     pub async fn increment_concurrency(&self, api_key: &ApiKey, request_id: &str) -> Result<()> {
         // 如果没有设置并发限制,直接返回
         if api_key.concurrency_limit == 0 {
             return Ok(());
         }
 
-        let key = format!("concurrency:{}", api_key.id);
-        let ttl_seconds = 600; // 10分钟过期
-        let expiry_time = Utc::now().timestamp_millis() + (ttl_seconds as i64 * 1000);
+        if self
+            .try_acquire_concurrency(api_key, request_id, false)
+            .await?
+        {
+            return Ok(());
+        }
 
-        let mut conn = self.redis.get_connection().await?;
+        if !api_key.concurrency_queue_enabled {
+            return Err(AppError::ConcurrencyLimitExceeded(format!(
+                "Concurrency limit exceeded: {} concurrent requests",
+                api_key.concurrency_limit
+            )));
+        }
 
-        // 清理过期的并发记录
+        self.wait_in_concurrency_queue(api_key, request_id).await
+    }
+
+    /// 尝试获取并发槽位
+    ///
+    /// `from_queue` 为 true 时只有排在队列前列 (排名小于空闲槽位数) 的请求才能获取槽位；
+    /// 为 false 时如果已有请求在排队则不允许插队
+    async fn try_acquire_concurrency(
+        &self,
+        api_key: &ApiKey,
+        request_id: &str,
+        from_queue: bool,
+    ) -> Result<bool> {
+        let key = format!("concurrency:{}", api_key.id);
+        let queue_key = format!("concurrency_queue:{}", api_key.id);
         let now = Utc::now().timestamp_millis();
-        redis::cmd("ZREMRANGEBYSCORE")
-            .arg(&key)
-            .arg("-inf")
+        let expiry_time = now + CONCURRENCY_TTL_SECONDS * 1000;
+
+        // Lua 脚本：清理过期记录、检查空闲槽位和排队顺序、占用槽位，保证原子性
+        let lua_script = r#"
+            redis.call("ZREMRANGEBYSCORE", KEYS[1], "-inf", ARGV[1])
+            local available = tonumber(ARGV[4]) - redis.call("ZCARD", KEYS[1])
+            if available <= 0 then
+                return 0
+            end
+            if ARGV[5] == "1" then
+                local rank = redis.call("ZRANK", KEYS[2], ARGV[3])
+                if (not rank) or rank >= available then
+                    return 0
+                end
+                redis.call("ZREM", KEYS[2], ARGV[3])
+            elseif redis.call("ZCARD", KEYS[2]) >= available then
+                return 0
+            end
+            redis.call("ZADD", KEYS[1], ARGV[2], ARGV[3])
+            redis.call("EXPIRE", KEYS[1], ARGV[6])
+            return 1
+        "#;
+
+        let mut conn = self.redis.get_connection().await?;
+        let acquired: i32 = redis::Script::new(lua_script)
+            .key(&key)
+            .key(&queue_key)
             .arg(now)
-            .query_async::<_, ()>(&mut conn)
+            .arg(expiry_time)
+            .arg(request_id)
+            .arg(api_key.concurrency_limit)
+            .arg(if from_queue { "1" } else { "0" })
+            .arg(CONCURRENCY_TTL_SECONDS + 60)
+            .invoke_async(&mut conn)
             .await
-            .map_err(|e| AppError::RedisError(format!("Failed to cleanup concurrency: {}", e)))?;
+            .map_err(|e| AppError::RedisError(format!("Failed to increment concurrency: {}", e)))?;
 
-        // 检查当前并发数
-        let current_count: i64 = redis::cmd("ZCARD")
-            .arg(&key)
-            .query_async(&mut conn)
-            .await
-            .map_err(|e| AppError::RedisError(format!("Failed to get concurrency count: {}", e)))?;
+        Ok(acquired == 1)
+    }
 
-        if current_count >= api_key.concurrency_limit {
-            return Err(AppError::RateLimitExceeded(format!(
-                "Concurrency limit exceeded: {} concurrent requests",
-                api_key.concurrency_limit
+    /// 在并发队列中排队等待槽位
+    async fn wait_in_concurrency_queue(&self, api_key: &ApiKey, request_id: &str) -> Result<()> {
+        let queue_key = format!("concurrency_queue:{}", api_key.id);
+        let stats_key = format!("concurrency_queue_stats:{}", api_key.id);
+        let timeout_ms = api_key.concurrency_queue_timeout_ms.max(0);
+        let started_at = Utc::now().timestamp_millis();
+        // 超过最长等待时间仍留在队列中的记录视为异常退出的请求
+        let stale_before = started_at - timeout_ms - CONCURRENCY_QUEUE_STALE_GRACE_MS;
+
+        let lua_script = r#"
+            redis.call("ZREMRANGEBYSCORE", KEYS[1], "-inf", ARGV[4])
+            if redis.call("ZCARD", KEYS[1]) >= tonumber(ARGV[3]) then
+                return 0
+            end
+            redis.call("ZADD", KEYS[1], ARGV[1], ARGV[2])
+            redis.call("EXPIRE", KEYS[1], ARGV[5])
+            return 1
+        "#;
+
+        let mut conn = self.redis.get_connection().await?;
+        let enqueued: i32 = redis::Script::new(lua_script)
+            .key(&queue_key)
+            .arg(started_at)
+            .arg(request_id)
+            .arg(api_key.concurrency_queue_max_size)
+            .arg(stale_before)
+            .arg(timeout_ms / 1000 + CONCURRENCY_TTL_SECONDS)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to enqueue request: {}", e)))?;
+        drop(conn);
+
+        if enqueued == 0 {
+            self.incr_queue_stat(&stats_key, "total_rejected", 1).await;
+            return Err(AppError::ConcurrencyLimitExceeded(format!(
+                "Concurrency queue is full: {} requests already waiting for {} concurrent slots",
+                api_key.concurrency_queue_max_size, api_key.concurrency_limit
             )));
         }
 
-        // 添加到 Sorted Set
-        redis::cmd("ZADD")
-            .arg(&key)
-            .arg(expiry_time)
-            .arg(request_id)
-            .query_async::<_, ()>(&mut conn)
+        self.incr_queue_stat(&stats_key, "total_queued", 1).await;
+        info!(
+            "⏳ Request {} queued for concurrency slot (key: {})",
+            request_id, api_key.id
+        );
+
+        // 请求被取消 (如客户端断开) 时从队列中移除，避免阻塞后续请求
+        let mut ticket = QueueTicket {
+            redis: self.redis.clone(),
+            queue_key,
+            request_id: request_id.to_string(),
+            acquired: false,
+        };
+
+        loop {
+            tokio::time::sleep(std::time::Duration::from_millis(
+                CONCURRENCY_QUEUE_POLL_INTERVAL_MS,
+            ))
+            .await;
+
+            let waited_ms = Utc::now().timestamp_millis() - started_at;
+
+            if self
+                .try_acquire_concurrency(api_key, request_id, true)
+                .await?
+            {
+                ticket.acquired = true;
+                self.record_queue_wait(&stats_key, waited_ms).await;
+                info!(
+                    "✅ Request {} acquired concurrency slot after {}ms (key: {})",
+                    request_id, waited_ms, api_key.id
+                );
+                return Ok(());
+            }
+
+            if waited_ms >= timeout_ms {
+                self.incr_queue_stat(&stats_key, "total_timeouts", 1).await;
+                warn!(
+                    "⏰ Request {} timed out waiting for concurrency slot (key: {})",
+                    request_id, api_key.id
+                );
+                return Err(AppError::ConcurrencyLimitExceeded(format!(
+                    "Timed out after {}ms waiting for one of {} concurrent slots",
+                    timeout_ms, api_key.concurrency_limit
+                )));
+            }
+        }
+    }
+
+    /// 记录排队等待时间
+    async fn record_queue_wait(&self, stats_key: &str, waited_ms: i64) {
+        self.incr_queue_stat(stats_key, "total_acquired", 1).await;
+        self.incr_queue_stat(stats_key, "total_wait_ms", waited_ms)
+            .await;
+
+        // 更新最长等待时间 (统计数据允许轻微误差)
+        let max_wait_ms: i64 = self
+            .redis
+            .hget::<String>(stats_key, "max_wait_ms")
             .await
-            .map_err(|e| AppError::RedisError(format!("Failed to increment concurrency: {}", e)))?;
+            .ok()
+            .flatten()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
+        if waited_ms > max_wait_ms {
+            let _ = self
+                .redis
+                .hset(stats_key, "max_wait_ms", &waited_ms.to_string())
+                .await;
+        }
+    }
 
-        // 设置 key 过期时间
-        redis::cmd("EXPIRE")
+    /// 累加排队统计字段 (统计失败不影响请求)
+    async fn incr_queue_stat(&self, stats_key: &str, field: &str, delta: i64) {
+        let result = async {
+            let mut conn = self.redis.get_connection().await?;
+            redis::cmd("HINCRBY")
+                .arg(stats_key)
+                .arg(field)
+                .arg(delta)
+                .query_async::<_, ()>(&mut conn)
+                .await
+                .map_err(|e| AppError::RedisError(e.to_string()))
+        }
+        .await;
+
+        if let Err(e) = result {
+            warn!("⚠️ Failed to update concurrency queue stats: {}", e);
+        }
+    }
+
+    /// 获取并发排队统计
+    ///
+    /// # 参数
+    ///
+    /// * `key_id` - API Key ID
+    ///
+    /// # 返回
+    ///
+    /// 返回当前并发数、排队长度以及累计排队/等待统计
+    pub async fn get_concurrency_queue_stats(&self, key_id: &str) -> Result<ConcurrencyQueueStats> {
+        let key = format!("concurrency:{}", key_id);
+        let queue_key = format!("concurrency_queue:{}", key_id);
+        let stats_key = format!("concurrency_queue_stats:{}", key_id);
+        let now = Utc::now().timestamp_millis();
+
+        let mut conn = self.redis.get_connection().await?;
+        let (active_requests, queue_depth, stats): (
+            i64,
+            i64,
+            std::collections::HashMap<String, String>,
+        ) = redis::pipe()
+            .cmd("ZCOUNT")
             .arg(&key)
-            .arg(ttl_seconds + 60)
-            .query_async::<_, ()>(&mut conn)
+            .arg(now)
+            .arg("+inf")
+            .cmd("ZCARD")
+            .arg(&queue_key)
+            .cmd("HGETALL")
+            .arg(&stats_key)
+            .query_async(&mut conn)
             .await
             .map_err(|e| {
-                AppError::RedisError(format!("Failed to set concurrency expiry: {}", e))
+                AppError::RedisError(format!("Failed to get concurrency queue stats: {}", e))
             })?;
 
-        Ok(())
+        let field =
+            |name: &str| -> i64 { stats.get(name).and_then(|v| v.parse().ok()).unwrap_or(0) };
+
+        let total_acquired = field("total_acquired");
+        let avg_wait_ms = if total_acquired > 0 {
+            field("total_wait_ms") as f64 / total_acquired as f64
+        } else {
+            0.0
+        };
+
+        Ok(ConcurrencyQueueStats {
+            active_requests,
+            queue_depth,
+            total_queued: field("total_queued"),
+            total_acquired,
+            total_rejected: field("total_rejected"),
+            total_timeouts: field("total_timeouts"),
+            avg_wait_ms,
+            max_wait_ms: field("max_wait_ms"),
+        })
     }
 
     /// 减少并发计数
@@ -1054,11 +2808,13 @@ impl ApiKeyService {
         let usage_key = format!("api_key_usage:{}", key_id);
         let mut conn = self.redis.get_connection().await?;
 
-        // 使用 HSET 将 daily_cost 重置为 0
+        // 使用 HSET 将 daily_cost 和 daily_billed_cost 重置为 0
         redis::cmd("HSET")
             .arg(&usage_key)
             .arg("daily_cost")
             .arg("0")
+            .arg("daily_billed_cost")
+            .arg("0")
             .query_async::<_, ()>(&mut conn)
             .await
             .map_err(|e| AppError::RedisError(format!("Failed to reset daily stats: {}", e)))?;
@@ -1079,11 +2835,13 @@ impl ApiKeyService {
         let usage_key = format!("api_key_usage:{}", key_id);
         let mut conn = self.redis.get_connection().await?;
 
-        // 使用 HSET 将 weekly_opus_cost 重置为 0
+        // 使用 HSET 将 weekly_opus_cost 和 weekly_opus_billed_cost 重置为 0
         redis::cmd("HSET")
             .arg(&usage_key)
             .arg("weekly_opus_cost")
             .arg("0")
+            .arg("weekly_opus_billed_cost")
+            .arg("0")
             .query_async::<_, ()>(&mut conn)
             .await
             .map_err(|e| AppError::RedisError(format!("Failed to reset weekly stats: {}", e)))?;
@@ -1109,6 +2867,54 @@ mod tests {
         (service, config)
     }
 
+    #[test]
+    fn test_render_batch_key_name() {
+        let render = |pattern: &str, index: usize| render_batch_key_name(pattern, index).unwrap();
+        assert_eq!(render("Class A - {n}", 7), "Class A - 7");
+        assert_eq!(render("student-{n:3}", 7), "student-007");
+        assert_eq!(render("Team", 2), "Team 2");
+        assert_eq!(render("{n}/{n:2}", 3), "3/03");
+        assert_eq!(render("bad-{n:x}", 1), "bad-{n:x}");
+        assert_eq!(render("open-{n", 1), "open-{n");
+        assert_eq!(render("wide-{n:10}", 1), "wide-0000000001");
+        assert!(matches!(
+            render_batch_key_name("huge-{n:4000000000}", 1),
+            Err(AppError::BadRequest(_))
+        ));
+    }
+
+    #[test]
+    fn test_period_model_usage_key() {
+        let key =
+            period_model_usage_key("key-1", UsagePeriod::Daily, "2025-01-31", "claude-3-opus");
+        assert_eq!(
+            key,
+            "api_key_usage:model:daily:key-1:2025-01-31:claude-3-opus"
+        );
+
+        let mut hash = std::collections::HashMap::new();
+        hash.insert("requests".to_string(), "3".to_string());
+        hash.insert("cost".to_string(), "0.25".to_string());
+        let usage = parse_model_usage(&hash);
+        assert_eq!(usage.requests, 3);
+        assert_eq!(usage.input_tokens, 0);
+        assert_eq!(usage.cost, 0.25);
+    }
+
+    #[test]
+    fn test_usage_key_patterns() {
+        let patterns = usage_key_patterns("key-1");
+        for pattern in [
+            "api_key_usage:model:key-1:*",
+            "api_key_usage:model:daily:key-1:*:*",
+            "api_key_usage:model:monthly:key-1:*:*",
+            "api_key_usage:errors:key-1:*",
+            "usage:key:key-1:*",
+        ] {
+            assert!(patterns.iter().any(|p| p == pattern), "missing {}", pattern);
+        }
+    }
+
     #[test]
     fn test_hash_key_consistency() {
         let (service, _config) = create_mock_service();
@@ -1175,6 +2981,9 @@ mod tests {
             permissions: ApiKeyPermissions::All,
             token_limit: 0,
             concurrency_limit: 0,
+            concurrency_queue_enabled: false,
+            concurrency_queue_max_size: 10,
+            concurrency_queue_timeout_ms: 30_000,
             rate_limit_window: None,
             rate_limit_requests: None,
             rate_limit_cost: None,
@@ -1231,6 +3040,9 @@ mod tests {
             permissions: ApiKeyPermissions::Claude,
             token_limit: 0,
             concurrency_limit: 0,
+            concurrency_queue_enabled: false,
+            concurrency_queue_max_size: 10,
+            concurrency_queue_timeout_ms: 30_000,
             rate_limit_window: None,
             rate_limit_requests: None,
             rate_limit_cost: None,
@@ -1289,6 +3101,9 @@ mod tests {
             permissions: ApiKeyPermissions::Gemini,
             token_limit: 0,
             concurrency_limit: 0,
+            concurrency_queue_enabled: false,
+            concurrency_queue_max_size: 10,
+            concurrency_queue_timeout_ms: 30_000,
             rate_limit_window: None,
             rate_limit_requests: None,
             rate_limit_cost: None,
@@ -1346,6 +3161,9 @@ mod tests {
             permissions: ApiKeyPermissions::OpenAI,
             token_limit: 0,
             concurrency_limit: 0,
+            concurrency_queue_enabled: false,
+            concurrency_queue_max_size: 10,
+            concurrency_queue_timeout_ms: 30_000,
             rate_limit_window: None,
             rate_limit_requests: None,
             rate_limit_cost: None,
@@ -1440,6 +3258,9 @@ mod tests {
             permissions: ApiKeyPermissions::All,
             token_limit: 0,
             concurrency_limit: 0,
+            concurrency_queue_enabled: false,
+            concurrency_queue_max_size: 10,
+            concurrency_queue_timeout_ms: 30_000,
             rate_limit_window: None,
             rate_limit_requests: None,
             rate_limit_cost: None,
@@ -1487,4 +3308,168 @@ mod tests {
         assert_eq!(fixed_json, "fixed");
         assert_eq!(activation_json, "activation");
     }
+
+    fn create_cost_limited_key(total: f64, daily: f64, weekly_opus: f64) -> ApiKey {
+        ApiKey {
+            id: "test".to_string(),
+            key: None,
+            key_hash: "hash".to_string(),
+            name: "test".to_string(),
+            description: None,
+            icon: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            expires_at: None,
+            activated_at: None,
+            last_used_at: None,
+            is_active: true,
+            is_deleted: false,
+            deleted_at: None,
+            deleted_by: None,
+            deleted_by_type: None,
+            permissions: ApiKeyPermissions::All,
+            token_limit: 0,
+            concurrency_limit: 0,
+            concurrency_queue_enabled: false,
+            concurrency_queue_max_size: 10,
+            concurrency_queue_timeout_ms: 30_000,
+            rate_limit_window: None,
+            rate_limit_requests: None,
+            rate_limit_cost: None,
+            daily_cost_limit: daily,
+            total_cost_limit: total,
+            weekly_opus_cost_limit: weekly_opus,
+            enable_model_restriction: false,
+            restricted_models: Vec::new(),
+            enable_client_restriction: false,
+            allowed_clients: Vec::new(),
+            tags: Vec::new(),
+            expiration_mode: ExpirationMode::Fixed,
+            activation_days: 0,
+            activation_unit: crate::models::api_key::ActivationUnit::Days,
+            claude_account_id: None,
+            claude_console_account_id: None,
+            gemini_account_id: None,
+            openai_account_id: None,
+            azure_openai_account_id: None,
+            bedrock_account_id: None,
+            droid_account_id: None,
+            user_id: None,
+            created_by: None,
+            created_by_type: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_cost_reservation() {
+        assert_eq!(
+            parse_cost_reservation("0.25|1700000000000|1"),
+            Some((0.25, 1_700_000_000_000, true))
+        );
+        assert_eq!(
+            parse_cost_reservation("0.1|1700000000000|0"),
+            Some((0.1, 1_700_000_000_000, false))
+        );
+        assert_eq!(parse_cost_reservation("invalid"), None);
+
+        let value = "0.25|1700000000000|1|wallet_reservation:user:u1";
+        assert_eq!(
+            parse_cost_reservation(value),
+            Some((0.25, 1_700_000_000_000, true))
+        );
+        assert_eq!(
+            reservation_wallet_key(value),
+            Some("wallet_reservation:user:u1")
+        );
+        assert_eq!(reservation_wallet_key("0.1|1700000000000|0"), None);
+    }
+
+    #[test]
+    fn test_evaluate_cost_limits_includes_pending_cost() {
+        let api_key = create_cost_limited_key(10.0, 0.0, 0.0);
+        let stats = ApiKeyUsageStats {
+            total_billed_cost: 9.0,
+            ..Default::default()
+        };
+
+        assert!(ApiKeyService::evaluate_cost_limits(&api_key, &stats, 0.5, None).is_ok());
+        // 在途请求费用叠加后超过总限额
+        assert!(ApiKeyService::evaluate_cost_limits(&api_key, &stats, 1.5, None).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_cost_limits_daily() {
+        let api_key = create_cost_limited_key(0.0, 2.0, 0.0);
+        let stats = ApiKeyUsageStats {
+            total_billed_cost: 100.0,
+            daily_billed_cost: 1.9,
+            ..Default::default()
+        };
+
+        assert!(ApiKeyService::evaluate_cost_limits(&api_key, &stats, 0.05, None).is_ok());
+        assert!(ApiKeyService::evaluate_cost_limits(&api_key, &stats, 0.2, None).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_cost_limits_weekly_opus_only_for_opus() {
+        let api_key = create_cost_limited_key(0.0, 0.0, 5.0);
+        let stats = ApiKeyUsageStats {
+            weekly_opus_billed_cost: 5.0,
+            ..Default::default()
+        };
+
+        // 非 Opus 请求不受每周 Opus 限额影响
+        assert!(ApiKeyService::evaluate_cost_limits(&api_key, &stats, 1.0, None).is_ok());
+        assert!(ApiKeyService::evaluate_cost_limits(&api_key, &stats, 1.0, Some(1.0)).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_wallet_balance() {
+        assert!(ApiKeyService::evaluate_wallet_balance(1.0, 0.0).is_ok());
+        assert!(ApiKeyService::evaluate_wallet_balance(1.0, 0.5).is_ok());
+        // 在途请求已占满余额
+        assert!(matches!(
+            ApiKeyService::evaluate_wallet_balance(1.0, 1.0),
+            Err(AppError::InsufficientBalance(_))
+        ));
+        assert!(ApiKeyService::evaluate_wallet_balance(-0.2, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_cost_limits_uses_billed_cost() {
+        let api_key = create_cost_limited_key(10.0, 2.0, 0.0);
+        // 上游成本已超过限额，但按倍率计费后的成本未超过
+        let stats = ApiKeyUsageStats {
+            total_cost: 12.0,
+            daily_cost: 3.0,
+            total_billed_cost: 6.0,
+            daily_billed_cost: 1.5,
+            ..Default::default()
+        };
+
+        assert!(ApiKeyService::evaluate_cost_limits(&api_key, &stats, 0.4, None).is_ok());
+        assert!(ApiKeyService::evaluate_cost_limits(&api_key, &stats, 0.6, None).is_err());
+    }
+
+    #[test]
+    fn test_rotated_key_record_roundtrip() {
+        let now = Utc::now();
+        let record = RotatedKeyRecord {
+            key_id: "key-1".to_string(),
+            rotated_at: now,
+            grace_expires_at: now
+                + chrono::Duration::seconds(DEFAULT_ROTATION_GRACE_PERIOD_SECONDS),
+            notify_on_use: true,
+        };
+
+        let json = serde_json::to_string(&record).unwrap();
+        let parsed: RotatedKeyRecord = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed.key_id, "key-1");
+        assert!(parsed.notify_on_use);
+        assert_eq!(
+            (parsed.grace_expires_at - parsed.rotated_at).num_seconds(),
+            DEFAULT_ROTATION_GRACE_PERIOD_SECONDS
+        );
+    }
 }
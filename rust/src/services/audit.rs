@@ -0,0 +1,273 @@
+use chrono::{Duration, Utc};
+use serde_json::{json, Map, Value as JsonValue};
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::models::audit::{AuditLogEntry, AuditLogQuery};
+use crate::redis::RedisPool;
+use crate::utils::error::{AppError, Result};
+
+/// 审计日志保留天数
+pub const AUDIT_LOG_RETENTION_DAYS: i64 = 90;
+
+/// 审计日志最多保留条数 (超出时删除最旧的记录)
+pub const AUDIT_LOG_MAX_ENTRIES: isize = 100_000;
+
+/// 单次查询最多返回条数
+pub const AUDIT_LOG_MAX_PAGE_SIZE: usize = 500;
+
+const AUDIT_LOG_KEY: &str = "audit_log";
+
+/// 按时间倒序扫描时每批读取的条数
+const SCAN_BATCH_SIZE: isize = 1000;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// 审计日志服务
+pub struct AuditService {
+    redis: Arc<RedisPool>,
+}
+
+impl AuditService {
+    pub fn new(redis: Arc<RedisPool>) -> Self {
+        Self { redis }
+    }
+
+    /// 追加一条审计日志，并按保留天数和最大条数清理旧记录
+    pub async fn record(&self, entry: &AuditLogEntry) -> Result<()> {
+        let json = serde_json::to_string(entry)
+            .map_err(|e| AppError::InternalError(format!("序列化失败: {}", e)))?;
+        let cutoff = Utc::now() - Duration::days(AUDIT_LOG_RETENTION_DAYS);
+
+        let mut conn = self.redis.get_connection().await?;
+        redis::pipe()
+            .atomic()
+            .cmd("ZADD")
+            .arg(AUDIT_LOG_KEY)
+            .arg(entry.timestamp.timestamp_millis())
+            .arg(&json)
+            .ignore()
+            .cmd("ZREMRANGEBYSCORE")
+            .arg(AUDIT_LOG_KEY)
+            .arg("-inf")
+            .arg(format!("({}", cutoff.timestamp_millis()))
+            .ignore()
+            .cmd("ZREMRANGEBYRANK")
+            .arg(AUDIT_LOG_KEY)
+            .arg(0)
+            .arg(-(AUDIT_LOG_MAX_ENTRIES + 1))
+            .ignore()
+            .query_async::<_, ()>(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to write audit log: {}", e)))
+    }
+
+    /// 按条件分页查询 (时间倒序)
+    pub async fn query(&self, query: &AuditLogQuery) -> Result<Vec<AuditLogEntry>> {
+        let limit = query.limit.unwrap_or(50).clamp(1, AUDIT_LOG_MAX_PAGE_SIZE);
+        self.scan(query, query.offset.unwrap_or(0), Some(limit))
+            .await
+    }
+
+    /// 导出所有匹配的记录 (时间倒序，忽略分页参数)
+    pub async fn export(&self, query: &AuditLogQuery) -> Result<Vec<AuditLogEntry>> {
+        self.scan(query, 0, None).await
+    }
+
+    async fn scan(
+        &self,
+        query: &AuditLogQuery,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Result<Vec<AuditLogEntry>> {
+        let max = query
+            .until
+            .map(|t| t.timestamp_millis().to_string())
+            .unwrap_or_else(|| "+inf".to_string());
+        let min = query
+            .since
+            .map(|t| t.timestamp_millis().to_string())
+            .unwrap_or_else(|| "-inf".to_string());
+
+        let mut conn = self.redis.get_connection().await?;
+        let mut entries = Vec::new();
+        let mut skipped = 0;
+        let mut cursor = 0;
+
+        loop {
+            let batch: Vec<String> = redis::cmd("ZREVRANGEBYSCORE")
+                .arg(AUDIT_LOG_KEY)
+                .arg(&max)
+                .arg(&min)
+                .arg("LIMIT")
+                .arg(cursor)
+                .arg(SCAN_BATCH_SIZE)
+                .query_async(&mut conn)
+                .await
+                .map_err(|e| AppError::RedisError(format!("Failed to read audit log: {}", e)))?;
+            let batch_len = batch.len() as isize;
+
+            for json in batch {
+                let entry = match serde_json::from_str::<AuditLogEntry>(&json) {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        warn!("⚠️  Skipping malformed audit log entry: {}", e);
+                        continue;
+                    }
+                };
+                if !query.matches(&entry) {
+                    continue;
+                }
+                if skipped < offset {
+                    skipped += 1;
+                    continue;
+                }
+
+                entries.push(entry);
+                if limit.is_some_and(|limit| entries.len() >= limit) {
+                    return Ok(entries);
+                }
+            }
+
+            if batch_len < SCAN_BATCH_SIZE {
+                return Ok(entries);
+            }
+            cursor += SCAN_BATCH_SIZE;
+        }
+    }
+}
+
+/// 计算变更内容并脱敏
+///
+/// - 创建：`{"after": {...}}`
+/// - 删除：`{"before": {...}}`
+/// - 更新 (均为对象)：只包含变化的字段 `{"field": {"before": .., "after": ..}}`
+///
+/// 没有任何变化时返回 None
+pub fn diff_changes(before: Option<&JsonValue>, after: Option<&JsonValue>) -> Option<JsonValue> {
+    match (before, after) {
+        (Some(JsonValue::Object(before)), Some(JsonValue::Object(after))) => {
+            let mut changes = Map::new();
+            let fields = before
+                .keys()
+                .chain(after.keys().filter(|k| !before.contains_key(*k)));
+            for field in fields {
+                let old = before.get(field).unwrap_or(&JsonValue::Null);
+                let new = after.get(field).unwrap_or(&JsonValue::Null);
+                if old == new {
+                    continue;
+                }
+
+                let change = if is_secret_field(field) {
+                    json!({ "before": REDACTED, "after": REDACTED })
+                } else {
+                    json!({ "before": redact(old), "after": redact(new) })
+                };
+                changes.insert(field.clone(), change);
+            }
+
+            (!changes.is_empty()).then_some(JsonValue::Object(changes))
+        }
+        (None, None) => None,
+        (before, after) => {
+            let mut changes = Map::new();
+            if let Some(before) = before {
+                changes.insert("before".to_string(), redact(before));
+            }
+            if let Some(after) = after {
+                changes.insert("after".to_string(), redact(after));
+            }
+            Some(JsonValue::Object(changes))
+        }
+    }
+}
+
+/// 递归替换敏感字段的值
+pub fn redact(value: &JsonValue) -> JsonValue {
+    match value {
+        JsonValue::Object(map) => JsonValue::Object(
+            map.iter()
+                .map(|(field, value)| {
+                    let value = if is_secret_field(field) && !value.is_null() {
+                        JsonValue::String(REDACTED.to_string())
+                    } else {
+                        redact(value)
+                    };
+                    (field.clone(), value)
+                })
+                .collect(),
+        ),
+        JsonValue::Array(items) => JsonValue::Array(items.iter().map(redact).collect()),
+        other => other.clone(),
+    }
+}
+
+/// 密钥、密码、令牌、双因素验证码、OAuth 数据和代理配置 (含代理密码) 视为敏感字段
+fn is_secret_field(field: &str) -> bool {
+    let normalized: String = field
+        .chars()
+        .filter(|c| *c != '_' && *c != '-')
+        .collect::<String>()
+        .to_lowercase();
+
+    matches!(
+        normalized.as_str(),
+        "key" | "keyhash" | "apikey" | "claudeaioauth" | "proxy" | "credentials"
+    ) || normalized.contains("password")
+        || normalized.contains("totp")
+        || normalized.contains("recoverycode")
+        || normalized.contains("secret")
+        || normalized.contains("privatekey")
+        || normalized.ends_with("token")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_secret_fields() {
+        let value = json!({
+            "name": "prod",
+            "key": "cr_abc",
+            "key_hash": "deadbeef",
+            "tokenLimit": 1000,
+            "oauth": { "accessToken": "at", "refresh_token": "rt", "scopes": "user" },
+            "proxy": "{\"password\":\"p\"}",
+            "items": [{ "password": "x" }],
+            "totpCode": "123456"
+        });
+
+        let redacted = redact(&value);
+        assert_eq!(redacted["name"], "prod");
+        assert_eq!(redacted["key"], REDACTED);
+        assert_eq!(redacted["key_hash"], REDACTED);
+        assert_eq!(redacted["tokenLimit"], 1000);
+        assert_eq!(redacted["oauth"]["accessToken"], REDACTED);
+        assert_eq!(redacted["oauth"]["refresh_token"], REDACTED);
+        assert_eq!(redacted["oauth"]["scopes"], "user");
+        assert_eq!(redacted["proxy"], REDACTED);
+        assert_eq!(redacted["items"][0]["password"], REDACTED);
+        assert_eq!(redacted["totpCode"], REDACTED);
+    }
+
+    #[test]
+    fn test_diff_changes() {
+        let before = json!({ "name": "a", "isActive": true, "key_hash": "h1", "tags": ["x"] });
+        let after = json!({ "name": "b", "isActive": true, "key_hash": "h2", "tags": ["x"] });
+
+        let changes = diff_changes(Some(&before), Some(&after)).unwrap();
+        assert_eq!(changes["name"], json!({ "before": "a", "after": "b" }));
+        assert_eq!(changes["key_hash"]["after"], REDACTED);
+        assert!(changes.get("isActive").is_none());
+        assert!(changes.get("tags").is_none());
+
+        assert!(diff_changes(Some(&before), Some(&before)).is_none());
+        assert!(diff_changes(None, None).is_none());
+
+        let created = diff_changes(None, Some(&after)).unwrap();
+        assert_eq!(created["after"]["name"], "b");
+        assert_eq!(created["after"]["key_hash"], REDACTED);
+        assert!(created.get("before").is_none());
+    }
+}
@@ -430,6 +430,8 @@ mod tests {
                 host: "0.0.0.0".to_string(),
                 port: 8080,
                 request_timeout: 600000,
+                timezone_offset: 8.0,
+                trusted_proxies: Vec::new(),
             },
             redis: RedisSettings {
                 host: "localhost".to_string(),
@@ -447,6 +449,10 @@ mod tests {
                 level: "info".to_string(),
                 format: "pretty".to_string(),
             },
+            ldap: Default::default(),
+            billing: Default::default(),
+            usage_retention: Default::default(),
+            metrics: Default::default(),
         }
     }
 
@@ -0,0 +1,393 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tracing::{debug, info, warn};
+
+use crate::config::BillingSettings;
+use crate::models::api_key::ApiKey;
+use crate::models::billing_event::{
+    next_stream_id, stream_id_range, BillingAccount, BillingApiKey, BillingCost, BillingEvent,
+    BillingEventEntry, BillingUsage, CostBreakdown, BILLING_EVENTS_STREAM, BILLING_EVENT_TYPE,
+    BILLING_EVENT_VERSION,
+};
+use crate::models::usage_record::UsageRecord;
+use crate::redis::RedisPool;
+use crate::services::pricing_service::CostResult;
+use crate::utils::error::{AppError, Result};
+
+/// 单次查询返回的最大事件数
+pub const MAX_BILLING_EVENTS_PAGE: usize = 1000;
+
+/// 单次重放的最大事件数
+pub const MAX_BILLING_EVENTS_REPLAY: usize = 10_000;
+
+impl From<&CostResult> for CostBreakdown {
+    fn from(cost: &CostResult) -> Self {
+        Self {
+            input: cost.input_cost,
+            output: cost.output_cost,
+            cache_create: cost.cache_create_cost,
+            cache_read: cost.cache_read_cost,
+            ephemeral_5m: cost.ephemeral_5m_cost,
+            ephemeral_1h: cost.ephemeral_1h_cost,
+        }
+    }
+}
+
+/// 由使用记录、所属 API Key 和计费倍率生成计费事件
+pub fn billing_event(
+    record: &UsageRecord,
+    api_key: &ApiKey,
+    multiplier: f64,
+    now: DateTime<Utc>,
+) -> BillingEvent {
+    build_event(
+        record,
+        BillingApiKey {
+            id: record.key_id.clone(),
+            name: Some(api_key.name.clone()),
+            user_id: api_key.user_id.clone(),
+        },
+        multiplier,
+        now,
+    )
+}
+
+fn build_event(
+    record: &UsageRecord,
+    api_key: BillingApiKey,
+    multiplier: f64,
+    now: DateTime<Utc>,
+) -> BillingEvent {
+    let billing = record.billing.as_ref();
+    let ephemeral_5m_tokens = billing.map_or(0, |b| b.ephemeral_5m_tokens);
+    let ephemeral_1h_tokens = billing.map_or(0, |b| b.ephemeral_1h_tokens);
+
+    BillingEvent {
+        event_id: uuid::Uuid::new_v4().to_string(),
+        event_type: BILLING_EVENT_TYPE.to_string(),
+        version: BILLING_EVENT_VERSION.to_string(),
+        request_id: billing.map(|b| b.request_id.clone()),
+        timestamp: now,
+        request_timestamp: billing.map(|b| b.requested_at),
+        api_key,
+        account: BillingAccount {
+            id: record.account_id.clone(),
+            platform: billing.and_then(|b| b.platform.clone()),
+        },
+        usage: BillingUsage {
+            model: record.model.clone(),
+            input_tokens: record.input_tokens,
+            output_tokens: record.output_tokens,
+            cache_create_tokens: record.cache_creation_tokens,
+            cache_read_tokens: record.cache_read_tokens,
+            ephemeral_5m_tokens,
+            ephemeral_1h_tokens,
+            total_tokens: record.input_tokens
+                + record.output_tokens
+                + record.cache_creation_tokens
+                + record.cache_read_tokens,
+        },
+        cost: BillingCost {
+            total: record.cost,
+            currency: "USD".to_string(),
+            breakdown: billing
+                .map(|b| b.cost_breakdown.clone())
+                .unwrap_or_default(),
+            billed_total: Some(record.cost * multiplier),
+            multiplier: Some(multiplier),
+        },
+        pricing_version: billing.and_then(|b| b.pricing_version.clone()),
+        is_long_context: billing.is_some_and(|b| b.is_long_context),
+        replay_of: None,
+    }
+}
+
+/// 计费事件分页结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BillingEventPage {
+    pub events: Vec<BillingEventEntry>,
+    /// 下一页的 `cursor`，没有更多事件时为 None
+    pub next_cursor: Option<String>,
+}
+
+/// 重放结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BillingReplayResult {
+    pub replayed: usize,
+    /// 达到单次重放上限时为 true，可从 `next_cursor` 继续
+    pub truncated: bool,
+    pub next_cursor: Option<String>,
+}
+
+/// 计费事件发布服务
+///
+/// 事件写入 Redis Stream `billing:events`，ID 由 Redis 自动生成 (`{毫秒时间戳}-{序号}`)，
+/// 外部账务系统可以用消费组 (XREADGROUP) 消费，`MAXLEN ~` 近似截断旧事件
+#[derive(Clone)]
+pub struct BillingEventService {
+    redis: RedisPool,
+    enabled: bool,
+    max_len: usize,
+}
+
+impl BillingEventService {
+    pub fn new(redis: RedisPool, settings: &BillingSettings) -> Self {
+        Self {
+            redis,
+            enabled: settings.events_enabled,
+            max_len: settings.stream_max_len,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// 发布一条计费事件，返回 Stream ID (未启用时返回 None)
+    pub async fn publish(&self, event: &BillingEvent) -> Result<Option<String>> {
+        if !self.enabled {
+            return Ok(None);
+        }
+
+        let mut pipe = redis::pipe();
+        self.xadd(&mut pipe, event)?;
+        let mut conn = self.redis.get_connection().await?;
+        let (stream_id,): (String,) = pipe
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to publish billing event: {}", e)))?;
+
+        debug!(
+            "📤 Published billing event: {} | Key: {} | Cost: ${:.6}",
+            stream_id, event.api_key.id, event.cost.total
+        );
+        Ok(Some(stream_id))
+    }
+
+    /// 按时间范围分页读取事件 (`cursor` 为上一页返回的 `next_cursor`)
+    pub async fn list_events(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<BillingEventPage> {
+        let (from, to) = stream_id_range(start, end);
+        let from = cursor.map(str::to_string).unwrap_or(from);
+        let limit = limit.clamp(1, MAX_BILLING_EVENTS_PAGE);
+
+        let events = self.range(&from, &to, limit).await?;
+        let next_cursor = if events.len() == limit {
+            events.last().and_then(|e| next_stream_id(&e.stream_id))
+        } else {
+            None
+        };
+
+        Ok(BillingEventPage {
+            events,
+            next_cursor,
+        })
+    }
+
+    /// 把时间范围内的事件重新发布到 Stream 末尾，供消费组重新处理
+    ///
+    /// 重放事件保留原 `eventId` 并带上 `replayOf` (原 Stream ID)，消费方可据此去重；
+    /// 已经是重放的事件不会再次重放
+    pub async fn replay(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<BillingReplayResult> {
+        if !self.enabled {
+            return Err(AppError::BadRequest(
+                "Billing events are disabled".to_string(),
+            ));
+        }
+
+        let (from, to) = stream_id_range(start, end);
+        let mut from = cursor.map(str::to_string).unwrap_or(from);
+        let limit = limit.clamp(1, MAX_BILLING_EVENTS_REPLAY);
+        // 重放前的最后一个 ID，避免读到本次写入的事件
+        let to = match self.last_stream_id().await? {
+            Some(last) if stream_id_key(&last) < stream_id_key(&to) => last,
+            _ => to,
+        };
+
+        let mut scanned = 0;
+        let mut replayed = 0;
+        let mut next_cursor = None;
+        while scanned < limit {
+            let page_size = (limit - scanned).min(MAX_BILLING_EVENTS_PAGE);
+            let entries = self.range(&from, &to, page_size).await?;
+            let Some(last) = entries.last() else {
+                break;
+            };
+            scanned += entries.len();
+            next_cursor = if entries.len() == page_size {
+                next_stream_id(&last.stream_id)
+            } else {
+                None
+            };
+
+            let mut pipe = redis::pipe();
+            let mut count = 0;
+            for entry in entries.iter().filter(|e| e.event.replay_of.is_none()) {
+                let mut event = entry.event.clone();
+                event.replay_of = Some(entry.stream_id.clone());
+                self.xadd(&mut pipe, &event)?;
+                count += 1;
+            }
+            if count > 0 {
+                let mut conn = self.redis.get_connection().await?;
+                pipe.query_async::<_, ()>(&mut conn).await.map_err(|e| {
+                    AppError::RedisError(format!("Failed to replay billing events: {}", e))
+                })?;
+                replayed += count;
+            }
+
+            match &next_cursor {
+                Some(cursor) => from = cursor.clone(),
+                None => break,
+            }
+        }
+
+        info!(
+            "🔁 Replayed {} billing events ({} - {})",
+            replayed, start, end
+        );
+
+        Ok(BillingReplayResult {
+            replayed,
+            truncated: next_cursor.is_some(),
+            next_cursor,
+        })
+    }
+
+    fn xadd(&self, pipe: &mut redis::Pipeline, event: &BillingEvent) -> Result<()> {
+        let data = serde_json::to_string(event)?;
+        pipe.cmd("XADD")
+            .arg(BILLING_EVENTS_STREAM)
+            .arg("MAXLEN")
+            .arg("~")
+            .arg(self.max_len)
+            .arg("*")
+            .arg("data")
+            .arg(data);
+        Ok(())
+    }
+
+    async fn range(&self, from: &str, to: &str, count: usize) -> Result<Vec<BillingEventEntry>> {
+        let mut conn = self.redis.get_connection().await?;
+        let rows: Vec<(String, Vec<String>)> = redis::cmd("XRANGE")
+            .arg(BILLING_EVENTS_STREAM)
+            .arg(from)
+            .arg(to)
+            .arg("COUNT")
+            .arg(count)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to read billing events: {}", e)))?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|(stream_id, fields)| {
+                let data = fields
+                    .chunks(2)
+                    .find(|pair| pair[0] == "data")
+                    .and_then(|pair| pair.get(1))?;
+                match serde_json::from_str(data) {
+                    Ok(event) => Some(BillingEventEntry { stream_id, event }),
+                    Err(e) => {
+                        warn!("⚠️  Skipping malformed billing event {}: {}", stream_id, e);
+                        None
+                    }
+                }
+            })
+            .collect())
+    }
+
+    async fn last_stream_id(&self) -> Result<Option<String>> {
+        let mut conn = self.redis.get_connection().await?;
+        let rows: Vec<(String, Vec<String>)> = redis::cmd("XREVRANGE")
+            .arg(BILLING_EVENTS_STREAM)
+            .arg("+")
+            .arg("-")
+            .arg("COUNT")
+            .arg(1)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to read billing events: {}", e)))?;
+        Ok(rows.into_iter().next().map(|(id, _)| id))
+    }
+}
+
+/// Stream ID 的排序键
+fn stream_id_key(id: &str) -> (u64, u64) {
+    let (ms, seq) = id.split_once('-').unwrap_or((id, "0"));
+    (ms.parse().unwrap_or(0), seq.parse().unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::billing_event::BillingDetails;
+
+    #[test]
+    fn test_billing_event_from_record() {
+        let now: DateTime<Utc> = "2025-01-31T16:42:00Z".parse().unwrap();
+        let api_key = BillingApiKey {
+            id: "k1".to_string(),
+            name: Some("finance".to_string()),
+            user_id: Some("u1".to_string()),
+        };
+
+        let record = UsageRecord::new(
+            "k1".to_string(),
+            "claude-sonnet-4".to_string(),
+            100,
+            50,
+            300,
+            20,
+            0.0123,
+        )
+        .with_account("a1")
+        .with_billing(BillingDetails {
+            request_id: "req-1".to_string(),
+            platform: Some("claude-official".to_string()),
+            ephemeral_5m_tokens: 100,
+            ephemeral_1h_tokens: 200,
+            cost_breakdown: CostBreakdown {
+                input: 0.0003,
+                ..Default::default()
+            },
+            pricing_version: Some("abc123".to_string()),
+            is_long_context: false,
+            requested_at: now,
+        });
+
+        let event = build_event(&record, api_key, 1.5, now);
+        assert_eq!(event.request_id.as_deref(), Some("req-1"));
+        assert_eq!(event.api_key.user_id.as_deref(), Some("u1"));
+        assert_eq!(event.account.id.as_deref(), Some("a1"));
+        assert_eq!(event.usage.total_tokens, 470);
+        assert_eq!(event.usage.ephemeral_1h_tokens, 200);
+        assert_eq!(event.cost.breakdown.input, 0.0003);
+        assert_eq!(event.cost.billed_total, Some(0.0123 * 1.5));
+
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["usage"]["ephemeral5mTokens"], 100);
+        assert_eq!(json["pricingVersion"], "abc123");
+        assert_eq!(json["cost"]["multiplier"], 1.5);
+        assert!(json.get("replayOf").is_none());
+    }
+
+    #[test]
+    fn test_stream_id_key_ordering() {
+        assert!(stream_id_key("1738281600000-9") < stream_id_key("1738281600000-10"));
+        assert!(stream_id_key("1738281600000-10") < stream_id_key("1738281600001-0"));
+    }
+}
@@ -0,0 +1,59 @@
+use tracing::{info, warn};
+
+use crate::models::api_key::ApiKey;
+use crate::models::billing_multiplier::{
+    BillingMultipliers, BillingMultipliersUpdate, BILLING_MULTIPLIERS_KEY,
+};
+use crate::redis::RedisPool;
+use crate::utils::error::{AppError, Result};
+
+/// 计费倍率服务
+///
+/// 倍率存储在 `billing_multipliers`，修改后对之后的请求立即生效，已记录的费用不变
+#[derive(Clone)]
+pub struct BillingMultiplierService {
+    redis: RedisPool,
+}
+
+impl BillingMultiplierService {
+    pub fn new(redis: RedisPool) -> Self {
+        Self { redis }
+    }
+
+    /// 当前倍率，未配置过或数据损坏时返回默认值 (全部为 1 倍)
+    pub async fn get(&self) -> Result<BillingMultipliers> {
+        let Some(json) = self.redis.get::<String>(BILLING_MULTIPLIERS_KEY).await? else {
+            return Ok(BillingMultipliers::default());
+        };
+
+        Ok(serde_json::from_str(&json).unwrap_or_else(|e| {
+            warn!(
+                "⚠️ Invalid billing multipliers in Redis, using defaults: {}",
+                e
+            );
+            BillingMultipliers::default()
+        }))
+    }
+
+    /// 替换全部倍率，返回更新前后的设置
+    pub async fn update(
+        &self,
+        update: BillingMultipliersUpdate,
+        updated_by: &str,
+    ) -> Result<(BillingMultipliers, BillingMultipliers)> {
+        update.validate().map_err(AppError::ValidationError)?;
+
+        let before = self.get().await?;
+        let multipliers = update.into_multipliers(updated_by);
+        let json = serde_json::to_string(&multipliers)?;
+        self.redis.set(BILLING_MULTIPLIERS_KEY, &json).await?;
+
+        info!("💱 Billing multipliers updated by: {}", updated_by);
+        Ok((before, multipliers))
+    }
+
+    /// 指定 Key 使用某个模型时的倍率
+    pub async fn multiplier_for(&self, api_key: &ApiKey, model: &str) -> Result<f64> {
+        Ok(self.get().await?.resolve(&api_key.id, &api_key.tags, model))
+    }
+}
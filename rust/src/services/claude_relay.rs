@@ -2,7 +2,9 @@ use crate::models::{AccountType, ClaudeAccount, Platform};
 use crate::redis::RedisPool;
 use crate::services::account::ClaudeAccountService;
 use crate::services::account_scheduler::{AccountScheduler, SelectedAccount};
+use crate::services::pricing_service::CacheCreation;
 use crate::utils::error::{AppError, Result};
+use crate::utils::metrics;
 use anyhow::Context;
 use bytes::Bytes;
 use futures::stream::StreamExt;
@@ -10,7 +12,7 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 use tokio::time::timeout;
 use tracing::{debug, error, info, warn};
@@ -67,6 +69,9 @@ pub struct Usage {
     pub cache_creation_input_tokens: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cache_read_input_tokens: Option<u32>,
+    /// 缓存写入按 TTL 的明细 (5 分钟 / 1 小时)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_creation: Option<CacheCreation>,
 }
 
 /// 转发响应结果
@@ -274,8 +279,7 @@ impl ClaudeRelayService {
         // Claude Console 使用 custom_api_endpoint，否则使用默认 API URL
         let base_url = account
             .custom_api_endpoint
-            .as_ref()
-            .map(|s| s.as_str())
+            .as_deref()
             .unwrap_or(&self.config.api_url);
         let url = format!("{}/v1/messages", base_url);
 
@@ -299,13 +303,16 @@ impl ClaudeRelayService {
         }
 
         // 执行请求（带超时）
+        let started = Instant::now();
         let response = timeout(
             Duration::from_secs(self.config.timeout_seconds),
             request_builder.send(),
         )
-        .await
-        .context("Request timeout")?
-        .context("Failed to send request")?;
+        .await;
+        metrics::observe_upstream("claude", &account.id.to_string(), started, &response);
+        let response = response
+            .context("Request timeout")?
+            .context("Failed to send request")?;
 
         let status_code = response.status().as_u16();
         let headers: Vec<(String, String)> = response
@@ -624,8 +631,7 @@ impl ClaudeRelayService {
         // Claude Console 使用 custom_api_endpoint，否则使用默认 API URL
         let base_url = account
             .custom_api_endpoint
-            .as_ref()
-            .map(|s| s.as_str())
+            .as_deref()
             .unwrap_or(&config.api_url);
         let url = format!("{}/v1/messages", base_url);
 
@@ -644,13 +650,17 @@ impl ClaudeRelayService {
             request_builder = request_builder.header("User-Agent", "claude_code");
         }
 
+        let account_id = account.id.to_string();
+        let started = Instant::now();
         let response = timeout(
             Duration::from_secs(config.timeout_seconds),
             request_builder.json(&stream_body).send(),
         )
-        .await
-        .context("Request timeout")?
-        .context("Failed to send request")?;
+        .await;
+        metrics::observe_upstream("claude", &account_id, started, &response);
+        let response = response
+            .context("Request timeout")?
+            .context("Failed to send request")?;
 
         let status_code = response.status().as_u16();
 
@@ -674,11 +684,18 @@ impl ClaudeRelayService {
             output_tokens: 0,
             cache_creation_input_tokens: None,
             cache_read_input_tokens: None,
+            cache_creation: None,
         };
+        let mut first_chunk = true;
 
         while let Some(chunk_result) = stream.next().await {
             match chunk_result {
                 Ok(chunk) => {
+                    if first_chunk {
+                        first_chunk = false;
+                        metrics::UPSTREAM_TTFB
+                            .observe(&["claude", &account_id], started.elapsed().as_secs_f64());
+                    }
                     // 转发原始数据块
                     let chunk_bytes = chunk.to_vec();
                     if let Err(e) = tx
@@ -768,6 +785,7 @@ impl ClaudeRelayService {
                 accumulated.input_tokens = message.usage.input_tokens;
                 accumulated.cache_creation_input_tokens = message.usage.cache_creation_input_tokens;
                 accumulated.cache_read_input_tokens = message.usage.cache_read_input_tokens;
+                accumulated.cache_creation = message.usage.cache_creation.clone();
 
                 debug!(
                     "📊 Collected from message_start - Input: {}, Cache Create: {:?}, Cache Read: {:?}",
@@ -0,0 +1,375 @@
+use chrono::Utc;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+use crate::models::api_key::{ApiKey, ModelUsage, UsagePeriod};
+use crate::models::cost_recalculation::{
+    cost_changed, cost_recalculation_job_key, CostRecalculationJob, CostRecalculationOptions,
+    CostRecalculationStatus, KeyCostDiff, ModelCostDiff, COST_RECALCULATION_LOCK_KEY,
+};
+use crate::redis::RedisPool;
+use crate::services::api_key::{parse_model_usage, EPHEMERAL_1H_TOKENS_FIELD};
+use crate::services::pricing_service::{CacheCreation, PricingService, Usage};
+use crate::services::ApiKeyService;
+use crate::utils::error::{AppError, Result};
+
+/// 每处理多少个 Key 保存一次进度
+const PROGRESS_SAVE_INTERVAL: usize = 20;
+
+/// 任务锁过期时间 (秒)，进程意外退出后锁会自动释放
+const LOCK_TTL_SECONDS: u64 = 3600;
+
+/// 任务记录保留时间 (秒)
+const JOB_TTL_SECONDS: u64 = 7 * 24 * 3600;
+
+/// 历史成本重算服务
+///
+/// 按传入的定价重新计算各 Key 按模型统计中的 `cost`，并把差额写回
+/// 累计、日/月模型统计和 `api_key_usage:{key_id}` 的 `total_cost`。
+/// 管理接口总是使用服务当前维护的定价；只有 CLI (`recalculate-costs --pricing-file`)
+/// 可以指定定价快照。
+/// 写回使用 HINCRBYFLOAT 差额，重算期间新记录的使用量不会丢失。
+/// 限额计数 (`daily_cost`、`weekly_opus_cost`)、计费成本 (`*billed_cost`) 和趋势桶保持不变
+pub struct CostRecalculationService {
+    redis: Arc<RedisPool>,
+    api_key_service: Arc<ApiKeyService>,
+}
+
+impl CostRecalculationService {
+    pub fn new(redis: Arc<RedisPool>, api_key_service: Arc<ApiKeyService>) -> Self {
+        Self {
+            redis,
+            api_key_service,
+        }
+    }
+
+    /// 创建重算任务并占用任务锁
+    pub async fn start_job(
+        &self,
+        pricing: &PricingService,
+        options: &CostRecalculationOptions,
+        requested_by: &str,
+    ) -> Result<CostRecalculationJob> {
+        let pricing_version = pricing.pricing_version().await;
+        if pricing_version.is_none() {
+            return Err(AppError::BadRequest(
+                "Pricing data is not loaded".to_string(),
+            ));
+        }
+
+        let job = CostRecalculationJob::new(options.dry_run, pricing_version, requested_by);
+        let mut conn = self.redis.get_connection().await?;
+        let acquired: Option<String> = redis::cmd("SET")
+            .arg(COST_RECALCULATION_LOCK_KEY)
+            .arg(&job.id)
+            .arg("NX")
+            .arg("EX")
+            .arg(LOCK_TTL_SECONDS)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to acquire lock: {}", e)))?;
+        if acquired.is_none() {
+            let running: Option<String> = self.redis.get(COST_RECALCULATION_LOCK_KEY).await?;
+            return Err(AppError::BadRequest(format!(
+                "Cost recalculation {} is already running",
+                running.unwrap_or_default()
+            )));
+        }
+
+        self.save_job(&job).await?;
+        info!(
+            "🧮 Cost recalculation {} started by {} (dry run: {})",
+            job.id, requested_by, job.dry_run
+        );
+        Ok(job)
+    }
+
+    /// 执行重算任务，结束后保存结果并释放任务锁
+    pub async fn run_job(
+        &self,
+        pricing: &PricingService,
+        mut job: CostRecalculationJob,
+        options: &CostRecalculationOptions,
+    ) -> CostRecalculationJob {
+        match self.recalculate(pricing, &mut job, options).await {
+            Ok(()) => {
+                job.status = CostRecalculationStatus::Completed;
+                info!(
+                    "✅ Cost recalculation {} completed: {} keys, ${:.6} -> ${:.6}",
+                    job.id, job.processed_keys, job.old_total_cost, job.new_total_cost
+                );
+            }
+            Err(e) => {
+                error!("❌ Cost recalculation {} failed: {}", job.id, e);
+                job.status = CostRecalculationStatus::Failed;
+                job.error = Some(e.to_string());
+            }
+        }
+        job.finished_at = Some(Utc::now());
+
+        if let Err(e) = self.save_job(&job).await {
+            warn!("⚠️ Failed to save cost recalculation {}: {}", job.id, e);
+        }
+        if let Err(e) = self.redis.del(COST_RECALCULATION_LOCK_KEY).await {
+            warn!("⚠️ Failed to release cost recalculation lock: {}", e);
+        }
+        job
+    }
+
+    /// 获取任务进度和结果
+    pub async fn get_job(&self, job_id: &str) -> Result<CostRecalculationJob> {
+        let json: Option<String> = self.redis.get(&cost_recalculation_job_key(job_id)).await?;
+        let json = json.ok_or_else(|| {
+            AppError::NotFound(format!("Cost recalculation job not found: {}", job_id))
+        })?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    async fn save_job(&self, job: &CostRecalculationJob) -> Result<()> {
+        let json = serde_json::to_string(job)?;
+        self.redis
+            .setex(&cost_recalculation_job_key(&job.id), &json, JOB_TTL_SECONDS)
+            .await
+    }
+
+    async fn recalculate(
+        &self,
+        pricing: &PricingService,
+        job: &mut CostRecalculationJob,
+        options: &CostRecalculationOptions,
+    ) -> Result<()> {
+        let api_keys = if options.key_ids.is_empty() {
+            self.api_key_service.get_all_keys(true).await?
+        } else {
+            let mut api_keys = Vec::with_capacity(options.key_ids.len());
+            for key_id in &options.key_ids {
+                api_keys.push(self.api_key_service.get_key(key_id).await?);
+            }
+            api_keys
+        };
+        job.total_keys = api_keys.len();
+        self.save_job(job).await?;
+
+        for api_key in &api_keys {
+            let diff = self
+                .recalculate_key(pricing, api_key, job.dry_run, &mut job.unpriced_models)
+                .await?;
+            job.add_key(diff);
+
+            if job.processed_keys.is_multiple_of(PROGRESS_SAVE_INTERVAL) {
+                info!(
+                    "🧮 Cost recalculation {}: {}/{} keys",
+                    job.id, job.processed_keys, job.total_keys
+                );
+                self.save_job(job).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 重算单个 Key 的累计和日/月模型统计，返回累计统计的成本变化
+    async fn recalculate_key(
+        &self,
+        pricing: &PricingService,
+        api_key: &ApiKey,
+        dry_run: bool,
+        unpriced_models: &mut BTreeSet<String>,
+    ) -> Result<KeyCostDiff> {
+        let key_id = &api_key.id;
+        let mut usage_keys = self
+            .redis
+            .keys(&format!("api_key_usage:model:{}:*", key_id))
+            .await?;
+        let lifetime_keys = usage_keys.len();
+        for period in [UsagePeriod::Daily, UsagePeriod::Monthly] {
+            let pattern = format!("api_key_usage:model:{}:{}:*", period.as_str(), key_id);
+            usage_keys.extend(self.redis.keys(&pattern).await?);
+        }
+        if usage_keys.is_empty() {
+            return Ok(KeyCostDiff::new(key_id, &api_key.name, Vec::new()));
+        }
+
+        let mut conn = self.redis.get_connection().await?;
+        let mut pipe = redis::pipe();
+        for usage_key in &usage_keys {
+            pipe.cmd("HGETALL").arg(usage_key);
+        }
+        let hashes: Vec<HashMap<String, String>> = pipe
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to read model usage: {}", e)))?;
+
+        let mut models = Vec::new();
+        let mut total_delta = 0.0;
+        let mut writes = redis::pipe();
+        let mut write_count = 0;
+        for (index, (usage_key, hash)) in usage_keys.iter().zip(&hashes).enumerate() {
+            let Some(model) = model_from_usage_key(usage_key, key_id) else {
+                continue;
+            };
+            if hash.is_empty() {
+                continue;
+            }
+            let usage = parse_model_usage(hash);
+            let is_lifetime = index < lifetime_keys;
+
+            let new_cost = match recalculated_cost(pricing, model, hash).await {
+                Some(cost) => cost,
+                None => {
+                    unpriced_models.insert(model.to_string());
+                    usage.cost
+                }
+            };
+            if cost_changed(usage.cost, new_cost) {
+                writes
+                    .cmd("HINCRBYFLOAT")
+                    .arg(usage_key)
+                    .arg("cost")
+                    .arg(new_cost - usage.cost);
+                write_count += 1;
+            }
+            if is_lifetime {
+                total_delta += new_cost - usage.cost;
+                models.push(ModelCostDiff {
+                    model: model.to_string(),
+                    requests: usage.requests,
+                    old_cost: usage.cost,
+                    new_cost,
+                });
+            }
+        }
+
+        if !dry_run && write_count > 0 {
+            if cost_changed(0.0, total_delta) {
+                writes
+                    .cmd("HINCRBYFLOAT")
+                    .arg(format!("api_key_usage:{}", key_id))
+                    .arg("total_cost")
+                    .arg(total_delta);
+            }
+            writes.query_async::<_, ()>(&mut conn).await.map_err(|e| {
+                AppError::RedisError(format!("Failed to write recalculated cost: {}", e))
+            })?;
+        }
+
+        models.sort_by(|a, b| a.model.cmp(&b.model));
+        Ok(KeyCostDiff::new(key_id, &api_key.name, models))
+    }
+}
+
+/// 从模型统计键中取出模型名 (模型名本身可能包含 `:`)
+///
+/// 支持 `api_key_usage:model:{key_id}:{model}` 和
+/// `api_key_usage:model:{period}:{key_id}:{bucket}:{model}`
+fn model_from_usage_key<'a>(usage_key: &'a str, key_id: &str) -> Option<&'a str> {
+    let rest = usage_key.strip_prefix("api_key_usage:model:")?;
+    if let Some(model) = rest.strip_prefix(key_id).and_then(|r| r.strip_prefix(':')) {
+        return Some(model);
+    }
+    let (_period, rest) = rest.split_once(':')?;
+    let rest = rest.strip_prefix(key_id)?.strip_prefix(':')?;
+    rest.split_once(':').map(|(_bucket, model)| model)
+}
+
+/// 按当前定价重算一条模型统计的成本，模型没有定价时返回 None
+async fn recalculated_cost(
+    pricing: &PricingService,
+    model: &str,
+    hash: &HashMap<String, String>,
+) -> Option<f64> {
+    let usage = parse_model_usage(hash);
+    let ephemeral_1h_tokens = hash
+        .get(EPHEMERAL_1H_TOKENS_FIELD)
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    // 1M 上下文模型按单次请求的平均用量判断是否超过 200K 阈值
+    let requests = if model.contains("[1m]") {
+        usage.requests.max(1)
+    } else {
+        1
+    };
+
+    let cost = pricing
+        .calculate_cost(&pricing_usage(&usage, ephemeral_1h_tokens, requests), model)
+        .await;
+    cost.has_pricing
+        .then_some(cost.total_cost * requests as f64)
+}
+
+/// 把聚合统计转换为定价用量 (按 `requests` 平均)
+///
+/// 没有单独记录 1 小时缓存写入的历史数据全部按 5 分钟价格计算
+fn pricing_usage(usage: &ModelUsage, ephemeral_1h_tokens: i64, requests: i64) -> Usage {
+    let per_request = |tokens: i64| tokens / requests;
+    let ephemeral_1h_tokens = ephemeral_1h_tokens.clamp(0, usage.cache_creation_tokens);
+
+    Usage {
+        input_tokens: per_request(usage.input_tokens),
+        output_tokens: per_request(usage.output_tokens),
+        cache_creation_input_tokens: per_request(usage.cache_creation_tokens),
+        cache_read_input_tokens: per_request(usage.cache_read_tokens),
+        cache_creation: (ephemeral_1h_tokens > 0).then(|| CacheCreation {
+            ephemeral_5m_input_tokens: per_request(
+                usage.cache_creation_tokens - ephemeral_1h_tokens,
+            ),
+            ephemeral_1h_input_tokens: per_request(ephemeral_1h_tokens),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_from_usage_key() {
+        assert_eq!(
+            model_from_usage_key("api_key_usage:model:k1:claude-sonnet-4", "k1"),
+            Some("claude-sonnet-4")
+        );
+        assert_eq!(
+            model_from_usage_key(
+                "api_key_usage:model:daily:k1:2025-01-31:claude-sonnet-4",
+                "k1"
+            ),
+            Some("claude-sonnet-4")
+        );
+        assert_eq!(
+            model_from_usage_key(
+                "api_key_usage:model:monthly:k1:2025-01:anthropic.claude-3-5-sonnet-v2:0",
+                "k1"
+            ),
+            Some("anthropic.claude-3-5-sonnet-v2:0")
+        );
+        assert_eq!(
+            model_from_usage_key("api_key_usage:model:k2:claude-sonnet-4", "k1"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_pricing_usage_splits_cache_writes() {
+        let usage = ModelUsage {
+            requests: 4,
+            input_tokens: 4000,
+            output_tokens: 800,
+            cache_creation_tokens: 1000,
+            cache_read_tokens: 400,
+            cost: 0.0,
+            billed_cost: 0.0,
+        };
+
+        let legacy = pricing_usage(&usage, 0, 1);
+        assert_eq!(legacy.input_tokens, 4000);
+        assert!(legacy.cache_creation.is_none());
+
+        let averaged = pricing_usage(&usage, 600, 4);
+        assert_eq!(averaged.input_tokens, 1000);
+        assert_eq!(averaged.cache_creation_input_tokens, 250);
+        let cache_creation = averaged.cache_creation.unwrap();
+        assert_eq!(cache_creation.ephemeral_5m_input_tokens, 100);
+        assert_eq!(cache_creation.ephemeral_1h_input_tokens, 150);
+    }
+}
@@ -0,0 +1,305 @@
+use chrono::{Timelike, Utc};
+use serde::Serialize;
+use serde_json::{json, Value as JsonValue};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::warn;
+
+use crate::config::timezone_hours;
+use crate::models::account::{AccountStatus, ClaudeAccount, Platform};
+use crate::redis::RedisPool;
+use crate::services::api_key::ApiKeyService;
+use crate::utils::error::{AppError, Result};
+
+/// 实时 RPM/TPM 统计窗口 (分钟)
+pub const DASHBOARD_METRICS_WINDOW_MINUTES: i64 = 5;
+
+/// 所有平台 (仪表板中即使没有账户也返回 0)
+const ALL_PLATFORMS: [Platform; 8] = [
+    Platform::Claude,
+    Platform::ClaudeConsole,
+    Platform::Gemini,
+    Platform::OpenAI,
+    Platform::Bedrock,
+    Platform::Azure,
+    Platform::Droid,
+    Platform::CCR,
+];
+
+/// 单个平台的账户健康统计
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlatformAccountStats {
+    pub total: u64,
+    pub normal: u64,
+    pub abnormal: u64,
+    pub paused: u64,
+    pub rate_limited: u64,
+    /// 已启用的账户 (含限流中的)
+    #[serde(skip)]
+    pub active: u64,
+}
+
+/// 账户健康分类 (每个账户只属于一类)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AccountHealth {
+    Normal,
+    Abnormal,
+    Paused,
+    RateLimited,
+}
+
+/// 仪表板数据服务
+///
+/// 汇总 API Key、各平台账户健康状态、使用量计数器和系统状态
+pub struct DashboardService {
+    redis: Arc<RedisPool>,
+    api_key_service: Arc<ApiKeyService>,
+    started_at: Instant,
+}
+
+impl DashboardService {
+    pub fn new(redis: Arc<RedisPool>, api_key_service: Arc<ApiKeyService>) -> Self {
+        Self {
+            redis,
+            api_key_service,
+            started_at: Instant::now(),
+        }
+    }
+
+    /// 生成仪表板数据 (与前端 dashboard store 的结构一致)
+    pub async fn get_dashboard(&self) -> Result<JsonValue> {
+        let redis_connected = self.redis.ping().await.is_ok();
+        let timezone = self.api_key_service.system_timezone();
+
+        let keys = self.api_key_service.get_all_keys(false).await?;
+        let now = Utc::now();
+        let active_keys = keys
+            .iter()
+            .filter(|key| key.is_active && key.expires_at.is_none_or(|at| at > now))
+            .count();
+        let key_ids: Vec<String> = keys.iter().map(|key| key.id.clone()).collect();
+        let totals = self.api_key_service.get_total_usage(&key_ids).await?;
+
+        let platforms = self.account_stats_by_platform().await?;
+        let sum = |field: fn(&PlatformAccountStats) -> u64| -> u64 {
+            platforms.values().map(field).sum()
+        };
+
+        let today = self
+            .api_key_service
+            .get_global_daily_usage(&self.api_key_service.system_today())
+            .await?;
+        let tokens_today = today.input_tokens
+            + today.output_tokens
+            + today.cache_creation_tokens
+            + today.cache_read_tokens;
+
+        // 今日平均：按系统时区今天已经过去的分钟数计算
+        let local_now = now.with_timezone(&timezone);
+        let minutes_today = (local_now.num_seconds_from_midnight() / 60).max(1) as f64;
+
+        let (window_requests, window_tokens) = self
+            .api_key_service
+            .get_realtime_usage(DASHBOARD_METRICS_WINDOW_MINUTES)
+            .await?;
+        let window = DASHBOARD_METRICS_WINDOW_MINUTES as f64;
+
+        let accounts_by_platform: serde_json::Map<String, JsonValue> = platforms
+            .iter()
+            .map(|(key, stats)| (key.to_string(), json!(stats)))
+            .collect();
+
+        Ok(json!({
+            "overview": {
+                "totalApiKeys": keys.len(),
+                "activeApiKeys": active_keys,
+                "totalAccounts": sum(|s| s.total),
+                "normalAccounts": sum(|s| s.normal),
+                "abnormalAccounts": sum(|s| s.abnormal),
+                "pausedAccounts": sum(|s| s.paused),
+                "activeAccounts": sum(|s| s.active),
+                "rateLimitedAccounts": sum(|s| s.rate_limited),
+                "accountsByPlatform": accounts_by_platform,
+                "totalRequestsUsed": totals.requests,
+                "totalTokensUsed": totals.input_tokens
+                    + totals.output_tokens
+                    + totals.cache_creation_tokens
+                    + totals.cache_read_tokens,
+                "totalInputTokensUsed": totals.input_tokens,
+                "totalOutputTokensUsed": totals.output_tokens,
+                "totalCacheCreateTokensUsed": totals.cache_creation_tokens,
+                "totalCacheReadTokensUsed": totals.cache_read_tokens
+            },
+            "recentActivity": {
+                "requestsToday": today.requests,
+                "tokensToday": tokens_today,
+                "inputTokensToday": today.input_tokens,
+                "outputTokensToday": today.output_tokens,
+                "cacheCreateTokensToday": today.cache_creation_tokens,
+                "cacheReadTokensToday": today.cache_read_tokens
+            },
+            "systemAverages": {
+                "rpm": round2(today.requests as f64 / minutes_today),
+                "tpm": round2(tokens_today as f64 / minutes_today)
+            },
+            "realtimeMetrics": {
+                "rpm": round2(window_requests as f64 / window),
+                "tpm": round2(window_tokens as f64 / window),
+                "windowMinutes": DASHBOARD_METRICS_WINDOW_MINUTES,
+                "isHistorical": false
+            },
+            "systemHealth": {
+                "redisConnected": redis_connected,
+                "uptime": self.started_at.elapsed().as_secs()
+            },
+            "systemTimezone": timezone_hours(timezone)
+        }))
+    }
+
+    /// 按平台统计账户健康状态
+    async fn account_stats_by_platform(
+        &self,
+    ) -> Result<BTreeMap<&'static str, PlatformAccountStats>> {
+        let mut stats: BTreeMap<&'static str, PlatformAccountStats> = ALL_PLATFORMS
+            .iter()
+            .map(|platform| (platform_key(*platform), PlatformAccountStats::default()))
+            .collect();
+
+        let mut conn = self.redis.get_connection().await?;
+        let account_ids: Vec<String> = redis::cmd("SMEMBERS")
+            .arg("claude_accounts")
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to get account list: {}", e)))?;
+        if account_ids.is_empty() {
+            return Ok(stats);
+        }
+
+        // 一次读取账户数据和调度器写入的限流/过载标记
+        let mut pipe = redis::pipe();
+        for id in &account_ids {
+            pipe.cmd("GET")
+                .arg(format!("claude_account:{}", id))
+                .cmd("EXISTS")
+                .arg(format!("rate_limit:scheduler:{}", id))
+                .cmd("EXISTS")
+                .arg(format!("overload:{}", id));
+        }
+        let rows: Vec<(Option<String>, bool, bool)> = pipe
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to fetch accounts: {}", e)))?;
+
+        for (json, rate_limited, overloaded) in rows {
+            let Some(json) = json else {
+                continue;
+            };
+            let account = match serde_json::from_str::<ClaudeAccount>(&json) {
+                Ok(account) => account,
+                Err(e) => {
+                    warn!("⚠️  Skipping malformed account in dashboard: {}", e);
+                    continue;
+                }
+            };
+
+            let entry = stats.entry(platform_key(account.platform)).or_default();
+            entry.total += 1;
+            if account.is_active {
+                entry.active += 1;
+            }
+            match classify_account(&account, rate_limited || overloaded) {
+                AccountHealth::Normal => entry.normal += 1,
+                AccountHealth::Abnormal => entry.abnormal += 1,
+                AccountHealth::Paused => entry.paused += 1,
+                AccountHealth::RateLimited => entry.rate_limited += 1,
+            }
+        }
+
+        Ok(stats)
+    }
+}
+
+/// 账户健康分类：错误/过期 > 停用/暂停调度 > 限流/过载 > 正常
+fn classify_account(account: &ClaudeAccount, limited: bool) -> AccountHealth {
+    match account.status {
+        AccountStatus::Error | AccountStatus::Expired => AccountHealth::Abnormal,
+        AccountStatus::Inactive => AccountHealth::Paused,
+        _ if !account.is_active || !account.schedulable => AccountHealth::Paused,
+        AccountStatus::Overloaded => AccountHealth::RateLimited,
+        _ if limited => AccountHealth::RateLimited,
+        _ => AccountHealth::Normal,
+    }
+}
+
+/// 前端使用的平台标识
+fn platform_key(platform: Platform) -> &'static str {
+    match platform {
+        Platform::Claude => "claude",
+        Platform::ClaudeConsole => "claude-console",
+        Platform::Gemini => "gemini",
+        Platform::OpenAI => "openai",
+        Platform::Bedrock => "bedrock",
+        Platform::Azure => "azure",
+        Platform::Droid => "droid",
+        Platform::CCR => "ccr",
+    }
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(status: AccountStatus, is_active: bool, schedulable: bool) -> ClaudeAccount {
+        serde_json::from_value(json!({
+            "id": "5f8d3c2e-0000-4000-8000-000000000001",
+            "name": "test",
+            "isActive": is_active,
+            "accountType": "shared",
+            "platform": "claude",
+            "priority": 50,
+            "schedulable": schedulable,
+            "autoStopOnWarning": false,
+            "useUnifiedUserAgent": false,
+            "useUnifiedClientId": false,
+            "status": status,
+            "concurrencyLimit": 5,
+            "currentConcurrency": 0,
+            "createdAt": Utc::now(),
+            "updatedAt": Utc::now()
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_classify_account() {
+        let normal = account(AccountStatus::Active, true, true);
+        assert_eq!(classify_account(&normal, false), AccountHealth::Normal);
+        assert_eq!(classify_account(&normal, true), AccountHealth::RateLimited);
+        assert_eq!(
+            classify_account(&account(AccountStatus::Overloaded, true, true), false),
+            AccountHealth::RateLimited
+        );
+        assert_eq!(
+            classify_account(&account(AccountStatus::Active, true, false), true),
+            AccountHealth::Paused
+        );
+        assert_eq!(
+            classify_account(&account(AccountStatus::Error, false, false), false),
+            AccountHealth::Abnormal
+        );
+    }
+
+    #[test]
+    fn test_platform_keys_are_unique() {
+        let keys: std::collections::HashSet<_> =
+            ALL_PLATFORMS.iter().map(|p| platform_key(*p)).collect();
+        assert_eq!(keys.len(), ALL_PLATFORMS.len());
+        assert_eq!(platform_key(Platform::ClaudeConsole), "claude-console");
+    }
+}
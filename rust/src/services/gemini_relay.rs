@@ -6,13 +6,14 @@ use crate::services::relay_trait::{
     GenericRelayResponse, GenericStreamChunk, RelayRequest, RelayService, UsageStats,
 };
 use crate::utils::error::{AppError, Result};
+use crate::utils::metrics;
 use anyhow::Context;
 use async_trait::async_trait;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value as JsonValue};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 use tokio::time::timeout;
 use tracing::{info, warn};
@@ -249,6 +250,7 @@ impl GeminiRelayService {
         config: GeminiRelayConfig,
         request: RelayRequest,
         api_key: String,
+        account_id: String,
         tx: mpsc::Sender<Result<GenericStreamChunk>>,
     ) -> Result<()> {
         use bytes::Buf;
@@ -296,6 +298,7 @@ impl GeminiRelayService {
         };
 
         // 3. 发送流式请求
+        let started = Instant::now();
         let response = timeout(
             Duration::from_secs(config.timeout_seconds),
             http_client
@@ -304,9 +307,11 @@ impl GeminiRelayService {
                 .json(&gemini_body)
                 .send(),
         )
-        .await
-        .context("Request timeout")?
-        .context("Failed to send request")?;
+        .await;
+        metrics::observe_upstream("gemini", &account_id, started, &response);
+        let response = response
+            .context("Request timeout")?
+            .context("Failed to send request")?;
 
         let status_code = response.status();
 
@@ -335,9 +340,15 @@ impl GeminiRelayService {
         };
 
         // 7. 处理流式数据
+        let mut first_chunk = true;
         while let Some(chunk_result) = bytes_stream.next().await {
             match chunk_result {
                 Ok(chunk) => {
+                    if first_chunk {
+                        first_chunk = false;
+                        metrics::UPSTREAM_TTFB
+                            .observe(&["gemini", &account_id], started.elapsed().as_secs_f64());
+                    }
                     // 尝试解析 SSE 事件
                     if let Ok(text) = std::str::from_utf8(chunk.chunk()) {
                         // Gemini 流式响应格式: data: {...}\n\n
@@ -450,6 +461,7 @@ impl RelayService for GeminiRelayService {
         );
 
         // 6. 发送请求
+        let started = Instant::now();
         let response = timeout(
             Duration::from_secs(self.config.timeout_seconds),
             self.http_client
@@ -458,9 +470,11 @@ impl RelayService for GeminiRelayService {
                 .json(&gemini_body)
                 .send(),
         )
-        .await
-        .context("Request timeout")?
-        .context("Failed to send request")?;
+        .await;
+        metrics::observe_upstream("gemini", &selected_account.account_id, started, &response);
+        let response = response
+            .context("Request timeout")?
+            .context("Failed to send request")?;
 
         let status_code = response.status().as_u16();
         let headers: Vec<(String, String)> = response
@@ -544,6 +558,7 @@ impl RelayService for GeminiRelayService {
                 config,
                 request,
                 api_key,
+                account_id.clone(),
                 tx.clone(),
             )
             .await;
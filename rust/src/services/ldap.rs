@@ -0,0 +1,445 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::config::LdapSettings;
+use crate::models::role::{ADMIN_ROLE_NAME, USER_ROLE_NAME};
+use crate::utils::error::{AppError, Result};
+
+/// LDAP 目录中的条目
+#[derive(Debug, Clone, Default)]
+pub struct LdapEntry {
+    pub dn: String,
+    pub attrs: HashMap<String, Vec<String>>,
+}
+
+impl LdapEntry {
+    /// 获取属性值 (属性名不区分大小写)
+    pub fn values(&self, attr: &str) -> Vec<String> {
+        self.attrs
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(attr))
+            .map(|(_, values)| values.clone())
+            .unwrap_or_default()
+    }
+
+    /// 获取属性的第一个非空值
+    pub fn first(&self, attr: &str) -> Option<String> {
+        self.values(attr)
+            .into_iter()
+            .find(|value| !value.is_empty())
+    }
+}
+
+/// LDAP 目录访问
+///
+/// 生产环境使用 ldap3 连接目录服务器，测试中可替换为进程内实现
+#[async_trait]
+pub trait LdapDirectory: Send + Sync {
+    /// 使用服务账号按过滤条件搜索用户条目
+    async fn search_users(&self, filter: &str, attrs: &[String]) -> Result<Vec<LdapEntry>>;
+
+    /// 使用指定 DN 和密码绑定，返回密码是否正确
+    async fn bind(&self, dn: &str, password: &str) -> Result<bool>;
+}
+
+/// 通过 LDAP 认证的用户
+#[derive(Debug, Clone)]
+pub struct LdapUser {
+    pub username: String,
+    pub dn: String,
+    pub email: Option<String>,
+    pub display_name: Option<String>,
+    pub groups: Vec<String>,
+    /// 由组映射得到的角色 ("admin" 或 "user")
+    pub role: String,
+}
+
+/// LDAP 认证提供者
+pub struct LdapAuthProvider {
+    settings: LdapSettings,
+    directory: Arc<dyn LdapDirectory>,
+}
+
+impl LdapAuthProvider {
+    /// 使用 ldap3 连接配置中的目录服务器
+    #[cfg(feature = "ldap")]
+    pub fn from_settings(settings: LdapSettings) -> Self {
+        let directory = Arc::new(Ldap3Directory {
+            settings: settings.clone(),
+        });
+        Self::with_directory(settings, directory)
+    }
+
+    pub fn with_directory(settings: LdapSettings, directory: Arc<dyn LdapDirectory>) -> Self {
+        Self {
+            settings,
+            directory,
+        }
+    }
+
+    /// 验证目录用户的用户名和密码
+    ///
+    /// 流程：服务账号搜索用户 → 使用用户 DN 绑定验证密码 → 组映射为角色。
+    /// 用户不存在、存在多个匹配或密码错误时统一返回 Unauthorized，
+    /// 不属于任何允许的组时返回 Forbidden
+    pub async fn authenticate(&self, username: &str, password: &str) -> Result<LdapUser> {
+        let invalid = || AppError::Unauthorized("Invalid credentials".to_string());
+
+        // 空密码在多数目录上会被当作匿名绑定而成功，必须提前拒绝
+        let username = username.trim();
+        if username.is_empty() || password.is_empty() {
+            return Err(invalid());
+        }
+
+        let settings = &self.settings;
+        let filter = build_user_filter(&settings.user_filter, username);
+        let attrs = vec![
+            settings.username_attribute.clone(),
+            settings.email_attribute.clone(),
+            settings.display_name_attribute.clone(),
+            settings.group_attribute.clone(),
+        ];
+
+        let mut entries = self.directory.search_users(&filter, &attrs).await?;
+        if entries.len() != 1 {
+            if entries.len() > 1 {
+                warn!(
+                    "⚠️  LDAP filter matched {} entries for user: {}",
+                    entries.len(),
+                    username
+                );
+            }
+            return Err(invalid());
+        }
+        let entry = entries.remove(0);
+
+        if !self.directory.bind(&entry.dn, password).await? {
+            return Err(invalid());
+        }
+
+        let groups = entry.values(&settings.group_attribute);
+        let role = resolve_role(settings, &groups).ok_or_else(|| {
+            AppError::Forbidden("User is not a member of any allowed LDAP group".to_string())
+        })?;
+
+        info!("📇 LDAP authentication succeeded: {} ({})", entry.dn, role);
+
+        Ok(LdapUser {
+            username: entry
+                .first(&settings.username_attribute)
+                .unwrap_or_else(|| username.to_string()),
+            email: entry.first(&settings.email_attribute),
+            display_name: entry.first(&settings.display_name_attribute),
+            dn: entry.dn,
+            groups,
+            role: role.to_string(),
+        })
+    }
+}
+
+/// 按 RFC 4515 转义过滤器中的值，防止 LDAP 注入
+pub fn escape_filter_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\5c"),
+            '*' => escaped.push_str("\\2a"),
+            '(' => escaped.push_str("\\28"),
+            ')' => escaped.push_str("\\29"),
+            '\0' => escaped.push_str("\\00"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// 将过滤器模板中的 `{username}` 替换为转义后的用户名
+pub fn build_user_filter(template: &str, username: &str) -> String {
+    template.replace("{username}", &escape_filter_value(username))
+}
+
+/// 根据组成员关系确定角色
+///
+/// - 属于 `admin_groups` 中任意组 → admin
+/// - `user_groups` 为空，或属于其中任意组 → user
+/// - 否则拒绝登录
+pub fn resolve_role(settings: &LdapSettings, groups: &[String]) -> Option<&'static str> {
+    let member_of = |configured: &[String]| {
+        configured
+            .iter()
+            .any(|wanted| groups.iter().any(|group| group_matches(group, wanted)))
+    };
+
+    if member_of(&settings.admin_groups) {
+        Some(ADMIN_ROLE_NAME)
+    } else if settings.user_groups.is_empty() || member_of(&settings.user_groups) {
+        Some(USER_ROLE_NAME)
+    } else {
+        None
+    }
+}
+
+/// 配置的组可以是完整 DN，也可以只写组名 (匹配 DN 的第一个 RDN 值)
+fn group_matches(group_dn: &str, wanted: &str) -> bool {
+    let wanted = wanted.trim();
+    if wanted.contains('=') {
+        return normalize_dn(group_dn) == normalize_dn(wanted);
+    }
+
+    group_dn
+        .split(',')
+        .next()
+        .and_then(|rdn| rdn.split_once('='))
+        .map(|(_, name)| name.trim().eq_ignore_ascii_case(wanted))
+        .unwrap_or(false)
+}
+
+fn normalize_dn(dn: &str) -> String {
+    dn.split(',')
+        .map(|rdn| rdn.trim().to_lowercase())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// 基于 ldap3 的目录实现，每次操作使用独立连接
+#[cfg(feature = "ldap")]
+struct Ldap3Directory {
+    settings: LdapSettings,
+}
+
+#[cfg(feature = "ldap")]
+impl Ldap3Directory {
+    async fn connect(&self) -> Result<ldap3::Ldap> {
+        let timeout = std::time::Duration::from_secs(self.settings.timeout);
+        let conn_settings = ldap3::LdapConnSettings::new()
+            .set_conn_timeout(timeout)
+            .set_starttls(self.settings.start_tls)
+            .set_no_tls_verify(self.settings.tls_skip_verify);
+
+        let (conn, mut ldap) =
+            ldap3::LdapConnAsync::with_settings(conn_settings, &self.settings.url)
+                .await
+                .map_err(|e| AppError::InternalError(format!("LDAP connection failed: {}", e)))?;
+        ldap3::drive!(conn);
+        ldap.with_timeout(timeout);
+
+        Ok(ldap)
+    }
+}
+
+#[cfg(feature = "ldap")]
+#[async_trait]
+impl LdapDirectory for Ldap3Directory {
+    async fn search_users(&self, filter: &str, attrs: &[String]) -> Result<Vec<LdapEntry>> {
+        let ldap_error =
+            |e: ldap3::LdapError| AppError::InternalError(format!("LDAP error: {}", e));
+        let mut ldap = self.connect().await?;
+
+        if !self.settings.bind_dn.is_empty() {
+            ldap.simple_bind(&self.settings.bind_dn, &self.settings.bind_password)
+                .await
+                .and_then(|result| result.success())
+                .map_err(|e| {
+                    AppError::InternalError(format!("LDAP service account bind failed: {}", e))
+                })?;
+        }
+
+        let (entries, _) = ldap
+            .search(
+                &self.settings.search_base,
+                ldap3::Scope::Subtree,
+                filter,
+                attrs.to_vec(),
+            )
+            .await
+            .and_then(|result| result.success())
+            .map_err(ldap_error)?;
+        let _ = ldap.unbind().await;
+
+        Ok(entries
+            .into_iter()
+            .filter(|entry| !entry.is_ref() && !entry.is_intermediate())
+            .map(|entry| {
+                let entry = ldap3::SearchEntry::construct(entry);
+                LdapEntry {
+                    dn: entry.dn,
+                    attrs: entry.attrs,
+                }
+            })
+            .collect())
+    }
+
+    async fn bind(&self, dn: &str, password: &str) -> Result<bool> {
+        let mut ldap = self.connect().await?;
+        let result = ldap
+            .simple_bind(dn, password)
+            .await
+            .map_err(|e| AppError::InternalError(format!("LDAP error: {}", e)))?;
+        let _ = ldap.unbind().await;
+
+        // 49 = invalidCredentials
+        match result.rc {
+            0 => Ok(true),
+            49 => Ok(false),
+            _ => Err(AppError::InternalError(format!(
+                "LDAP bind failed: {}",
+                result
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 进程内的目录替身：按过滤器精确匹配条目，密码按 DN 校验
+    struct InMemoryDirectory {
+        entries: Vec<(String, LdapEntry)>,
+        passwords: HashMap<String, String>,
+    }
+
+    impl InMemoryDirectory {
+        fn new() -> Self {
+            Self {
+                entries: Vec::new(),
+                passwords: HashMap::new(),
+            }
+        }
+
+        fn add_user(mut self, uid: &str, password: &str, groups: &[&str]) -> Self {
+            let dn = format!("uid={},ou=people,dc=example,dc=com", uid);
+            let mut attrs = HashMap::new();
+            attrs.insert("uid".to_string(), vec![uid.to_string()]);
+            attrs.insert("mail".to_string(), vec![format!("{}@example.com", uid)]);
+            attrs.insert(
+                "memberOf".to_string(),
+                groups.iter().map(|g| g.to_string()).collect(),
+            );
+            self.entries.push((
+                format!("(uid={})", uid),
+                LdapEntry {
+                    dn: dn.clone(),
+                    attrs,
+                },
+            ));
+            self.passwords.insert(dn, password.to_string());
+            self
+        }
+    }
+
+    #[async_trait]
+    impl LdapDirectory for InMemoryDirectory {
+        async fn search_users(&self, filter: &str, _attrs: &[String]) -> Result<Vec<LdapEntry>> {
+            Ok(self
+                .entries
+                .iter()
+                .filter(|(entry_filter, _)| entry_filter == filter)
+                .map(|(_, entry)| entry.clone())
+                .collect())
+        }
+
+        async fn bind(&self, dn: &str, password: &str) -> Result<bool> {
+            Ok(self.passwords.get(dn).map(String::as_str) == Some(password))
+        }
+    }
+
+    const ADMINS_DN: &str = "cn=relay-admins,ou=groups,dc=example,dc=com";
+    const STAFF_DN: &str = "cn=staff,ou=groups,dc=example,dc=com";
+
+    fn test_settings() -> LdapSettings {
+        LdapSettings {
+            enabled: true,
+            search_base: "ou=people,dc=example,dc=com".to_string(),
+            admin_groups: vec![ADMINS_DN.to_string()],
+            user_groups: vec!["staff".to_string()],
+            ..Default::default()
+        }
+    }
+
+    fn test_provider() -> LdapAuthProvider {
+        let directory = InMemoryDirectory::new()
+            .add_user("alice", "alice-pass", &[ADMINS_DN])
+            .add_user("bob", "bob-pass", &[STAFF_DN])
+            .add_user(
+                "eve",
+                "eve-pass",
+                &["cn=contractors,ou=groups,dc=example,dc=com"],
+            );
+        LdapAuthProvider::with_directory(test_settings(), Arc::new(directory))
+    }
+
+    #[test]
+    fn test_build_user_filter_escapes_value() {
+        assert_eq!(
+            build_user_filter("(uid={username})", "alice"),
+            "(uid=alice)"
+        );
+        assert_eq!(
+            build_user_filter("(&(objectClass=person)(uid={username}))", "*)(uid=*"),
+            "(&(objectClass=person)(uid=\\2a\\29\\28uid=\\2a))"
+        );
+    }
+
+    #[test]
+    fn test_resolve_role() {
+        let settings = test_settings();
+
+        assert_eq!(
+            resolve_role(&settings, &[ADMINS_DN.to_uppercase()]),
+            Some(ADMIN_ROLE_NAME)
+        );
+        assert_eq!(
+            resolve_role(&settings, &[STAFF_DN.to_string()]),
+            Some(USER_ROLE_NAME)
+        );
+        assert_eq!(resolve_role(&settings, &[]), None);
+
+        let open = LdapSettings {
+            user_groups: Vec::new(),
+            ..test_settings()
+        };
+        assert_eq!(resolve_role(&open, &[]), Some(USER_ROLE_NAME));
+    }
+
+    #[tokio::test]
+    async fn test_authenticate_maps_groups_to_roles() {
+        let provider = test_provider();
+
+        let alice = provider.authenticate("alice", "alice-pass").await.unwrap();
+        assert_eq!(alice.role, ADMIN_ROLE_NAME);
+        assert_eq!(alice.email.as_deref(), Some("alice@example.com"));
+        assert_eq!(alice.dn, "uid=alice,ou=people,dc=example,dc=com");
+
+        let bob = provider.authenticate("bob", "bob-pass").await.unwrap();
+        assert_eq!(bob.role, USER_ROLE_NAME);
+
+        assert!(matches!(
+            provider.authenticate("eve", "eve-pass").await,
+            Err(AppError::Forbidden(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_authenticate_rejects_invalid_credentials() {
+        let provider = test_provider();
+
+        for (username, password) in [
+            ("alice", "wrong"),
+            ("alice", ""),
+            ("mallory", "alice-pass"),
+            ("*", "alice-pass"),
+        ] {
+            assert!(
+                matches!(
+                    provider.authenticate(username, password).await,
+                    Err(AppError::Unauthorized(_))
+                ),
+                "{} should be rejected",
+                username
+            );
+        }
+    }
+}
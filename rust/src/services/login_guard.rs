@@ -0,0 +1,310 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::redis::RedisPool;
+use crate::services::webhook::WebhookService;
+use crate::utils::error::{AppError, Result};
+
+/// 失败计数窗口 (最后一次失败后多久清零)
+pub const LOGIN_FAILURE_WINDOW_SECONDS: i64 = 15 * 60;
+
+/// 锁定时长
+pub const LOGIN_LOCKOUT_SECONDS: u64 = 15 * 60;
+
+/// 退避等待的上限
+pub const LOGIN_MAX_BACKOFF_SECONDS: u64 = 300;
+
+/// 用户名：前 N 次失败不退避，达到上限后锁定
+const USERNAME_FREE_ATTEMPTS: i64 = 3;
+const USERNAME_LOCKOUT_THRESHOLD: i64 = 10;
+
+/// IP：同一 IP 可能对应多个管理员 (NAT)，阈值更宽松
+const IP_FREE_ATTEMPTS: i64 = 10;
+const IP_LOCKOUT_THRESHOLD: i64 = 50;
+
+/// 锁定维度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LockoutScope {
+    Username,
+    Ip,
+}
+
+impl LockoutScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Username => "username",
+            Self::Ip => "ip",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "username" => Some(Self::Username),
+            "ip" => Some(Self::Ip),
+            _ => None,
+        }
+    }
+
+    fn free_attempts(&self) -> i64 {
+        match self {
+            Self::Username => USERNAME_FREE_ATTEMPTS,
+            Self::Ip => IP_FREE_ATTEMPTS,
+        }
+    }
+
+    fn lockout_threshold(&self) -> i64 {
+        match self {
+            Self::Username => USERNAME_LOCKOUT_THRESHOLD,
+            Self::Ip => IP_LOCKOUT_THRESHOLD,
+        }
+    }
+}
+
+/// 一条锁定记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoginLockout {
+    pub scope: LockoutScope,
+    pub subject: String,
+    pub failures: i64,
+    pub locked_at: DateTime<Utc>,
+    pub locked_until: DateTime<Utc>,
+    /// 触发锁定的最后一次尝试的用户名和 IP
+    pub last_username: String,
+    pub last_ip: String,
+}
+
+/// 登录暴力破解防护
+///
+/// 按用户名和客户端 IP 分别统计失败次数 (Redis)：
+/// - 超过免费次数后，每次失败要求等待 `2^(失败次数 - 免费次数)` 秒 (最多 5 分钟)
+/// - 达到锁定阈值后锁定 15 分钟，并通过 webhook 发送 `login.locked_out` 事件
+///
+/// 登录成功只清除该用户名的计数，IP 计数需等待窗口过期，
+/// 避免攻击者用一个已知账号重置对其他账号的尝试次数。
+pub struct LoginGuardService {
+    redis: Arc<RedisPool>,
+    webhook_service: Option<Arc<WebhookService>>,
+}
+
+impl LoginGuardService {
+    pub fn new(redis: Arc<RedisPool>) -> Self {
+        Self {
+            redis,
+            webhook_service: None,
+        }
+    }
+
+    /// 设置 Webhook 服务 (用于锁定通知)
+    pub fn with_webhook_service(mut self, webhook_service: Arc<WebhookService>) -> Self {
+        self.webhook_service = Some(webhook_service);
+        self
+    }
+
+    /// 登录前检查，处于锁定或退避期时返回 429
+    pub async fn check(&self, username: &str, ip: &str) -> Result<()> {
+        for (scope, subject) in subjects(username, ip) {
+            let locked = self.redis.ttl(&lockout_key(scope, &subject)).await?;
+            if locked > 0 {
+                return Err(AppError::RateLimitExceeded(format!(
+                    "Too many failed login attempts, locked for {} seconds",
+                    locked
+                )));
+            }
+
+            let backoff = self.redis.ttl(&backoff_key(scope, &subject)).await?;
+            if backoff > 0 {
+                return Err(AppError::RateLimitExceeded(format!(
+                    "Too many failed login attempts, please retry in {} seconds",
+                    backoff
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 记录一次失败，按需设置退避或锁定
+    pub async fn record_failure(&self, username: &str, ip: &str) -> Result<()> {
+        for (scope, subject) in subjects(username, ip) {
+            let counter = failures_key(scope, &subject);
+            let failures = self.redis.incr(&counter).await?;
+            self.redis
+                .expire(&counter, LOGIN_FAILURE_WINDOW_SECONDS)
+                .await?;
+
+            if failures >= scope.lockout_threshold() {
+                self.lock(scope, &subject, failures, username, ip).await?;
+                continue;
+            }
+
+            let delay = backoff_seconds(failures, scope.free_attempts());
+            if delay > 0 {
+                self.redis
+                    .setex(&backoff_key(scope, &subject), "1", delay)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 登录成功后清除该用户名的失败计数和退避
+    pub async fn record_success(&self, username: &str) -> Result<()> {
+        let subject = normalize_username(username);
+        self.redis
+            .del(&failures_key(LockoutScope::Username, &subject))
+            .await?;
+        self.redis
+            .del(&backoff_key(LockoutScope::Username, &subject))
+            .await
+    }
+
+    /// 当前所有锁定 (按锁定时间倒序)
+    pub async fn list_lockouts(&self) -> Result<Vec<LoginLockout>> {
+        let mut lockouts = Vec::new();
+        for key in self.redis.keys("login_lockout:*").await? {
+            let Some(json) = self.redis.get::<String>(&key).await? else {
+                continue;
+            };
+            match serde_json::from_str::<LoginLockout>(&json) {
+                Ok(lockout) => lockouts.push(lockout),
+                Err(e) => warn!("⚠️  Skipping malformed login lockout {}: {}", key, e),
+            }
+        }
+
+        lockouts.sort_by_key(|lockout| std::cmp::Reverse(lockout.locked_at));
+        Ok(lockouts)
+    }
+
+    /// 解除锁定，同时清除失败计数和退避，返回是否存在锁定
+    pub async fn clear_lockout(&self, scope: LockoutScope, subject: &str) -> Result<bool> {
+        let subject = match scope {
+            LockoutScope::Username => normalize_username(subject),
+            LockoutScope::Ip => subject.to_string(),
+        };
+        let key = lockout_key(scope, &subject);
+        let existed = self.redis.exists(&key).await?;
+
+        self.redis.del(&key).await?;
+        self.redis.del(&failures_key(scope, &subject)).await?;
+        self.redis.del(&backoff_key(scope, &subject)).await?;
+
+        Ok(existed)
+    }
+
+    async fn lock(
+        &self,
+        scope: LockoutScope,
+        subject: &str,
+        failures: i64,
+        username: &str,
+        ip: &str,
+    ) -> Result<()> {
+        let now = Utc::now();
+        let lockout = LoginLockout {
+            scope,
+            subject: subject.to_string(),
+            failures,
+            locked_at: now,
+            locked_until: now + Duration::seconds(LOGIN_LOCKOUT_SECONDS as i64),
+            last_username: username.to_string(),
+            last_ip: ip.to_string(),
+        };
+        let json = serde_json::to_string(&lockout)?;
+
+        self.redis
+            .setex(&lockout_key(scope, subject), &json, LOGIN_LOCKOUT_SECONDS)
+            .await?;
+        self.redis.del(&failures_key(scope, subject)).await?;
+        self.redis.del(&backoff_key(scope, subject)).await?;
+
+        warn!(
+            "🔒 Admin login locked for {} {} after {} failed attempts",
+            scope.as_str(),
+            subject,
+            failures
+        );
+
+        if let Some(webhook_service) = self.webhook_service.clone() {
+            let data = serde_json::to_value(&lockout)?;
+            tokio::spawn(async move {
+                if let Err(e) = webhook_service
+                    .trigger_event("login.locked_out", data)
+                    .await
+                {
+                    warn!("⚠️ Failed to send login lockout webhook: {}", e);
+                }
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// 超过免费次数后的退避秒数 (指数增长，有上限)
+fn backoff_seconds(failures: i64, free_attempts: i64) -> u64 {
+    let exponent = failures - free_attempts;
+    if exponent <= 0 {
+        return 0;
+    }
+
+    1u64.checked_shl(exponent.min(63) as u32)
+        .unwrap_or(u64::MAX)
+        .min(LOGIN_MAX_BACKOFF_SECONDS)
+}
+
+fn normalize_username(username: &str) -> String {
+    username.trim().to_lowercase()
+}
+
+fn subjects(username: &str, ip: &str) -> [(LockoutScope, String); 2] {
+    [
+        (LockoutScope::Username, normalize_username(username)),
+        (LockoutScope::Ip, ip.to_string()),
+    ]
+}
+
+fn failures_key(scope: LockoutScope, subject: &str) -> String {
+    format!("login_failures:{}:{}", scope.as_str(), subject)
+}
+
+fn backoff_key(scope: LockoutScope, subject: &str) -> String {
+    format!("login_backoff:{}:{}", scope.as_str(), subject)
+}
+
+fn lockout_key(scope: LockoutScope, subject: &str) -> String {
+    format!("login_lockout:{}:{}", scope.as_str(), subject)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_seconds() {
+        assert_eq!(backoff_seconds(1, 3), 0);
+        assert_eq!(backoff_seconds(3, 3), 0);
+        assert_eq!(backoff_seconds(4, 3), 2);
+        assert_eq!(backoff_seconds(6, 3), 8);
+        assert_eq!(backoff_seconds(12, 3), LOGIN_MAX_BACKOFF_SECONDS);
+        assert_eq!(backoff_seconds(500, 3), LOGIN_MAX_BACKOFF_SECONDS);
+    }
+
+    #[test]
+    fn test_lockout_scope_and_keys() {
+        assert_eq!(LockoutScope::parse("ip"), Some(LockoutScope::Ip));
+        assert_eq!(LockoutScope::parse("user"), None);
+        assert_eq!(
+            lockout_key(LockoutScope::Username, &normalize_username(" Admin ")),
+            "login_lockout:username:admin"
+        );
+        assert_eq!(
+            serde_json::to_value(LockoutScope::Username).unwrap(),
+            "username"
+        );
+    }
+}
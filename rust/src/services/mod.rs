@@ -1,34 +1,65 @@
 pub mod account;
 pub mod account_scheduler;
+pub mod account_usage;
 pub mod admin;
+pub mod admin_token;
 pub mod api_key;
+pub mod audit;
 pub mod bedrock_relay;
+pub mod billing_events;
+pub mod billing_multiplier;
 pub mod claude_relay;
+pub mod cost_recalculation;
+pub mod dashboard;
 pub mod gemini_relay;
+pub mod ldap;
+pub mod login_guard;
+pub mod oem;
 pub mod openai_relay;
+pub mod pricing_override;
 pub mod pricing_service;
 pub mod relay_trait;
+pub mod role;
+pub mod statement;
 pub mod token_refresh;
+pub mod two_factor;
 pub mod unified_claude_scheduler;
 pub mod unified_gemini_scheduler;
 pub mod unified_openai_scheduler;
+pub mod usage_retention;
+pub mod usage_settlement;
+pub mod usage_trend;
+pub mod user;
+pub mod wallet;
 pub mod webhook;
 
 pub use account::ClaudeAccountService;
 pub use account_scheduler::{
     AccountScheduler, AccountSchedulerConfig, SelectedAccount, SessionMapping,
 };
+pub use account_usage::AccountUsageService;
 pub use admin::{
-    AdminCredentials, AdminService, Claims, InitData, LoginRequest, LoginResponse, UserInfo,
+    AdminCredentials, AdminService, Claims, InitData, LoginRequest, LoginResponse, LogoutRequest,
+    RefreshTokenRequest, UserInfo,
 };
+pub use admin_token::AdminApiTokenService;
 pub use api_key::ApiKeyService;
+pub use audit::AuditService;
 pub use bedrock_relay::{BedrockRelayConfig, BedrockRelayService};
+pub use billing_events::BillingEventService;
+pub use billing_multiplier::BillingMultiplierService;
 pub use claude_relay::{
     ClaudeRelayConfig, ClaudeRelayService, ClaudeRequest, ClaudeResponse, Message, RelayResponse,
     StreamChunk, Usage,
 };
+pub use cost_recalculation::CostRecalculationService;
+pub use dashboard::DashboardService;
 pub use gemini_relay::{GeminiRelayConfig, GeminiRelayService};
+pub use ldap::{LdapAuthProvider, LdapDirectory, LdapEntry, LdapUser};
+pub use login_guard::{LockoutScope, LoginGuardService, LoginLockout};
+pub use oem::OemSettingsService;
 pub use openai_relay::{OpenAIRelayConfig, OpenAIRelayService};
+pub use pricing_override::PricingOverrideService;
 pub use pricing_service::{
     CacheCreation, CostResult, LongContextPricing, ModelPricing, PricingDetails, PricingService,
     PricingStatus, UpdateResult, Usage as PricingUsage,
@@ -36,7 +67,10 @@ pub use pricing_service::{
 pub use relay_trait::{
     GenericRelayResponse, GenericStreamChunk, RelayManager, RelayRequest, RelayService, UsageStats,
 };
+pub use role::RoleService;
+pub use statement::StatementService;
 pub use token_refresh::{RefreshResult, TokenRefreshConfig, TokenRefreshService};
+pub use two_factor::{TwoFactorEnrollment, TwoFactorService, TwoFactorStatus};
 pub use unified_claude_scheduler::{
     SchedulerAccountVariant, SelectedAccount as UnifiedSelectedAccount, UnifiedClaudeScheduler,
 };
@@ -46,4 +80,9 @@ pub use unified_gemini_scheduler::{
 pub use unified_openai_scheduler::{
     SelectedAccount as UnifiedOpenAISelectedAccount, UnifiedOpenAIScheduler,
 };
+pub use usage_retention::UsageRetentionService;
+pub use usage_settlement::{SettleableUsage, UsageSettlement};
+pub use usage_trend::UsageTrendService;
+pub use user::{RegisterUserRequest, UserService};
+pub use wallet::WalletService;
 pub use webhook::{WebhookConfig, WebhookPayload, WebhookService};
@@ -0,0 +1,52 @@
+use chrono::Utc;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::models::oem::{OemSettings, OemSettingsUpdate};
+use crate::redis::RedisPool;
+use crate::utils::error::{AppError, Result};
+
+const OEM_SETTINGS_KEY: &str = "oem_settings";
+
+/// OEM 品牌设置服务
+pub struct OemSettingsService {
+    redis: Arc<RedisPool>,
+}
+
+impl OemSettingsService {
+    pub fn new(redis: Arc<RedisPool>) -> Self {
+        Self { redis }
+    }
+
+    /// 当前设置，未保存过或数据损坏时返回默认值
+    pub async fn get_settings(&self) -> Result<OemSettings> {
+        let Some(json) = self.redis.get::<String>(OEM_SETTINGS_KEY).await? else {
+            return Ok(OemSettings::default());
+        };
+
+        Ok(serde_json::from_str(&json).unwrap_or_else(|e| {
+            warn!("⚠️  Invalid OEM settings in Redis, using defaults: {}", e);
+            OemSettings::default()
+        }))
+    }
+
+    /// 合并并校验更新，返回更新前后的设置
+    pub async fn update_settings(
+        &self,
+        update: OemSettingsUpdate,
+        updated_by: &str,
+    ) -> Result<(OemSettings, OemSettings)> {
+        let before = self.get_settings().await?;
+        let mut settings = before.clone();
+        settings.apply(update);
+        settings.validate().map_err(AppError::ValidationError)?;
+        settings.updated_at = Some(Utc::now());
+
+        let json = serde_json::to_string(&settings)?;
+        self.redis.set(OEM_SETTINGS_KEY, &json).await?;
+
+        info!("🎨 OEM settings updated by: {}", updated_by);
+
+        Ok((before, settings))
+    }
+}
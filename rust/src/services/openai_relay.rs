@@ -6,13 +6,14 @@ use crate::services::relay_trait::{
     GenericRelayResponse, GenericStreamChunk, RelayRequest, RelayService, UsageStats,
 };
 use crate::utils::error::{AppError, Result};
+use crate::utils::metrics;
 use anyhow::Context;
 use async_trait::async_trait;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value as JsonValue};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 use tokio::time::timeout;
 use tracing::info;
@@ -181,6 +182,7 @@ impl RelayService for OpenAIRelayService {
         let url = format!("{}/chat/completions", self.config.api_base_url);
 
         // 6. 发送请求
+        let started = Instant::now();
         let response = timeout(
             Duration::from_secs(self.config.timeout_seconds),
             self.http_client
@@ -190,9 +192,11 @@ impl RelayService for OpenAIRelayService {
                 .json(&openai_body)
                 .send(),
         )
-        .await
-        .context("Request timeout")?
-        .context("Failed to send request")?;
+        .await;
+        metrics::observe_upstream("openai", &selected_account.account_id, started, &response);
+        let response = response
+            .context("Request timeout")?
+            .context("Failed to send request")?;
 
         let status_code = response.status().as_u16();
         let headers: Vec<(String, String)> = response
@@ -0,0 +1,157 @@
+use chrono::{TimeZone, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::models::pricing_override::{
+    PricingOverride, PricingOverrideRequest, UnpricedModel, PRICING_OVERRIDES_KEY,
+    UNPRICED_MODELS_KEY, UNPRICED_MODEL_REQUESTS_KEY,
+};
+use crate::redis::RedisPool;
+use crate::services::pricing_service::PricingService;
+use crate::utils::error::{AppError, Result};
+
+/// 无定价模型列表的最大返回数
+pub const MAX_UNPRICED_MODELS: usize = 1000;
+
+/// 读取全部管理员定价 (模型名 -> 定价)，跳过无法解析的记录
+pub(crate) async fn load_pricing_overrides(
+    redis: &RedisPool,
+) -> Result<HashMap<String, PricingOverride>> {
+    let mut overrides = HashMap::new();
+    for (model, json) in redis.hgetall(PRICING_OVERRIDES_KEY).await? {
+        match serde_json::from_str(&json) {
+            Ok(pricing) => {
+                overrides.insert(model, pricing);
+            }
+            Err(e) => warn!("⚠️ Invalid pricing override for {}: {}", model, e),
+        }
+    }
+    Ok(overrides)
+}
+
+/// 管理员定价服务
+///
+/// 定价存储在 `pricing_overrides` Hash，`PricingService` 最多 30 秒后读取到修改
+pub struct PricingOverrideService {
+    redis: Arc<RedisPool>,
+}
+
+impl PricingOverrideService {
+    pub fn new(redis: Arc<RedisPool>) -> Self {
+        Self { redis }
+    }
+
+    /// 全部管理员定价 (按模型名排序)
+    pub async fn list(&self) -> Result<Vec<PricingOverride>> {
+        let mut overrides: Vec<PricingOverride> = load_pricing_overrides(&self.redis)
+            .await?
+            .into_values()
+            .collect();
+        overrides.sort_by(|a, b| a.model.cmp(&b.model));
+        Ok(overrides)
+    }
+
+    pub async fn get(&self, model: &str) -> Result<PricingOverride> {
+        self.find(model)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Pricing override not found: {}", model)))
+    }
+
+    /// 创建或替换模型定价，返回修改前后的定价
+    pub async fn upsert(
+        &self,
+        model: &str,
+        request: PricingOverrideRequest,
+        updated_by: &str,
+    ) -> Result<(Option<PricingOverride>, PricingOverride)> {
+        let model = model.trim();
+        if model.is_empty() {
+            return Err(AppError::ValidationError(
+                "Model name is required".to_string(),
+            ));
+        }
+        request.validate().map_err(AppError::ValidationError)?;
+
+        let before = self.find(model).await?;
+        let pricing = request.into_override(model, updated_by);
+        let json = serde_json::to_string(&pricing)?;
+
+        // 有定价后不再出现在无定价模型列表中
+        let mut conn = self.redis.get_connection().await?;
+        redis::pipe()
+            .atomic()
+            .hset(PRICING_OVERRIDES_KEY, model, json)
+            .zrem(UNPRICED_MODELS_KEY, model)
+            .hdel(UNPRICED_MODEL_REQUESTS_KEY, model)
+            .query_async::<_, ()>(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to save pricing override: {}", e)))?;
+
+        info!("💰 Pricing override for {} saved by: {}", model, updated_by);
+        Ok((before, pricing))
+    }
+
+    /// 删除模型定价，返回删除前的定价
+    pub async fn delete(&self, model: &str, deleted_by: &str) -> Result<PricingOverride> {
+        let before = self.get(model).await?;
+
+        let mut conn = self.redis.get_connection().await?;
+        redis::cmd("HDEL")
+            .arg(PRICING_OVERRIDES_KEY)
+            .arg(model)
+            .query_async::<_, ()>(&mut conn)
+            .await
+            .map_err(|e| {
+                AppError::RedisError(format!("Failed to delete pricing override: {}", e))
+            })?;
+
+        info!(
+            "🗑️ Pricing override for {} deleted by: {}",
+            model, deleted_by
+        );
+        Ok(before)
+    }
+
+    /// 流量中出现过、目前仍没有定价的模型 (最近出现的在前)
+    ///
+    /// `pricing` 需已加载镜像数据并启用管理员定价，之后补上定价的模型会被过滤掉
+    pub async fn unpriced_models(
+        &self,
+        pricing: &PricingService,
+        limit: usize,
+    ) -> Result<Vec<UnpricedModel>> {
+        let limit = limit.clamp(1, MAX_UNPRICED_MODELS);
+        let mut conn = self.redis.get_connection().await?;
+        let (seen, requests): (Vec<(String, i64)>, HashMap<String, i64>) = redis::pipe()
+            .cmd("ZREVRANGE")
+            .arg(UNPRICED_MODELS_KEY)
+            .arg(0)
+            .arg(limit as isize - 1)
+            .arg("WITHSCORES")
+            .cmd("HGETALL")
+            .arg(UNPRICED_MODEL_REQUESTS_KEY)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to get unpriced models: {}", e)))?;
+
+        let mut models = Vec::with_capacity(seen.len());
+        for (model, last_seen) in seen {
+            if pricing.get_model_pricing(&model).await.is_some() {
+                continue;
+            }
+            models.push(UnpricedModel {
+                requests: requests.get(&model).copied().unwrap_or(0),
+                last_seen_at: Utc.timestamp_opt(last_seen, 0).single(),
+                model,
+            });
+        }
+        Ok(models)
+    }
+
+    async fn find(&self, model: &str) -> Result<Option<PricingOverride>> {
+        let json: Option<String> = self.redis.hget(PRICING_OVERRIDES_KEY, model).await?;
+        json.map(|json| serde_json::from_str(&json).map_err(AppError::from))
+            .transpose()
+    }
+}
@@ -7,18 +7,25 @@
 // - 定时更新（24小时）和哈希轮询（10分钟）
 // - 文件监听和自动重载
 // - 成本计算（支持 1M 上下文和 1h 缓存）
+// - 管理员配置的模型定价覆盖（Redis）
 
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use tokio::time::{interval, Duration};
+use tokio::time::{interval, Duration, Instant};
 use tracing::{debug, error, info, warn};
 
+use crate::models::pricing_override::{
+    PricingOverride, UNPRICED_MODELS_KEY, UNPRICED_MODEL_REQUESTS_KEY,
+};
+use crate::redis::RedisPool;
+use crate::services::pricing_override::load_pricing_overrides;
+
 /// 模型定价数据
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ModelPricing {
@@ -75,7 +82,8 @@ pub struct Usage {
 }
 
 /// 详细缓存创建数据
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
 pub struct CacheCreation {
     pub ephemeral_5m_input_tokens: i64,
     pub ephemeral_1h_input_tokens: i64,
@@ -110,6 +118,8 @@ pub struct PricingService {
     // 数据
     pricing_data: Arc<RwLock<Option<HashMap<String, ModelPricing>>>>,
     last_updated: Arc<RwLock<Option<DateTime<Utc>>>>,
+    /// 当前定价数据内容的 SHA-256 (计费事件中的定价版本)
+    pricing_version: Arc<RwLock<Option<String>>>,
 
     // 间隔
     update_interval: Duration,     // 24 小时
@@ -124,6 +134,39 @@ pub struct PricingService {
 
     // 哈希同步状态
     hash_sync_in_progress: Arc<RwLock<bool>>,
+
+    // 管理员配置的定价 (未设置 Redis 时不启用)
+    redis: Option<Arc<RedisPool>>,
+    overrides: Arc<RwLock<OverrideCache>>,
+}
+
+/// 管理员定价的内存缓存
+#[derive(Default)]
+struct OverrideCache {
+    entries: HashMap<String, PricingOverride>,
+    loaded_at: Option<Instant>,
+}
+
+impl OverrideCache {
+    fn is_fresh(&self) -> bool {
+        self.loaded_at
+            .is_some_and(|at| at.elapsed() < OVERRIDE_REFRESH_INTERVAL)
+    }
+}
+
+/// 管理员定价的刷新间隔，修改后最多延迟这么久在所有实例生效
+const OVERRIDE_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+impl From<&PricingOverride> for ModelPricing {
+    fn from(pricing: &PricingOverride) -> Self {
+        Self {
+            input_cost_per_token: pricing.input_cost_per_token,
+            output_cost_per_token: pricing.output_cost_per_token,
+            cache_creation_input_token_cost: pricing.cache_creation_input_token_cost,
+            cache_read_input_token_cost: pricing.cache_read_input_token_cost,
+            litellm_provider: None,
+        }
+    }
 }
 
 impl PricingService {
@@ -214,15 +257,24 @@ impl PricingService {
             local_hash_file,
             pricing_data: Arc::new(RwLock::new(None)),
             last_updated: Arc::new(RwLock::new(None)),
+            pricing_version: Arc::new(RwLock::new(None)),
             update_interval: Duration::from_secs(24 * 3600),
             hash_check_interval: Duration::from_secs(10 * 60),
             ephemeral_1h_pricing,
             long_context_pricing,
             http_client,
             hash_sync_in_progress: Arc::new(RwLock::new(false)),
+            redis: None,
+            overrides: Arc::new(RwLock::new(OverrideCache::default())),
         }
     }
 
+    /// 启用管理员配置的定价 (存储在 Redis 的 `pricing_overrides`)
+    pub fn with_overrides(mut self, redis: Arc<RedisPool>) -> Self {
+        self.redis = Some(redis);
+        self
+    }
+
     /// 初始化价格服务
     pub async fn initialize(&self) -> Result<()> {
         // 确保 data 目录存在
@@ -245,6 +297,32 @@ impl PricingService {
         Ok(())
     }
 
+    /// 只加载本地定价数据 (data 目录或 fallback)，不下载也不启动定时任务
+    ///
+    /// 供命令行等一次性任务使用，读取的是运行中服务维护的同一份定价文件
+    pub async fn load_local(&self) -> Result<()> {
+        tokio::fs::create_dir_all(&self.data_dir).await?;
+        self.load_pricing_data().await
+    }
+
+    /// 加载指定的定价快照文件 (格式同价格镜像)，不写入 data 目录
+    pub async fn load_snapshot(&self, path: &Path) -> Result<()> {
+        let data = tokio::fs::read(path).await?;
+        let json_data: HashMap<String, ModelPricing> = serde_json::from_slice(&data)?;
+
+        *self.pricing_data.write().await = Some(json_data.clone());
+        *self.pricing_version.write().await = Some(content_hash(&data));
+        *self.last_updated.write().await = Some(Utc::now());
+
+        info!(
+            "💰 Loaded pricing snapshot for {} models from {:?}",
+            json_data.len(),
+            path
+        );
+
+        Ok(())
+    }
+
     /// 检查并更新价格数据
     async fn check_and_update_pricing(&self) -> Result<()> {
         let needs_update = self.needs_update().await?;
@@ -311,10 +389,11 @@ impl PricingService {
         tokio::fs::write(&self.pricing_file, &content).await?;
 
         // 更新哈希
-        self.persist_local_hash(&content).await?;
+        let hash = self.persist_local_hash(&content).await?;
 
         // 更新内存数据
         *self.pricing_data.write().await = Some(json_data.clone());
+        *self.pricing_version.write().await = Some(hash);
         *self.last_updated.write().await = Some(Utc::now());
 
         info!("💰 Downloaded pricing data for {} models", json_data.len());
@@ -342,10 +421,11 @@ impl PricingService {
 
         // 保存到 data 目录
         tokio::fs::write(&self.pricing_file, &formatted_json).await?;
-        self.persist_local_hash(&formatted_json).await?;
+        let hash = self.persist_local_hash(&formatted_json).await?;
 
         // 更新内存数据
         *self.pricing_data.write().await = Some(json_data.clone());
+        *self.pricing_version.write().await = Some(hash);
         *self.last_updated.write().await = Some(Utc::now());
 
         warn!(
@@ -368,6 +448,7 @@ impl PricingService {
         let json_data: HashMap<String, ModelPricing> = serde_json::from_slice(&data)?;
 
         *self.pricing_data.write().await = Some(json_data.clone());
+        *self.pricing_version.write().await = Some(content_hash(&data));
 
         let metadata = tokio::fs::metadata(&self.pricing_file).await?;
         *self.last_updated.write().await = Some(DateTime::from(metadata.modified()?));
@@ -468,9 +549,7 @@ impl PricingService {
 
     /// 持久化本地哈希
     async fn persist_local_hash(&self, content: &[u8]) -> Result<String> {
-        let mut hasher = Sha256::new();
-        hasher.update(content);
-        let hash = format!("{:x}", hasher.finalize());
+        let hash = content_hash(content);
 
         tokio::fs::write(&self.local_hash_file, format!("{}\n", hash)).await?;
 
@@ -513,6 +592,12 @@ impl PricingService {
 
     /// 获取模型定价
     pub async fn get_model_pricing(&self, model_name: &str) -> Option<ModelPricing> {
+        // 0. 管理员配置的定价优先于镜像数据
+        if let Some(pricing) = self.model_override(model_name).await {
+            debug!("💰 Using pricing override for {}", model_name);
+            return Some(ModelPricing::from(&pricing));
+        }
+
         let data = self.pricing_data.read().await;
         let pricing_map = data.as_ref()?;
 
@@ -612,33 +697,46 @@ impl PricingService {
 
     /// 计算使用费用
     pub async fn calculate_cost(&self, usage: &Usage, model_name: &str) -> CostResult {
-        // 检查是否为 1M 上下文模型
-        let is_long_context_model = model_name.contains("[1m]");
+        let model_override = self.model_override(model_name).await;
+        let total_input_tokens =
+            usage.input_tokens + usage.cache_creation_input_tokens + usage.cache_read_input_tokens;
         let mut is_long_context_request = false;
-        let mut use_long_context_pricing = false;
 
-        if is_long_context_model {
-            let total_input_tokens = usage.input_tokens
-                + usage.cache_creation_input_tokens
-                + usage.cache_read_input_tokens;
-
-            if total_input_tokens > 200_000 {
+        let long_context_tier = model_override
+            .as_ref()
+            .and_then(|o| o.long_context.as_ref());
+        let long_prices = match long_context_tier {
+            // 管理员配置的长上下文档位
+            Some(tier) => {
+                is_long_context_request = total_input_tokens > tier.threshold_tokens;
+                is_long_context_request.then_some(LongContextPricing {
+                    input: tier.input_cost_per_token,
+                    output: tier.output_cost_per_token,
+                })
+            }
+            // 1M 上下文模型
+            None if model_name.contains("[1m]") && total_input_tokens > 200_000 => {
                 is_long_context_request = true;
-                if self.long_context_pricing.contains_key(model_name) {
-                    use_long_context_pricing = true;
-                } else if let Some(default_model) = self.long_context_pricing.keys().next() {
-                    use_long_context_pricing = true;
-                    warn!(
-                        "⚠️ No specific 1M pricing for {}, using default from {}",
-                        model_name, default_model
-                    );
+                match self.long_context_pricing.get(model_name) {
+                    Some(prices) => Some(prices.clone()),
+                    None => {
+                        let default = self.long_context_pricing.iter().next();
+                        if let Some((default_model, _)) = default {
+                            warn!(
+                                "⚠️ No specific 1M pricing for {}, using default from {}",
+                                model_name, default_model
+                            );
+                        }
+                        default.map(|(_, prices)| prices.clone())
+                    }
                 }
             }
-        }
+            None => None,
+        };
 
         let pricing = self.get_model_pricing(model_name).await;
 
-        if pricing.is_none() && !use_long_context_pricing {
+        if pricing.is_none() && long_prices.is_none() {
             return CostResult {
                 input_cost: 0.0,
                 output_cost: 0.0,
@@ -659,29 +757,6 @@ impl PricingService {
             };
         }
 
-        let (input_cost, output_cost) = if use_long_context_pricing {
-            let long_prices = self
-                .long_context_pricing
-                .get(model_name)
-                .or_else(|| self.long_context_pricing.values().next())
-                .unwrap();
-
-            let input_cost = usage.input_tokens as f64 * long_prices.input;
-            let output_cost = usage.output_tokens as f64 * long_prices.output;
-
-            info!(
-                "💰 Using 1M context pricing for {}: input=${}/token, output=${}/token",
-                model_name, long_prices.input, long_prices.output
-            );
-
-            (input_cost, output_cost)
-        } else {
-            let pricing = pricing.as_ref().unwrap();
-            let input_cost = usage.input_tokens as f64 * pricing.input_cost_per_token;
-            let output_cost = usage.output_tokens as f64 * pricing.output_cost_per_token;
-            (input_cost, output_cost)
-        };
-
         let pricing_ref = pricing.as_ref().unwrap_or(&ModelPricing {
             input_cost_per_token: 0.0,
             output_cost_per_token: 0.0,
@@ -690,16 +765,36 @@ impl PricingService {
             litellm_provider: None,
         });
 
+        let (input_price, output_price) = match &long_prices {
+            Some(long_prices) => {
+                info!(
+                    "💰 Using long context pricing for {}: input=${}/token, output=${}/token",
+                    model_name, long_prices.input, long_prices.output
+                );
+                (long_prices.input, long_prices.output)
+            }
+            None => (
+                pricing_ref.input_cost_per_token,
+                pricing_ref.output_cost_per_token,
+            ),
+        };
+        let input_cost = usage.input_tokens as f64 * input_price;
+        let output_cost = usage.output_tokens as f64 * output_price;
+
         let cache_read_cost = usage.cache_read_input_tokens as f64
             * pricing_ref.cache_read_input_token_cost.unwrap_or(0.0);
 
+        let ephemeral_1h_price = model_override
+            .as_ref()
+            .and_then(|o| o.cache_creation_1h_input_token_cost)
+            .unwrap_or_else(|| self.get_ephemeral_1h_pricing(model_name));
+
         // 处理缓存创建费用
         let (ephemeral_5m_cost, ephemeral_1h_cost, cache_create_cost) =
             if let Some(ref cache_creation) = usage.cache_creation {
                 let ephemeral_5m = cache_creation.ephemeral_5m_input_tokens as f64
                     * pricing_ref.cache_creation_input_token_cost.unwrap_or(0.0);
 
-                let ephemeral_1h_price = self.get_ephemeral_1h_pricing(model_name);
                 let ephemeral_1h =
                     cache_creation.ephemeral_1h_input_tokens as f64 * ephemeral_1h_price;
 
@@ -723,31 +818,81 @@ impl PricingService {
             has_pricing: true,
             is_long_context_request,
             pricing: PricingDetails {
-                input: if use_long_context_pricing {
-                    self.long_context_pricing
-                        .get(model_name)
-                        .or_else(|| self.long_context_pricing.values().next())
-                        .map(|p| p.input)
-                        .unwrap_or(0.0)
-                } else {
-                    pricing_ref.input_cost_per_token
-                },
-                output: if use_long_context_pricing {
-                    self.long_context_pricing
-                        .get(model_name)
-                        .or_else(|| self.long_context_pricing.values().next())
-                        .map(|p| p.output)
-                        .unwrap_or(0.0)
-                } else {
-                    pricing_ref.output_cost_per_token
-                },
+                input: input_price,
+                output: output_price,
                 cache_create: pricing_ref.cache_creation_input_token_cost.unwrap_or(0.0),
                 cache_read: pricing_ref.cache_read_input_token_cost.unwrap_or(0.0),
-                ephemeral_1h: self.get_ephemeral_1h_pricing(model_name),
+                ephemeral_1h: ephemeral_1h_price,
             },
         }
     }
 
+    /// 管理员为该模型配置的定价 (按模型名精确匹配)
+    ///
+    /// 从 Redis 读取并在内存缓存 30 秒，未启用覆盖时返回 None
+    pub async fn model_override(&self, model_name: &str) -> Option<PricingOverride> {
+        let redis = self.redis.as_ref()?;
+        {
+            let cache = self.overrides.read().await;
+            if cache.is_fresh() {
+                return cache.entries.get(model_name).cloned();
+            }
+        }
+
+        let mut cache = self.overrides.write().await;
+        if !cache.is_fresh() {
+            match load_pricing_overrides(redis).await {
+                Ok(entries) => cache.entries = entries,
+                Err(e) => warn!("⚠️ Failed to load pricing overrides: {}", e),
+            }
+            cache.loaded_at = Some(Instant::now());
+        }
+        cache.entries.get(model_name).cloned()
+    }
+
+    /// 记录流量中出现的无定价模型，供管理界面提示补充定价
+    pub async fn record_unpriced_model(&self, model_name: &str) {
+        let Some(redis) = &self.redis else {
+            return;
+        };
+        let result = async {
+            let mut conn = redis.get_connection().await?;
+            redis::pipe()
+                .zadd(UNPRICED_MODELS_KEY, model_name, Utc::now().timestamp())
+                .hincr(UNPRICED_MODEL_REQUESTS_KEY, model_name, 1)
+                .query_async::<_, ()>(&mut conn)
+                .await?;
+            Ok::<(), anyhow::Error>(())
+        }
+        .await;
+
+        match result {
+            Ok(()) => debug!("💰 Recorded unpriced model: {}", model_name),
+            Err(e) => warn!("⚠️ Failed to record unpriced model {}: {}", model_name, e),
+        }
+    }
+
+    /// 预估请求费用（用于请求前的成本限制检查）
+    ///
+    /// 按输入 tokens 全部计入、输出 tokens 取 `max_output_tokens` 上限计算，
+    /// 结果为该请求可能产生的最大费用
+    pub async fn estimate_cost(
+        &self,
+        model_name: &str,
+        input_tokens: i64,
+        max_output_tokens: i64,
+    ) -> f64 {
+        let usage = Usage {
+            input_tokens,
+            output_tokens: max_output_tokens,
+            cache_creation_input_tokens: 0,
+            cache_read_input_tokens: 0,
+            cache_creation: None,
+        };
+
+        self.calculate_cost(&usage, model_name).await.total_cost
+    }
+
     /// 格式化费用
     pub fn format_cost(&self, cost: f64) -> String {
         if cost == 0.0 {
@@ -763,6 +908,15 @@ impl PricingService {
         }
     }
 
+    /// 当前定价数据的版本 (内容 SHA-256 的前 12 位)，未加载时为 None
+    pub async fn pricing_version(&self) -> Option<String> {
+        self.pricing_version
+            .read()
+            .await
+            .as_ref()
+            .map(|hash| hash.chars().take(12).collect())
+    }
+
     /// 获取服务状态
     pub async fn get_status(&self) -> PricingStatus {
         let data = self.pricing_data.read().await;
@@ -800,6 +954,13 @@ impl PricingService {
     }
 }
 
+/// 定价文件内容的 SHA-256
+fn content_hash(content: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(content);
+    format!("{:x}", hasher.finalize())
+}
+
 // 实现 Clone 用于定时任务
 impl Clone for PricingService {
     fn clone(&self) -> Self {
@@ -812,12 +973,15 @@ impl Clone for PricingService {
             local_hash_file: self.local_hash_file.clone(),
             pricing_data: Arc::clone(&self.pricing_data),
             last_updated: Arc::clone(&self.last_updated),
+            pricing_version: Arc::clone(&self.pricing_version),
             update_interval: self.update_interval,
             hash_check_interval: self.hash_check_interval,
             ephemeral_1h_pricing: self.ephemeral_1h_pricing.clone(),
             long_context_pricing: self.long_context_pricing.clone(),
             http_client: Arc::clone(&self.http_client),
             hash_sync_in_progress: Arc::clone(&self.hash_sync_in_progress),
+            redis: self.redis.clone(),
+            overrides: Arc::clone(&self.overrides),
         }
     }
 }
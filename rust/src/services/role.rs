@@ -0,0 +1,205 @@
+use chrono::Utc;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::models::role::{builtin_roles, Permission, Role, USER_ROLE_NAME};
+use crate::redis::RedisPool;
+use crate::utils::error::{AppError, Result};
+
+/// 角色管理服务
+///
+/// 内置角色固定在代码中，自定义角色存储在 `admin_role:{name}`
+pub struct RoleService {
+    redis: Arc<RedisPool>,
+}
+
+impl RoleService {
+    pub fn new(redis: Arc<RedisPool>) -> Self {
+        Self { redis }
+    }
+
+    /// 获取角色，普通用户角色和不存在的角色返回 None
+    pub async fn get_role(&self, name: &str) -> Result<Option<Role>> {
+        if let Some(role) = builtin_roles().into_iter().find(|role| role.name == name) {
+            return Ok(Some(role));
+        }
+        if name == USER_ROLE_NAME {
+            return Ok(None);
+        }
+
+        Ok(self
+            .redis
+            .get::<String>(&role_key(name))
+            .await?
+            .and_then(|json| serde_json::from_str(&json).ok()))
+    }
+
+    /// 获取角色的权限列表，未知角色没有任何权限
+    pub async fn permissions_for(&self, name: &str) -> Result<Vec<Permission>> {
+        Ok(self
+            .get_role(name)
+            .await?
+            .map(|role| role.permissions)
+            .unwrap_or_default())
+    }
+
+    /// 获取所有角色 (内置角色在前，自定义角色按名称排序)
+    pub async fn list_roles(&self) -> Result<Vec<Role>> {
+        let mut custom = Vec::new();
+        for key in self.redis.keys("admin_role:*").await? {
+            if let Some(json) = self.redis.get::<String>(&key).await? {
+                if let Ok(role) = serde_json::from_str::<Role>(&json) {
+                    custom.push(role);
+                }
+            }
+        }
+        custom.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut roles = builtin_roles();
+        roles.extend(custom);
+
+        Ok(roles)
+    }
+
+    /// 创建自定义角色
+    pub async fn create_role(
+        &self,
+        name: &str,
+        description: Option<String>,
+        permissions: Vec<Permission>,
+        created_by: &str,
+    ) -> Result<Role> {
+        validate_role_name(name)?;
+        if self.get_role(name).await?.is_some() {
+            return Err(AppError::BadRequest(format!(
+                "Role {} already exists",
+                name
+            )));
+        }
+
+        let now = Utc::now();
+        let role = Role {
+            name: name.to_string(),
+            description: description.unwrap_or_default(),
+            permissions: normalize_permissions(permissions),
+            built_in: false,
+            created_at: Some(now),
+            updated_at: Some(now),
+        };
+        self.save_role(&role).await?;
+
+        info!("🛡️  Role {} created by: {}", role.name, created_by);
+
+        Ok(role)
+    }
+
+    /// 更新自定义角色的描述和权限
+    pub async fn update_role(
+        &self,
+        name: &str,
+        description: Option<String>,
+        permissions: Vec<Permission>,
+        updated_by: &str,
+    ) -> Result<Role> {
+        let mut role = self.get_custom_role(name).await?;
+
+        if let Some(description) = description {
+            role.description = description;
+        }
+        role.permissions = normalize_permissions(permissions);
+        role.updated_at = Some(Utc::now());
+        self.save_role(&role).await?;
+
+        info!("🛡️  Role {} updated by: {}", role.name, updated_by);
+
+        Ok(role)
+    }
+
+    /// 删除自定义角色 (调用方需确保没有用户仍在使用)
+    pub async fn delete_role(&self, name: &str, deleted_by: &str) -> Result<()> {
+        self.get_custom_role(name).await?;
+        self.redis.del(&role_key(name)).await?;
+
+        info!("🗑️  Role {} deleted by: {}", name, deleted_by);
+
+        Ok(())
+    }
+
+    async fn get_custom_role(&self, name: &str) -> Result<Role> {
+        match self.get_role(name).await? {
+            Some(role) if role.built_in => Err(AppError::Forbidden(format!(
+                "Built-in role {} cannot be modified",
+                name
+            ))),
+            Some(role) => Ok(role),
+            None => Err(AppError::NotFound(format!("Role {} not found", name))),
+        }
+    }
+
+    async fn save_role(&self, role: &Role) -> Result<()> {
+        let json = serde_json::to_string(role)
+            .map_err(|e| AppError::InternalError(format!("序列化失败: {}", e)))?;
+        self.redis.set(&role_key(&role.name), &json).await
+    }
+}
+
+fn role_key(name: &str) -> String {
+    format!("admin_role:{}", name)
+}
+
+/// 角色名: 2-32 位小写字母、数字、`_`、`-`，且不能使用保留的 `user`
+fn validate_role_name(name: &str) -> Result<()> {
+    let valid_chars = name
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '_' | '-'));
+
+    if name.len() < 2 || name.len() > 32 || !valid_chars {
+        return Err(AppError::ValidationError(
+            "Role name must be 2-32 characters of lowercase letters, digits, '_' or '-'"
+                .to_string(),
+        ));
+    }
+    if name == USER_ROLE_NAME {
+        return Err(AppError::ValidationError(format!(
+            "Role name {} is reserved",
+            name
+        )));
+    }
+
+    Ok(())
+}
+
+/// 去重并按固定顺序排列权限
+fn normalize_permissions(permissions: Vec<Permission>) -> Vec<Permission> {
+    Permission::ALL
+        .into_iter()
+        .filter(|permission| permissions.contains(permission))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_role_name() {
+        assert!(validate_role_name("billing-viewer").is_ok());
+        assert!(validate_role_name("ops_2").is_ok());
+        assert!(validate_role_name("a").is_err());
+        assert!(validate_role_name("Ops").is_err());
+        assert!(validate_role_name("user").is_err());
+    }
+
+    #[test]
+    fn test_normalize_permissions() {
+        let permissions = normalize_permissions(vec![
+            Permission::StatsRead,
+            Permission::KeysRead,
+            Permission::StatsRead,
+        ]);
+        assert_eq!(
+            permissions,
+            vec![Permission::KeysRead, Permission::StatsRead]
+        );
+    }
+}
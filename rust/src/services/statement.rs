@@ -0,0 +1,178 @@
+use chrono::{NaiveDate, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use crate::models::api_key::ApiKey;
+use crate::models::statement::{
+    Statement, StatementDay, StatementKey, StatementModel, StatementScope, StatementTotals,
+};
+use crate::services::pricing_service::{PricingService, Usage};
+use crate::services::{ApiKeyService, UserService};
+use crate::utils::cost_calculator::CostCalculator;
+use crate::utils::error::{AppError, Result};
+
+/// 使用量对账单服务
+///
+/// 汇总 Key 的日模型统计 (`api_key_usage:model:daily:*`) 和失败请求计数
+/// (`api_key_usage:errors:*`)，日期为系统时区日期，与仪表盘和趋势一致
+pub struct StatementService {
+    api_key_service: Arc<ApiKeyService>,
+    user_service: Arc<UserService>,
+    cost_calculator: CostCalculator,
+}
+
+impl StatementService {
+    pub fn new(api_key_service: Arc<ApiKeyService>, user_service: Arc<UserService>) -> Self {
+        // 缓存节省只使用 CostCalculator 的静态定价，无需加载定价文件
+        let pricing = Arc::new(PricingService::new(Arc::new(reqwest::Client::new())));
+        Self {
+            api_key_service,
+            user_service,
+            cost_calculator: CostCalculator::new(pricing),
+        }
+    }
+
+    /// 生成 Key、用户或标签在日期范围内的对账单 (包含已删除的 Key)
+    pub async fn generate(
+        &self,
+        scope: StatementScope,
+        id: &str,
+        first: NaiveDate,
+        last: NaiveDate,
+    ) -> Result<Statement> {
+        let (subject_name, keys) = self.resolve(scope, id).await?;
+
+        let mut by_day: BTreeMap<NaiveDate, StatementTotals> = first
+            .iter_days()
+            .take_while(|date| *date <= last)
+            .map(|date| (date, StatementTotals::default()))
+            .collect();
+        let mut by_model: HashMap<String, StatementTotals> = HashMap::new();
+
+        for api_key in &keys {
+            let usage = self
+                .api_key_service
+                .get_daily_model_usage(&api_key.id, first, last)
+                .await?;
+            for ((date, model), usage) in usage {
+                let savings = self.cache_savings(usage.cache_read_tokens, &model).await;
+                by_day.entry(date).or_default().add_usage(&usage, savings);
+                by_model
+                    .entry(model)
+                    .or_default()
+                    .add_usage(&usage, savings);
+            }
+
+            let errors = self
+                .api_key_service
+                .get_daily_errors(&api_key.id, first, last)
+                .await?;
+            for ((date, model), count) in errors {
+                by_day.entry(date).or_default().errors += count;
+                by_model.entry(model).or_default().errors += count;
+            }
+        }
+
+        let mut totals = StatementTotals::default();
+        let days: Vec<StatementDay> = by_day
+            .into_iter()
+            .map(|(date, day_totals)| {
+                totals.add(&day_totals);
+                StatementDay {
+                    date,
+                    totals: day_totals,
+                }
+            })
+            .collect();
+        let mut models: Vec<StatementModel> = by_model
+            .into_iter()
+            .map(|(model, totals)| StatementModel { model, totals })
+            .collect();
+        models.sort_by(|a, b| {
+            b.totals
+                .billed_cost
+                .total_cmp(&a.totals.billed_cost)
+                .then_with(|| a.model.cmp(&b.model))
+        });
+
+        Ok(Statement {
+            scope,
+            subject_id: id.to_string(),
+            subject_name,
+            start_date: first,
+            end_date: last,
+            generated_at: Utc::now(),
+            keys: keys
+                .into_iter()
+                .map(|api_key| StatementKey {
+                    id: api_key.id,
+                    name: api_key.name,
+                    is_deleted: api_key.is_deleted,
+                })
+                .collect(),
+            days,
+            models,
+            totals,
+        })
+    }
+
+    /// 对账单对象的显示名和包含的 Key
+    async fn resolve(&self, scope: StatementScope, id: &str) -> Result<(String, Vec<ApiKey>)> {
+        match scope {
+            StatementScope::Key => {
+                let api_key = self.api_key_service.get_key(id).await?;
+                Ok((api_key.name.clone(), vec![api_key]))
+            }
+            StatementScope::User => {
+                let user = self.user_service.get_user(id).await?;
+                let keys = self
+                    .all_keys(|api_key| api_key.user_id.as_deref() == Some(id))
+                    .await?;
+                Ok((user.display_name.unwrap_or(user.username), keys))
+            }
+            StatementScope::Tag => {
+                let tag = id.trim();
+                if tag.is_empty() {
+                    return Err(AppError::ValidationError("Tag is required".to_string()));
+                }
+                let keys = self
+                    .all_keys(|api_key| api_key.tags.iter().any(|t| t == tag))
+                    .await?;
+                if keys.is_empty() {
+                    return Err(AppError::NotFound(format!("No API keys with tag: {}", tag)));
+                }
+                Ok((tag.to_string(), keys))
+            }
+        }
+    }
+
+    async fn all_keys(&self, filter: impl Fn(&ApiKey) -> bool) -> Result<Vec<ApiKey>> {
+        let mut keys: Vec<ApiKey> = self
+            .api_key_service
+            .get_all_keys(true)
+            .await?
+            .into_iter()
+            .filter(|api_key| filter(api_key))
+            .collect();
+        keys.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(keys)
+    }
+
+    /// 缓存读取 tokens 相对按普通输入价格计费节省的成本
+    async fn cache_savings(&self, cache_read_tokens: i64, model: &str) -> f64 {
+        if cache_read_tokens == 0 {
+            return 0.0;
+        }
+        let usage = Usage {
+            input_tokens: 0,
+            output_tokens: 0,
+            cache_creation_input_tokens: 0,
+            cache_read_input_tokens: cache_read_tokens,
+            cache_creation: None,
+        };
+        self.cost_calculator
+            .calculate_cache_savings(&usage, model)
+            .await
+            .savings
+    }
+}
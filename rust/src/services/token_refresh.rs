@@ -1,6 +1,6 @@
 use crate::redis::RedisPool;
 use crate::services::ClaudeAccountService;
-use crate::utils::{AppError, HttpClient, Result};
+use crate::utils::{metrics, AppError, HttpClient, Result};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -336,6 +336,17 @@ impl TokenRefreshService {
     /// # Returns
     /// * `Result<RefreshResult>` - 刷新结果
     pub async fn refresh_account_token(&self, account_id: &str) -> Result<RefreshResult> {
+        let result = self.refresh_token_inner(account_id).await;
+        let outcome = match &result {
+            Ok(refresh) if refresh.success => "success",
+            _ => "failure",
+        };
+        metrics::TOKEN_REFRESHES.inc(&["claude", outcome]);
+        result
+    }
+
+    /// 刷新 Token 的具体逻辑 (`refresh_account_token` 在此之上记录刷新结果指标)
+    async fn refresh_token_inner(&self, account_id: &str) -> Result<RefreshResult> {
         // 1. 获取账户数据
         let account = match self
             .account_service
@@ -0,0 +1,586 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::redis::RedisPool;
+use crate::utils::crypto::CryptoService;
+use crate::utils::error::{AppError, Result};
+
+/// otpauth URI 中显示的签发方
+pub const TOTP_ISSUER: &str = "Claude Relay Service";
+
+/// TOTP 时间步长 (秒)
+pub const TOTP_PERIOD_SECONDS: u64 = 30;
+
+/// TOTP 验证码位数
+pub const TOTP_DIGITS: u32 = 6;
+
+/// 每次生成的恢复码数量
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+/// 允许的时钟偏差 (前后各一个时间步)
+const TOTP_SKEW_STEPS: i64 = 1;
+
+/// 密钥长度 (160 位，RFC 4226 推荐值)
+const SECRET_BYTES: usize = 20;
+
+/// 并发修改记录时的最大重试次数
+const MAX_SAVE_ATTEMPTS: usize = 3;
+
+/// 记录未被其他请求修改时才写入 (比较序列化后的原值)
+const COMPARE_AND_SET_LUA: &str = r#"
+if redis.call('GET', KEYS[1]) ~= ARGV[1] then
+    return 0
+end
+redis.call('SET', KEYS[1], ARGV[2])
+return 1
+"#;
+
+/// 管理员双因素认证配置 (存储在 `admin_2fa:{username}`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TwoFactorRecord {
+    username: String,
+    /// 加密后的 Base32 密钥
+    secret: String,
+    /// 完成验证后才启用，未启用的记录只是待确认的注册
+    enabled: bool,
+    /// 恢复码的 SHA-256 哈希，使用后移除
+    recovery_code_hashes: Vec<String>,
+    /// 最后一次成功使用的时间步，防止同一验证码重放
+    last_used_step: Option<u64>,
+    created_at: DateTime<Utc>,
+    enabled_at: Option<DateTime<Utc>>,
+}
+
+/// 开始注册时返回的密钥和 otpauth URI (用于生成二维码)
+#[derive(Debug, Clone, Serialize)]
+pub struct TwoFactorEnrollment {
+    pub secret: String,
+    #[serde(rename = "otpauthUri")]
+    pub otpauth_uri: String,
+}
+
+/// 双因素认证状态
+#[derive(Debug, Clone, Serialize)]
+pub struct TwoFactorStatus {
+    pub enabled: bool,
+    #[serde(rename = "recoveryCodesRemaining")]
+    pub recovery_codes_remaining: usize,
+    #[serde(rename = "enabledAt")]
+    pub enabled_at: Option<DateTime<Utc>>,
+}
+
+/// 管理员 TOTP 双因素认证服务
+///
+/// 密钥使用 `CryptoService` 加密存储，恢复码只保存哈希
+pub struct TwoFactorService {
+    redis: Arc<RedisPool>,
+    crypto: CryptoService,
+}
+
+impl TwoFactorService {
+    pub fn new(redis: Arc<RedisPool>, encryption_key: String) -> Self {
+        Self {
+            redis,
+            crypto: CryptoService::new(encryption_key),
+        }
+    }
+
+    /// 获取双因素认证状态
+    pub async fn status(&self, username: &str) -> Result<TwoFactorStatus> {
+        let record = self.get_record(username).await?.filter(|r| r.enabled);
+
+        Ok(TwoFactorStatus {
+            enabled: record.is_some(),
+            recovery_codes_remaining: record
+                .as_ref()
+                .map(|r| r.recovery_code_hashes.len())
+                .unwrap_or(0),
+            enabled_at: record.and_then(|r| r.enabled_at),
+        })
+    }
+
+    /// 是否已启用双因素认证
+    pub async fn is_enabled(&self, username: &str) -> Result<bool> {
+        Ok(self.get_record(username).await?.is_some_and(|r| r.enabled))
+    }
+
+    /// 开始注册：生成新密钥 (覆盖未完成的注册)
+    pub async fn begin_enrollment(&self, username: &str) -> Result<TwoFactorEnrollment> {
+        if self.is_enabled(username).await? {
+            return Err(AppError::BadRequest(
+                "Two-factor authentication is already enabled".to_string(),
+            ));
+        }
+
+        let secret = generate_secret();
+        let record = TwoFactorRecord {
+            username: username.to_string(),
+            secret: self.crypto.encrypt(&secret)?,
+            enabled: false,
+            recovery_code_hashes: Vec::new(),
+            last_used_step: None,
+            created_at: Utc::now(),
+            enabled_at: None,
+        };
+        self.save_record(&record).await?;
+
+        Ok(TwoFactorEnrollment {
+            otpauth_uri: otpauth_uri(username, &secret),
+            secret,
+        })
+    }
+
+    /// 确认注册：校验验证码后启用，返回一次性明文恢复码
+    pub async fn confirm_enrollment(&self, username: &str, code: &str) -> Result<Vec<String>> {
+        let (original, mut record) = self
+            .get_record_with_raw(username)
+            .await?
+            .ok_or_else(|| AppError::BadRequest("No pending two-factor enrollment".to_string()))?;
+        if record.enabled {
+            return Err(AppError::BadRequest(
+                "Two-factor authentication is already enabled".to_string(),
+            ));
+        }
+
+        let step = self
+            .match_totp(&record, code)?
+            .ok_or_else(|| AppError::BadRequest("Invalid verification code".to_string()))?;
+
+        let (codes, hashes) = generate_recovery_codes();
+        record.enabled = true;
+        record.enabled_at = Some(Utc::now());
+        record.last_used_step = Some(step);
+        record.recovery_code_hashes = hashes;
+        // 并发确认或重新开始注册时放弃本次确认
+        if !self.compare_and_save(&original, &record).await? {
+            return Err(AppError::BadRequest(
+                "Two-factor enrollment changed, please retry".to_string(),
+            ));
+        }
+
+        info!("🔐 Two-factor authentication enabled for: {}", username);
+
+        Ok(codes)
+    }
+
+    /// 登录时校验 TOTP 验证码或恢复码
+    ///
+    /// 未启用双因素认证时直接通过；恢复码使用后即失效。
+    /// 校验和消费通过比较写入原子完成，并发请求不能重复使用同一验证码或恢复码
+    pub async fn verify(
+        &self,
+        username: &str,
+        totp_code: Option<&str>,
+        recovery_code: Option<&str>,
+    ) -> Result<()> {
+        let invalid = || AppError::Unauthorized("Invalid two-factor code".to_string());
+        let recovery_code = recovery_code.filter(|c| !c.trim().is_empty());
+
+        for _ in 0..MAX_SAVE_ATTEMPTS {
+            let (original, mut record) = match self.get_record_with_raw(username).await? {
+                Some((original, record)) if record.enabled => (original, record),
+                _ => return Ok(()),
+            };
+
+            if let Some(code) = recovery_code {
+                let hash = hash_recovery_code(code);
+                let index = record
+                    .recovery_code_hashes
+                    .iter()
+                    .position(|h| *h == hash)
+                    .ok_or_else(invalid)?;
+                record.recovery_code_hashes.remove(index);
+            } else {
+                let code = totp_code.filter(|c| !c.trim().is_empty()).ok_or_else(|| {
+                    AppError::Unauthorized("Two-factor code required".to_string())
+                })?;
+                let step = self.match_totp(&record, code)?.ok_or_else(invalid)?;
+                record.last_used_step = Some(step);
+            }
+
+            // 记录已被并发请求修改时重新读取，已被消费的验证码在下一轮校验失败
+            if !self.compare_and_save(&original, &record).await? {
+                continue;
+            }
+
+            if recovery_code.is_some() {
+                warn!(
+                    "🔑 Recovery code used by: {} ({} remaining)",
+                    username,
+                    record.recovery_code_hashes.len()
+                );
+            }
+            return Ok(());
+        }
+
+        Err(invalid())
+    }
+
+    /// 重新生成恢复码 (需要当前验证码)，旧恢复码全部失效
+    pub async fn regenerate_recovery_codes(
+        &self,
+        username: &str,
+        code: &str,
+    ) -> Result<Vec<String>> {
+        self.require_enabled(username).await?;
+        self.verify(username, Some(code), None).await?;
+
+        let mut record = self.require_enabled(username).await?;
+        let (codes, hashes) = generate_recovery_codes();
+        record.recovery_code_hashes = hashes;
+        self.save_record(&record).await?;
+
+        info!("🔑 Recovery codes regenerated for: {}", username);
+
+        Ok(codes)
+    }
+
+    /// 关闭双因素认证 (需要验证码或恢复码)
+    pub async fn disable(
+        &self,
+        username: &str,
+        totp_code: Option<&str>,
+        recovery_code: Option<&str>,
+    ) -> Result<()> {
+        self.require_enabled(username).await?;
+        self.verify(username, totp_code, recovery_code).await?;
+        self.redis.del(&record_key(username)).await?;
+
+        info!("🔓 Two-factor authentication disabled by: {}", username);
+
+        Ok(())
+    }
+
+    /// 强制重置 (管理员接口或命令行，用于找回被锁定的账号)
+    ///
+    /// 返回是否存在双因素认证配置
+    pub async fn reset(&self, username: &str, reset_by: &str) -> Result<bool> {
+        let existed = self.get_record(username).await?.is_some();
+        if existed {
+            self.redis.del(&record_key(username)).await?;
+            warn!(
+                "🔓 Two-factor authentication reset for: {} by: {}",
+                username, reset_by
+            );
+        }
+
+        Ok(existed)
+    }
+
+    async fn require_enabled(&self, username: &str) -> Result<TwoFactorRecord> {
+        self.get_record(username)
+            .await?
+            .filter(|r| r.enabled)
+            .ok_or_else(|| {
+                AppError::BadRequest("Two-factor authentication is not enabled".to_string())
+            })
+    }
+
+    /// 返回匹配的时间步，已使用过的时间步视为不匹配
+    fn match_totp(&self, record: &TwoFactorRecord, code: &str) -> Result<Option<u64>> {
+        let secret = base32_decode(&self.crypto.decrypt(&record.secret)?)
+            .ok_or_else(|| AppError::InternalError("Invalid two-factor secret".to_string()))?;
+        let now = Utc::now().timestamp() as u64;
+
+        Ok(find_totp_step(&secret, code, now)
+            .filter(|step| record.last_used_step.is_none_or(|last| *step > last)))
+    }
+
+    async fn get_record(&self, username: &str) -> Result<Option<TwoFactorRecord>> {
+        Ok(self
+            .get_record_with_raw(username)
+            .await?
+            .map(|(_, record)| record))
+    }
+
+    /// 读取记录及其原始 JSON (用于比较写入)
+    ///
+    /// 旧版本按原样用户名保存的记录在首次读取时迁移到规范化的键
+    async fn get_record_with_raw(
+        &self,
+        username: &str,
+    ) -> Result<Option<(String, TwoFactorRecord)>> {
+        let key = record_key(username);
+        let mut json = self.redis.get::<String>(&key).await?;
+        if json.is_none() && legacy_record_key(username) != key {
+            let mut conn = self.redis.get_connection().await?;
+            let migrated: redis::RedisResult<i32> = redis::cmd("RENAMENX")
+                .arg(legacy_record_key(username))
+                .arg(&key)
+                .query_async(&mut conn)
+                .await;
+            if migrated == Ok(1) {
+                json = self.redis.get::<String>(&key).await?;
+            }
+        }
+
+        Ok(json.and_then(|json| {
+            let record = serde_json::from_str(&json).ok()?;
+            Some((json, record))
+        }))
+    }
+
+    /// 记录仍为 `original` 时写入，返回是否写入成功
+    async fn compare_and_save(&self, original: &str, record: &TwoFactorRecord) -> Result<bool> {
+        let json = serde_json::to_string(record)
+            .map_err(|e| AppError::InternalError(format!("序列化失败: {}", e)))?;
+
+        let mut conn = self.redis.get_connection().await?;
+        let saved: i32 = redis::Script::new(COMPARE_AND_SET_LUA)
+            .key(record_key(&record.username))
+            .arg(original)
+            .arg(json)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| {
+                AppError::RedisError(format!("Failed to save two-factor record: {}", e))
+            })?;
+
+        Ok(saved == 1)
+    }
+
+    async fn save_record(&self, record: &TwoFactorRecord) -> Result<()> {
+        let json = serde_json::to_string(record)
+            .map_err(|e| AppError::InternalError(format!("序列化失败: {}", e)))?;
+        self.redis.set(&record_key(&record.username), &json).await
+    }
+}
+
+/// 双因素认证记录的键，用户名与凭据校验一致忽略大小写和首尾空白
+fn record_key(username: &str) -> String {
+    format!("admin_2fa:{}", username.trim().to_lowercase())
+}
+
+/// 旧版本按原样用户名保存的记录键
+fn legacy_record_key(username: &str) -> String {
+    format!("admin_2fa:{}", username)
+}
+
+/// 当前时间步的 TOTP 验证码 (测试中模拟认证器)
+#[cfg(test)]
+pub(crate) fn current_totp(secret: &str) -> String {
+    let secret = base32_decode(secret).expect("valid base32 secret");
+    totp(&secret, Utc::now().timestamp() as u64 / TOTP_PERIOD_SECONDS)
+}
+
+/// 生成 Base32 编码的随机密钥
+fn generate_secret() -> String {
+    let bytes: [u8; SECRET_BYTES] = rand::thread_rng().gen();
+    base32_encode(&bytes)
+}
+
+/// 生成明文恢复码及其哈希 (格式 `xxxxx-xxxxx`)
+fn generate_recovery_codes() -> (Vec<String>, Vec<String>) {
+    const CHARSET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+    let mut rng = rand::thread_rng();
+
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let mut code: String = (0..10)
+                .map(|_| CHARSET[rng.gen_range(0..CHARSET.len())] as char)
+                .collect();
+            code.insert(5, '-');
+            code
+        })
+        .collect();
+    let hashes = codes.iter().map(|c| hash_recovery_code(c)).collect();
+
+    (codes, hashes)
+}
+
+/// 恢复码哈希 (忽略大小写、空格和连字符)
+fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_lowercase();
+    hex::encode(Sha256::digest(normalized.as_bytes()))
+}
+
+fn otpauth_uri(username: &str, secret: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        percent_encode(TOTP_ISSUER),
+        percent_encode(username),
+        secret,
+        percent_encode(TOTP_ISSUER),
+        TOTP_DIGITS,
+        TOTP_PERIOD_SECONDS
+    )
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// RFC 6238 TOTP (HMAC-SHA1)
+fn totp(secret: &[u8], step: u64) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(&step.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    // RFC 4226 动态截断
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+
+    format!(
+        "{:0width$}",
+        binary % 10u32.pow(TOTP_DIGITS),
+        width = TOTP_DIGITS as usize
+    )
+}
+
+/// 在允许的时钟偏差内查找与验证码匹配的时间步
+fn find_totp_step(secret: &[u8], code: &str, unix_time: u64) -> Option<u64> {
+    let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+    if code.len() != TOTP_DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let current = (unix_time / TOTP_PERIOD_SECONDS) as i64;
+    (-TOTP_SKEW_STEPS..=TOTP_SKEW_STEPS)
+        .map(|delta| current + delta)
+        .filter(|step| *step >= 0)
+        .map(|step| step as u64)
+        .find(|step| totp(secret, *step) == code)
+}
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// RFC 4648 Base32 编码 (无填充)
+fn base32_encode(data: &[u8]) -> String {
+    let mut output = String::with_capacity(data.len().div_ceil(5) * 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for &byte in data {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            output.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        output.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+
+    output
+}
+
+/// RFC 4648 Base32 解码 (忽略填充、空格和大小写)
+fn base32_decode(input: &str) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(input.len() * 5 / 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for c in input.chars().filter(|c| *c != '=' && !c.is_whitespace()) {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|a| *a as char == c.to_ascii_uppercase())? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+        }
+    }
+
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_totp_rfc6238_vectors() {
+        // RFC 6238 附录 B (SHA1)，取 8 位结果的后 6 位
+        let secret = b"12345678901234567890";
+        let cases = [
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1111111111, "050471"),
+            (1234567890, "005924"),
+            (2000000000, "279037"),
+        ];
+        for (time, expected) in cases {
+            assert_eq!(totp(secret, time / TOTP_PERIOD_SECONDS), expected);
+        }
+    }
+
+    #[test]
+    fn test_find_totp_step_allows_skew() {
+        let secret = b"12345678901234567890";
+        let step = 1111111109 / TOTP_PERIOD_SECONDS;
+        let code = totp(secret, step);
+
+        assert_eq!(find_totp_step(secret, &code, 1111111109), Some(step));
+        assert_eq!(find_totp_step(secret, &code, 1111111109 + 30), Some(step));
+        assert_eq!(find_totp_step(secret, &code, 1111111109 + 90), None);
+        assert_eq!(find_totp_step(secret, "12345", 1111111109), None);
+        assert_eq!(find_totp_step(secret, "abcdef", 1111111109), None);
+    }
+
+    #[test]
+    fn test_record_key_ignores_case_and_whitespace() {
+        assert_eq!(record_key("alice"), "admin_2fa:alice");
+        assert_eq!(record_key(" ALICE "), record_key("alice"));
+        assert_eq!(legacy_record_key("Alice"), "admin_2fa:Alice");
+    }
+
+    #[test]
+    fn test_base32_roundtrip() {
+        assert_eq!(base32_encode(b"foobar"), "MZXW6YTBOI");
+        assert_eq!(base32_decode("MZXW6YTBOI======").unwrap(), b"foobar");
+        assert_eq!(base32_decode("mzxw 6ytb oi").unwrap(), b"foobar");
+        assert!(base32_decode("MZXW1").is_none());
+
+        let secret = generate_secret();
+        assert_eq!(secret.len(), 32);
+        assert_eq!(base32_decode(&secret).unwrap().len(), SECRET_BYTES);
+    }
+
+    #[test]
+    fn test_recovery_codes() {
+        let (codes, hashes) = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        assert_eq!(codes[0].len(), 11);
+        assert_eq!(hashes[0], hash_recovery_code(&codes[0]));
+        assert_eq!(
+            hash_recovery_code(&codes[0].to_uppercase().replace('-', " ")),
+            hashes[0]
+        );
+        assert!(!hashes.contains(&codes[0]));
+    }
+
+    #[test]
+    fn test_otpauth_uri() {
+        let uri = otpauth_uri("ops admin", "JBSWY3DPEHPK3PXP");
+        assert_eq!(
+            uri,
+            "otpauth://totp/Claude%20Relay%20Service:ops%20admin?secret=JBSWY3DPEHPK3PXP\
+             &issuer=Claude%20Relay%20Service&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+}
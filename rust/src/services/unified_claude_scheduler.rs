@@ -15,7 +15,7 @@ use crate::services::account_scheduler::AccountScheduler;
 use crate::utils::model_helper::{
     is_claude_official_model, is_opus_model, parse_vendor_prefixed_model, ParsedModel,
 };
-use crate::utils::AppError;
+use crate::utils::{metrics, AppError};
 use crate::RedisPool;
 use redis::AsyncCommands;
 use serde::{Deserialize, Serialize};
@@ -119,6 +119,17 @@ impl UnifiedClaudeScheduler {
         &self,
         session_hash: Option<&str>,
         requested_model: Option<&str>,
+    ) -> Result<SelectedAccount, AppError> {
+        let result = self.pick_account(session_hash, requested_model).await;
+        metrics::observe_selection("claude", &result);
+        result
+    }
+
+    /// 选择账户的具体逻辑 (`select_account` 在此之上记录调度结果指标)
+    async fn pick_account(
+        &self,
+        session_hash: Option<&str>,
+        requested_model: Option<&str>,
     ) -> Result<SelectedAccount, AppError> {
         // 1. 解析 vendor 前缀
         let parsed = requested_model
@@ -13,6 +13,7 @@ use crate::redis::RedisPool;
 use crate::services::account::ClaudeAccountService;
 use crate::services::account_scheduler::AccountScheduler;
 use crate::utils::error::{AppError, Result};
+use crate::utils::metrics;
 use redis::AsyncCommands;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
@@ -75,6 +76,20 @@ impl UnifiedGeminiScheduler {
         api_key: &ApiKey,
         session_hash: Option<&str>,
         requested_model: Option<&str>,
+    ) -> Result<SelectedAccount> {
+        let result = self
+            .pick_account(api_key, session_hash, requested_model)
+            .await;
+        metrics::observe_selection("gemini", &result);
+        result
+    }
+
+    /// 选择账户的具体逻辑 (`select_account` 在此之上记录调度结果指标)
+    async fn pick_account(
+        &self,
+        api_key: &ApiKey,
+        session_hash: Option<&str>,
+        requested_model: Option<&str>,
     ) -> Result<SelectedAccount> {
         // 1. 检查 API Key 是否绑定了专属 Gemini 账户
         if let Some(ref gemini_account_id) = api_key.gemini_account_id {
@@ -0,0 +1,276 @@
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, Utc};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use tokio::time::interval;
+use tracing::{error, info};
+
+use crate::config::{Settings, UsageRetentionSettings};
+use crate::models::usage_trend::{TrendGranularity, UsageBucket, UsageDimension};
+use crate::redis::RedisPool;
+use crate::services::usage_trend::{fetch_members, fetch_usages, incr_bucket_usage, sum_usages};
+use crate::utils::error::{AppError, Result};
+
+/// 清理进度 (Hash)：`rollup` 为已汇总的最后一天，`expire` 为已清理的最后一天
+const RETENTION_STATE_KEY: &str = "usage:retention:state";
+
+/// 首次清理日桶时向前检查的天数
+const MAX_EXPIRE_LOOKBACK_DAYS: i64 = 400;
+
+/// 有成员集合的统计维度
+const MEMBER_DIMENSIONS: [UsageDimension; 3] = [
+    UsageDimension::ApiKey,
+    UsageDimension::Account,
+    UsageDimension::Model,
+];
+
+/// 一次清理的结果
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RetentionReport {
+    /// 小时桶已汇总并删除的日期
+    pub rolled_up_days: Vec<NaiveDate>,
+    /// 日桶已删除的日期
+    pub expired_days: Vec<NaiveDate>,
+}
+
+/// 使用量统计保留策略
+///
+/// 超过保留期的小时桶按系统时区汇总进日桶 (日桶已存在时不重复累加) 后删除，
+/// 超过保留期的日桶删除。统计桶本身也带过期时间，清理任务停止时数据同样会过期
+pub struct UsageRetentionService {
+    redis: Arc<RedisPool>,
+    retention: UsageRetentionSettings,
+    system_timezone: FixedOffset,
+}
+
+impl UsageRetentionService {
+    pub fn new(redis: Arc<RedisPool>, settings: &Settings) -> Self {
+        Self {
+            redis,
+            retention: settings.usage_retention.clone(),
+            system_timezone: settings.server.timezone(),
+        }
+    }
+
+    /// 启动定时清理任务 (`cleanup_interval` 为 0 时不启动)
+    pub fn start(self: Arc<Self>) {
+        if self.retention.cleanup_interval == 0 {
+            info!("🧹 Usage retention cleanup disabled");
+            return;
+        }
+
+        info!(
+            "🧹 Usage retention cleanup started: hourly {} days, daily {} days",
+            self.retention.hourly_days, self.retention.daily_days
+        );
+        let period = std::time::Duration::from_secs(self.retention.cleanup_interval);
+        tokio::spawn(async move {
+            let mut interval = interval(period);
+            loop {
+                interval.tick().await;
+                match self.run_once(Utc::now()).await {
+                    Ok(report) => {
+                        if !report.rolled_up_days.is_empty() || !report.expired_days.is_empty() {
+                            info!(
+                                "🧹 Usage retention: {} days rolled up, {} days expired",
+                                report.rolled_up_days.len(),
+                                report.expired_days.len()
+                            );
+                        }
+                    }
+                    Err(e) => error!("❌ Usage retention cleanup failed: {}", e),
+                }
+            }
+        });
+    }
+
+    /// 执行一次清理，每处理完一天保存进度
+    pub async fn run_once(&self, now: DateTime<Utc>) -> Result<RetentionReport> {
+        let state = self.redis.hgetall(RETENTION_STATE_KEY).await?;
+        let cursor = |field: &str| {
+            state
+                .iter()
+                .find(|(name, _)| name == field)
+                .and_then(|(_, date)| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        };
+        let (rollup_first, rollup_last) =
+            rollup_range(&self.retention, self.system_timezone, now, cursor("rollup"));
+        let (expire_first, expire_last) =
+            expire_range(&self.retention, self.system_timezone, now, cursor("expire"));
+
+        let mut report = RetentionReport::default();
+        for date in days(rollup_first, rollup_last) {
+            self.rollup_day(date).await?;
+            self.save_cursor("rollup", date).await?;
+            report.rolled_up_days.push(date);
+        }
+        for date in days(expire_first, expire_last) {
+            self.expire_day(date).await?;
+            self.save_cursor("expire", date).await?;
+            report.expired_days.push(date);
+        }
+        Ok(report)
+    }
+
+    /// 把一天的小时桶汇总进日桶，然后删除小时桶
+    async fn rollup_day(&self, date: NaiveDate) -> Result<()> {
+        let hours = UsageBucket::hours_of_day(date, self.system_timezone);
+        let hour_refs: Vec<&UsageBucket> = hours.iter().collect();
+        let day = UsageBucket::day(date);
+
+        let mut targets = vec![(UsageDimension::Global, String::new())];
+        let mut hourly_keys = Vec::new();
+        for dimension in MEMBER_DIMENSIONS {
+            let members: BTreeSet<String> = fetch_members(&self.redis, dimension, &hour_refs)
+                .await?
+                .into_iter()
+                .flatten()
+                .collect();
+            targets.extend(members.into_iter().map(|member| (dimension, member)));
+            hourly_keys.extend(hours.iter().map(|hour| hour.members_key(dimension)));
+        }
+
+        let mut conn = self.redis.get_connection().await?;
+        let ttl = TrendGranularity::Day.retention_seconds(&self.retention);
+        for (dimension, member) in &targets {
+            let keys: Vec<String> = hours
+                .iter()
+                .map(|hour| hour.usage_key(*dimension, member))
+                .collect();
+            let usage = sum_usages(fetch_usages(&self.redis, &keys).await?.into_iter());
+            hourly_keys.extend(keys);
+            if usage.requests == 0 {
+                continue;
+            }
+
+            // 日桶在记录使用量时已同步写入，只补写缺失的日桶
+            let day_key = day.usage_key(*dimension, member);
+            let exists: bool = redis::cmd("EXISTS")
+                .arg(&day_key)
+                .query_async(&mut conn)
+                .await
+                .map_err(|e| AppError::RedisError(format!("Failed to check usage: {}", e)))?;
+            if exists {
+                continue;
+            }
+
+            let mut pipe = redis::pipe();
+            incr_bucket_usage(&mut pipe, &day_key, &usage, ttl);
+            if *dimension != UsageDimension::Global {
+                let members_key = day.members_key(*dimension);
+                pipe.sadd(&members_key, member).expire(&members_key, ttl);
+            }
+            pipe.query_async::<_, ()>(&mut conn)
+                .await
+                .map_err(|e| AppError::RedisError(format!("Failed to roll up usage: {}", e)))?;
+        }
+
+        delete_keys(&mut conn, &hourly_keys).await
+    }
+
+    /// 删除一天的日桶和成员集合
+    async fn expire_day(&self, date: NaiveDate) -> Result<()> {
+        let day = UsageBucket::day(date);
+        let mut keys = vec![day.usage_key(UsageDimension::Global, "")];
+        for dimension in MEMBER_DIMENSIONS {
+            let members = fetch_members(&self.redis, dimension, &[&day])
+                .await?
+                .pop()
+                .unwrap_or_default();
+            keys.extend(
+                members
+                    .iter()
+                    .map(|member| day.usage_key(dimension, member)),
+            );
+            keys.push(day.members_key(dimension));
+        }
+
+        let mut conn = self.redis.get_connection().await?;
+        delete_keys(&mut conn, &keys).await
+    }
+
+    async fn save_cursor(&self, field: &str, date: NaiveDate) -> Result<()> {
+        self.redis
+            .hset(
+                RETENTION_STATE_KEY,
+                field,
+                &date.format("%Y-%m-%d").to_string(),
+            )
+            .await
+    }
+}
+
+async fn delete_keys(conn: &mut deadpool_redis::Connection, keys: &[String]) -> Result<()> {
+    for chunk in keys.chunks(500) {
+        redis::cmd("DEL")
+            .arg(chunk)
+            .query_async::<_, ()>(&mut *conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to delete usage: {}", e)))?;
+    }
+    Ok(())
+}
+
+/// `first` 到 `last` 的每一天 (`first` 晚于 `last` 时为空)
+fn days(first: NaiveDate, last: NaiveDate) -> impl Iterator<Item = NaiveDate> {
+    first.iter_days().take_while(move |date| *date <= last)
+}
+
+/// 需要汇总的日期范围：小时桶全部超过保留期的日期，且不早于仍可能存在小时桶的日期
+fn rollup_range(
+    retention: &UsageRetentionSettings,
+    timezone: FixedOffset,
+    now: DateTime<Utc>,
+    cursor: Option<NaiveDate>,
+) -> (NaiveDate, NaiveDate) {
+    let cutoff = now - Duration::days(retention.hourly_days);
+    let last = cutoff.with_timezone(&timezone).date_naive() - Duration::days(1);
+    let oldest = (now - Duration::seconds(TrendGranularity::Hour.retention_seconds(retention)))
+        .with_timezone(&timezone)
+        .date_naive();
+    let first = cursor.map_or(oldest, |date| (date + Duration::days(1)).max(oldest));
+    (first, last)
+}
+
+/// 需要删除日桶的日期范围：距今 `daily_days` 天及更早的日期
+fn expire_range(
+    retention: &UsageRetentionSettings,
+    timezone: FixedOffset,
+    now: DateTime<Utc>,
+    cursor: Option<NaiveDate>,
+) -> (NaiveDate, NaiveDate) {
+    let today = now.with_timezone(&timezone).date_naive();
+    let last = today - Duration::days(retention.daily_days);
+    let first = cursor.map_or(last - Duration::days(MAX_EXPIRE_LOOKBACK_DAYS), |date| {
+        date + Duration::days(1)
+    });
+    (first, last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_retention_ranges() {
+        let retention = UsageRetentionSettings::default();
+        let tz = FixedOffset::east_opt(8 * 3600).unwrap();
+        let now: DateTime<Utc> = "2025-01-31T12:00:00Z".parse().unwrap();
+
+        // 小时桶保留 8 天 (截止 01-23 20:00 UTC+8)，再宽限 1 天
+        let (first, last) = rollup_range(&retention, tz, now, None);
+        assert_eq!(first, date("2025-01-22"));
+        assert_eq!(last, date("2025-01-22"));
+        let (first, last) = rollup_range(&retention, tz, now, Some(date("2025-01-22")));
+        assert!(first > last);
+
+        // 日桶保留 90 天
+        let (first, last) = expire_range(&retention, tz, now, Some(date("2024-10-31")));
+        assert_eq!(first, date("2024-11-01"));
+        assert_eq!(last, date("2024-11-02"));
+        assert_eq!(days(first, last).count(), 2);
+    }
+}
@@ -0,0 +1,256 @@
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures::Stream;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex, OnceLock};
+use tracing::warn;
+
+use crate::models::{ApiKey, BillingDetails, CostBreakdown, UsageRecord};
+use crate::services::api_key::ApiKeyService;
+use crate::services::claude_relay::Usage;
+use crate::services::pricing_service::{self, CacheCreation, PricingService};
+use crate::services::relay_trait::UsageStats;
+use crate::utils::error::Result;
+
+/// 可按实际用量结算的上游 usage
+pub trait SettleableUsage {
+    /// 转换为计价用的 usage (上游未返回缓存 TTL 明细时，按全部为 1h ephemeral 计算)
+    fn to_pricing_usage(&self) -> pricing_service::Usage;
+}
+
+impl SettleableUsage for Usage {
+    fn to_pricing_usage(&self) -> pricing_service::Usage {
+        let cache_creation = self.cache_creation.clone().or_else(|| {
+            self.cache_creation_input_tokens
+                .map(|tokens| CacheCreation {
+                    ephemeral_5m_input_tokens: 0,
+                    ephemeral_1h_input_tokens: tokens as i64,
+                })
+        });
+
+        pricing_service::Usage {
+            input_tokens: self.input_tokens as i64,
+            output_tokens: self.output_tokens as i64,
+            cache_creation_input_tokens: self.cache_creation_input_tokens.unwrap_or(0) as i64,
+            cache_read_input_tokens: self.cache_read_input_tokens.unwrap_or(0) as i64,
+            cache_creation,
+        }
+    }
+}
+
+impl SettleableUsage for UsageStats {
+    fn to_pricing_usage(&self) -> pricing_service::Usage {
+        pricing_service::Usage {
+            input_tokens: self.input_tokens as i64,
+            output_tokens: self.output_tokens as i64,
+            cache_creation_input_tokens: self.cache_creation_tokens.unwrap_or(0) as i64,
+            cache_read_input_tokens: self.cache_read_tokens.unwrap_or(0) as i64,
+            cache_creation: self.cache_creation_tokens.map(|tokens| CacheCreation {
+                ephemeral_5m_input_tokens: 0,
+                ephemeral_1h_input_tokens: tokens as i64,
+            }),
+        }
+    }
+}
+
+/// 单次请求的用量结算上下文
+///
+/// 持有请求前预占成本的请求 ID，在拿到实际用量后记录使用量并释放预占。
+/// 各转发路由共用，请求失败时记录错误数并释放预占
+pub struct UsageSettlement {
+    api_key_service: Arc<ApiKeyService>,
+    pricing_service: Arc<PricingService>,
+    key_id: String,
+    request_id: String,
+    model: String,
+    /// 实际处理请求的账户 (调度选中后绑定，转发服务返回的账户优先)
+    account_id: Mutex<Option<String>>,
+    /// 选中账户的类型 (计费事件中的平台)
+    platform: OnceLock<String>,
+    started_at: DateTime<Utc>,
+}
+
+impl UsageSettlement {
+    /// 预占请求的预估成本 (超出成本限制时返回错误)，返回结算上下文
+    pub async fn reserve(
+        api_key_service: Arc<ApiKeyService>,
+        pricing_service: Arc<PricingService>,
+        api_key: &ApiKey,
+        model: &str,
+        estimated_cost: f64,
+    ) -> Result<Arc<Self>> {
+        let settlement = Self {
+            api_key_service,
+            pricing_service,
+            key_id: api_key.id.clone(),
+            request_id: uuid::Uuid::new_v4().to_string(),
+            model: model.to_string(),
+            account_id: Mutex::new(None),
+            platform: OnceLock::new(),
+            started_at: Utc::now(),
+        };
+
+        settlement
+            .api_key_service
+            .reserve_cost(api_key, &settlement.request_id, model, estimated_cost)
+            .await?;
+
+        Ok(Arc::new(settlement))
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// 记录处理请求的账户，用于按账户统计使用量和成本 (空 ID 忽略)
+    pub fn bind_account(&self, account_id: impl Into<String>) {
+        let account_id = account_id.into();
+        if account_id.is_empty() {
+            return;
+        }
+        if let Ok(mut bound) = self.account_id.lock() {
+            *bound = Some(account_id);
+        }
+    }
+
+    /// 记录计费事件中的平台 (只取第一次设置的值)
+    pub fn set_platform(&self, platform: impl Into<String>) {
+        let _ = self.platform.set(platform.into());
+    }
+
+    /// 按实际用量计算成本并记录，然后释放成本预占
+    pub async fn settle<U: SettleableUsage>(&self, usage: Option<&U>) {
+        if let Some(usage) = usage {
+            if let Err(e) = self.record(&usage.to_pricing_usage()).await {
+                warn!(
+                    "⚠️ Failed to record usage for key {} (request: {}): {}",
+                    self.key_id, self.request_id, e
+                );
+            }
+        }
+
+        self.release().await;
+    }
+
+    async fn record(&self, usage: &pricing_service::Usage) -> Result<()> {
+        let ephemeral = usage.cache_creation.clone().unwrap_or_default();
+
+        // 计算实际成本
+        let cost_result = self
+            .pricing_service
+            .calculate_cost(usage, &self.model)
+            .await;
+        if !cost_result.has_pricing {
+            self.pricing_service
+                .record_unpriced_model(&self.model)
+                .await;
+        }
+
+        let mut record = UsageRecord::new(
+            self.key_id.clone(),
+            self.model.clone(),
+            usage.input_tokens,
+            usage.output_tokens,
+            usage.cache_creation_input_tokens,
+            usage.cache_read_input_tokens,
+            cost_result.total_cost,
+        )
+        .with_billing(BillingDetails {
+            request_id: self.request_id.clone(),
+            platform: self.platform.get().cloned(),
+            ephemeral_5m_tokens: ephemeral.ephemeral_5m_input_tokens,
+            ephemeral_1h_tokens: ephemeral.ephemeral_1h_input_tokens,
+            cost_breakdown: CostBreakdown::from(&cost_result),
+            pricing_version: self.pricing_service.pricing_version().await,
+            is_long_context: cost_result.is_long_context_request,
+            requested_at: self.started_at,
+        });
+        let account_id = self.account_id.lock().ok().and_then(|id| id.clone());
+        if let Some(account_id) = account_id {
+            record = record.with_account(account_id);
+        }
+
+        self.api_key_service.record_usage(record).await
+    }
+
+    /// 记录失败的请求 (对账单中的错误数)
+    pub async fn record_error(&self) {
+        if let Err(e) = self
+            .api_key_service
+            .record_error(&self.key_id, &self.model)
+            .await
+        {
+            warn!(
+                "⚠️ Failed to record error for key {} (request: {}): {}",
+                self.key_id, self.request_id, e
+            );
+        }
+    }
+
+    /// 释放成本预占 (重复释放无副作用)
+    pub async fn release(&self) {
+        if let Err(e) = self
+            .api_key_service
+            .release_cost_reservation(&self.key_id, &self.request_id)
+            .await
+        {
+            warn!(
+                "⚠️ Failed to release cost reservation for key {} (request: {}): {}",
+                self.key_id, self.request_id, e
+            );
+        }
+    }
+
+    /// 转发失败时记录错误并释放成本预占，原样返回结果
+    pub async fn release_on_error<T>(&self, result: Result<T>) -> Result<T> {
+        if result.is_err() {
+            self.record_error().await;
+            self.release().await;
+        }
+        result
+    }
+
+    /// 流结束时释放成本预占 (上游未返回 usage 时兜底)，接在 SSE 流末尾
+    pub fn release_on_stream_end(
+        self: Arc<Self>,
+    ) -> impl Stream<Item = std::result::Result<Bytes, Infallible>> {
+        futures::stream::once(async move {
+            self.release().await;
+            Ok(Bytes::new())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_pricing_usage_cache_fallback() {
+        let usage = Usage {
+            input_tokens: 100,
+            output_tokens: 50,
+            cache_creation_input_tokens: Some(30),
+            cache_read_input_tokens: Some(20),
+            cache_creation: None,
+        };
+        let pricing = usage.to_pricing_usage();
+        assert_eq!(pricing.cache_creation_input_tokens, 30);
+        assert_eq!(pricing.cache_read_input_tokens, 20);
+        let cache = pricing.cache_creation.unwrap();
+        assert_eq!(cache.ephemeral_5m_input_tokens, 0);
+        assert_eq!(cache.ephemeral_1h_input_tokens, 30);
+
+        let stats = UsageStats {
+            input_tokens: 10,
+            output_tokens: 5,
+            cache_creation_tokens: None,
+            cache_read_tokens: Some(7),
+            total_tokens: 22,
+        };
+        let pricing = stats.to_pricing_usage();
+        assert_eq!(pricing.input_tokens, 10);
+        assert_eq!(pricing.cache_read_input_tokens, 7);
+        assert!(pricing.cache_creation.is_none());
+    }
+}
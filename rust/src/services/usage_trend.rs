@@ -0,0 +1,315 @@
+use chrono::{DateTime, FixedOffset, Utc};
+use futures::Stream;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::config::UsageRetentionSettings;
+use crate::models::api_key::ModelUsage;
+use crate::models::usage_trend::{TrendRange, UsageBucket, UsageDimension, UsageExportRow};
+use crate::redis::RedisPool;
+use crate::services::api_key::parse_model_usage;
+use crate::utils::error::{AppError, Result};
+
+/// 一次请求在时间序列中的统计对象
+pub(crate) struct UsageTarget<'a> {
+    pub key_id: &'a str,
+    pub model: &'a str,
+    pub account_id: Option<&'a str>,
+}
+
+/// 把一次使用量写入全局、Key、账户、模型四个维度的小时桶和日桶
+///
+/// 由 `ApiKeyService::record_usage` 调用，与其他统计在同一个 pipeline 中执行
+pub(crate) fn record_usage_buckets(
+    pipe: &mut redis::Pipeline,
+    target: &UsageTarget,
+    usage: &ModelUsage,
+    now: DateTime<Utc>,
+    system_timezone: FixedOffset,
+    retention: &UsageRetentionSettings,
+) {
+    let buckets = [
+        UsageBucket::hour(now),
+        UsageBucket::day(now.with_timezone(&system_timezone).date_naive()),
+    ];
+
+    let mut members = vec![
+        (UsageDimension::ApiKey, target.key_id),
+        (UsageDimension::Model, target.model),
+    ];
+    if let Some(account_id) = target.account_id {
+        members.push((UsageDimension::Account, account_id));
+    }
+
+    for bucket in &buckets {
+        let ttl = bucket.granularity.retention_seconds(retention);
+
+        let usage_keys = std::iter::once(bucket.usage_key(UsageDimension::Global, ""))
+            .chain(members.iter().map(|(dim, id)| bucket.usage_key(*dim, id)));
+        for key in usage_keys {
+            incr_bucket_usage(pipe, &key, usage, ttl);
+        }
+
+        for (dimension, id) in &members {
+            let members_key = bucket.members_key(*dimension);
+            pipe.sadd(&members_key, *id).expire(&members_key, ttl);
+        }
+    }
+}
+
+/// 把使用量累加到一个统计桶并刷新过期时间
+pub(crate) fn incr_bucket_usage(
+    pipe: &mut redis::Pipeline,
+    key: &str,
+    usage: &ModelUsage,
+    ttl: i64,
+) {
+    pipe.hincr(key, "requests", usage.requests)
+        .hincr(key, "input_tokens", usage.input_tokens)
+        .hincr(key, "output_tokens", usage.output_tokens)
+        .hincr(key, "cache_creation_tokens", usage.cache_creation_tokens)
+        .hincr(key, "cache_read_tokens", usage.cache_read_tokens)
+        .cmd("HINCRBYFLOAT")
+        .arg(key)
+        .arg("cost")
+        .arg(usage.cost)
+        .cmd("HINCRBYFLOAT")
+        .arg(key)
+        .arg("billed_cost")
+        .arg(usage.billed_cost)
+        .expire(key, ttl);
+}
+
+/// 使用量时间序列查询服务
+///
+/// 读取 `record_usage` 写入的小时桶 (`usage:*:hourly:*`) 和日桶 (`usage:*:daily:*`)
+pub struct UsageTrendService {
+    redis: Arc<RedisPool>,
+}
+
+impl UsageTrendService {
+    pub fn new(redis: Arc<RedisPool>) -> Self {
+        Self { redis }
+    }
+
+    /// 单个统计对象在每个时间点的使用量 (全局统计时 `id` 为空)
+    pub async fn get_series(
+        &self,
+        dimension: UsageDimension,
+        id: &str,
+        range: &TrendRange,
+    ) -> Result<Vec<ModelUsage>> {
+        let keys: Vec<String> = range
+            .buckets()
+            .map(|bucket| bucket.usage_key(dimension, id))
+            .collect();
+        let mut usages = fetch_usages(&self.redis, &keys).await?.into_iter();
+
+        Ok(range
+            .points
+            .iter()
+            .map(|point| sum_usages(usages.by_ref().take(point.buckets.len())))
+            .collect())
+    }
+
+    /// 每个时间点中各成员 (Key / 账户 / 模型) 的使用量
+    pub async fn get_member_series(
+        &self,
+        dimension: UsageDimension,
+        range: &TrendRange,
+    ) -> Result<Vec<HashMap<String, ModelUsage>>> {
+        let buckets: Vec<&UsageBucket> = unique(range.buckets());
+        let members = fetch_members(&self.redis, dimension, &buckets).await?;
+
+        let mut keys = Vec::new();
+        let mut owners = Vec::new();
+        for (bucket, bucket_members) in buckets.iter().zip(&members) {
+            for member in bucket_members {
+                keys.push(bucket.usage_key(dimension, member));
+                owners.push((*bucket, member.as_str()));
+            }
+        }
+        let usages = fetch_usages(&self.redis, &keys).await?;
+
+        let mut by_bucket: HashMap<&UsageBucket, HashMap<&str, ModelUsage>> = HashMap::new();
+        for ((bucket, member), usage) in owners.into_iter().zip(usages) {
+            by_bucket.entry(bucket).or_default().insert(member, usage);
+        }
+
+        Ok(range
+            .points
+            .iter()
+            .map(|point| {
+                let mut totals: HashMap<String, ModelUsage> = HashMap::new();
+                for bucket in &point.buckets {
+                    for (member, usage) in by_bucket.get(bucket).into_iter().flatten() {
+                        totals.entry(member.to_string()).or_default().add(usage);
+                    }
+                }
+                totals
+            })
+            .collect())
+    }
+
+    /// 整个范围内各成员的使用量合计
+    pub async fn get_member_totals(
+        &self,
+        dimension: UsageDimension,
+        range: &TrendRange,
+    ) -> Result<HashMap<String, ModelUsage>> {
+        let mut totals: HashMap<String, ModelUsage> = HashMap::new();
+        for point in self.get_member_series(dimension, range).await? {
+            for (member, usage) in point {
+                totals.entry(member).or_default().add(&usage);
+            }
+        }
+        Ok(totals)
+    }
+
+    /// 按统计桶逐个读取并导出使用量 (每个桶产出一批行，没有使用量的成员跳过)
+    ///
+    /// `id` 为空时导出维度下的全部成员，全局维度忽略 `id`
+    pub fn export_rows(
+        &self,
+        dimension: UsageDimension,
+        id: Option<String>,
+        buckets: Vec<UsageBucket>,
+    ) -> impl Stream<Item = Result<Vec<UsageExportRow>>> {
+        let redis = self.redis.clone();
+        futures::stream::unfold(buckets.into_iter(), move |mut buckets| {
+            let redis = redis.clone();
+            let id = id.clone();
+            async move {
+                let bucket = buckets.next()?;
+                let rows = export_bucket(&redis, dimension, id, &bucket).await;
+                Some((rows, buckets))
+            }
+        })
+    }
+}
+
+async fn export_bucket(
+    redis: &RedisPool,
+    dimension: UsageDimension,
+    id: Option<String>,
+    bucket: &UsageBucket,
+) -> Result<Vec<UsageExportRow>> {
+    let members = match (dimension, id) {
+        (UsageDimension::Global, _) => vec![String::new()],
+        (_, Some(id)) => vec![id],
+        (_, None) => {
+            let mut members = fetch_members(redis, dimension, &[bucket])
+                .await?
+                .pop()
+                .unwrap_or_default();
+            members.sort();
+            members
+        }
+    };
+
+    let keys: Vec<String> = members
+        .iter()
+        .map(|member| bucket.usage_key(dimension, member))
+        .collect();
+    let usages = fetch_usages(redis, &keys).await?;
+
+    Ok(members
+        .iter()
+        .zip(usages)
+        .filter(|(_, usage)| usage.requests > 0)
+        .map(|(member, usage)| UsageExportRow::new(bucket, dimension, member, &usage))
+        .collect())
+}
+
+pub(crate) async fn fetch_usages(redis: &RedisPool, keys: &[String]) -> Result<Vec<ModelUsage>> {
+    if keys.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut pipe = redis::pipe();
+    for key in keys {
+        pipe.cmd("HGETALL").arg(key);
+    }
+    let mut conn = redis.get_connection().await?;
+    let hashes: Vec<HashMap<String, String>> = pipe
+        .query_async(&mut conn)
+        .await
+        .map_err(|e| AppError::RedisError(format!("Failed to get usage trend: {}", e)))?;
+
+    Ok(hashes.iter().map(parse_model_usage).collect())
+}
+
+pub(crate) async fn fetch_members(
+    redis: &RedisPool,
+    dimension: UsageDimension,
+    buckets: &[&UsageBucket],
+) -> Result<Vec<Vec<String>>> {
+    if buckets.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut pipe = redis::pipe();
+    for bucket in buckets {
+        pipe.cmd("SMEMBERS").arg(bucket.members_key(dimension));
+    }
+    let mut conn = redis.get_connection().await?;
+    pipe.query_async(&mut conn)
+        .await
+        .map_err(|e| AppError::RedisError(format!("Failed to get usage members: {}", e)))
+}
+
+pub(crate) fn sum_usages(usages: impl Iterator<Item = ModelUsage>) -> ModelUsage {
+    usages.fold(ModelUsage::default(), |mut total, usage| {
+        total.add(&usage);
+        total
+    })
+}
+
+/// 去重并保持顺序
+fn unique<'a>(buckets: impl Iterator<Item = &'a UsageBucket>) -> Vec<&'a UsageBucket> {
+    let mut seen = HashSet::new();
+    buckets.filter(|bucket| seen.insert(*bucket)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_usage_buckets_keys() {
+        let mut pipe = redis::pipe();
+        let now: DateTime<Utc> = "2025-01-31T16:42:00Z".parse().unwrap();
+        let usage = ModelUsage {
+            requests: 1,
+            input_tokens: 100,
+            cost: 0.01,
+            ..Default::default()
+        };
+        let target = UsageTarget {
+            key_id: "k1",
+            model: "claude-sonnet-4",
+            account_id: Some("a1"),
+        };
+        record_usage_buckets(
+            &mut pipe,
+            &target,
+            &usage,
+            now,
+            FixedOffset::east_opt(8 * 3600).unwrap(),
+            &UsageRetentionSettings::default(),
+        );
+
+        let packed = String::from_utf8_lossy(&pipe.get_packed_pipeline()).to_string();
+        for key in [
+            "usage:global:hourly:2025-01-31T16",
+            "usage:key:k1:hourly:2025-01-31T16",
+            "usage:account:a1:hourly:2025-01-31T16",
+            "usage:model:claude-sonnet-4:hourly:2025-01-31T16",
+            // 日桶按系统时区 (UTC+8) 已是 2 月 1 日
+            "usage:global:daily:2025-02-01",
+            "usage:members:account:daily:2025-02-01",
+        ] {
+            assert!(packed.contains(key), "missing {}", key);
+        }
+    }
+}
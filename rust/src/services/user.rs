@@ -0,0 +1,945 @@
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use chrono::{Duration, Utc};
+use rand::rngs::OsRng;
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::models::api_key::{
+    ApiKey, ApiKeyCreateOptions, BulkOperationFailure, BulkOperationResult,
+};
+use crate::models::role::ADMIN_ROLE_NAME;
+use crate::models::user::{User, UserAuthSource, UserInvitation, UserManagementSettings};
+use crate::redis::RedisPool;
+use crate::services::admin::{AdminService, LoginResponse};
+use crate::services::api_key::ApiKeyService;
+use crate::services::ldap::LdapUser;
+use crate::utils::error::{AppError, Result};
+
+/// 普通用户在 JWT 中的角色
+pub const USER_ROLE: &str = "user";
+
+/// 邀请码默认有效期 (小时)
+pub const DEFAULT_INVITATION_TTL_HOURS: i64 = 72;
+
+const USER_SETTINGS_KEY: &str = "user_management_settings";
+
+/// 创建中的 Key 名额计数过期时间 (秒)，防止进程异常退出后名额一直被占用
+const PENDING_KEY_SLOT_TTL_SECONDS: i64 = 60;
+
+/// 用户注册请求
+#[derive(Debug, Deserialize)]
+pub struct RegisterUserRequest {
+    pub username: String,
+    pub password: String,
+    pub email: Option<String>,
+    #[serde(rename = "displayName")]
+    pub display_name: Option<String>,
+    #[serde(rename = "invitationCode")]
+    pub invitation_code: Option<String>,
+}
+
+/// 用户管理服务
+///
+/// 负责用户注册/邀请、登录以及用户自有 API Key 的配额管理
+pub struct UserService {
+    redis: Arc<RedisPool>,
+    admin_service: Arc<AdminService>,
+    api_key_service: Arc<ApiKeyService>,
+}
+
+impl UserService {
+    pub fn new(
+        redis: Arc<RedisPool>,
+        admin_service: Arc<AdminService>,
+        api_key_service: Arc<ApiKeyService>,
+    ) -> Self {
+        Self {
+            redis,
+            admin_service,
+            api_key_service,
+        }
+    }
+
+    // ========================================
+    // 设置与邀请
+    // ========================================
+
+    /// 获取用户管理设置，未配置时返回默认值
+    pub async fn get_settings(&self) -> Result<UserManagementSettings> {
+        Ok(self
+            .redis
+            .get::<String>(USER_SETTINGS_KEY)
+            .await?
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default())
+    }
+
+    /// 更新用户管理设置
+    pub async fn update_settings(
+        &self,
+        settings: UserManagementSettings,
+    ) -> Result<UserManagementSettings> {
+        if settings.default_max_api_keys < 0 || settings.default_max_key_daily_cost_limit < 0.0 {
+            return Err(AppError::ValidationError(
+                "Quota values must not be negative".to_string(),
+            ));
+        }
+
+        let json = serde_json::to_string(&settings)
+            .map_err(|e| AppError::InternalError(format!("序列化失败: {}", e)))?;
+        self.redis.set(USER_SETTINGS_KEY, &json).await?;
+
+        Ok(settings)
+    }
+
+    /// 创建注册邀请
+    ///
+    /// # 参数
+    ///
+    /// * `created_by` - 创建邀请的管理员
+    /// * `email` - 限定注册邮箱 (可选)
+    /// * `max_api_keys` - 覆盖默认的 Key 数量上限 (可选)
+    /// * `ttl_hours` - 邀请有效期 (小时)
+    pub async fn create_invitation(
+        &self,
+        created_by: &str,
+        email: Option<String>,
+        max_api_keys: Option<i64>,
+        ttl_hours: i64,
+    ) -> Result<UserInvitation> {
+        if ttl_hours <= 0 {
+            return Err(AppError::ValidationError(
+                "Invitation TTL must be positive".to_string(),
+            ));
+        }
+
+        let now = Utc::now();
+        let invitation = UserInvitation {
+            code: Uuid::new_v4().simple().to_string(),
+            email,
+            max_api_keys,
+            created_by: created_by.to_string(),
+            created_at: now,
+            expires_at: now + Duration::hours(ttl_hours),
+        };
+
+        let json = serde_json::to_string(&invitation)
+            .map_err(|e| AppError::InternalError(format!("序列化失败: {}", e)))?;
+        self.redis
+            .setex(
+                &format!("user_invitation:{}", invitation.code),
+                &json,
+                (ttl_hours * 3600) as u64,
+            )
+            .await?;
+
+        info!("✉️  User invitation created by: {}", created_by);
+
+        Ok(invitation)
+    }
+
+    // ========================================
+    // 注册与登录
+    // ========================================
+
+    /// 注册新用户
+    ///
+    /// 未开放注册时必须提供有效的邀请码，邀请码使用后立即失效
+    pub async fn register(&self, request: RegisterUserRequest) -> Result<User> {
+        validate_username(&request.username)?;
+        // 会话、2FA 和登录锁定都以用户名区分，不能与管理员同名
+        if self.is_admin_username(&request.username).await? {
+            return Err(AppError::BadRequest(format!(
+                "Username {} is reserved",
+                request.username.trim()
+            )));
+        }
+        if request.password.len() < 8 {
+            return Err(AppError::ValidationError(
+                "Password must be at least 8 characters".to_string(),
+            ));
+        }
+
+        let settings = self.get_settings().await?;
+        let invitation_code = request
+            .invitation_code
+            .as_deref()
+            .filter(|code| !code.is_empty());
+        if invitation_code.is_none() && !settings.allow_registration {
+            return Err(AppError::Forbidden(
+                "Registration requires an invitation code".to_string(),
+            ));
+        }
+
+        // 用户名唯一性通过 SET NX 保证，先占用用户名再消费邀请码
+        let user_id = Uuid::new_v4().to_string();
+        let index_key = username_index_key(&request.username);
+        if !self.reserve_username(&request.username, &user_id).await? {
+            return Err(AppError::BadRequest(format!(
+                "Username {} is already taken",
+                request.username.trim()
+            )));
+        }
+
+        let invitation = match invitation_code {
+            Some(code) => match self.take_invitation(code, request.email.as_deref()).await {
+                Ok(invitation) => Some(invitation),
+                Err(e) => {
+                    self.redis.del(&index_key).await?;
+                    return Err(e);
+                }
+            },
+            None => None,
+        };
+
+        let now = Utc::now();
+        let user = User {
+            id: user_id,
+            username: request.username.trim().to_string(),
+            email: request.email,
+            display_name: request.display_name,
+            password_hash: Some(hash_password(&request.password)?),
+            role: USER_ROLE.to_string(),
+            auth_source: UserAuthSource::Local,
+            is_active: true,
+            max_api_keys: invitation
+                .as_ref()
+                .and_then(|i| i.max_api_keys)
+                .unwrap_or(settings.default_max_api_keys),
+            max_key_daily_cost_limit: settings.default_max_key_daily_cost_limit,
+            created_at: now,
+            updated_at: now,
+            last_login_at: None,
+            invited_by: invitation.map(|i| i.created_by),
+        };
+
+        self.save_user(&user).await?;
+        info!("👤 User registered: {}", user.username);
+
+        Ok(user)
+    }
+
+    /// 通过 SET NX 占用用户名索引，用户名已存在时返回 false
+    async fn reserve_username(&self, username: &str, user_id: &str) -> Result<bool> {
+        let mut conn = self.redis.get_connection().await?;
+        let reserved: Option<String> = redis::cmd("SET")
+            .arg(username_index_key(username))
+            .arg(user_id)
+            .arg("NX")
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to reserve username: {}", e)))?;
+
+        Ok(reserved.is_some())
+    }
+
+    /// 校验并消费邀请码
+    ///
+    /// 邀请绑定了邮箱时，注册邮箱必须一致；校验失败的邀请码不会被消费
+    async fn take_invitation(&self, code: &str, email: Option<&str>) -> Result<UserInvitation> {
+        let key = format!("user_invitation:{}", code);
+        let invitation: UserInvitation = self
+            .redis
+            .get::<String>(&key)
+            .await?
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .ok_or_else(|| AppError::Forbidden("Invalid or expired invitation code".to_string()))?;
+
+        if let Some(expected) = invitation.email.as_deref() {
+            if email != Some(expected) {
+                return Err(AppError::Forbidden(
+                    "Invitation is bound to a different email".to_string(),
+                ));
+            }
+        }
+
+        // DEL 返回 0 说明邀请码已被并发请求使用
+        let mut conn = self.redis.get_connection().await?;
+        let deleted: i64 = redis::cmd("DEL")
+            .arg(&key)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to consume invitation: {}", e)))?;
+        if deleted == 0 {
+            return Err(AppError::Forbidden(
+                "Invalid or expired invitation code".to_string(),
+            ));
+        }
+
+        Ok(invitation)
+    }
+
+    /// 为已通过校验的用户签发角色为 `user` 的 JWT
+    pub async fn issue_user_login(&self, user: User) -> Result<LoginResponse> {
+        self.issue_login(user, USER_ROLE).await
+    }
+
+    /// 管理后台登录，签发用户当前角色的 JWT
+    ///
+    /// 只有被分配了管理角色 (非 `user`) 的用户可以登录管理后台
+    pub async fn authenticate_staff(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> Result<LoginResponse> {
+        let user = self.verify_staff_credentials(username, password).await?;
+        self.issue_staff_login(user).await
+    }
+
+    /// 校验管理后台登录的用户名和密码 (不签发 token)
+    pub async fn verify_staff_credentials(&self, username: &str, password: &str) -> Result<User> {
+        let user = self.verify_credentials(username, password).await?;
+        if user.role == USER_ROLE {
+            return Err(AppError::Forbidden("Admin role required".to_string()));
+        }
+
+        Ok(user)
+    }
+
+    /// 为已通过校验的管理角色用户签发 JWT
+    pub async fn issue_staff_login(&self, user: User) -> Result<LoginResponse> {
+        let role = user.role.clone();
+        self.issue_login(user, &role).await
+    }
+
+    /// 校验用户名和密码
+    ///
+    /// 本地用户校验密码哈希；未注册或来自 LDAP 的用户在启用 LDAP 时交由目录认证，
+    /// 首次 LDAP 登录时自动创建用户记录
+    pub async fn verify_credentials(&self, username: &str, password: &str) -> Result<User> {
+        let invalid = || AppError::Unauthorized("Invalid credentials".to_string());
+
+        let user = self.get_user_by_username(username).await.ok();
+        let is_local = user
+            .as_ref()
+            .is_some_and(|user| user.auth_source == UserAuthSource::Local);
+
+        let user = match self.admin_service.ldap_provider() {
+            Some(provider) if !is_local => {
+                let ldap_user = provider.authenticate(username, password).await?;
+                self.provision_ldap_user(&ldap_user).await?
+            }
+            _ => {
+                let user = user.ok_or_else(invalid)?;
+                let password_hash = user.password_hash.as_deref().ok_or_else(invalid)?;
+
+                let parsed_hash = PasswordHash::new(password_hash).map_err(|e| {
+                    AppError::InternalError(format!("Failed to parse password hash: {}", e))
+                })?;
+                Argon2::default()
+                    .verify_password(password.as_bytes(), &parsed_hash)
+                    .map_err(|_| invalid())?;
+
+                user
+            }
+        };
+
+        if !user.is_active {
+            return Err(AppError::Forbidden("User is disabled".to_string()));
+        }
+
+        Ok(user)
+    }
+
+    /// 用户名是否与管理员用户名相同 (不区分大小写)
+    async fn is_admin_username(&self, username: &str) -> Result<bool> {
+        Ok(self
+            .admin_service
+            .admin_username()
+            .await?
+            .is_some_and(|admin| same_username(&admin, username)))
+    }
+
+    /// 创建登录会话并记录最后登录时间
+    ///
+    /// 与管理员同名的历史用户记录不允许登录，避免共用管理员的会话版本和 2FA 记录
+    async fn issue_login(&self, mut user: User, role: &str) -> Result<LoginResponse> {
+        if self.is_admin_username(&user.username).await? {
+            warn!(
+                "⚠️  Login refused for user {} sharing the admin username",
+                user.username
+            );
+            return Err(AppError::Unauthorized("Invalid credentials".to_string()));
+        }
+
+        let response = self
+            .admin_service
+            .create_session(&user.username, role)
+            .await?;
+
+        user.last_login_at = Some(Utc::now());
+        self.save_user(&user).await?;
+
+        Ok(response)
+    }
+
+    /// 获取或创建 LDAP 用户记录，并同步目录中的邮箱和显示名
+    ///
+    /// 目录组只决定 admin 角色，其他管理角色由管理员在后台分配
+    async fn provision_ldap_user(&self, ldap_user: &LdapUser) -> Result<User> {
+        if self.is_admin_username(&ldap_user.username).await? {
+            warn!(
+                "⚠️  LDAP login for {} conflicts with the admin username",
+                ldap_user.username
+            );
+            return Err(AppError::Unauthorized("Invalid credentials".to_string()));
+        }
+
+        match self.get_user_by_username(&ldap_user.username).await {
+            Ok(mut user) => {
+                // 同名本地账号不允许被目录账号接管
+                if user.auth_source != UserAuthSource::Ldap {
+                    warn!(
+                        "⚠️  LDAP login for {} conflicts with a local user",
+                        ldap_user.username
+                    );
+                    return Err(AppError::Unauthorized("Invalid credentials".to_string()));
+                }
+
+                user.email = ldap_user.email.clone().or(user.email);
+                user.display_name = ldap_user.display_name.clone().or(user.display_name);
+                let role_changed = (ldap_user.role == ADMIN_ROLE_NAME
+                    || user.role == ADMIN_ROLE_NAME)
+                    && user.role != ldap_user.role;
+                if role_changed {
+                    info!(
+                        "🛡️  LDAP user {} role changed by directory groups: {} -> {}",
+                        user.username, user.role, ldap_user.role
+                    );
+                    user.role = ldap_user.role.clone();
+                    // 立即保存并让旧令牌失效，即使本次登录未完成 (如等待双因素验证码)
+                    self.save_user(&user).await?;
+                    self.admin_service
+                        .bump_token_version(&user.username)
+                        .await?;
+                }
+
+                Ok(user)
+            }
+            Err(AppError::NotFound(_)) => {
+                let user_id = Uuid::new_v4().to_string();
+                if !self.reserve_username(&ldap_user.username, &user_id).await? {
+                    // 并发的首次登录正在创建记录
+                    return Err(AppError::Unauthorized(
+                        "User is being provisioned, please retry".to_string(),
+                    ));
+                }
+
+                let settings = self.get_settings().await?;
+                let now = Utc::now();
+                let user = User {
+                    id: user_id,
+                    username: ldap_user.username.clone(),
+                    email: ldap_user.email.clone(),
+                    display_name: ldap_user.display_name.clone(),
+                    password_hash: None,
+                    role: ldap_user.role.clone(),
+                    auth_source: UserAuthSource::Ldap,
+                    is_active: true,
+                    max_api_keys: settings.default_max_api_keys,
+                    max_key_daily_cost_limit: settings.default_max_key_daily_cost_limit,
+                    created_at: now,
+                    updated_at: now,
+                    last_login_at: None,
+                    invited_by: None,
+                };
+
+                self.save_user(&user).await?;
+                info!(
+                    "👤 LDAP user provisioned: {} ({})",
+                    user.username, ldap_user.dn
+                );
+
+                Ok(user)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // ========================================
+    // 用户查询与管理
+    // ========================================
+
+    pub async fn get_user(&self, user_id: &str) -> Result<User> {
+        let json: String = self
+            .redis
+            .get(&format!("user:{}", user_id))
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("User {} not found", user_id)))?;
+
+        serde_json::from_str(&json)
+            .map_err(|e| AppError::InternalError(format!("反序列化失败: {}", e)))
+    }
+
+    pub async fn get_user_by_username(&self, username: &str) -> Result<User> {
+        let user_id: String = self
+            .redis
+            .get(&username_index_key(username))
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("User {} not found", username)))?;
+
+        self.get_user(&user_id).await
+    }
+
+    /// 获取所有用户 (按创建时间排序)
+    pub async fn list_users(&self) -> Result<Vec<User>> {
+        let mut users = Vec::new();
+        for key in self.redis.keys("user:*").await? {
+            if let Some(json) = self.redis.get::<String>(&key).await? {
+                if let Ok(user) = serde_json::from_str::<User>(&json) {
+                    users.push(user);
+                }
+            }
+        }
+        users.sort_by_key(|user| user.created_at);
+
+        Ok(users)
+    }
+
+    /// 启用/禁用用户
+    ///
+    /// # 参数
+    ///
+    /// * `user_id` - 用户 ID
+    /// * `is_active` - 新状态
+    /// * `update_keys` - 是否同步启用/禁用该用户的所有 API Key
+    /// * `updated_by` - 操作者
+    pub async fn set_user_active(
+        &self,
+        user_id: &str,
+        is_active: bool,
+        update_keys: bool,
+        updated_by: &str,
+    ) -> Result<User> {
+        let mut user = self.get_user(user_id).await?;
+        user.is_active = is_active;
+        self.save_user(&user).await?;
+
+        // 禁用后立即注销该用户的所有会话
+        if !is_active {
+            self.admin_service
+                .bump_token_version(&user.username)
+                .await?;
+        }
+
+        if update_keys {
+            let key_ids: Vec<String> = self
+                .api_key_service
+                .get_keys_by_user(user_id)
+                .await?
+                .into_iter()
+                .map(|api_key| api_key.id)
+                .collect();
+            let result = self
+                .api_key_service
+                .bulk_set_active(&key_ids, is_active, updated_by)
+                .await;
+            if !result.failed.is_empty() {
+                warn!(
+                    "⚠️  Failed to update {} keys of user {}",
+                    result.failed.len(),
+                    user.username
+                );
+            }
+        }
+
+        info!(
+            "👤 User {} {} by: {}",
+            user.username,
+            if is_active { "enabled" } else { "disabled" },
+            updated_by
+        );
+
+        Ok(user)
+    }
+
+    /// 更新用户的 API Key 配额
+    pub async fn update_quota(
+        &self,
+        user_id: &str,
+        max_api_keys: Option<i64>,
+        max_key_daily_cost_limit: Option<f64>,
+    ) -> Result<User> {
+        let mut user = self.get_user(user_id).await?;
+
+        if let Some(max) = max_api_keys {
+            if max < 0 {
+                return Err(AppError::ValidationError(
+                    "maxApiKeys must not be negative".to_string(),
+                ));
+            }
+            user.max_api_keys = max;
+        }
+        if let Some(limit) = max_key_daily_cost_limit {
+            if limit < 0.0 {
+                return Err(AppError::ValidationError(
+                    "maxKeyDailyCostLimit must not be negative".to_string(),
+                ));
+            }
+            user.max_key_daily_cost_limit = limit;
+        }
+
+        self.save_user(&user).await?;
+
+        Ok(user)
+    }
+
+    /// 分配角色 (调用方需确保角色存在)
+    pub async fn set_user_role(&self, user_id: &str, role: &str, updated_by: &str) -> Result<User> {
+        let mut user = self.get_user(user_id).await?;
+        let role_changed = user.role != role;
+        user.role = role.to_string();
+        self.save_user(&user).await?;
+
+        // 角色变化后旧令牌中的角色已过期，要求重新登录
+        if role_changed {
+            self.admin_service
+                .bump_token_version(&user.username)
+                .await?;
+        }
+
+        info!(
+            "🛡️  User {} assigned role {} by: {}",
+            user.username, role, updated_by
+        );
+
+        Ok(user)
+    }
+
+    /// 将用户的 API Keys 转移给其他用户或收回
+    ///
+    /// # 参数
+    ///
+    /// * `from_user_id` - 原所属用户
+    /// * `to_user_id` - 新所属用户，None 表示收回为管理员所有
+    /// * `key_ids` - 要转移的 Key，为空时转移该用户的全部 Key
+    pub async fn reassign_keys(
+        &self,
+        from_user_id: &str,
+        to_user_id: Option<&str>,
+        key_ids: &[String],
+    ) -> Result<BulkOperationResult> {
+        if let Some(to_user_id) = to_user_id {
+            self.get_user(to_user_id).await?;
+        }
+
+        let owned: Vec<String> = self
+            .api_key_service
+            .get_keys_by_user(from_user_id)
+            .await?
+            .into_iter()
+            .map(|api_key| api_key.id)
+            .collect();
+        let targets = if key_ids.is_empty() {
+            owned.clone()
+        } else {
+            key_ids.to_vec()
+        };
+
+        let mut result = BulkOperationResult::default();
+        for key_id in targets {
+            if !owned.contains(&key_id) {
+                result.failed.push(BulkOperationFailure {
+                    id: key_id,
+                    error: "API Key does not belong to this user".to_string(),
+                });
+                continue;
+            }
+
+            match self
+                .api_key_service
+                .set_key_owner(&key_id, to_user_id.map(str::to_string))
+                .await
+            {
+                Ok(_) => result.succeeded.push(key_id),
+                Err(e) => result.failed.push(BulkOperationFailure {
+                    id: key_id,
+                    error: e.to_string(),
+                }),
+            }
+        }
+
+        info!(
+            "🔀 Reassigned {} keys from user {} to {}",
+            result.succeeded.len(),
+            from_user_id,
+            to_user_id.unwrap_or("admin")
+        );
+
+        Ok(result)
+    }
+
+    // ========================================
+    // 用户自有 API Key
+    // ========================================
+
+    /// 获取当前登录用户 (JWT 必须是 user 角色且用户处于启用状态)
+    pub async fn get_active_user(&self, username: &str, role: &str) -> Result<User> {
+        if role != USER_ROLE {
+            return Err(AppError::Forbidden("User role required".to_string()));
+        }
+
+        let user = self
+            .get_user_by_username(username)
+            .await
+            .map_err(|_| AppError::Unauthorized("User not found".to_string()))?;
+        if !user.is_active {
+            return Err(AppError::Forbidden("User is disabled".to_string()));
+        }
+
+        Ok(user)
+    }
+
+    /// 获取用户的所有 API Keys (不含已删除)
+    pub async fn list_user_keys(&self, user_id: &str) -> Result<Vec<ApiKey>> {
+        self.api_key_service.get_keys_by_user(user_id).await
+    }
+
+    /// 用户创建自己的 API Key (受配额限制)
+    ///
+    /// 先递增用户的创建中计数占用名额再统计已有 Key，并发创建时每个请求都能看到
+    /// 其他尚未完成的创建，不会超出配额
+    pub async fn create_user_key(
+        &self,
+        user: &User,
+        options: ApiKeyCreateOptions,
+    ) -> Result<(String, ApiKey)> {
+        let pending_key = format!("user_key_slots_pending:{}", user.id);
+        let pending = self.redis.incr(&pending_key).await?;
+        let result = match self
+            .redis
+            .expire(&pending_key, PENDING_KEY_SLOT_TTL_SECONDS)
+            .await
+        {
+            Ok(_) => self.create_key_in_slot(user, options, pending - 1).await,
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = self.redis.incr_by(&pending_key, -1).await {
+            warn!(
+                "⚠️  Failed to release pending API key slot for user {}: {}",
+                user.username, e
+            );
+        }
+
+        result
+    }
+
+    /// 在已占用的名额内创建 Key，`other_pending` 为其他创建中的请求数
+    async fn create_key_in_slot(
+        &self,
+        user: &User,
+        mut options: ApiKeyCreateOptions,
+        other_pending: i64,
+    ) -> Result<(String, ApiKey)> {
+        let existing = self.api_key_service.get_keys_by_user(&user.id).await?.len();
+        apply_key_quota(user, existing + other_pending.max(0) as usize, &mut options)?;
+
+        options.user_id = Some(user.id.clone());
+        options.created_by = Some(user.username.clone());
+        options.created_by_type = Some(USER_ROLE.to_string());
+
+        let result = self.api_key_service.generate_key(options).await?;
+        info!(
+            "🔑 User {} created API key: {}",
+            user.username, result.1.name
+        );
+
+        Ok(result)
+    }
+
+    /// 获取用户自己的 API Key，校验归属
+    pub async fn get_user_key(&self, user: &User, key_id: &str) -> Result<ApiKey> {
+        let api_key = self.api_key_service.get_key(key_id).await?;
+        if api_key.user_id.as_deref() != Some(user.id.as_str()) || api_key.is_deleted {
+            return Err(AppError::NotFound(format!("API Key {} not found", key_id)));
+        }
+
+        Ok(api_key)
+    }
+
+    /// 用户修改自己 API Key 的名称或停用 Key
+    ///
+    /// 停用的 Key 只能由管理员重新启用，用户不能借此恢复被管理员停用的 Key
+    pub async fn update_user_key(
+        &self,
+        user: &User,
+        key_id: &str,
+        name: Option<String>,
+        is_active: Option<bool>,
+    ) -> Result<ApiKey> {
+        let api_key = self.get_user_key(user, key_id).await?;
+        let is_active = match is_active {
+            Some(true) if !api_key.is_active => {
+                return Err(AppError::Forbidden(
+                    "Disabled API Keys can only be re-enabled by an administrator".to_string(),
+                ));
+            }
+            Some(true) => None,
+            other => other,
+        };
+
+        self.api_key_service
+            .update_key(
+                key_id, name, is_active, None, None, None, None, None, None, None, None, None,
+                None, None, None, None, None, None, None, None, None, None, None, None, None,
+            )
+            .await
+    }
+
+    /// 用户删除自己的 API Key (软删除)
+    pub async fn delete_user_key(&self, user: &User, key_id: &str) -> Result<()> {
+        self.get_user_key(user, key_id).await?;
+        self.api_key_service
+            .delete_key(key_id, &user.username)
+            .await
+    }
+
+    async fn save_user(&self, user: &User) -> Result<()> {
+        let mut user = user.clone();
+        user.updated_at = Utc::now();
+
+        let json = serde_json::to_string(&user)
+            .map_err(|e| AppError::InternalError(format!("序列化失败: {}", e)))?;
+        self.redis.set(&format!("user:{}", user.id), &json).await
+    }
+}
+
+fn username_index_key(username: &str) -> String {
+    format!("user_username:{}", username.trim().to_lowercase())
+}
+
+fn same_username(a: &str, b: &str) -> bool {
+    a.trim().eq_ignore_ascii_case(b.trim())
+}
+
+fn hash_password(password: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| AppError::InternalError(format!("Failed to hash password: {}", e)))
+}
+
+/// 用户名: 3-32 位字母、数字、`_`、`-`、`.`
+fn validate_username(username: &str) -> Result<()> {
+    let username = username.trim();
+    let valid_chars = username
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+
+    if username.len() < 3 || username.len() > 32 || !valid_chars {
+        return Err(AppError::ValidationError(
+            "Username must be 3-32 characters of letters, digits, '_', '-' or '.'".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// 检查用户配额并调整 Key 选项
+///
+/// - 已有 Key 数量达到上限时拒绝
+/// - 配置了每日费用上限时，未设置限制的 Key 使用上限值，超过上限则拒绝
+fn apply_key_quota(
+    user: &User,
+    existing_keys: usize,
+    options: &mut ApiKeyCreateOptions,
+) -> Result<()> {
+    if existing_keys as i64 >= user.max_api_keys {
+        return Err(AppError::Forbidden(format!(
+            "API Key quota exceeded ({} / {})",
+            existing_keys, user.max_api_keys
+        )));
+    }
+
+    let cap = user.max_key_daily_cost_limit;
+    if cap > 0.0 {
+        if options.daily_cost_limit <= 0.0 {
+            options.daily_cost_limit = cap;
+        } else if options.daily_cost_limit > cap {
+            return Err(AppError::Forbidden(format!(
+                "Daily cost limit must not exceed {}",
+                cap
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_user(max_api_keys: i64, max_key_daily_cost_limit: f64) -> User {
+        let now = Utc::now();
+        User {
+            id: "u1".to_string(),
+            username: "alice".to_string(),
+            email: None,
+            display_name: None,
+            password_hash: None,
+            role: USER_ROLE.to_string(),
+            auth_source: UserAuthSource::Local,
+            is_active: true,
+            max_api_keys,
+            max_key_daily_cost_limit,
+            created_at: now,
+            updated_at: now,
+            last_login_at: None,
+            invited_by: None,
+        }
+    }
+
+    #[test]
+    fn test_apply_key_quota_count() {
+        let user = test_user(2, 0.0);
+        let mut options = ApiKeyCreateOptions::default();
+
+        assert!(apply_key_quota(&user, 1, &mut options).is_ok());
+        assert!(matches!(
+            apply_key_quota(&user, 2, &mut options),
+            Err(AppError::Forbidden(_))
+        ));
+    }
+
+    #[test]
+    fn test_apply_key_quota_daily_cost_cap() {
+        let user = test_user(5, 10.0);
+
+        // 未设置限制时使用上限
+        let mut options = ApiKeyCreateOptions::default();
+        apply_key_quota(&user, 0, &mut options).unwrap();
+        assert_eq!(options.daily_cost_limit, 10.0);
+
+        // 低于上限保持不变
+        options.daily_cost_limit = 3.0;
+        apply_key_quota(&user, 0, &mut options).unwrap();
+        assert_eq!(options.daily_cost_limit, 3.0);
+
+        // 超过上限拒绝
+        options.daily_cost_limit = 20.0;
+        assert!(apply_key_quota(&user, 0, &mut options).is_err());
+    }
+
+    #[test]
+    fn test_validate_username() {
+        assert!(validate_username("alice_01").is_ok());
+        assert!(validate_username("ab").is_err());
+        assert!(validate_username("bad name").is_err());
+        assert!(validate_username(&"a".repeat(33)).is_err());
+    }
+
+    #[test]
+    fn test_same_username() {
+        assert!(same_username("admin", "Admin"));
+        assert!(same_username(" admin ", "ADMIN"));
+        assert!(!same_username("admin", "admin2"));
+    }
+}
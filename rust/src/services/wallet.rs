@@ -0,0 +1,499 @@
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+use crate::models::api_key::ApiKey;
+use crate::models::wallet::{
+    redemption_code_key, validate_amount, GenerateRedemptionCodesRequest, LedgerEntry,
+    LedgerEntryType, RedemptionCode, RedemptionCodeStatus, Wallet, WalletOwner,
+    MAX_WALLET_LEDGER_ENTRIES, MAX_WALLET_LEDGER_PAGE, REDEMPTION_CODES_KEY,
+};
+use crate::redis::RedisPool;
+use crate::utils::error::{AppError, Result};
+
+/// 兑换码字符集 (去掉易混淆的 0/O/1/I)
+const REDEMPTION_CODE_ALPHABET: &[u8] = b"23456789ABCDEFGHJKLMNPQRSTUVWXYZ";
+
+/// 兑换码随机部分长度 (4 段 × 4 位，80 bit)
+const REDEMPTION_CODE_LENGTH: usize = 16;
+
+/// 更新余额并写入流水
+///
+/// 余额、累计金额和流水在同一个脚本中修改，并发请求之间流水的 `balanceAfter` 始终连续。
+/// 流水按条数截断；入账 (充值、兑换) 同时写入不截断的入账流水，不会被用量扣费挤掉
+const APPLY_LEDGER_LUA: &str = r#"
+local function apply(wallet_key, ledger_key, credit_key, amount, entry_json, now, max_entries)
+    local balance = redis.call('HINCRBYFLOAT', wallet_key, 'balance', amount)
+    if amount >= 0 then
+        redis.call('HINCRBYFLOAT', wallet_key, 'total_credited', amount)
+    else
+        redis.call('HINCRBYFLOAT', wallet_key, 'total_spent', -amount)
+    end
+    redis.call('HSETNX', wallet_key, 'created_at', now)
+    redis.call('HSET', wallet_key, 'updated_at', now)
+
+    local entry = cjson.decode(entry_json)
+    entry['amount'] = amount
+    entry['balanceAfter'] = tonumber(balance)
+    local encoded = cjson.encode(entry)
+    redis.call('LPUSH', ledger_key, encoded)
+    redis.call('LTRIM', ledger_key, 0, max_entries - 1)
+    if amount >= 0 then
+        redis.call('LPUSH', credit_key, encoded)
+    end
+    return balance
+end
+"#;
+
+/// KEYS: 钱包、流水、入账流水；ARGV: 金额、流水 JSON、当前时间、流水上限
+const CREDIT_OR_DEBIT_LUA: &str = r#"
+return apply(KEYS[1], KEYS[2], KEYS[3], tonumber(ARGV[1]), ARGV[2], ARGV[3], tonumber(ARGV[4]))
+"#;
+
+/// KEYS: 兑换码、钱包、流水、入账流水；ARGV: 当前时间、流水 JSON、流水上限、钱包所有者
+///
+/// 返回 `{状态, 金额, 余额}`，状态为 ok / not_found / redeemed / disabled / expired
+const REDEEM_LUA: &str = r#"
+local code = redis.call('HMGET', KEYS[1], 'status', 'amount', 'expires_at')
+if not code[1] then
+    return {'not_found', '0', '0'}
+end
+if code[1] ~= 'active' then
+    return {code[1], code[2], '0'}
+end
+local expires_at = tonumber(code[3] or '0')
+if expires_at > 0 and expires_at <= tonumber(ARGV[1]) then
+    return {'expired', code[2], '0'}
+end
+
+redis.call('HSET', KEYS[1], 'status', 'redeemed', 'redeemed_to', ARGV[4], 'redeemed_at', ARGV[1])
+local balance = apply(
+    KEYS[2], KEYS[3], KEYS[4], tonumber(code[2]), ARGV[2], ARGV[1], tonumber(ARGV[3])
+)
+return {'ok', code[2], balance}
+"#;
+
+/// KEYS: 兑换码；只有未兑换的兑换码可以停用
+const DISABLE_CODE_LUA: &str = r#"
+local status = redis.call('HGET', KEYS[1], 'status')
+if status == 'active' then
+    redis.call('HSET', KEYS[1], 'status', 'disabled')
+    return 'disabled'
+end
+return status or 'not_found'
+"#;
+
+/// 预付费钱包服务
+///
+/// 有钱包的 Key 每次请求后按计费成本扣减余额，余额用尽后拒绝新请求。
+/// Key 自己没有钱包时使用所属用户的钱包；两者都没有时不受余额限制
+#[derive(Clone)]
+pub struct WalletService {
+    redis: RedisPool,
+}
+
+impl WalletService {
+    pub fn new(redis: RedisPool) -> Self {
+        Self { redis }
+    }
+
+    /// Key 扣费使用的钱包 (Key 钱包优先于用户钱包)，没有钱包时返回 None
+    pub async fn resolve_wallet(&self, api_key: &ApiKey) -> Result<Option<WalletOwner>> {
+        let owner = WalletOwner::key(&api_key.id);
+        if self.redis.exists(&owner.wallet_key()).await? {
+            return Ok(Some(owner));
+        }
+
+        if let Some(user_id) = &api_key.user_id {
+            let owner = WalletOwner::user(user_id);
+            if self.redis.exists(&owner.wallet_key()).await? {
+                return Ok(Some(owner));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// 钱包余额信息，钱包不存在时返回 None
+    pub async fn get_wallet(&self, owner: &WalletOwner) -> Result<Option<Wallet>> {
+        let hash: HashMap<String, String> = self
+            .redis
+            .hgetall(&owner.wallet_key())
+            .await?
+            .into_iter()
+            .collect();
+        if hash.is_empty() {
+            return Ok(None);
+        }
+
+        let amount = |name: &str| hash.get(name).and_then(|v| v.parse().ok()).unwrap_or(0.0);
+        let timestamp = |name: &str| {
+            hash.get(name)
+                .and_then(|v| v.parse().ok())
+                .and_then(|ts| DateTime::from_timestamp(ts, 0))
+        };
+        Ok(Some(Wallet {
+            owner: owner.clone(),
+            balance: amount("balance"),
+            total_credited: amount("total_credited"),
+            total_spent: amount("total_spent"),
+            created_at: timestamp("created_at"),
+            updated_at: timestamp("updated_at"),
+        }))
+    }
+
+    /// 流水 (最新的在前)，`credits_only` 时读取完整的入账流水
+    pub async fn get_ledger(
+        &self,
+        owner: &WalletOwner,
+        offset: usize,
+        limit: usize,
+        credits_only: bool,
+    ) -> Result<(Vec<LedgerEntry>, usize)> {
+        let limit = limit.clamp(1, MAX_WALLET_LEDGER_PAGE);
+        let ledger_key = if credits_only {
+            owner.credit_ledger_key()
+        } else {
+            owner.ledger_key()
+        };
+        let mut conn = self.redis.get_connection().await?;
+        let (entries, total): (Vec<String>, usize) = redis::pipe()
+            .cmd("LRANGE")
+            .arg(&ledger_key)
+            .arg(offset)
+            .arg(offset + limit - 1)
+            .cmd("LLEN")
+            .arg(&ledger_key)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to get wallet ledger: {}", e)))?;
+
+        let entries = entries
+            .iter()
+            .filter_map(|json| {
+                serde_json::from_str(json)
+                    .inspect_err(|e| warn!("⚠️ Invalid ledger entry for {}: {}", owner, e))
+                    .ok()
+            })
+            .collect();
+        Ok((entries, total))
+    }
+
+    /// 管理员充值，钱包不存在时创建
+    pub async fn top_up(
+        &self,
+        owner: &WalletOwner,
+        amount: f64,
+        note: Option<String>,
+        operator: &str,
+    ) -> Result<LedgerEntry> {
+        validate_amount(amount).map_err(AppError::ValidationError)?;
+
+        let mut entry = LedgerEntry::new(LedgerEntryType::Topup, amount);
+        entry.operator = Some(operator.to_string());
+        entry.note = note;
+        entry.balance_after = self.apply(owner, &entry).await?;
+
+        info!(
+            "💰 Wallet {} topped up by {}: +{:.6}",
+            owner, operator, amount
+        );
+        Ok(entry)
+    }
+
+    /// 按计费成本扣减余额 (余额可能因此变为负数)
+    pub async fn debit_usage(
+        &self,
+        owner: &WalletOwner,
+        amount: f64,
+        request_id: Option<String>,
+        model: &str,
+    ) -> Result<f64> {
+        let mut entry = LedgerEntry::new(LedgerEntryType::Usage, -amount);
+        entry.reference = request_id;
+        entry.model = Some(model.to_string());
+        self.apply(owner, &entry).await
+    }
+
+    async fn apply(&self, owner: &WalletOwner, entry: &LedgerEntry) -> Result<f64> {
+        let script = redis::Script::new(&format!("{}{}", APPLY_LEDGER_LUA, CREDIT_OR_DEBIT_LUA));
+        let mut conn = self.redis.get_connection().await?;
+        let balance: f64 = script
+            .key(owner.wallet_key())
+            .key(owner.ledger_key())
+            .key(owner.credit_ledger_key())
+            .arg(entry.amount)
+            .arg(serde_json::to_string(entry)?)
+            .arg(entry.created_at.timestamp())
+            .arg(MAX_WALLET_LEDGER_ENTRIES)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to update wallet: {}", e)))?;
+        Ok(balance)
+    }
+
+    /// 批量生成兑换码，返回批次 ID 和生成的兑换码
+    pub async fn generate_codes(
+        &self,
+        request: &GenerateRedemptionCodesRequest,
+        created_by: &str,
+    ) -> Result<(String, Vec<RedemptionCode>)> {
+        let now = Utc::now();
+        request.validate(now).map_err(AppError::ValidationError)?;
+
+        let batch_id = uuid::Uuid::new_v4().to_string();
+        let codes: Vec<RedemptionCode> = (0..request.count)
+            .map(|_| RedemptionCode {
+                code: generate_redemption_code(),
+                amount: request.amount,
+                status: RedemptionCodeStatus::Active,
+                batch_id: batch_id.clone(),
+                note: request.note.clone(),
+                created_by: created_by.to_string(),
+                created_at: Some(now),
+                expires_at: request.expires_at,
+                redeemed_to: None,
+                redeemed_at: None,
+            })
+            .collect();
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for code in &codes {
+            pipe.hset_multiple(
+                redemption_code_key(&code.code),
+                &[
+                    ("amount", code.amount.to_string()),
+                    ("status", "active".to_string()),
+                    ("batch_id", batch_id.clone()),
+                    ("note", code.note.clone().unwrap_or_default()),
+                    ("created_by", created_by.to_string()),
+                    ("created_at", now.timestamp().to_string()),
+                    (
+                        "expires_at",
+                        code.expires_at.map_or(0, |at| at.timestamp()).to_string(),
+                    ),
+                ],
+            )
+            .zadd(REDEMPTION_CODES_KEY, &code.code, now.timestamp());
+        }
+        let mut conn = self.redis.get_connection().await?;
+        pipe.query_async::<_, ()>(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to save redemption codes: {}", e)))?;
+
+        info!(
+            "🎟️ {} redemption codes of {:.2} generated by {} (batch: {})",
+            codes.len(),
+            request.amount,
+            created_by,
+            batch_id
+        );
+        Ok((batch_id, codes))
+    }
+
+    /// 兑换码列表 (最新的在前)，可按状态和批次过滤
+    pub async fn list_codes(
+        &self,
+        status: Option<RedemptionCodeStatus>,
+        batch_id: Option<&str>,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<RedemptionCode>, usize)> {
+        let mut conn = self.redis.get_connection().await?;
+        let codes: Vec<String> = redis::cmd("ZREVRANGE")
+            .arg(REDEMPTION_CODES_KEY)
+            .arg(0)
+            .arg(-1)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to list redemption codes: {}", e)))?;
+
+        let mut pipe = redis::pipe();
+        for code in &codes {
+            pipe.hgetall(redemption_code_key(code));
+        }
+        let hashes: Vec<HashMap<String, String>> = if codes.is_empty() {
+            Vec::new()
+        } else {
+            pipe.query_async(&mut conn).await.map_err(|e| {
+                AppError::RedisError(format!("Failed to get redemption codes: {}", e))
+            })?
+        };
+
+        let matched: Vec<RedemptionCode> = codes
+            .iter()
+            .zip(&hashes)
+            .filter_map(|(code, hash)| parse_redemption_code(code, hash))
+            .filter(|code| status.is_none_or(|status| code.status == status))
+            .filter(|code| batch_id.is_none_or(|batch_id| code.batch_id == batch_id))
+            .collect();
+        let total = matched.len();
+        Ok((
+            matched.into_iter().skip(offset).take(limit).collect(),
+            total,
+        ))
+    }
+
+    /// 停用未兑换的兑换码
+    pub async fn disable_code(&self, code: &str) -> Result<()> {
+        let mut conn = self.redis.get_connection().await?;
+        let status: String = redis::Script::new(DISABLE_CODE_LUA)
+            .key(redemption_code_key(code))
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| {
+                AppError::RedisError(format!("Failed to disable redemption code: {}", e))
+            })?;
+
+        match status.as_str() {
+            "disabled" => Ok(()),
+            "not_found" => Err(AppError::NotFound(format!(
+                "Redemption code not found: {}",
+                code
+            ))),
+            other => Err(AppError::BadRequest(format!(
+                "Redemption code is already {}",
+                other
+            ))),
+        }
+    }
+
+    /// Key 持有者兑换，金额计入 Key 扣费使用的钱包 (没有钱包时创建 Key 钱包)
+    ///
+    /// 返回兑换流水。已禁用或已过期的 Key 不能兑换
+    pub async fn redeem(&self, api_key: &ApiKey, code: &str) -> Result<LedgerEntry> {
+        if !api_key.is_active {
+            return Err(AppError::Unauthorized("API Key is inactive".to_string()));
+        }
+        if api_key.expires_at.is_some_and(|at| Utc::now() > at) {
+            return Err(AppError::Unauthorized("API Key has expired".to_string()));
+        }
+
+        let code = code.trim().to_uppercase();
+        if code.is_empty() {
+            return Err(AppError::BadRequest("code is required".to_string()));
+        }
+
+        let owner = self
+            .resolve_wallet(api_key)
+            .await?
+            .unwrap_or_else(|| WalletOwner::key(&api_key.id));
+        let mut entry = LedgerEntry::new(LedgerEntryType::Redemption, 0.0);
+        entry.operator = Some(api_key.id.clone());
+        entry.reference = Some(code.clone());
+
+        let script = redis::Script::new(&format!("{}{}", APPLY_LEDGER_LUA, REDEEM_LUA));
+        let mut conn = self.redis.get_connection().await?;
+        let (status, amount, balance): (String, f64, f64) = script
+            .key(redemption_code_key(&code))
+            .key(owner.wallet_key())
+            .key(owner.ledger_key())
+            .key(owner.credit_ledger_key())
+            .arg(entry.created_at.timestamp())
+            .arg(serde_json::to_string(&entry)?)
+            .arg(MAX_WALLET_LEDGER_ENTRIES)
+            .arg(owner.to_string())
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| AppError::RedisError(format!("Failed to redeem code: {}", e)))?;
+
+        match status.as_str() {
+            "ok" => {
+                entry.amount = amount;
+                entry.balance_after = balance;
+                info!(
+                    "🎟️ Redemption code redeemed to wallet {}: +{:.2}",
+                    owner, amount
+                );
+                Ok(entry)
+            }
+            "not_found" => Err(AppError::BadRequest("Invalid redemption code".to_string())),
+            "expired" => Err(AppError::BadRequest(
+                "Redemption code has expired".to_string(),
+            )),
+            other => Err(AppError::BadRequest(format!(
+                "Redemption code is {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// 生成形如 `CRS-XXXX-XXXX-XXXX-XXXX` 的兑换码
+fn generate_redemption_code() -> String {
+    let mut rng = rand::thread_rng();
+    let chars: Vec<char> = (0..REDEMPTION_CODE_LENGTH)
+        .map(|_| REDEMPTION_CODE_ALPHABET[rng.gen_range(0..REDEMPTION_CODE_ALPHABET.len())] as char)
+        .collect();
+    let groups: Vec<String> = chars.chunks(4).map(|c| c.iter().collect()).collect();
+    format!("CRS-{}", groups.join("-"))
+}
+
+fn parse_redemption_code(code: &str, hash: &HashMap<String, String>) -> Option<RedemptionCode> {
+    let timestamp = |name: &str| {
+        hash.get(name)
+            .and_then(|v| v.parse::<i64>().ok())
+            .filter(|ts| *ts > 0)
+            .and_then(|ts| DateTime::from_timestamp(ts, 0))
+    };
+    let text = |name: &str| hash.get(name).filter(|v| !v.is_empty()).cloned();
+
+    Some(RedemptionCode {
+        code: code.to_string(),
+        amount: hash.get("amount")?.parse().ok()?,
+        status: hash.get("status")?.parse().ok()?,
+        batch_id: text("batch_id").unwrap_or_default(),
+        note: text("note"),
+        created_by: text("created_by").unwrap_or_default(),
+        created_at: timestamp("created_at"),
+        expires_at: timestamp("expires_at"),
+        redeemed_to: text("redeemed_to"),
+        redeemed_at: timestamp("redeemed_at"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_redemption_code_format() {
+        let code = generate_redemption_code();
+        assert_eq!(code.len(), "CRS-".len() + 16 + 3);
+        assert!(code.starts_with("CRS-"));
+        assert!(code[4..]
+            .chars()
+            .all(|c| c == '-' || REDEMPTION_CODE_ALPHABET.contains(&(c as u8))));
+        assert_ne!(code, generate_redemption_code());
+    }
+
+    #[test]
+    fn test_parse_redemption_code() {
+        let hash: HashMap<String, String> = [
+            ("amount", "5"),
+            ("status", "redeemed"),
+            ("batch_id", "b1"),
+            ("note", ""),
+            ("created_by", "admin"),
+            ("created_at", "1738281600"),
+            ("expires_at", "0"),
+            ("redeemed_to", "key:k1"),
+            ("redeemed_at", "1738285200"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+        let code = parse_redemption_code("CRS-TEST", &hash).unwrap();
+        assert_eq!(code.amount, 5.0);
+        assert_eq!(code.status, RedemptionCodeStatus::Redeemed);
+        assert_eq!(code.note, None);
+        assert_eq!(code.expires_at, None);
+        assert_eq!(code.redeemed_to.as_deref(), Some("key:k1"));
+        assert!(code.redeemed_at.is_some());
+
+        assert!(parse_redemption_code("CRS-GONE", &HashMap::new()).is_none());
+    }
+}
@@ -155,6 +155,34 @@ impl WebhookService {
         Ok(())
     }
 
+    /// 获取所有 webhook 配置
+    pub async fn list_configs(&self) -> Result<Vec<WebhookConfig>, String> {
+        let mut conn = self
+            .redis
+            .get_connection()
+            .await
+            .map_err(|e| format!("Redis connection failed: {}", e))?;
+
+        let keys: Vec<String> = conn
+            .keys(Self::config_key("*"))
+            .await
+            .map_err(|e| format!("Failed to list configs: {}", e))?;
+
+        let mut configs = Vec::new();
+        for key in keys {
+            let config_json: Option<String> = conn
+                .get(&key)
+                .await
+                .map_err(|e| format!("Failed to get config: {}", e))?;
+
+            if let Some(config) = config_json.and_then(|json| serde_json::from_str(&json).ok()) {
+                configs.push(config);
+            }
+        }
+
+        Ok(configs)
+    }
+
     /// 生成 HMAC 签名
     fn generate_signature(secret: &str, payload: &str) -> String {
         use hmac::{Hmac, Mac};
@@ -314,6 +342,31 @@ impl WebhookService {
 
         Ok(())
     }
+
+    /// 向所有订阅了该事件的已启用配置发送通知
+    pub async fn trigger_event(
+        &self,
+        event_type: &str,
+        data: serde_json::Value,
+    ) -> Result<(), String> {
+        let configs = self.list_configs().await?;
+
+        let mut errors = Vec::new();
+        for config in configs
+            .iter()
+            .filter(|c| c.enabled && c.events.iter().any(|e| e == event_type))
+        {
+            if let Err(e) = self.trigger(&config.id, event_type, data.clone()).await {
+                errors.push(format!("{}: {}", config.id, e));
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(format!("Some webhooks failed: {}", errors.join(", ")));
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
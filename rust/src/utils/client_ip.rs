@@ -0,0 +1,187 @@
+// Client IP Helper
+//
+// 获取请求来源 IP。只有 TCP 对端是配置的可信反向代理时才读取 X-Forwarded-For / X-Real-IP，
+// 否则请求头由客户端任意填写，不能用于限流、白名单和审计
+
+use axum::http::HeaderMap;
+use once_cell::sync::OnceCell;
+use std::net::{IpAddr, SocketAddr};
+
+/// 可信反向代理的 IP 或 CIDR (启动时从 `server.trusted_proxies` 设置)
+static TRUSTED_PROXIES: OnceCell<Vec<String>> = OnceCell::new();
+
+/// 设置可信反向代理列表 (启动时调用一次)
+pub fn configure_trusted_proxies(proxies: &[String]) {
+    let _ = TRUSTED_PROXIES.set(proxies.to_vec());
+}
+
+/// 获取客户端 IP
+///
+/// TCP 对端不是可信代理时直接返回对端地址；是可信代理时：
+/// 1. X-Forwarded-For 从右向左跳过可信代理后的第一个地址
+/// 2. X-Real-IP
+/// 3. TCP 连接地址
+///
+/// 无法获取对端地址时返回 "unknown"
+pub fn extract_client_ip(headers: &HeaderMap, remote_addr: Option<SocketAddr>) -> String {
+    let trusted = TRUSTED_PROXIES.get().map(Vec::as_slice).unwrap_or_default();
+    resolve_client_ip(headers, remote_addr, trusted)
+}
+
+fn resolve_client_ip(
+    headers: &HeaderMap,
+    remote_addr: Option<SocketAddr>,
+    trusted: &[String],
+) -> String {
+    let Some(peer) = remote_addr.map(|addr| addr.ip().to_canonical()) else {
+        return "unknown".to_string();
+    };
+    let is_trusted = |ip: IpAddr| trusted.iter().any(|entry| ip_matches(entry, ip));
+    if !is_trusted(peer) {
+        return peer.to_string();
+    }
+
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+    };
+
+    if let Some(forwarded) = header("x-forwarded-for") {
+        // 每一跳代理把上一跳地址追加在末尾，最右侧的不可信地址才是代理实际看到的客户端
+        let mut client = peer;
+        for entry in forwarded.rsplit(',') {
+            let Ok(ip) = entry.trim().parse::<IpAddr>() else {
+                break;
+            };
+            client = ip.to_canonical();
+            if !is_trusted(client) {
+                break;
+            }
+        }
+        return client.to_string();
+    }
+
+    header("x-real-ip")
+        .and_then(|v| v.parse::<IpAddr>().ok())
+        .unwrap_or(peer)
+        .to_canonical()
+        .to_string()
+}
+
+/// 校验 IP / CIDR 条目格式
+pub fn is_valid_ip_entry(entry: &str) -> bool {
+    match entry.split_once('/') {
+        None => entry.parse::<IpAddr>().is_ok(),
+        Some((network, prefix)) => match (network.parse::<IpAddr>(), prefix.parse::<u8>()) {
+            (Ok(IpAddr::V4(_)), Ok(prefix)) => prefix <= 32,
+            (Ok(IpAddr::V6(_)), Ok(prefix)) => prefix <= 128,
+            _ => false,
+        },
+    }
+}
+
+/// IP 是否匹配单个 IP 或 CIDR 条目
+pub fn ip_matches(entry: &str, ip: IpAddr) -> bool {
+    let Some((network, prefix)) = entry.split_once('/') else {
+        return entry.parse::<IpAddr>().is_ok_and(|allowed| allowed == ip);
+    };
+    let Ok(prefix) = prefix.parse::<u32>() else {
+        return false;
+    };
+
+    match (network.parse::<IpAddr>(), ip) {
+        (Ok(IpAddr::V4(network)), IpAddr::V4(ip)) if prefix <= 32 => {
+            let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+            u32::from(network) & mask == u32::from(ip) & mask
+        }
+        (Ok(IpAddr::V6(network)), IpAddr::V6(ip)) if prefix <= 128 => {
+            let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+            u128::from(network) & mask == u128::from(ip) & mask
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_client_ip_priority() {
+        let remote: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+        let trusted = vec!["10.0.0.0/8".to_string()];
+
+        let mut headers = HeaderMap::new();
+        assert_eq!(
+            resolve_client_ip(&headers, Some(remote), &trusted),
+            "10.0.0.1"
+        );
+        assert_eq!(resolve_client_ip(&headers, None, &trusted), "unknown");
+
+        headers.insert("x-real-ip", "192.168.1.5".parse().unwrap());
+        assert_eq!(
+            resolve_client_ip(&headers, Some(remote), &trusted),
+            "192.168.1.5"
+        );
+
+        headers.insert("x-forwarded-for", "203.0.113.7, 10.0.0.2".parse().unwrap());
+        assert_eq!(
+            resolve_client_ip(&headers, Some(remote), &trusted),
+            "203.0.113.7"
+        );
+    }
+
+    #[test]
+    fn test_forwarded_headers_require_trusted_peer() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "198.51.100.1".parse().unwrap());
+        headers.insert("x-real-ip", "198.51.100.2".parse().unwrap());
+
+        // 非可信对端发来的转发头被忽略
+        let remote: SocketAddr = "203.0.113.9:4000".parse().unwrap();
+        assert_eq!(
+            resolve_client_ip(&headers, Some(remote), &[]),
+            "203.0.113.9"
+        );
+        let trusted = vec!["10.0.0.1".to_string()];
+        assert_eq!(
+            resolve_client_ip(&headers, Some(remote), &trusted),
+            "203.0.113.9"
+        );
+
+        // 客户端伪造的最左侧地址不会被采用
+        let proxy: SocketAddr = "10.0.0.1:4000".parse().unwrap();
+        headers.insert(
+            "x-forwarded-for",
+            "1.2.3.4, 198.51.100.7, 10.0.0.1".parse().unwrap(),
+        );
+        assert_eq!(
+            resolve_client_ip(&headers, Some(proxy), &trusted),
+            "198.51.100.7"
+        );
+
+        // IPv4-mapped IPv6 对端按 IPv4 匹配
+        let mapped: SocketAddr = "[::ffff:10.0.0.1]:4000".parse().unwrap();
+        assert_eq!(
+            resolve_client_ip(&headers, Some(mapped), &trusted),
+            "198.51.100.7"
+        );
+    }
+
+    #[test]
+    fn test_ip_entries() {
+        assert!(is_valid_ip_entry("192.168.0.0/16"));
+        assert!(is_valid_ip_entry("::1"));
+        assert!(!is_valid_ip_entry("192.168.0.0/33"));
+        assert!(!is_valid_ip_entry("example.com"));
+
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        assert!(ip_matches("10.0.0.0/8", ip("10.1.2.3")));
+        assert!(!ip_matches("10.0.0.0/8", ip("11.0.0.1")));
+        assert!(ip_matches("2001:db8::/32", ip("2001:db8::1")));
+        assert!(ip_matches("0.0.0.0/0", ip("8.8.8.8")));
+    }
+}
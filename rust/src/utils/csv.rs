@@ -0,0 +1,27 @@
+// CSV Helper
+//
+// 导出 CSV 时使用的字段转义工具
+
+/// 按 RFC 4180 转义单个 CSV 字段
+///
+/// 含逗号、双引号或换行的字段用双引号包裹，内部双引号写成两个
+pub fn escape_csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_csv_field() {
+        assert_eq!(escape_csv_field("plain"), "plain");
+        assert_eq!(escape_csv_field("a,b"), "\"a,b\"");
+        assert_eq!(escape_csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape_csv_field("line\nbreak"), "\"line\nbreak\"");
+    }
+}
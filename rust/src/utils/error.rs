@@ -27,6 +27,7 @@ pub enum AppError {
     NotFound(String),
     RateLimitExceeded(String),
     ConcurrencyLimitExceeded(String),
+    InsufficientBalance(String),
     NoAvailableAccounts(String),
 
     // External service errors
@@ -52,6 +53,7 @@ impl fmt::Display for AppError {
             Self::NotFound(msg) => write!(f, "Not found: {}", msg),
             Self::RateLimitExceeded(msg) => write!(f, "Rate limit exceeded: {}", msg),
             Self::ConcurrencyLimitExceeded(msg) => write!(f, "Concurrency limit exceeded: {}", msg),
+            Self::InsufficientBalance(msg) => write!(f, "Insufficient balance: {}", msg),
             Self::NoAvailableAccounts(msg) => write!(f, "No available accounts: {}", msg),
             Self::UpstreamError(msg) => write!(f, "Upstream error: {}", msg),
             Self::ProxyError(msg) => write!(f, "Proxy error: {}", msg),
@@ -101,6 +103,11 @@ impl IntoResponse for AppError {
                 msg.clone(),
                 "concurrency_limit_exceeded",
             ),
+            Self::InsufficientBalance(msg) => (
+                StatusCode::PAYMENT_REQUIRED,
+                msg.clone(),
+                "insufficient_balance",
+            ),
             Self::NoAvailableAccounts(msg) => (
                 StatusCode::SERVICE_UNAVAILABLE,
                 msg.clone(),
@@ -0,0 +1,419 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::config::MetricsSettings;
+
+/// 标签组合超过上限后使用的标签值
+pub const OVERFLOW_LABEL: &str = "other";
+
+/// 未通过认证的请求的模型标签值 (请求体由调用方任意填写，不能作为标签)
+pub const UNKNOWN_MODEL_LABEL: &str = "unknown";
+
+/// 请求和上游延迟的直方图桶 (秒)
+const LATENCY_BUCKETS: &[f64] = &[
+    0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0,
+];
+
+static MAX_SERIES: AtomicUsize = AtomicUsize::new(1000);
+static PER_KEY_LABELS: AtomicBool = AtomicBool::new(false);
+
+/// 应用指标设置 (启动时调用一次)
+pub fn configure(settings: &MetricsSettings) {
+    MAX_SERIES.store(settings.max_series, Ordering::Relaxed);
+    PER_KEY_LABELS.store(settings.per_key_labels, Ordering::Relaxed);
+}
+
+/// API Key 标签值：未开启 `per_key_labels` 时为空，所有 Key 合并为一个序列
+pub fn key_label(key_id: &str) -> &str {
+    if PER_KEY_LABELS.load(Ordering::Relaxed) {
+        key_id
+    } else {
+        ""
+    }
+}
+
+enum Kind {
+    Counter,
+    Gauge,
+    Histogram(&'static [f64]),
+}
+
+#[derive(Default)]
+struct Series {
+    /// 计数器/仪表的值，直方图的观测值之和
+    value: f64,
+    /// 直方图每个桶的累计计数 (不含 +Inf)
+    buckets: Vec<u64>,
+    count: u64,
+}
+
+/// 一个指标及其全部标签组合
+pub struct Family {
+    name: &'static str,
+    help: &'static str,
+    labels: &'static [&'static str],
+    kind: Kind,
+    series: Mutex<BTreeMap<Vec<String>, Series>>,
+}
+
+impl Family {
+    const fn new(
+        name: &'static str,
+        help: &'static str,
+        labels: &'static [&'static str],
+        kind: Kind,
+    ) -> Self {
+        Self {
+            name,
+            help,
+            labels,
+            kind,
+            series: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub const fn counter(
+        name: &'static str,
+        help: &'static str,
+        labels: &'static [&'static str],
+    ) -> Self {
+        Self::new(name, help, labels, Kind::Counter)
+    }
+
+    pub const fn gauge(
+        name: &'static str,
+        help: &'static str,
+        labels: &'static [&'static str],
+    ) -> Self {
+        Self::new(name, help, labels, Kind::Gauge)
+    }
+
+    pub const fn histogram(
+        name: &'static str,
+        help: &'static str,
+        labels: &'static [&'static str],
+    ) -> Self {
+        Self::new(name, help, labels, Kind::Histogram(LATENCY_BUCKETS))
+    }
+
+    pub fn inc(&self, labels: &[&str]) {
+        self.add(labels, 1.0);
+    }
+
+    /// 计数器累加或仪表增减
+    pub fn add(&self, labels: &[&str], value: f64) {
+        self.record(labels, MAX_SERIES.load(Ordering::Relaxed), |series| {
+            series.value += value
+        });
+    }
+
+    pub fn observe(&self, labels: &[&str], value: f64) {
+        let bounds = match self.kind {
+            Kind::Histogram(bounds) => bounds,
+            _ => return,
+        };
+        self.record(labels, MAX_SERIES.load(Ordering::Relaxed), |series| {
+            if series.buckets.len() != bounds.len() {
+                series.buckets = vec![0; bounds.len()];
+            }
+            for (bucket, bound) in series.buckets.iter_mut().zip(bounds) {
+                if value <= *bound {
+                    *bucket += 1;
+                }
+            }
+            series.value += value;
+            series.count += 1;
+        });
+    }
+
+    /// 记录到标签组合对应的序列，超过 `max_series` 的新组合合并到 `other`
+    fn record(&self, labels: &[&str], max_series: usize, update: impl FnOnce(&mut Series)) {
+        debug_assert_eq!(labels.len(), self.labels.len());
+        let mut series = self.series.lock().unwrap_or_else(|e| e.into_inner());
+        let mut key: Vec<String> = labels.iter().map(|label| label.to_string()).collect();
+        if !series.contains_key(&key) && series.len() >= max_series {
+            key = vec![OVERFLOW_LABEL.to_string(); labels.len()];
+        }
+        update(series.entry(key).or_default());
+    }
+
+    fn render(&self, out: &mut String) {
+        let series = self.series.lock().unwrap_or_else(|e| e.into_inner());
+        let kind = match self.kind {
+            Kind::Counter => "counter",
+            Kind::Gauge => "gauge",
+            Kind::Histogram(_) => "histogram",
+        };
+        let _ = writeln!(out, "# HELP {} {}", self.name, self.help);
+        let _ = writeln!(out, "# TYPE {} {}", self.name, kind);
+
+        for (values, series) in series.iter() {
+            let labels = format_labels(self.labels, values);
+            match self.kind {
+                Kind::Histogram(bounds) => {
+                    for (bound, count) in bounds.iter().zip(&series.buckets) {
+                        let le = format!("le=\"{}\"", bound);
+                        let _ = writeln!(
+                            out,
+                            "{}_bucket{} {}",
+                            self.name,
+                            join_labels(&labels, &le),
+                            count
+                        );
+                    }
+                    let _ = writeln!(
+                        out,
+                        "{}_bucket{} {}",
+                        self.name,
+                        join_labels(&labels, "le=\"+Inf\""),
+                        series.count
+                    );
+                    let _ = writeln!(out, "{}_sum{} {}", self.name, braces(&labels), series.value);
+                    let _ = writeln!(
+                        out,
+                        "{}_count{} {}",
+                        self.name,
+                        braces(&labels),
+                        series.count
+                    );
+                }
+                _ => {
+                    let _ = writeln!(out, "{}{} {}", self.name, braces(&labels), series.value);
+                }
+            }
+        }
+    }
+}
+
+/// 仪表 +1，Drop 时 -1 (用于进行中的请求和流)
+pub struct InflightGuard {
+    family: &'static Family,
+    labels: Vec<String>,
+}
+
+impl InflightGuard {
+    pub fn new(family: &'static Family, labels: &[&str]) -> Self {
+        family.add(labels, 1.0);
+        Self {
+            family,
+            labels: labels.iter().map(|label| label.to_string()).collect(),
+        }
+    }
+}
+
+impl Drop for InflightGuard {
+    fn drop(&mut self) {
+        let labels: Vec<&str> = self.labels.iter().map(String::as_str).collect();
+        self.family.add(&labels, -1.0);
+    }
+}
+
+pub static REQUESTS: Family = Family::counter(
+    "relay_requests_total",
+    "Relay API requests by response status",
+    &["platform", "route", "model", "status"],
+);
+pub static REQUEST_DURATION: Family = Family::histogram(
+    "relay_request_duration_seconds",
+    "Relay API latency until response headers are sent",
+    &["platform", "route", "model"],
+);
+pub static ACTIVE_STREAMS: Family = Family::gauge(
+    "relay_active_streams",
+    "Streaming responses currently being sent",
+    &["platform"],
+);
+pub static UPSTREAM_RESPONSES: Family = Family::counter(
+    "relay_upstream_responses_total",
+    "Upstream responses by status (error = timeout or connection failure)",
+    &["platform", "account", "status"],
+);
+pub static UPSTREAM_DURATION: Family = Family::histogram(
+    "relay_upstream_duration_seconds",
+    "Upstream latency until response headers are received",
+    &["platform", "account"],
+);
+pub static UPSTREAM_TTFB: Family = Family::histogram(
+    "relay_upstream_ttfb_seconds",
+    "Time from sending a streaming upstream request to its first body chunk",
+    &["platform", "account"],
+);
+pub static TOKENS: Family = Family::counter(
+    "relay_tokens_total",
+    "Recorded tokens by type (input, output, cache_creation, cache_read)",
+    &["model", "type", "api_key"],
+);
+pub static COST: Family = Family::counter(
+    "relay_cost_usd_total",
+    "Recorded upstream cost in USD",
+    &["model", "api_key"],
+);
+pub static BILLED_COST: Family = Family::counter(
+    "relay_billed_cost_usd_total",
+    "Recorded cost in USD after billing multipliers",
+    &["model", "api_key"],
+);
+pub static ACCOUNT_CONCURRENCY: Family = Family::gauge(
+    "relay_account_concurrency",
+    "Requests in flight per upstream account",
+    &["account"],
+);
+pub static API_KEY_CONCURRENCY: Family = Family::gauge(
+    "relay_api_key_concurrency",
+    "Requests in flight per API key",
+    &["api_key"],
+);
+pub static SCHEDULER_SELECTIONS: Family = Family::counter(
+    "relay_scheduler_selections_total",
+    "Account selections by outcome (selected, no_available, error)",
+    &["platform", "outcome"],
+);
+pub static TOKEN_REFRESHES: Family = Family::counter(
+    "relay_token_refreshes_total",
+    "OAuth token refreshes by result (success, failure)",
+    &["platform", "result"],
+);
+
+static FAMILIES: [&Family; 13] = [
+    &REQUESTS,
+    &REQUEST_DURATION,
+    &ACTIVE_STREAMS,
+    &UPSTREAM_RESPONSES,
+    &UPSTREAM_DURATION,
+    &UPSTREAM_TTFB,
+    &TOKENS,
+    &COST,
+    &BILLED_COST,
+    &ACCOUNT_CONCURRENCY,
+    &API_KEY_CONCURRENCY,
+    &SCHEDULER_SELECTIONS,
+    &TOKEN_REFRESHES,
+];
+
+/// 记录上游请求结果和延迟 (外层为超时，内层为请求错误)
+pub fn observe_upstream<E1, E2>(
+    platform: &str,
+    account_id: &str,
+    started: Instant,
+    result: &std::result::Result<std::result::Result<reqwest::Response, E1>, E2>,
+) {
+    let status = match result {
+        Ok(Ok(response)) => response.status().as_u16().to_string(),
+        _ => "error".to_string(),
+    };
+    UPSTREAM_RESPONSES.inc(&[platform, account_id, &status]);
+    UPSTREAM_DURATION.observe(&[platform, account_id], started.elapsed().as_secs_f64());
+}
+
+/// 记录调度结果
+pub fn observe_selection<T>(platform: &str, result: &crate::utils::Result<T>) {
+    let outcome = match result {
+        Ok(_) => "selected",
+        Err(crate::utils::AppError::NoAvailableAccounts(_)) => "no_available",
+        Err(_) => "error",
+    };
+    SCHEDULER_SELECTIONS.inc(&[platform, outcome]);
+}
+
+/// 以 Prometheus 文本格式输出全部指标
+pub fn render() -> String {
+    let mut out = String::new();
+    for family in FAMILIES {
+        family.render(&mut out);
+    }
+    out
+}
+
+/// 输出一个抓取时读取的无标签仪表
+pub fn write_gauge(out: &mut String, name: &str, help: &str, value: f64) {
+    write_single(out, name, "gauge", help, value);
+}
+
+/// 输出一个抓取时读取的无标签计数器 (计数由其他模块维护)
+pub fn write_counter(out: &mut String, name: &str, help: &str, value: f64) {
+    write_single(out, name, "counter", help, value);
+}
+
+fn write_single(out: &mut String, name: &str, kind: &str, help: &str, value: f64) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+fn format_labels(names: &[&str], values: &[String]) -> String {
+    names
+        .iter()
+        .zip(values)
+        .map(|(name, value)| format!("{}=\"{}\"", name, escape_label(value)))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn join_labels(labels: &str, extra: &str) -> String {
+    if labels.is_empty() {
+        format!("{{{}}}", extra)
+    } else {
+        format!("{{{},{}}}", labels, extra)
+    }
+}
+
+fn braces(labels: &str) -> String {
+    if labels.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", labels)
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_counter_and_histogram() {
+        let counter = Family::counter("test_total", "Test counter", &["model"]);
+        counter.inc(&["claude \"x\""]);
+        counter.add(&["claude \"x\""], 2.0);
+        let histogram = Family::histogram("test_seconds", "Test histogram", &["platform"]);
+        histogram.observe(&["claude"], 0.2);
+        histogram.observe(&["claude"], 400.0);
+
+        let mut out = String::new();
+        counter.render(&mut out);
+        histogram.render(&mut out);
+        assert!(out.contains("# TYPE test_total counter\n"));
+        assert!(out.contains("test_total{model=\"claude \\\"x\\\"\"} 3\n"));
+        assert!(out.contains("test_seconds_bucket{platform=\"claude\",le=\"0.1\"} 0\n"));
+        assert!(out.contains("test_seconds_bucket{platform=\"claude\",le=\"0.25\"} 1\n"));
+        assert!(out.contains("test_seconds_bucket{platform=\"claude\",le=\"300\"} 1\n"));
+        assert!(out.contains("test_seconds_bucket{platform=\"claude\",le=\"+Inf\"} 2\n"));
+        assert!(out.contains("test_seconds_count{platform=\"claude\"} 2\n"));
+    }
+
+    #[test]
+    fn test_series_limit() {
+        let gauge = Family::gauge("test_gauge", "Test gauge", &["account", "status"]);
+        gauge.record(&["a", "200"], 2, |s| s.value += 1.0);
+        gauge.record(&["b", "200"], 2, |s| s.value += 1.0);
+        gauge.record(&["c", "200"], 2, |s| s.value += 1.0);
+        gauge.record(&["d", "500"], 2, |s| s.value += 1.0);
+        // 已存在的组合不受上限影响
+        gauge.record(&["a", "200"], 2, |s| s.value -= 1.0);
+
+        let mut out = String::new();
+        gauge.render(&mut out);
+        assert!(out.contains("test_gauge{account=\"a\",status=\"200\"} 0\n"));
+        assert!(out.contains("test_gauge{account=\"other\",status=\"other\"} 2\n"));
+        assert!(!out.contains("account=\"c\""));
+    }
+}
@@ -1,16 +1,21 @@
+pub mod client_ip;
 pub mod cost_calculator;
 pub mod crypto;
+pub mod csv;
 pub mod error;
 pub mod http_client;
 pub mod logger;
+pub mod metrics;
 pub mod model_helper;
 pub mod session_helper;
 
+pub use client_ip::extract_client_ip;
 pub use cost_calculator::{
     AggregatedUsage, CacheSavings, CostCalculationResult, CostCalculator, CostDetails, DebugInfo,
     FormattedCosts, FormattedSavings, StaticModelPricing, UsageDetails,
 };
 pub use crypto::CryptoService;
+pub use csv::escape_csv_field;
 pub use error::{AppError, Result};
 pub use http_client::HttpClient;
 pub use logger::init_logger;
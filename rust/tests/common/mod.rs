@@ -107,6 +107,9 @@ impl TestContext {
             is_active: true,
             token_limit: 1000,
             concurrency_limit: 5,
+            concurrency_queue_enabled: false,
+            concurrency_queue_max_size: 10,
+            concurrency_queue_timeout_ms: 30_000,
             rate_limit_window: Some(60),
             rate_limit_requests: Some(1000),
             rate_limit_cost: None,
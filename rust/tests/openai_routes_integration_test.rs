@@ -14,7 +14,7 @@ use claude_relay::{
     routes::{create_openai_router, OpenAIState},
     services::{
         account::ClaudeAccountService, account_scheduler::AccountScheduler, api_key::ApiKeyService,
-        unified_openai_scheduler::UnifiedOpenAIScheduler,
+        pricing_service::PricingService, unified_openai_scheduler::UnifiedOpenAIScheduler,
     },
     RedisPool, Settings,
 };
@@ -49,6 +49,9 @@ async fn create_test_openai_state(
         None, // Use default TTL
     ));
 
+    // Create pricing service
+    let pricing_service = Arc::new(PricingService::new(Arc::new(reqwest::Client::new())));
+
     Ok(OpenAIState {
         redis: redis_arc,
         settings: settings_arc,
@@ -56,6 +59,7 @@ async fn create_test_openai_state(
         api_key_service,
         scheduler,
         unified_openai_scheduler,
+        pricing_service,
     })
 }
 
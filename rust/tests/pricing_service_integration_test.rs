@@ -5,7 +5,10 @@ use claude_relay::models::UsageRecord;
 
 mod common;
 
+use claude_relay::models::PricingOverrideRequest;
 use claude_relay::services::pricing_service::{PricingService, Usage};
+use claude_relay::services::PricingOverrideService;
+use claude_relay::RedisPool;
 use claude_relay::utils::cost_calculator::{AggregatedUsage, CostCalculator};
 use std::sync::Arc;
 
@@ -414,6 +417,46 @@ async fn test_gpt5_codex_fallback() {
     }
 }
 
+#[tokio::test]
+async fn test_pricing_override_for_custom_model() {
+    let ctx = common::TestContext::new()
+        .await
+        .expect("Failed to setup test context");
+    let redis = Arc::new(RedisPool::new(&ctx.settings).expect("Failed to create Redis pool"));
+    let overrides = PricingOverrideService::new(redis.clone());
+    let service = PricingService::new(Arc::new(reqwest::Client::new())).with_overrides(redis);
+
+    let usage = Usage {
+        input_tokens: 300_000,
+        output_tokens: 1_000,
+        cache_creation_input_tokens: 0,
+        cache_read_input_tokens: 0,
+        cache_creation: None,
+    };
+
+    // 没有定价的模型成本为 0
+    let result = service.calculate_cost(&usage, "acme/finetune-v1").await;
+    assert!(!result.has_pricing);
+
+    let request: PricingOverrideRequest = serde_json::from_str(
+        r#"{"inputCostPerToken":0.000001,"outputCostPerToken":0.000002,
+            "longContext":{"thresholdTokens":200000,"inputCostPerToken":0.000002,"outputCostPerToken":0.000004}}"#,
+    )
+    .unwrap();
+    overrides
+        .upsert("acme/finetune-v1", request, "admin")
+        .await
+        .expect("Failed to save override");
+
+    // 新的服务实例读取到管理员定价，超过阈值按长上下文档位计算
+    let service = PricingService::new(Arc::new(reqwest::Client::new()))
+        .with_overrides(Arc::new(RedisPool::new(&ctx.settings).unwrap()));
+    let result = service.calculate_cost(&usage, "acme/finetune-v1").await;
+    assert!(result.has_pricing);
+    assert!(result.is_long_context_request);
+    assert!((result.total_cost - (300_000.0 * 0.000002 + 1_000.0 * 0.000004)).abs() < 1e-9);
+}
+
 // Helper tests for common module
 #[cfg(test)]
 mod tests {
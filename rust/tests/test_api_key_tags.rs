@@ -106,6 +106,9 @@ fn create_test_api_key_with_tags(tags: Vec<String>) -> ApiKey {
         permissions: claude_relay::models::api_key::ApiKeyPermissions::All,
         token_limit: 1000000,
         concurrency_limit: 10,
+        concurrency_queue_enabled: false,
+        concurrency_queue_max_size: 10,
+        concurrency_queue_timeout_ms: 30_000,
         rate_limit_window: Some(60),
         rate_limit_requests: Some(100),
         rate_limit_cost: Some(1.0),
@@ -40,6 +40,55 @@ class ApiClient {
     return authToken || null
   }
 
+  // 使用刷新令牌换取新的访问令牌（并发请求共用同一次刷新）
+  async refreshAuthToken() {
+    const refreshToken = localStorage.getItem('refreshToken')
+    if (!refreshToken) {
+      return false
+    }
+
+    if (!this.refreshPromise) {
+      this.refreshPromise = fetch(createApiUrl('/web/auth/refresh'), {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify({ refreshToken })
+      })
+        .then(async (response) => {
+          if (!response.ok) {
+            localStorage.removeItem('refreshToken')
+            return false
+          }
+          const result = await response.json()
+          localStorage.setItem('authToken', result.token)
+          localStorage.setItem('refreshToken', result.refreshToken)
+          return true
+        })
+        .catch(() => false)
+        .finally(() => {
+          this.refreshPromise = null
+        })
+    }
+
+    return this.refreshPromise
+  }
+
+  // 发送请求，访问令牌过期时自动刷新并重试一次
+  async send(url, config) {
+    const response = await fetch(url, config)
+    if (response.status !== 401 || !config.headers['Authorization']) {
+      return response
+    }
+
+    if (!(await this.refreshAuthToken())) {
+      return response
+    }
+
+    return fetch(url, {
+      ...config,
+      headers: { ...config.headers, Authorization: `Bearer ${this.getAuthToken()}` }
+    })
+  }
+
   // 构建请求配置
   buildConfig(options = {}) {
     const config = {
@@ -69,6 +118,7 @@ class ApiClient {
 
       if (!isLoginPage) {
         localStorage.removeItem('authToken')
+        localStorage.removeItem('refreshToken')
         // 使用统一的登录URL
         window.location.href = getLoginUrl()
       }
@@ -123,7 +173,7 @@ class ApiClient {
     })
 
     try {
-      const response = await fetch(fullUrl, config)
+      const response = await this.send(fullUrl, config)
       return await this.handleResponse(response)
     } catch (error) {
       console.error('API GET Error:', error)
@@ -141,7 +191,7 @@ class ApiClient {
     })
 
     try {
-      const response = await fetch(fullUrl, config)
+      const response = await this.send(fullUrl, config)
       return await this.handleResponse(response)
     } catch (error) {
       console.error('API POST Error:', error)
@@ -159,7 +209,7 @@ class ApiClient {
     })
 
     try {
-      const response = await fetch(fullUrl, config)
+      const response = await this.send(fullUrl, config)
       return await this.handleResponse(response)
     } catch (error) {
       console.error('API PUT Error:', error)
@@ -177,7 +227,7 @@ class ApiClient {
     })
 
     try {
-      const response = await fetch(fullUrl, config)
+      const response = await this.send(fullUrl, config)
       return await this.handleResponse(response)
     } catch (error) {
       console.error('API PATCH Error:', error)
@@ -197,7 +247,7 @@ class ApiClient {
     })
 
     try {
-      const response = await fetch(fullUrl, config)
+      const response = await this.send(fullUrl, config)
       return await this.handleResponse(response)
     } catch (error) {
       console.error('API DELETE Error:', error)
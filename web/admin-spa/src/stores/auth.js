@@ -36,6 +36,9 @@ export const useAuthStore = defineStore('auth', () => {
         username.value = result.username || credentials.username
         isLoggedIn.value = true
         localStorage.setItem('authToken', result.token)
+        if (result.refreshToken) {
+          localStorage.setItem('refreshToken', result.refreshToken)
+        }
 
         await router.push('/dashboard')
       } else {
@@ -49,10 +52,16 @@ export const useAuthStore = defineStore('auth', () => {
   }
 
   function logout() {
+    // 撤销服务端会话，失败不影响本地登出
+    if (authToken.value) {
+      apiClient.post('/web/auth/logout').catch(() => {})
+    }
+
     isLoggedIn.value = false
     authToken.value = ''
     username.value = ''
     localStorage.removeItem('authToken')
+    localStorage.removeItem('refreshToken')
     router.push('/login')
   }
 